@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use testcontainers_test::domain::minesweeper::{
+    generate_no_guess, BoardConfig, GeneratorOptions, MineLayout, Position,
+};
+
+fn board_generation(c: &mut Criterion) {
+    let boards = [
+        ("beginner", BoardConfig::beginner()),
+        ("intermediate", BoardConfig::intermediate()),
+        ("expert", BoardConfig::expert()),
+    ];
+    let options = GeneratorOptions {
+        time_budget: Duration::from_secs(30),
+        ..GeneratorOptions::default()
+    };
+    let mut group = c.benchmark_group("board_generation");
+    for (name, config) in boards {
+        let start = Position::new(config.width() / 2, config.height() / 2);
+        group.bench_with_input(BenchmarkId::new("random", name), &config, |b, &config| {
+            let mut seed = 0;
+            b.iter(|| {
+                seed += 1;
+                MineLayout::generate(config, seed, start).unwrap()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("no_guess", name), &config, |b, &config| {
+            let mut seed = 0;
+            b.iter(|| {
+                seed += 1;
+                generate_no_guess(config, seed, start, &options).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, board_generation);
+criterion_main!(benches);
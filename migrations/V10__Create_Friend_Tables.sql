@@ -0,0 +1,19 @@
+-- friend requests that haven't been answered yet
+CREATE TABLE FriendRequest (
+    SenderId INTEGER NOT NULL REFERENCES User(Id),
+    RecipientId INTEGER NOT NULL REFERENCES User(Id),
+    -- unix timestamp
+    SentAt INTEGER NOT NULL,
+    PRIMARY KEY (SenderId, RecipientId)
+);
+
+CREATE INDEX FriendRequestByRecipient ON FriendRequest (RecipientId);
+
+-- every friendship is stored both ways round, so either friend can look it up by UserId
+CREATE TABLE Friendship (
+    UserId INTEGER NOT NULL REFERENCES User(Id),
+    FriendId INTEGER NOT NULL REFERENCES User(Id),
+    -- unix timestamp
+    Since INTEGER NOT NULL,
+    PRIMARY KEY (UserId, FriendId)
+);
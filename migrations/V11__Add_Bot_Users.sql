@@ -0,0 +1,2 @@
+-- bots play under accounts of their own, which nobody can log in to
+ALTER TABLE User ADD COLUMN IsBot INTEGER NOT NULL DEFAULT 0;
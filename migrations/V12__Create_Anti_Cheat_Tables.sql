@@ -0,0 +1,31 @@
+-- where the session was logged in from, as the client's IP address. NULL if it isn't known
+ALTER TABLE Session ADD COLUMN Address TEXT;
+
+CREATE INDEX SessionByAddress ON Session (Address);
+
+-- everything the anti-cheat detectors found odd about a finished match
+CREATE TABLE CheatFinding (
+    MatchId INTEGER NOT NULL REFERENCES GameMatch(Id),
+    UserId INTEGER NOT NULL REFERENCES User(Id),
+    Detector TEXT NOT NULL,
+    Score REAL NOT NULL,
+    Reason TEXT NOT NULL
+);
+
+CREATE INDEX CheatFindingByMatch ON CheatFinding (MatchId, UserId);
+
+-- players whose findings in a match added up to enough for a moderator to look at
+CREATE TABLE CheatFlag (
+    Id INTEGER PRIMARY KEY,
+    MatchId INTEGER NOT NULL REFERENCES GameMatch(Id),
+    UserId INTEGER NOT NULL REFERENCES User(Id),
+    Score REAL NOT NULL,
+    -- unix timestamps
+    FlaggedAt INTEGER NOT NULL,
+    -- NULL until a moderator has looked at it
+    ReviewedAt INTEGER,
+    ReviewedBy INTEGER REFERENCES User(Id),
+    -- cleared or confirmed
+    Verdict TEXT,
+    UNIQUE (MatchId, UserId)
+);
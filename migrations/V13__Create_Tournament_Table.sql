@@ -0,0 +1,14 @@
+CREATE TABLE Tournament (
+    Id INTEGER PRIMARY KEY,
+    Name TEXT NOT NULL,
+    -- registration, running, finished or cancelled. Also in State, but kept here to find the
+    -- tournaments that still need running
+    Status TEXT NOT NULL,
+    -- unix timestamp in milliseconds
+    StartsAt INTEGER NOT NULL,
+    CreatedBy INTEGER NOT NULL REFERENCES User(Id),
+    -- the whole tournament as JSON: entrants, seeds, rounds, heats and results
+    State TEXT NOT NULL
+);
+
+CREATE INDEX TournamentByStatus ON Tournament (Status);
@@ -0,0 +1,29 @@
+-- set once a finished match has been added onto its players' stats, so it's never counted twice
+ALTER TABLE GameMatch ADD COLUMN StatsRecorded INTEGER NOT NULL DEFAULT 0;
+
+-- every player's totals over all the matches they've finished
+CREATE TABLE PlayerStats (
+    UserId INTEGER PRIMARY KEY REFERENCES User(Id),
+    MatchesPlayed INTEGER NOT NULL,
+    Wins INTEGER NOT NULL,
+    -- every placement added up, for the average
+    PlacementTotal INTEGER NOT NULL,
+    CellsRevealed INTEGER NOT NULL,
+    MinesFlagged INTEGER NOT NULL,
+    Clears INTEGER NOT NULL,
+    -- NULL until they've cleared a board
+    FastestClearMs INTEGER,
+    FastestClearMatchId INTEGER REFERENCES GameMatch(Id),
+    UpdatedAt INTEGER NOT NULL
+);
+
+-- one row per achievement a player has unlocked
+CREATE TABLE PlayerAchievement (
+    UserId INTEGER NOT NULL REFERENCES User(Id),
+    -- the achievement's id from the list in the code
+    AchievementId TEXT NOT NULL,
+    -- the match that unlocked it
+    MatchId INTEGER NOT NULL REFERENCES GameMatch(Id),
+    UnlockedAt INTEGER NOT NULL,
+    PRIMARY KEY (UserId, AchievementId)
+);
@@ -0,0 +1,25 @@
+-- square, hex or torus; everything played before this was square
+ALTER TABLE GameMatch ADD COLUMN Topology TEXT NOT NULL DEFAULT 'square';
+ALTER TABLE PracticeGame ADD COLUMN Topology TEXT NOT NULL DEFAULT 'square';
+
+-- custom boards of the same size but a different topology are kept apart too
+CREATE TABLE PersonalBestByTopology (
+    UserId INTEGER NOT NULL REFERENCES User(Id),
+    Level TEXT NOT NULL,
+    Width INTEGER NOT NULL,
+    Height INTEGER NOT NULL,
+    Mines INTEGER NOT NULL,
+    Topology TEXT NOT NULL,
+    GameId INTEGER NOT NULL REFERENCES PracticeGame(Id),
+    TimeMs INTEGER NOT NULL,
+    AchievedAt INTEGER NOT NULL,
+    PRIMARY KEY (UserId, Level, Width, Height, Mines, Topology)
+);
+
+INSERT INTO PersonalBestByTopology
+    (UserId, Level, Width, Height, Mines, Topology, GameId, TimeMs, AchievedAt)
+SELECT UserId, Level, Width, Height, Mines, 'square', GameId, TimeMs, AchievedAt
+FROM PersonalBest;
+
+DROP TABLE PersonalBest;
+ALTER TABLE PersonalBestByTopology RENAME TO PersonalBest;
@@ -0,0 +1,15 @@
+CREATE TABLE Notification (
+    Id INTEGER PRIMARY KEY,
+    UserId INTEGER NOT NULL REFERENCES User(Id),
+    -- friend_request, lobby_invite, tournament_heat or achievement_unlocked. Also in Body, but
+    -- kept here so notifications can be looked through without parsing them
+    Kind TEXT NOT NULL,
+    -- everything else about the notification as JSON
+    Body TEXT NOT NULL,
+    -- unix timestamp
+    CreatedAt INTEGER NOT NULL,
+    -- unix timestamp, NULL until the player marks it as read
+    ReadAt INTEGER
+);
+
+CREATE INDEX UnreadNotificationByUser ON Notification (UserId) WHERE ReadAt IS NULL;
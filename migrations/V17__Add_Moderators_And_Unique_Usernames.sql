@@ -0,0 +1,8 @@
+-- moderators are marked on their accounts, since usernames used to be shareable
+ALTER TABLE User ADD COLUMN IsModerator INTEGER NOT NULL DEFAULT 0;
+
+-- anyone who signed up under a name that was already taken gets their ID tacked on, so
+-- the first account with a name keeps it
+UPDATE User SET Username = Username || ' #' || Id
+WHERE Id NOT IN (SELECT MIN(Id) FROM User GROUP BY Username);
+CREATE UNIQUE INDEX UserByUsername ON User (Username);
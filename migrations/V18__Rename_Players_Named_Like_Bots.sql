@@ -0,0 +1,4 @@
+-- bots are named "Bot <id>", so players who signed up under a name like that would
+-- clash with a bot sooner or later
+UPDATE User SET Username = 'Player ' || Id
+WHERE IsBot = 0 AND Username LIKE 'Bot %';
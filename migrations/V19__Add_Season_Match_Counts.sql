@@ -0,0 +1,11 @@
+-- the leaderboards only count this season's matches, while MatchesPlayed is for good
+ALTER TABLE Rating ADD COLUMN SeasonMatchesPlayed INTEGER NOT NULL DEFAULT 0;
+
+-- MatchesPlayed used to be zeroed at the end of every season, so it's really this season's
+-- count. The lifetime count can be rebuilt from the rating history.
+UPDATE Rating SET
+    SeasonMatchesPlayed = MatchesPlayed,
+    MatchesPlayed = (
+        SELECT COUNT(*) FROM RatingHistory
+        WHERE RatingHistory.UserId = Rating.UserId AND RatingHistory.Mode = Rating.Mode
+    );
@@ -0,0 +1,5 @@
+CREATE TABLE Session (
+    Id TEXT PRIMARY KEY,
+    UserId INTEGER NOT NULL REFERENCES User(Id),
+    CreatedAt INTEGER NOT NULL
+);
@@ -0,0 +1,21 @@
+CREATE TABLE GameMatch (
+    Id INTEGER PRIMARY KEY,
+    LobbyCode TEXT NOT NULL,
+    Mode TEXT NOT NULL,
+    Width INTEGER NOT NULL,
+    Height INTEGER NOT NULL,
+    Mines INTEGER NOT NULL,
+    -- the u64 seed, stored bit-for-bit as a (possibly negative) i64
+    Seed INTEGER NOT NULL,
+    StartedAt INTEGER NOT NULL,
+    -- NULL until the match is over
+    FinishedAt INTEGER
+);
+
+CREATE TABLE GameMatchPlayer (
+    MatchId INTEGER NOT NULL REFERENCES GameMatch(Id),
+    UserId INTEGER NOT NULL REFERENCES User(Id),
+    -- 1 is first place. NULL until the match is over
+    Placement INTEGER,
+    PRIMARY KEY (MatchId, UserId)
+);
@@ -0,0 +1,11 @@
+CREATE TABLE GameMatchEvent (
+    MatchId INTEGER NOT NULL REFERENCES GameMatch(Id),
+    -- starts at 0 and counts up without gaps within a match
+    Seq INTEGER NOT NULL,
+    Tick INTEGER NOT NULL,
+    -- 'started', 'action' or 'event'
+    Kind TEXT NOT NULL,
+    -- the log entry as JSON
+    Payload TEXT NOT NULL,
+    PRIMARY KEY (MatchId, Seq)
+);
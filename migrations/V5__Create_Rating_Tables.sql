@@ -0,0 +1,28 @@
+CREATE TABLE Rating (
+    UserId INTEGER NOT NULL REFERENCES User(Id),
+    Mode TEXT NOT NULL,
+    Rating REAL NOT NULL,
+    Deviation REAL NOT NULL,
+    Volatility REAL NOT NULL,
+    MatchesPlayed INTEGER NOT NULL,
+    -- unix timestamp of the last rated match, which decay is measured from
+    LastPlayedAt INTEGER NOT NULL,
+    PRIMARY KEY (UserId, Mode)
+);
+
+-- one row per player per rated match
+CREATE TABLE RatingHistory (
+    Id INTEGER PRIMARY KEY,
+    UserId INTEGER NOT NULL REFERENCES User(Id),
+    Mode TEXT NOT NULL,
+    MatchId INTEGER NOT NULL REFERENCES GameMatch(Id),
+    Placement INTEGER NOT NULL,
+    RatingBefore REAL NOT NULL,
+    DeviationBefore REAL NOT NULL,
+    RatingAfter REAL NOT NULL,
+    DeviationAfter REAL NOT NULL,
+    VolatilityAfter REAL NOT NULL,
+    RecordedAt INTEGER NOT NULL
+);
+
+CREATE INDEX RatingHistoryByUser ON RatingHistory (UserId, Mode, RecordedAt);
@@ -0,0 +1,24 @@
+CREATE TABLE Season (
+    Id INTEGER PRIMARY KEY,
+    StartedAt INTEGER NOT NULL,
+    -- when the season is due to end
+    EndsAt INTEGER NOT NULL,
+    -- set once the season is over and its final standings have been archived
+    EndedAt INTEGER
+);
+
+-- the final standings of every finished season, one row per player per leaderboard
+CREATE TABLE SeasonStanding (
+    SeasonId INTEGER NOT NULL REFERENCES Season(Id),
+    Board TEXT NOT NULL,
+    UserId INTEGER NOT NULL REFERENCES User(Id),
+    Rank INTEGER NOT NULL,
+    Rating REAL NOT NULL,
+    Deviation REAL NOT NULL,
+    MatchesPlayed INTEGER NOT NULL,
+    PRIMARY KEY (SeasonId, Board, UserId)
+);
+
+-- leaderboard pages are read in this order
+CREATE INDEX SeasonStandingByRating ON SeasonStanding (SeasonId, Board, Rating DESC, UserId);
+CREATE INDEX RatingByMode ON Rating (Mode, Rating DESC, UserId);
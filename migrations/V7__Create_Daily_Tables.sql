@@ -0,0 +1,39 @@
+CREATE TABLE DailyChallenge (
+    -- the challenge's date, like 2022-07-31
+    Date TEXT PRIMARY KEY,
+    -- the u64 seed, stored bit-for-bit as a (possibly negative) i64
+    Seed INTEGER NOT NULL,
+    Width INTEGER NOT NULL,
+    Height INTEGER NOT NULL,
+    StartX INTEGER NOT NULL,
+    StartY INTEGER NOT NULL,
+    -- the generated layout as a JSON array of positions, so the board never changes even if
+    -- the generator does
+    Mines TEXT NOT NULL,
+    OpensAt INTEGER NOT NULL,
+    ClosesAt INTEGER NOT NULL
+);
+
+-- every player gets one attempt a day, played as a match with just them in it
+CREATE TABLE DailyAttempt (
+    Date TEXT NOT NULL REFERENCES DailyChallenge(Date),
+    UserId INTEGER NOT NULL REFERENCES User(Id),
+    MatchId INTEGER NOT NULL REFERENCES GameMatch(Id),
+    -- unix timestamps in milliseconds
+    StartedAt INTEGER NOT NULL,
+    -- NULL while the attempt is being played
+    FinishedAt INTEGER,
+    -- 1 if the board was cleared. NULL while the attempt is being played
+    Cleared INTEGER,
+    Revealed INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (Date, UserId)
+);
+
+CREATE INDEX DailyAttemptByTime ON DailyAttempt (Date, Cleared, FinishedAt - StartedAt);
+
+CREATE TABLE DailyStreak (
+    UserId INTEGER PRIMARY KEY REFERENCES User(Id),
+    Current INTEGER NOT NULL,
+    Best INTEGER NOT NULL,
+    LastCleared TEXT
+);
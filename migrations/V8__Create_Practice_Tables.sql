@@ -0,0 +1,33 @@
+-- one row per finished solo practice game
+CREATE TABLE PracticeGame (
+    Id INTEGER PRIMARY KEY,
+    UserId INTEGER NOT NULL REFERENCES User(Id),
+    -- beginner, intermediate, expert or custom
+    Level TEXT NOT NULL,
+    Width INTEGER NOT NULL,
+    Height INTEGER NOT NULL,
+    Mines INTEGER NOT NULL,
+    Won INTEGER NOT NULL,
+    TimeMs INTEGER NOT NULL,
+    ThreeBv INTEGER NOT NULL,
+    SolvedThreeBv INTEGER NOT NULL,
+    Clicks INTEGER NOT NULL,
+    Efficiency REAL NOT NULL,
+    ThreeBvPerSecond REAL NOT NULL,
+    FinishedAt INTEGER NOT NULL
+);
+
+CREATE INDEX PracticeGameByUser ON PracticeGame (UserId, FinishedAt);
+
+-- each player's fastest win on each board; custom boards of different sizes are kept apart
+CREATE TABLE PersonalBest (
+    UserId INTEGER NOT NULL REFERENCES User(Id),
+    Level TEXT NOT NULL,
+    Width INTEGER NOT NULL,
+    Height INTEGER NOT NULL,
+    Mines INTEGER NOT NULL,
+    GameId INTEGER NOT NULL REFERENCES PracticeGame(Id),
+    TimeMs INTEGER NOT NULL,
+    AchievedAt INTEGER NOT NULL,
+    PRIMARY KEY (UserId, Level, Width, Height, Mines)
+);
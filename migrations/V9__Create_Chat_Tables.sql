@@ -0,0 +1,47 @@
+CREATE TABLE ChatMessage (
+    Id INTEGER PRIMARY KEY,
+    -- lobby or match, and the lobby code or match ID
+    RoomKind TEXT NOT NULL,
+    RoomId TEXT NOT NULL,
+    UserId INTEGER NOT NULL REFERENCES User(Id),
+    -- what was sent, after the word filter
+    Text TEXT NOT NULL,
+    -- unix timestamp in milliseconds
+    SentAt INTEGER NOT NULL,
+    DeletedAt INTEGER,
+    DeletedBy INTEGER REFERENCES User(Id)
+);
+
+CREATE INDEX ChatMessageBySentAt ON ChatMessage (SentAt);
+
+CREATE TABLE ChatReport (
+    Id INTEGER PRIMARY KEY,
+    MessageId INTEGER NOT NULL REFERENCES ChatMessage(Id),
+    ReporterId INTEGER NOT NULL REFERENCES User(Id),
+    Reason TEXT NOT NULL,
+    ReportedAt INTEGER NOT NULL,
+    UNIQUE (MessageId, ReporterId)
+);
+
+-- players one player doesn't want to hear from: muted players' messages are hidden from them,
+-- and blocked players can't see their messages either
+CREATE TABLE ChatIgnore (
+    UserId INTEGER NOT NULL REFERENCES User(Id),
+    TargetId INTEGER NOT NULL REFERENCES User(Id),
+    -- mute or block
+    Kind TEXT NOT NULL,
+    CreatedAt INTEGER NOT NULL,
+    PRIMARY KEY (UserId, TargetId)
+);
+
+CREATE INDEX ChatIgnoreByTarget ON ChatIgnore (TargetId);
+
+-- players a moderator has stopped from chatting for a while
+CREATE TABLE ChatMute (
+    UserId INTEGER PRIMARY KEY REFERENCES User(Id),
+    MutedBy INTEGER NOT NULL REFERENCES User(Id),
+    Reason TEXT NOT NULL,
+    -- unix timestamps
+    MutedAt INTEGER NOT NULL,
+    Until INTEGER NOT NULL
+);
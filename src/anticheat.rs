@@ -0,0 +1,213 @@
+//! Running the anti-cheat pipeline over finished matches, and keeping track of who it flagged.
+//! The detectors themselves live in [`crate::domain::anticheat`].
+
+use std::{collections::HashMap, sync::Arc};
+
+use deadpool_sqlite::rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use snafu::ResultExt;
+
+use crate::{
+    db_handle::DbHandle,
+    domain::{
+        anticheat::{AntiCheatError, Finding, MatchEvidence, Pipeline, Review, Verdict},
+        errors::*,
+        replay::Replay,
+        User, UserId,
+    },
+    matches::load_finished_match,
+    session::unix_timestamp,
+};
+
+/// A player the pipeline flagged, for a moderator to look into.
+#[derive(Serialize)]
+pub(crate) struct CheatFlag {
+    pub(crate) id: i64,
+    pub(crate) match_id: i64,
+    pub(crate) user: User,
+    pub(crate) score: f64,
+    pub(crate) flagged_at: i64,
+    /// Everything that added up to the flag.
+    pub(crate) findings: Vec<Finding>,
+}
+
+/// Reviews a finished match in the background. Anything that goes wrong is only logged, since
+/// nobody's waiting on the result.
+pub(crate) fn spawn_match_review(
+    db_handle: DbHandle,
+    pipeline: Arc<Pipeline>,
+    match_id: i64,
+    exempt: Vec<UserId>,
+) {
+    tokio::spawn(async move {
+        match review_match(&db_handle, pipeline, match_id, &exempt).await {
+            Ok(review) if !review.flagged.is_empty() => {
+                tracing::warn!(match_id, flagged = ?review.flagged, "Flagged players for review");
+            }
+            Ok(_) => {}
+            Err(error) => tracing::error!(?error, match_id, "Failed to review a match"),
+        }
+    });
+}
+
+/// Runs the pipeline over a finished match and saves what it found. Players in `exempt`
+/// (bots, say) are left out of it.
+#[tracing::instrument(name = "Reviewing a match", skip(db_handle, pipeline))]
+pub(crate) async fn review_match(
+    db_handle: &DbHandle,
+    pipeline: Arc<Pipeline>,
+    match_id: i64,
+    exempt: &[UserId],
+) -> Result<Review, InnerError> {
+    let (records, _) = load_finished_match(db_handle, match_id).await?;
+    let replay = Replay::from_log(match_id, &records).context(ReplaySnafu)?;
+    let addresses = addresses_of(db_handle, replay.players.clone()).await?;
+    let evidence = MatchEvidence {
+        match_id,
+        replay,
+        addresses,
+    };
+    // Working out how lucky every guess was can take a while on big boards.
+    let mut review = tokio::task::spawn_blocking(move || pipeline.review(&evidence))
+        .await
+        .context(JoinSnafu)?;
+    review
+        .findings
+        .retain(|finding| !exempt.contains(&finding.user_id));
+    review
+        .flagged
+        .retain(|(user_id, _)| !exempt.contains(user_id));
+    let saved = review.clone();
+    db_handle
+        .transaction(move |transaction| {
+            for finding in &saved.findings {
+                transaction.execute(
+                    "INSERT INTO CheatFinding (MatchId, UserId, Detector, Score, Reason)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        match_id,
+                        finding.user_id,
+                        finding.detector,
+                        finding.score,
+                        finding.reason
+                    ],
+                )?;
+            }
+            for (user_id, score) in &saved.flagged {
+                transaction.execute(
+                    "INSERT OR IGNORE INTO CheatFlag (MatchId, UserId, Score, FlaggedAt)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![match_id, user_id, score, unix_timestamp()],
+                )?;
+            }
+            Ok(())
+        })
+        .await?;
+    Ok(review)
+}
+
+/// Every address each of `players` has logged in from.
+async fn addresses_of(
+    db_handle: &DbHandle,
+    players: Vec<UserId>,
+) -> Result<HashMap<UserId, Vec<String>>, InnerError> {
+    db_handle
+        .transaction(move |transaction| {
+            let mut statement = transaction.prepare(
+                "SELECT DISTINCT Address FROM Session WHERE UserId = ?1 AND Address IS NOT NULL",
+            )?;
+            let mut addresses = HashMap::new();
+            for user_id in players {
+                let found = statement
+                    .query_map(params![user_id], |row| row.get(0))?
+                    .collect::<Result<Vec<String>, _>>()?;
+                if !found.is_empty() {
+                    addresses.insert(user_id, found);
+                }
+            }
+            Ok(addresses)
+        })
+        .await
+}
+
+/// Flags nobody has reviewed yet, most suspicious first.
+pub(crate) async fn open_flags(db_handle: &DbHandle) -> Result<Vec<CheatFlag>, InnerError> {
+    db_handle
+        .transaction(|transaction| {
+            let flags = transaction
+                .prepare(
+                    "SELECT CheatFlag.Id, CheatFlag.MatchId, User.Id, User.Username,
+                         CheatFlag.Score, CheatFlag.FlaggedAt
+                     FROM CheatFlag
+                     JOIN User ON User.Id = CheatFlag.UserId
+                     WHERE CheatFlag.ReviewedAt IS NULL
+                     ORDER BY CheatFlag.Score DESC, CheatFlag.Id",
+                )?
+                .query_map([], |row| {
+                    Ok(CheatFlag {
+                        id: row.get(0)?,
+                        match_id: row.get(1)?,
+                        user: User::new(row.get(2)?, row.get(3)?),
+                        score: row.get(4)?,
+                        flagged_at: row.get(5)?,
+                        findings: vec![],
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut statement = transaction.prepare(
+                "SELECT Detector, Score, Reason FROM CheatFinding
+                 WHERE MatchId = ?1 AND UserId = ?2
+                 ORDER BY Score DESC",
+            )?;
+            flags
+                .into_iter()
+                .map(|mut flag| {
+                    let user_id = flag.user.id();
+                    flag.findings = statement
+                        .query_map(params![flag.match_id, user_id], |row| {
+                            Ok(Finding {
+                                user_id,
+                                detector: row.get(0)?,
+                                score: row.get(1)?,
+                                reason: row.get(2)?,
+                            })
+                        })?
+                        .collect::<Result<_, _>>()?;
+                    Ok(flag)
+                })
+                .collect()
+        })
+        .await
+}
+
+/// Records a moderator's verdict on a flag. Each flag can only be reviewed once.
+pub(crate) async fn review_flag(
+    db_handle: &DbHandle,
+    flag_id: i64,
+    moderator: UserId,
+    verdict: Verdict,
+) -> Result<(), InnerError> {
+    let reviewed = db_handle
+        .transaction(move |transaction| {
+            let reviewed_at: Option<Option<i64>> = transaction
+                .query_row(
+                    "SELECT ReviewedAt FROM CheatFlag WHERE Id = ?1",
+                    params![flag_id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            match reviewed_at {
+                None => return Ok(Err(AntiCheatError::FlagNotFound { flag_id })),
+                Some(Some(_)) => return Ok(Err(AntiCheatError::AlreadyReviewed { flag_id })),
+                Some(None) => {}
+            }
+            transaction.execute(
+                "UPDATE CheatFlag SET ReviewedAt = ?1, ReviewedBy = ?2, Verdict = ?3
+                 WHERE Id = ?4",
+                params![unix_timestamp(), moderator, verdict.as_str(), flag_id],
+            )?;
+            Ok(Ok(()))
+        })
+        .await?;
+    reviewed.context(AntiCheatSnafu)
+}
@@ -0,0 +1,58 @@
+//! Accounts for bots to play under. How bots play lives in [`crate::domain::bot`], and they
+//! get into matches through the game server.
+
+use deadpool_sqlite::rusqlite::params;
+
+use crate::{
+    db_handle::DbHandle,
+    domain::{errors::*, User},
+};
+
+/// What every bot's name starts with. Players can't sign up under names like it.
+pub(crate) const BOT_NAME_PREFIX: &str = "Bot ";
+
+/// Whether `username` looks like a bot's name, ignoring case.
+pub(crate) fn is_bot_name(username: &str) -> bool {
+    username
+        .get(..BOT_NAME_PREFIX.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(BOT_NAME_PREFIX))
+}
+
+/// Every bot account there is.
+pub(crate) async fn bot_accounts(db_handle: &DbHandle) -> Result<Vec<User>, InnerError> {
+    db_handle
+        .transaction(|transaction| {
+            transaction
+                .prepare("SELECT Id, Username FROM User WHERE IsBot = 1 ORDER BY Id")?
+                .query_map([], |row| Ok(User::new(row.get(0)?, row.get(1)?)))?
+                .collect()
+        })
+        .await
+}
+
+/// Signs up `count` new bots. They're named after their IDs, and have a password hash that
+/// nothing matches (on top of being left out of logins altogether).
+pub(crate) async fn create_bot_accounts(
+    db_handle: &DbHandle,
+    count: usize,
+) -> Result<Vec<User>, InnerError> {
+    db_handle
+        .transaction(move |transaction| {
+            (0..count)
+                .map(|_| {
+                    transaction.execute(
+                        "INSERT INTO User (Username, PasswordHash, IsBot) VALUES ('', '!', 1)",
+                        [],
+                    )?;
+                    let id = transaction.last_insert_rowid();
+                    let username = format!("{BOT_NAME_PREFIX}{id}");
+                    transaction.execute(
+                        "UPDATE User SET Username = ?1 WHERE Id = ?2",
+                        params![username, id],
+                    )?;
+                    Ok(User::new(id, username))
+                })
+                .collect()
+        })
+        .await
+}
@@ -0,0 +1,394 @@
+//! Storing chat messages, and everything players and moderators do about them. The rules
+//! for what can be said live in [`crate::domain::chat`].
+
+use std::{collections::HashSet, time::Duration};
+
+use actix_web::web;
+
+use deadpool_sqlite::rusqlite::{params, Connection, Error, OptionalExtension};
+use serde::Serialize;
+use snafu::ResultExt;
+
+use crate::{
+    db_handle::DbHandle,
+    domain::{
+        chat::{ChatError, ChatRoom, ChatSettings, IgnoreKind},
+        errors::*,
+        User, UserId,
+    },
+    friends::forget,
+    game_server::GameServer,
+    session::{unix_timestamp, unix_timestamp_millis},
+};
+
+/// Who a player has muted and blocked.
+#[derive(Serialize)]
+pub(crate) struct IgnoreList {
+    pub(crate) muted: Vec<User>,
+    pub(crate) blocked: Vec<User>,
+}
+
+/// A report about a message, for moderators to look into.
+#[derive(Serialize)]
+pub(crate) struct ChatReport {
+    pub(crate) id: i64,
+    pub(crate) message_id: i64,
+    pub(crate) room: Option<ChatRoom>,
+    pub(crate) author: User,
+    pub(crate) text: String,
+    pub(crate) sent_at: i64,
+    pub(crate) deleted: bool,
+    pub(crate) reporter: User,
+    pub(crate) reason: String,
+    pub(crate) reported_at: i64,
+}
+
+/// Saves a message that's about to be sent, returning its ID. Players muted by a moderator
+/// can't send anything.
+pub(crate) async fn save_message(
+    db_handle: &DbHandle,
+    room: &ChatRoom,
+    user_id: UserId,
+    text: &str,
+) -> Result<i64, InnerError> {
+    let (kind, room_id) = room.to_parts();
+    let text = text.to_string();
+    let saved = db_handle
+        .transaction(move |transaction| {
+            if let Some(until) = muted_until(transaction, user_id)? {
+                return Ok(Err(until));
+            }
+            transaction.execute(
+                "INSERT INTO ChatMessage (RoomKind, RoomId, UserId, Text, SentAt)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![kind, room_id, user_id, text, unix_timestamp_millis()],
+            )?;
+            Ok(Ok(transaction.last_insert_rowid()))
+        })
+        .await?;
+    saved
+        .map_err(|until| ChatError::Muted { until })
+        .context(ChatSnafu)
+}
+
+/// When a moderator's mute on a player runs out, if they're muted right now.
+fn muted_until(connection: &Connection, user_id: UserId) -> Result<Option<i64>, Error> {
+    connection
+        .query_row(
+            "SELECT Until FROM ChatMute WHERE UserId = ?1 AND Until > ?2",
+            params![user_id, unix_timestamp()],
+            |row| row.get(0),
+        )
+        .optional()
+}
+
+/// The players who shouldn't see `sender`'s messages: those who muted or blocked them, and
+/// those they blocked.
+pub(crate) async fn hidden_from(
+    db_handle: &DbHandle,
+    sender: UserId,
+) -> Result<HashSet<UserId>, InnerError> {
+    db_handle
+        .transaction(move |transaction| {
+            transaction
+                .prepare(
+                    "SELECT UserId FROM ChatIgnore WHERE TargetId = ?1
+                     UNION
+                     SELECT TargetId FROM ChatIgnore WHERE UserId = ?1 AND Kind = 'block'",
+                )?
+                .query_map(params![sender], |row| row.get(0))?
+                .collect()
+        })
+        .await
+}
+
+/// Mutes or blocks another player, replacing whatever was set for them before. Blocking a
+/// player also ends any friendship with them, so returns whether they were friends.
+pub(crate) async fn ignore(
+    db_handle: &DbHandle,
+    user_id: UserId,
+    target: UserId,
+    kind: IgnoreKind,
+) -> Result<bool, InnerError> {
+    if user_id == target {
+        return Err(ChatError::IgnoringSelf).context(ChatSnafu);
+    }
+    let ignored = db_handle
+        .transaction(move |transaction| {
+            if !user_exists(transaction, target)? {
+                return Ok(None);
+            }
+            transaction.execute(
+                "INSERT OR REPLACE INTO ChatIgnore (UserId, TargetId, Kind, CreatedAt)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![user_id, target, kind.as_str(), unix_timestamp()],
+            )?;
+            match kind {
+                IgnoreKind::Block => forget(transaction, user_id, target).map(Some),
+                IgnoreKind::Mute => Ok(Some(false)),
+            }
+        })
+        .await?;
+    ignored
+        .ok_or(ChatError::PlayerNotFound { user_id: target })
+        .context(ChatSnafu)
+}
+
+/// Takes back a mute or a block. Taking back a block leaves the player muted.
+pub(crate) async fn unignore(
+    db_handle: &DbHandle,
+    user_id: UserId,
+    target: UserId,
+    kind: IgnoreKind,
+) -> Result<(), InnerError> {
+    db_handle
+        .transaction(move |transaction| {
+            match kind {
+                IgnoreKind::Mute => transaction.execute(
+                    "DELETE FROM ChatIgnore WHERE UserId = ?1 AND TargetId = ?2",
+                    params![user_id, target],
+                )?,
+                IgnoreKind::Block => transaction.execute(
+                    "UPDATE ChatIgnore SET Kind = 'mute'
+                     WHERE UserId = ?1 AND TargetId = ?2 AND Kind = 'block'",
+                    params![user_id, target],
+                )?,
+            };
+            Ok(())
+        })
+        .await
+}
+
+pub(crate) async fn ignore_list(
+    db_handle: &DbHandle,
+    user_id: UserId,
+) -> Result<IgnoreList, InnerError> {
+    let ignored: Vec<(String, User)> = db_handle
+        .transaction(move |transaction| {
+            transaction
+                .prepare(
+                    "SELECT ChatIgnore.Kind, User.Id, User.Username
+                     FROM ChatIgnore JOIN User ON User.Id = ChatIgnore.TargetId
+                     WHERE ChatIgnore.UserId = ?1
+                     ORDER BY User.Username",
+                )?
+                .query_map(params![user_id], |row| {
+                    Ok((row.get(0)?, User::new(row.get(1)?, row.get(2)?)))
+                })?
+                .collect()
+        })
+        .await?;
+    let (blocked, muted): (Vec<_>, Vec<_>) = ignored
+        .into_iter()
+        .partition(|(kind, _)| kind == IgnoreKind::Block.as_str());
+    Ok(IgnoreList {
+        muted: muted.into_iter().map(|(_, user)| user).collect(),
+        blocked: blocked.into_iter().map(|(_, user)| user).collect(),
+    })
+}
+
+/// Reports a message to the moderators. Reporting the same message twice does nothing.
+pub(crate) async fn report_message(
+    db_handle: &DbHandle,
+    message_id: i64,
+    reporter: UserId,
+    reason: String,
+) -> Result<(), InnerError> {
+    let found = db_handle
+        .transaction(move |transaction| {
+            if message_room(transaction, message_id)?.is_none() {
+                return Ok(false);
+            }
+            transaction.execute(
+                "INSERT OR IGNORE INTO ChatReport (MessageId, ReporterId, Reason, ReportedAt)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![message_id, reporter, reason, unix_timestamp()],
+            )?;
+            Ok(true)
+        })
+        .await?;
+    if !found {
+        return Err(ChatError::MessageNotFound { message_id }).context(ChatSnafu);
+    }
+    Ok(())
+}
+
+/// Every report about a message that's still around, the newest first.
+pub(crate) async fn reports(db_handle: &DbHandle) -> Result<Vec<ChatReport>, InnerError> {
+    db_handle
+        .transaction(|transaction| {
+            transaction
+                .prepare(
+                    "SELECT ChatReport.Id, ChatMessage.Id, ChatMessage.RoomKind,
+                         ChatMessage.RoomId, Author.Id, Author.Username, ChatMessage.Text,
+                         ChatMessage.SentAt, ChatMessage.DeletedAt IS NOT NULL, Reporter.Id,
+                         Reporter.Username, ChatReport.Reason, ChatReport.ReportedAt
+                     FROM ChatReport
+                     JOIN ChatMessage ON ChatMessage.Id = ChatReport.MessageId
+                     JOIN User AS Author ON Author.Id = ChatMessage.UserId
+                     JOIN User AS Reporter ON Reporter.Id = ChatReport.ReporterId
+                     ORDER BY ChatReport.ReportedAt DESC, ChatReport.Id DESC",
+                )?
+                .query_map([], |row| {
+                    Ok(ChatReport {
+                        id: row.get(0)?,
+                        message_id: row.get(1)?,
+                        room: ChatRoom::from_parts(
+                            &row.get::<_, String>(2)?,
+                            &row.get::<_, String>(3)?,
+                        ),
+                        author: User::new(row.get(4)?, row.get(5)?),
+                        text: row.get(6)?,
+                        sent_at: row.get(7)?,
+                        deleted: row.get(8)?,
+                        reporter: User::new(row.get(9)?, row.get(10)?),
+                        reason: row.get(11)?,
+                        reported_at: row.get(12)?,
+                    })
+                })?
+                .collect()
+        })
+        .await
+}
+
+/// Marks a message as deleted by a moderator. Returns the room it was sent in, so the
+/// people there can be told.
+pub(crate) async fn delete_message(
+    db_handle: &DbHandle,
+    message_id: i64,
+    moderator: UserId,
+) -> Result<ChatRoom, InnerError> {
+    let room = db_handle
+        .transaction(move |transaction| {
+            let room = message_room(transaction, message_id)?;
+            transaction.execute(
+                "UPDATE ChatMessage SET DeletedAt = ?1, DeletedBy = ?2
+                 WHERE Id = ?3 AND DeletedAt IS NULL",
+                params![unix_timestamp(), moderator, message_id],
+            )?;
+            Ok(room)
+        })
+        .await?;
+    room.ok_or(ChatError::MessageNotFound { message_id })
+        .context(ChatSnafu)
+}
+
+fn message_room(connection: &Connection, message_id: i64) -> Result<Option<ChatRoom>, Error> {
+    Ok(connection
+        .query_row(
+            "SELECT RoomKind, RoomId FROM ChatMessage WHERE Id = ?1",
+            params![message_id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+        )
+        .optional()?
+        .and_then(|(kind, id)| ChatRoom::from_parts(&kind, &id)))
+}
+
+/// Stops a player from chatting until `until`, a unix timestamp.
+pub(crate) async fn add_chat_mute(
+    db_handle: &DbHandle,
+    user_id: UserId,
+    moderator: UserId,
+    reason: String,
+    until: i64,
+) -> Result<(), InnerError> {
+    let found = db_handle
+        .transaction(move |transaction| {
+            if !user_exists(transaction, user_id)? {
+                return Ok(false);
+            }
+            transaction.execute(
+                "INSERT OR REPLACE INTO ChatMute (UserId, MutedBy, Reason, MutedAt, Until)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![user_id, moderator, reason, unix_timestamp(), until],
+            )?;
+            Ok(true)
+        })
+        .await?;
+    if !found {
+        return Err(ChatError::PlayerNotFound { user_id }).context(ChatSnafu);
+    }
+    Ok(())
+}
+
+pub(crate) async fn remove_chat_mute(
+    db_handle: &DbHandle,
+    user_id: UserId,
+) -> Result<(), InnerError> {
+    db_handle
+        .transaction(move |transaction| {
+            transaction.execute("DELETE FROM ChatMute WHERE UserId = ?1", params![user_id])?;
+            Ok(())
+        })
+        .await
+}
+
+/// Lets the player called `username` moderate. Returns whether there's anyone by that name.
+#[tracing::instrument(name = "Making a player a moderator", skip(db_handle))]
+pub(crate) async fn grant_moderator(
+    db_handle: &DbHandle,
+    username: &str,
+) -> Result<bool, InnerError> {
+    let username = username.to_string();
+    db_handle
+        .transaction(move |transaction| {
+            let updated = transaction.execute(
+                "UPDATE User SET IsModerator = 1 WHERE Username = ?1 AND IsBot = 0",
+                params![username],
+            )?;
+            Ok(updated > 0)
+        })
+        .await
+}
+
+fn user_exists(connection: &Connection, user_id: UserId) -> Result<bool, Error> {
+    Ok(connection
+        .query_row("SELECT 1 FROM User WHERE Id = ?1", params![user_id], |_| {
+            Ok(())
+        })
+        .optional()?
+        .is_some())
+}
+
+/// Deletes messages (and reports about them) that are older than the retention window.
+#[tracing::instrument(
+    name = "Cleaning up old chat messages",
+    level = "debug",
+    skip(db_handle, settings)
+)]
+pub(crate) async fn purge_old_messages(
+    db_handle: &DbHandle,
+    settings: &ChatSettings,
+) -> Result<(), InnerError> {
+    let cutoff = unix_timestamp_millis() - settings.retention.as_millis() as i64;
+    db_handle
+        .transaction(move |transaction| {
+            transaction.execute(
+                "DELETE FROM ChatReport WHERE MessageId IN
+                     (SELECT Id FROM ChatMessage WHERE SentAt < ?1)",
+                params![cutoff],
+            )?;
+            transaction.execute("DELETE FROM ChatMessage WHERE SentAt < ?1", params![cutoff])?;
+            transaction.execute(
+                "DELETE FROM ChatMute WHERE Until <= ?1",
+                params![unix_timestamp()],
+            )?;
+            Ok(())
+        })
+        .await
+}
+
+/// Cleans up old messages, and forgets about players who haven't chatted in a while, when the
+/// server starts and then every hour for as long as it's up.
+pub(crate) fn spawn_chat_cleanup(db_handle: DbHandle, server: web::Data<GameServer>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+        loop {
+            interval.tick().await;
+            server.forget_quiet_chatters();
+            if let Err(error) = purge_old_messages(&db_handle, server.chat_settings()).await {
+                tracing::error!(?error, "Cleaning up old chat messages failed");
+            }
+        }
+    });
+}
@@ -1,6 +1,229 @@
-use std::{net::TcpListener, path::PathBuf};
+use std::{
+    env,
+    net::{IpAddr, TcpListener},
+    path::PathBuf,
+    time::Duration,
+};
+
+use snafu::{whatever, Whatever};
+
+use crate::{
+    domain::{
+        anticheat::AntiCheatSettings,
+        battle_royale::{BattleRoyaleSettings, ReconnectSettings},
+        bot::BotSettings,
+        chat::ChatSettings,
+        daily::DailySettings,
+        leaderboard::LeaderboardSettings,
+        lobby::MatchmakingSettings,
+        rating::RatingSettings,
+        spectate::SpectatorSettings,
+        tournament::TournamentSettings,
+    },
+    telemetry::LogFilter,
+};
 
 pub struct ApplicationConfiguration<Path: Into<PathBuf>> {
     pub listener: TcpListener,
     pub db_path: Path,
+    pub matchmaking: MatchmakingSettings,
+    pub battle_royale: BattleRoyaleSettings,
+    pub reconnects: ReconnectSettings,
+    pub ratings: RatingSettings,
+    pub leaderboards: LeaderboardSettings,
+    pub daily: DailySettings,
+    pub spectators: SpectatorSettings,
+    pub chat: ChatSettings,
+    pub bots: BotSettings,
+    pub anticheat: AntiCheatSettings,
+    pub tournaments: TournamentSettings,
+    pub websocket: WebSocketSettings,
+    pub events: EventStreamSettings,
+    pub proxies: ProxySettings,
+    pub admin: AdminSettings,
+    /// Changes what's logged while the server's running. Comes from
+    /// [`get_subscriber`](crate::telemetry::get_subscriber).
+    pub log_filter: LogFilter,
+}
+
+/// Settings for the real-time game channel (`/ws`).
+#[derive(Clone, Debug)]
+pub struct WebSocketSettings {
+    /// How often the server pings each client.
+    pub heartbeat_interval: Duration,
+    /// Clients that haven't sent anything (including pongs) for this long are disconnected.
+    pub client_timeout: Duration,
+    /// How many events can be waiting to be written to a single client before it's
+    /// considered too slow and disconnected.
+    pub send_buffer: usize,
+    /// How many unacknowledged events are kept per player for resuming after a reconnect.
+    pub replay_limit: usize,
+    /// How long a player's unacknowledged events are kept after they disconnect. Coming back
+    /// any later means starting over with a full refresh.
+    pub replay_window: Duration,
+}
+
+impl Default for WebSocketSettings {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval: Duration::from_secs(5),
+            client_timeout: Duration::from_secs(15),
+            send_buffer: 256,
+            replay_limit: 1024,
+            replay_window: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// Settings for the notification stream (`/events`).
+#[derive(Clone, Debug)]
+pub struct EventStreamSettings {
+    /// How often a comment is sent down a quiet stream, so proxies don't time it out and
+    /// clients that went away are noticed.
+    pub keep_alive: Duration,
+    /// How many notifications can be waiting to be written to a single stream before it's
+    /// considered too slow and closed.
+    pub send_buffer: usize,
+}
+
+impl Default for EventStreamSettings {
+    fn default() -> Self {
+        Self {
+            keep_alive: Duration::from_secs(15),
+            send_buffer: 64,
+        }
+    }
+}
+
+/// Settings for running behind a reverse proxy.
+#[derive(Clone, Debug, Default)]
+pub struct ProxySettings {
+    /// The addresses of the proxies in front of the server. A player's address is only taken
+    /// from `X-Forwarded-For` when the request came from one of these, since anyone else
+    /// could write whatever they like in it.
+    pub trusted: Vec<IpAddr>,
+}
+
+/// Settings for the endpoints operators use: the Prometheus endpoint (`/metrics`) and the log
+/// filter (`/admin/log-filter`).
+#[derive(Debug, Default)]
+pub struct AdminSettings {
+    /// If this is set, requests need an `Authorization: Bearer <token>` header with it. The log
+    /// filter can't be changed at all without one.
+    pub token: Option<String>,
+    /// If this is set, the endpoints are served on this listener instead of alongside
+    /// everything else, so they can be kept off the public network.
+    pub listener: Option<TcpListener>,
+}
+
+/// How the server's spans leave the process. This is read when the tracing subscriber is
+/// built, which happens before the server starts, so it isn't part of
+/// [`ApplicationConfiguration`].
+#[derive(Clone, Debug, Default)]
+pub struct TelemetrySettings {
+    pub format: LogFormat,
+    /// If this is set, logs are written to files as well as the usual sink.
+    pub file: Option<LogFileSettings>,
+    /// If this is set, spans are exported to an OpenTelemetry collector as well as logged.
+    pub otlp: Option<OtlpSettings>,
+}
+
+impl TelemetrySettings {
+    /// Reads the settings from the environment:
+    ///
+    /// - `LOG_FORMAT` is `bunyan` (the default), `pretty` or `logfmt`.
+    /// - `LOG_DIRECTORY` turns log files on. They're named after `LOG_FILE_PREFIX` (`app.log`
+    ///   if it isn't set) and a new one's started every `LOG_ROTATION`, which is `minutely`,
+    ///   `hourly`, `daily` (the default) or `never`.
+    /// - `OTEL_EXPORTER_OTLP_ENDPOINT` turns span export on, over
+    ///   `OTEL_EXPORTER_OTLP_PROTOCOL`, which is `grpc` or `http/protobuf` (the default), as
+    ///   for the OpenTelemetry SDKs.
+    pub fn from_env() -> Result<Self, Whatever> {
+        let format = match var("LOG_FORMAT").as_deref() {
+            None | Some("bunyan") => LogFormat::Bunyan,
+            Some("pretty") => LogFormat::Pretty,
+            Some("logfmt") => LogFormat::Logfmt,
+            Some(other) => whatever!("Unknown LOG_FORMAT {:?}", other),
+        };
+        let file = match var("LOG_DIRECTORY") {
+            Some(directory) => Some(LogFileSettings {
+                directory: directory.into(),
+                prefix: var("LOG_FILE_PREFIX").unwrap_or_else(|| String::from("app.log")),
+                rotation: match var("LOG_ROTATION").as_deref() {
+                    Some("minutely") => LogRotation::Minutely,
+                    Some("hourly") => LogRotation::Hourly,
+                    None | Some("daily") => LogRotation::Daily,
+                    Some("never") => LogRotation::Never,
+                    Some(other) => whatever!("Unknown LOG_ROTATION {:?}", other),
+                },
+            }),
+            None => None,
+        };
+        let otlp = match var("OTEL_EXPORTER_OTLP_ENDPOINT") {
+            Some(endpoint) => Some(OtlpSettings {
+                endpoint,
+                protocol: match var("OTEL_EXPORTER_OTLP_PROTOCOL").as_deref() {
+                    Some("grpc") => OtlpProtocol::Grpc,
+                    None | Some("http/protobuf") => OtlpProtocol::Http,
+                    Some(other) => {
+                        whatever!("Unknown OTEL_EXPORTER_OTLP_PROTOCOL {:?}", other)
+                    }
+                },
+            }),
+            None => None,
+        };
+        Ok(Self { format, file, otlp })
+    }
+}
+
+/// An environment variable, if it's set to something.
+fn var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// How log lines are written out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One bunyan JSON object per line, for feeding to log tooling.
+    #[default]
+    Bunyan,
+    /// Spread over several lines and coloured, for reading in a terminal while developing.
+    Pretty,
+    /// One line of `key=value` pairs per event.
+    Logfmt,
+}
+
+/// Where log files go, and how often a new one is started.
+#[derive(Clone, Debug)]
+pub struct LogFileSettings {
+    pub directory: PathBuf,
+    /// Each file is named this, followed by the date (and time, if it rolls over more than
+    /// daily) it was started.
+    pub prefix: String,
+    pub rotation: LogRotation,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    Daily,
+    /// Everything goes in one file, named just the prefix.
+    Never,
+}
+
+/// Where to export spans to over OTLP.
+#[derive(Clone, Debug)]
+pub struct OtlpSettings {
+    /// The collector's base URL, like `http://localhost:4317` for gRPC or
+    /// `http://localhost:4318` for HTTP.
+    pub endpoint: String,
+    pub protocol: OtlpProtocol,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OtlpProtocol {
+    Grpc,
+    /// Protobuf over HTTP. Spans are posted to `/v1/traces` under the endpoint.
+    Http,
 }
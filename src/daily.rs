@@ -0,0 +1,676 @@
+//! Storing and playing the daily challenge. The rules live in [`crate::domain::daily`].
+
+use std::time::Duration;
+
+use actix_web::web;
+use deadpool_sqlite::rusqlite::{params, Connection, Error, OptionalExtension};
+use serde::Serialize;
+use snafu::ResultExt;
+use time::Date;
+
+use crate::{
+    db_handle::DbHandle,
+    domain::{
+        battle_royale::{Action, BattleRoyale},
+        daily::{format_date, parse_date, DailyChallenge, DailyError, DailySettings, Streak},
+        errors::*,
+        minesweeper::{
+            generate_no_guess, BoardConfig, BoardView, CellView, GameStatus, GeneratorOptions,
+            MineLayout, Position,
+        },
+        replay::{LogEntry, MatchRecord, Replay},
+        UserId,
+    },
+    game_server::insert_records,
+    session::{unix_timestamp, unix_timestamp_millis},
+};
+
+/// A challenge along with its board.
+#[derive(Clone, Debug)]
+pub(crate) struct StoredChallenge {
+    pub(crate) challenge: DailyChallenge,
+    pub(crate) mines: Vec<Position>,
+    pub(crate) opens_at: i64,
+    pub(crate) closes_at: i64,
+}
+
+/// A player's attempt at a challenge, as they see it.
+#[derive(Serialize)]
+pub(crate) struct AttemptView {
+    pub(crate) date: String,
+    pub(crate) match_id: i64,
+    pub(crate) board: BoardView,
+    /// How long the attempt has taken so far, or took in total once it's over.
+    pub(crate) elapsed_ms: i64,
+    /// Whether the board was cleared, once the attempt is over.
+    pub(crate) cleared: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct StreakView {
+    pub(crate) current: u32,
+    pub(crate) best: u32,
+    pub(crate) last_cleared: Option<String>,
+}
+
+impl From<Streak> for StreakView {
+    fn from(streak: Streak) -> Self {
+        Self {
+            current: streak.current,
+            best: streak.best,
+            last_cleared: streak.last_cleared.map(format_date),
+        }
+    }
+}
+
+/// A cleared attempt on a day's leaderboard.
+#[derive(Clone, Serialize)]
+pub(crate) struct DailyResult {
+    pub(crate) rank: usize,
+    pub(crate) user_id: UserId,
+    pub(crate) username: String,
+    pub(crate) time_ms: i64,
+    pub(crate) match_id: i64,
+}
+
+/// How a past challenge went.
+#[derive(Serialize)]
+pub(crate) struct DailySummary {
+    pub(crate) date: String,
+    pub(crate) attempts: u32,
+    pub(crate) clears: u32,
+}
+
+/// The state of an attempt that's stored in `DailyAttempt`.
+struct AttemptRow {
+    match_id: i64,
+    started_at: i64,
+    finished_at: Option<i64>,
+    cleared: Option<bool>,
+}
+
+/// The challenge for `date`, generating and saving its board if nobody has asked for it yet.
+#[tracing::instrument(name = "Getting a daily challenge", skip(db_handle, settings), fields(date = %format_date(date)))]
+pub(crate) async fn ensure_challenge(
+    db_handle: &DbHandle,
+    settings: &DailySettings,
+    date: Date,
+) -> Result<StoredChallenge, InnerError> {
+    if let Some(stored) = load_challenge(db_handle, date).await? {
+        return Ok(stored);
+    }
+    let challenge = DailyChallenge::new(date, settings.board);
+    let layout = {
+        let challenge = challenge.clone();
+        tokio::task::spawn_blocking(move || {
+            match generate_no_guess(
+                challenge.config,
+                challenge.seed,
+                challenge.start,
+                &GeneratorOptions::default(),
+            ) {
+                Ok(generated) => generated.layout,
+                Err(error) => {
+                    tracing::warn!(%error, "Falling back to a random board");
+                    MineLayout::generate(challenge.config, challenge.seed, challenge.start)
+                        .expect("the start position is on the board")
+                }
+            }
+        })
+        .await
+        .context(JoinSnafu)?
+    };
+    let mines = serde_json::to_string(&layout.mine_positions()).expect("positions serialize");
+    let (opens_at, closes_at) = (settings.opens_at(date), settings.closes_at(date));
+    // Someone else may have generated it in the meantime, in which case theirs wins.
+    db_handle
+        .transaction(move |transaction| {
+            transaction.execute(
+                "INSERT OR IGNORE INTO DailyChallenge
+                     (Date, Seed, Width, Height, StartX, StartY, Mines, OpensAt, ClosesAt)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    format_date(date),
+                    challenge.seed as i64,
+                    challenge.config.width(),
+                    challenge.config.height(),
+                    challenge.start.x,
+                    challenge.start.y,
+                    mines,
+                    opens_at,
+                    closes_at,
+                ],
+            )
+        })
+        .await?;
+    Ok(load_challenge(db_handle, date)
+        .await?
+        .expect("the challenge was just saved"))
+}
+
+async fn load_challenge(
+    db_handle: &DbHandle,
+    date: Date,
+) -> Result<Option<StoredChallenge>, InnerError> {
+    let row = db_handle
+        .query_row(
+            "SELECT Seed, Width, Height, StartX, StartY, Mines, OpensAt, ClosesAt
+             FROM DailyChallenge WHERE Date = ?1",
+            [format_date(date)],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, usize>(1)?,
+                    row.get::<_, usize>(2)?,
+                    Position::new(row.get(3)?, row.get(4)?),
+                    row.get::<_, String>(5)?,
+                    row.get::<_, i64>(6)?,
+                    row.get::<_, i64>(7)?,
+                ))
+            },
+        )
+        .await?;
+    let (seed, width, height, start, mines, opens_at, closes_at) = match row {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+    let mines: Vec<Position> = serde_json::from_str(&mines).context(MatchLogSnafu)?;
+    let config =
+        BoardConfig::new(width, height, mines.len()).expect("only valid challenges are saved");
+    Ok(Some(StoredChallenge {
+        challenge: DailyChallenge {
+            date,
+            seed: seed as u64,
+            config,
+            start,
+        },
+        mines,
+        opens_at,
+        closes_at,
+    }))
+}
+
+/// Starts `user_id`'s one attempt at today's challenge.
+#[tracing::instrument(name = "Starting a daily attempt", skip(db_handle, settings))]
+pub(crate) async fn start_attempt(
+    db_handle: &DbHandle,
+    settings: &DailySettings,
+    user_id: UserId,
+) -> Result<AttemptView, InnerError> {
+    let date = settings.date_at(unix_timestamp());
+    let stored = ensure_challenge(db_handle, settings, date).await?;
+    let challenge = stored.challenge.clone();
+    let started = LogEntry::Started {
+        width: challenge.config.width(),
+        height: challenge.config.height(),
+        topology: challenge.config.topology(),
+        mines: stored.mines.clone(),
+        start: challenge.start,
+        players: vec![user_id],
+        settings: DailyChallenge::match_settings(),
+    };
+    let day = format_date(date);
+    let match_id = db_handle
+        .transaction(move |transaction| {
+            if load_attempt(transaction, &day, user_id)?.is_some() {
+                return Ok(Err(DailyError::AlreadyAttempted { date: day }));
+            }
+            transaction.execute(
+                "INSERT INTO GameMatch (LobbyCode, Mode, Width, Height, Mines, Seed, StartedAt)
+                 VALUES (?1, 'daily', ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    day,
+                    challenge.config.width(),
+                    challenge.config.height(),
+                    challenge.config.mines(),
+                    challenge.seed as i64,
+                    unix_timestamp(),
+                ],
+            )?;
+            let match_id = transaction.last_insert_rowid();
+            transaction.execute(
+                "INSERT INTO GameMatchPlayer (MatchId, UserId) VALUES (?1, ?2)",
+                params![match_id, user_id],
+            )?;
+            insert_records(
+                transaction,
+                match_id,
+                &[MatchRecord {
+                    seq: 0,
+                    tick: 0,
+                    entry: started,
+                }],
+            )?;
+            transaction.execute(
+                "INSERT INTO DailyAttempt (Date, UserId, MatchId, StartedAt)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![day, user_id, match_id, unix_timestamp_millis()],
+            )?;
+            Ok(Ok(match_id))
+        })
+        .await?
+        .context(DailySnafu)?;
+    let layout = MineLayout::from_positions_with_topology(
+        stored.challenge.config.width(),
+        stored.challenge.config.height(),
+        stored.challenge.config.topology(),
+        &stored.mines,
+    )
+    .expect("only valid challenges are saved");
+    let engine = BattleRoyale::new(
+        layout,
+        stored.challenge.start,
+        &[user_id],
+        DailyChallenge::match_settings(),
+    )
+    .expect("the start position is on the board");
+    Ok(AttemptView {
+        date: format_date(date),
+        match_id,
+        board: engine.view(user_id).expect("the player is in the match"),
+        elapsed_ms: 0,
+        cleared: None,
+    })
+}
+
+/// Where `user_id`'s attempt at today's challenge is at.
+pub(crate) async fn current_attempt(
+    db_handle: &DbHandle,
+    settings: &DailySettings,
+    user_id: UserId,
+) -> Result<AttemptView, InnerError> {
+    let date = settings.date_at(unix_timestamp());
+    let day = format_date(date);
+    let (attempt, records) = db_handle
+        .transaction(move |transaction| {
+            let attempt = match load_attempt(transaction, &day, user_id)? {
+                Some(attempt) => attempt,
+                None => return Ok(None),
+            };
+            let records = load_records(transaction, attempt.match_id)?;
+            Ok(Some((attempt, records)))
+        })
+        .await?
+        .ok_or(DailyError::NoAttempt)
+        .context(DailySnafu)?;
+    let engine = resume(attempt.match_id, &records)?;
+    Ok(AttemptView {
+        date: format_date(date),
+        match_id: attempt.match_id,
+        board: engine.view(user_id).expect("the player is in the match"),
+        elapsed_ms: attempt.finished_at.unwrap_or_else(unix_timestamp_millis) - attempt.started_at,
+        cleared: attempt.cleared,
+    })
+}
+
+/// Plays one of `user_id`'s moves in their attempt at today's challenge. Attempts aren't kept
+/// in memory: each move picks the attempt back up from its log.
+#[tracing::instrument(name = "Playing a daily move", skip(db_handle, settings))]
+pub(crate) async fn play_move(
+    db_handle: &DbHandle,
+    settings: &DailySettings,
+    user_id: UserId,
+    action: Action,
+) -> Result<AttemptView, InnerError> {
+    let date = settings.date_at(unix_timestamp());
+    let day = format_date(date);
+    let tick_length = DailyChallenge::match_settings().tick_length.as_millis() as i64;
+    let view = db_handle
+        .transaction(move |transaction| {
+            let attempt = match load_attempt(transaction, &day, user_id)? {
+                Some(attempt) => attempt,
+                None => {
+                    return Ok(Err(InnerError::DailyError {
+                        source: DailyError::NoAttempt,
+                    }))
+                }
+            };
+            if attempt.finished_at.is_some() {
+                return Ok(Err(InnerError::DailyError {
+                    source: DailyError::AttemptOver,
+                }));
+            }
+            let records = load_records(transaction, attempt.match_id)?;
+            let mut engine = match resume(attempt.match_id, &records) {
+                Ok(engine) => engine,
+                Err(error) => return Ok(Err(error)),
+            };
+            let now = unix_timestamp_millis();
+            let elapsed_ms = now - attempt.started_at;
+            let tick = (elapsed_ms / tick_length).max(0) as u64;
+            while engine.current_tick() < tick {
+                engine.tick();
+            }
+            let events = match engine.apply(user_id, action) {
+                Ok(events) => events,
+                Err(source) => {
+                    return Ok(Err(InnerError::DailyError {
+                        source: DailyError::InvalidMove { source },
+                    }))
+                }
+            };
+            let mut seq = records.len() as u64;
+            let mut entries = vec![LogEntry::Action { user_id, action }];
+            entries.extend(events.into_iter().map(|event| LogEntry::Event { event }));
+            let new_records: Vec<MatchRecord> = entries
+                .into_iter()
+                .map(|entry| {
+                    seq += 1;
+                    MatchRecord {
+                        seq: seq - 1,
+                        tick,
+                        entry,
+                    }
+                })
+                .collect();
+            insert_records(transaction, attempt.match_id, &new_records)?;
+            let board = engine.view(user_id).expect("the player is in the match");
+            let revealed = board
+                .cells
+                .iter()
+                .filter(|cell| matches!(cell, CellView::Revealed(_)))
+                .count();
+            transaction.execute(
+                "UPDATE DailyAttempt SET Revealed = ?1 WHERE Date = ?2 AND UserId = ?3",
+                params![revealed, day, user_id],
+            )?;
+            let cleared = match board.status {
+                GameStatus::Won => Some(true),
+                GameStatus::Lost { .. } => Some(false),
+                GameStatus::Ready | GameStatus::Playing => None,
+            };
+            if let Some(cleared) = cleared {
+                finish_attempt(transaction, &day, user_id, attempt.match_id, now, cleared)?;
+                if cleared {
+                    record_clear(transaction, user_id, date)?;
+                }
+            }
+            Ok(Ok(AttemptView {
+                date: day,
+                match_id: attempt.match_id,
+                board,
+                elapsed_ms,
+                cleared,
+            }))
+        })
+        .await??;
+    Ok(view)
+}
+
+fn load_attempt(
+    connection: &Connection,
+    day: &str,
+    user_id: UserId,
+) -> Result<Option<AttemptRow>, Error> {
+    connection
+        .query_row(
+            "SELECT MatchId, StartedAt, FinishedAt, Cleared FROM DailyAttempt
+             WHERE Date = ?1 AND UserId = ?2",
+            params![day, user_id],
+            |row| {
+                Ok(AttemptRow {
+                    match_id: row.get(0)?,
+                    started_at: row.get(1)?,
+                    finished_at: row.get(2)?,
+                    cleared: row.get(3)?,
+                })
+            },
+        )
+        .optional()
+}
+
+/// A match's log, still as JSON.
+fn load_records(connection: &Connection, match_id: i64) -> Result<Vec<(i64, String)>, Error> {
+    connection
+        .prepare("SELECT Tick, Payload FROM GameMatchEvent WHERE MatchId = ?1 ORDER BY Seq")?
+        .query_map(params![match_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect()
+}
+
+/// Picks an attempt back up from its log.
+fn resume(match_id: i64, records: &[(i64, String)]) -> Result<BattleRoyale, InnerError> {
+    let records = records
+        .iter()
+        .enumerate()
+        .map(|(seq, (tick, payload))| {
+            Ok(MatchRecord {
+                seq: seq as u64,
+                tick: *tick as u64,
+                entry: serde_json::from_str(payload).context(MatchLogSnafu)?,
+            })
+        })
+        .collect::<Result<Vec<_>, InnerError>>()?;
+    Replay::from_log(match_id, &records)
+        .and_then(|replay| replay.resume())
+        .context(ReplaySnafu)
+}
+
+fn finish_attempt(
+    connection: &Connection,
+    day: &str,
+    user_id: UserId,
+    match_id: i64,
+    finished_at: i64,
+    cleared: bool,
+) -> Result<(), Error> {
+    connection.execute(
+        "UPDATE DailyAttempt SET FinishedAt = ?1, Cleared = ?2 WHERE Date = ?3 AND UserId = ?4",
+        params![finished_at, cleared, day, user_id],
+    )?;
+    connection.execute(
+        "UPDATE GameMatch SET FinishedAt = ?1 WHERE Id = ?2",
+        params![finished_at / 1000, match_id],
+    )?;
+    connection.execute(
+        "UPDATE GameMatchPlayer SET Placement = 1 WHERE MatchId = ?1",
+        params![match_id],
+    )?;
+    Ok(())
+}
+
+fn load_streak(connection: &Connection, user_id: UserId) -> Result<Streak, Error> {
+    Ok(connection
+        .query_row(
+            "SELECT Current, Best, LastCleared FROM DailyStreak WHERE UserId = ?1",
+            params![user_id],
+            |row| {
+                Ok(Streak {
+                    current: row.get(0)?,
+                    best: row.get(1)?,
+                    last_cleared: row
+                        .get::<_, Option<String>>(2)?
+                        .as_deref()
+                        .and_then(parse_date),
+                })
+            },
+        )
+        .optional()?
+        .unwrap_or_default())
+}
+
+fn record_clear(connection: &Connection, user_id: UserId, date: Date) -> Result<(), Error> {
+    let streak = load_streak(connection, user_id)?.cleared(date);
+    connection.execute(
+        "INSERT INTO DailyStreak (UserId, Current, Best, LastCleared) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (UserId) DO UPDATE SET
+             Current = excluded.Current,
+             Best = excluded.Best,
+             LastCleared = excluded.LastCleared",
+        params![
+            user_id,
+            streak.current,
+            streak.best,
+            streak.last_cleared.map(format_date),
+        ],
+    )?;
+    Ok(())
+}
+
+/// `user_id`'s streak as it stands today.
+pub(crate) async fn streak_of(
+    db_handle: &DbHandle,
+    settings: &DailySettings,
+    user_id: UserId,
+) -> Result<Streak, InnerError> {
+    let today = settings.date_at(unix_timestamp());
+    db_handle
+        .transaction(move |transaction| load_streak(transaction, user_id))
+        .await
+        .map(|streak| streak.as_of(today))
+}
+
+/// Everyone who cleared the challenge for `date`, fastest first.
+pub(crate) async fn daily_leaderboard(
+    db_handle: &DbHandle,
+    date: Date,
+    limit: usize,
+) -> Result<Vec<DailyResult>, InnerError> {
+    let day = format_date(date);
+    let results = db_handle
+        .transaction(move |transaction| {
+            let exists = transaction
+                .query_row(
+                    "SELECT 1 FROM DailyChallenge WHERE Date = ?1",
+                    params![day],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            if !exists {
+                return Ok(None);
+            }
+            transaction
+                .prepare(
+                    "SELECT DailyAttempt.UserId, User.Username,
+                         DailyAttempt.FinishedAt - DailyAttempt.StartedAt, DailyAttempt.MatchId
+                     FROM DailyAttempt JOIN User ON User.Id = DailyAttempt.UserId
+                     WHERE DailyAttempt.Date = ?1 AND DailyAttempt.Cleared = 1
+                     ORDER BY DailyAttempt.FinishedAt - DailyAttempt.StartedAt,
+                         DailyAttempt.FinishedAt
+                     LIMIT ?2",
+                )?
+                .query_map(params![day, limit], |row| {
+                    Ok(DailyResult {
+                        rank: 0,
+                        user_id: row.get(0)?,
+                        username: row.get(1)?,
+                        time_ms: row.get(2)?,
+                        match_id: row.get(3)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()
+                .map(Some)
+        })
+        .await?
+        .ok_or_else(|| DailyError::ChallengeNotFound {
+            date: format_date(date),
+        })
+        .context(DailySnafu)?;
+    Ok(results
+        .into_iter()
+        .enumerate()
+        .map(|(index, result)| DailyResult {
+            rank: index + 1,
+            ..result
+        })
+        .collect())
+}
+
+/// The challenges before `date`, the latest first.
+pub(crate) async fn past_challenges(
+    db_handle: &DbHandle,
+    date: Date,
+    limit: usize,
+) -> Result<Vec<DailySummary>, InnerError> {
+    let day = format_date(date);
+    db_handle
+        .transaction(move |transaction| {
+            transaction
+                .prepare(
+                    "SELECT DailyChallenge.Date, COUNT(DailyAttempt.UserId),
+                         COALESCE(SUM(DailyAttempt.Cleared), 0)
+                     FROM DailyChallenge
+                     LEFT JOIN DailyAttempt ON DailyAttempt.Date = DailyChallenge.Date
+                     WHERE DailyChallenge.Date < ?1
+                     GROUP BY DailyChallenge.Date
+                     ORDER BY DailyChallenge.Date DESC
+                     LIMIT ?2",
+                )?
+                .query_map(params![day, limit], |row| {
+                    Ok(DailySummary {
+                        date: row.get(0)?,
+                        attempts: row.get(1)?,
+                        clears: row.get(2)?,
+                    })
+                })?
+                .collect()
+        })
+        .await
+}
+
+/// Wraps up every challenge before `today`: attempts nobody finished are over, and count as
+/// not cleared.
+#[tracing::instrument(name = "Closing past daily challenges", skip(db_handle), fields(today = %format_date(today)))]
+pub(crate) async fn close_past_challenges(
+    db_handle: &DbHandle,
+    today: Date,
+) -> Result<(), InnerError> {
+    let day = format_date(today);
+    db_handle
+        .transaction(move |transaction| {
+            let unfinished = transaction
+                .prepare(
+                    "SELECT DailyAttempt.Date, DailyAttempt.UserId, DailyAttempt.MatchId,
+                         DailyChallenge.ClosesAt
+                     FROM DailyAttempt
+                     JOIN DailyChallenge ON DailyChallenge.Date = DailyAttempt.Date
+                     WHERE DailyAttempt.Date < ?1 AND DailyAttempt.FinishedAt IS NULL",
+                )?
+                .query_map(params![day], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, UserId>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, i64>(3)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            for (date, user_id, match_id, closes_at) in unfinished {
+                finish_attempt(
+                    transaction,
+                    &date,
+                    user_id,
+                    match_id,
+                    closes_at * 1000,
+                    false,
+                )?;
+            }
+            Ok(())
+        })
+        .await
+}
+
+/// Rolls the daily challenge over at the configured time every day: the day that's over is
+/// wrapped up and the new day's board is generated ahead of the first player asking for it.
+pub(crate) fn spawn_daily_rollover(db_handle: DbHandle, settings: web::Data<DailySettings>) {
+    tokio::spawn(async move {
+        loop {
+            let today = settings.date_at(unix_timestamp());
+            if let Err(error) = roll_over(&db_handle, &settings, today).await {
+                tracing::error!(?error, "Rolling the daily challenge over failed");
+            }
+            let until_next = (settings.closes_at(today) - unix_timestamp()).max(1) as u64;
+            tokio::time::sleep(Duration::from_secs(until_next)).await;
+        }
+    });
+}
+
+async fn roll_over(
+    db_handle: &DbHandle,
+    settings: &DailySettings,
+    today: Date,
+) -> Result<(), InnerError> {
+    close_past_challenges(db_handle, today).await?;
+    ensure_challenge(db_handle, settings, today).await?;
+    Ok(())
+}
@@ -1,15 +1,30 @@
-use std::{fmt::Debug, path::PathBuf};
+use std::{
+    fmt::{self, Debug},
+    path::PathBuf,
+    sync::Arc,
+    time::Instant,
+};
 
-use crate::domain::errors::*;
+use crate::{domain::errors::*, metrics::Metrics};
 use deadpool_sqlite::{
-    rusqlite::{Error, OptionalExtension, Params, Row},
-    Config, Object, Pool, Runtime,
+    rusqlite::{Error, OptionalExtension, Params, Row, Transaction, TransactionBehavior},
+    Config, Object, Pool, Runtime, Status,
 };
 use snafu::{ResultExt, Whatever};
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub(crate) struct DbHandle {
     pool: Pool,
+    /// Where pool wait times and query durations are recorded.
+    metrics: Arc<Metrics>,
+}
+
+impl Debug for DbHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DbHandle")
+            .field("pool", &self.pool)
+            .finish_non_exhaustive()
+    }
 }
 
 /// The result of `DbHandle::execute`.
@@ -28,18 +43,27 @@ impl DbHandle {
     ///
     /// # Parameters
     /// `path`: represents the path to the database file. Must be a valid path URL according to SQLite.
+    /// `metrics`: where to record how long getting connections and running queries takes.
     ///
     /// # Returns
     /// `Ok(Self)` if the above operations succeed. Otherwise, an `Err(Whatever)` containing a catch-all error.
     /// With `Whatever`, there's nothing to match on, all you can do is terminate the operation you're doing.
-    pub(crate) async fn from_path(path: impl Into<PathBuf>) -> Result<Self, Whatever> {
+    pub(crate) async fn from_path(
+        path: impl Into<PathBuf>,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self, Whatever> {
         let pool = Config::new(path)
             .create_pool(Runtime::Tokio1)
             .with_whatever_context(|error| {
                 format!("Failed to create Database Connection Pool: {:?}", error)
             })?;
         Self::setup(&pool).await?;
-        Ok(Self { pool })
+        Ok(Self { pool, metrics })
+    }
+
+    /// How many connections the pool has, and how many of them are free.
+    pub(crate) fn pool_status(&self) -> Status {
+        self.pool.status()
     }
 
     /// Internal setup function. This mainly exists to de-dupe code in the creation functions.
@@ -85,7 +109,13 @@ impl DbHandle {
     /// A filled `Some(ResultType)` if a record should be returned. Otherwise, returns `None`.
     #[tracing::instrument(
         name = "Querying a single record (if it exists) from the database",
-        skip(row_mapper, self, parameters)
+        skip(row_mapper, self, parameters, sql),
+        fields(
+            otel.kind = "client",
+            db.system = "sqlite",
+            db.operation = db_operation(sql),
+            db.statement = sql,
+        )
     )]
     pub(crate) async fn query_row<ResultType, RowMapperFn, P>(
         &self,
@@ -98,17 +128,19 @@ impl DbHandle {
         P: Params + Send + 'static + Debug,
         RowMapperFn: Send + (FnOnce(&Row<'_>) -> Result<ResultType, Error>) + 'static,
     {
-        let pooled_conn = get_connection_from_pool(&self.pool).await?;
+        let pooled_conn = self.connection().await?;
+        let started = Instant::now();
         let result = pooled_conn
             .interact(move |conn| {
                 conn.prepare_cached(sql).and_then(move |mut statement| {
                     statement.query_row(parameters, row_mapper).optional()
                 })
             })
-            .await
+            .await;
+        self.metrics.record_query("query_row", started.elapsed());
+        result
             .context(DatabaseInteractSnafu)?
-            .context(DatabaseConnectionSnafu)?;
-        Ok(result)
+            .context(DatabaseConnectionSnafu)
     }
 
     /// Executes a SQL statement that modifies the database state in some way. Could be
@@ -126,7 +158,13 @@ impl DbHandle {
     /// if unsuccessful.
     #[tracing::instrument(
         name = "Executing a modification statement against the database",
-        skip(self, parameters)
+        skip(self, parameters, sql),
+        fields(
+            otel.kind = "client",
+            db.system = "sqlite",
+            db.operation = db_operation(sql),
+            db.statement = sql,
+        )
     )]
     pub(crate) async fn execute<P>(
         &self,
@@ -136,8 +174,9 @@ impl DbHandle {
     where
         P: Params + Send + 'static + Debug,
     {
-        let pooled_conn = get_connection_from_pool(&self.pool).await?;
-        pooled_conn
+        let pooled_conn = self.connection().await?;
+        let started = Instant::now();
+        let result = pooled_conn
             .interact(move |conn| {
                 conn.prepare_cached(sql)
                     .map(|mut statement| statement.execute(parameters))?
@@ -145,19 +184,80 @@ impl DbHandle {
                         last_insert_rowid: conn.last_insert_rowid(),
                     })
             })
-            .await
+            .await;
+        self.metrics.record_query("execute", started.elapsed());
+        result
+            .context(DatabaseInteractSnafu)?
+            .context(DatabaseConnectionSnafu)
+    }
+
+    /// Runs several statements as a single transaction. If `operation` returns an error, the
+    /// transaction is rolled back and nothing it did is kept.
+    ///
+    /// The transaction takes the write lock as soon as it starts. Otherwise one that reads and
+    /// then writes fails with `SQLITE_BUSY` straight away (rather than waiting its turn) if
+    /// another connection wrote in between, which happens all the time while everything's
+    /// starting up.
+    ///
+    /// # Parameters
+    /// * `operation`: function that runs the statements. It gets the open transaction to run them on.
+    ///
+    /// # Type Parameters
+    /// * `ResultType`: the type returned by `operation` when successful.
+    /// * `OperationFn`: the type of function of the `operation` parameter.
+    ///
+    /// # Returns
+    /// `Ok(ResultType)` if every statement succeeded and the transaction was committed, or
+    /// `Err(InnerError)` if anything failed.
+    #[tracing::instrument(
+        name = "Running a transaction against the database",
+        skip(self, operation),
+        fields(otel.kind = "client", db.system = "sqlite")
+    )]
+    pub(crate) async fn transaction<ResultType, OperationFn>(
+        &self,
+        operation: OperationFn,
+    ) -> Result<ResultType, InnerError>
+    where
+        ResultType: Send + 'static,
+        OperationFn: Send + (FnOnce(&Transaction<'_>) -> Result<ResultType, Error>) + 'static,
+    {
+        let pooled_conn = self.connection().await?;
+        let started = Instant::now();
+        let result = pooled_conn
+            .interact(move |conn| {
+                let transaction = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+                let result = operation(&transaction)?;
+                transaction.commit()?;
+                Ok(result)
+            })
+            .await;
+        self.metrics.record_query("transaction", started.elapsed());
+        result
             .context(DatabaseInteractSnafu)?
             .context(DatabaseConnectionSnafu)
     }
+
+    #[tracing::instrument(
+        name = "Getting a connection from the pool",
+        level = "trace",
+        skip(self)
+    )]
+    async fn connection(&self) -> Result<Object, InnerError> {
+        let started = Instant::now();
+        let connection = self.pool.get().await.context(DatabasePoolSnafu);
+        self.metrics.record_pool_wait(started.elapsed());
+        connection
+    }
 }
 
-#[tracing::instrument(
-    name = "Getting a connection from the pool",
-    level = "trace",
-    skip(pool)
-)]
-async fn get_connection_from_pool(pool: &Pool) -> Result<Object, InnerError> {
-    pool.get().await.context(DatabasePoolSnafu)
+/// The kind of statement `sql` is (`SELECT`, `INSERT` and so on), for the `db.operation`
+/// span field.
+fn db_operation(sql: &str) -> String {
+    sql.split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_uppercase()
 }
 
 mod embedded {
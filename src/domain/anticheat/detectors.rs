@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use super::{AntiCheatSettings, Detector, MatchEvidence, Suspicion};
+use crate::domain::{
+    battle_royale::Action,
+    minesweeper::{Solver, SolverOptions, Technique},
+    UserId,
+};
+
+/// Flags players who took more actions in a short space of time than a person could.
+pub struct ClickRate {
+    max_actions_per_second: f64,
+    window_ms: u128,
+}
+
+impl ClickRate {
+    pub fn new(settings: &AntiCheatSettings) -> Self {
+        Self {
+            max_actions_per_second: settings.max_actions_per_second,
+            window_ms: settings.rate_window.as_millis(),
+        }
+    }
+}
+
+impl Detector for ClickRate {
+    fn name(&self) -> &'static str {
+        "click_rate"
+    }
+
+    fn inspect(&self, evidence: &MatchEvidence) -> Vec<Suspicion> {
+        let replay = &evidence.replay;
+        // Actions are only timed to the tick they happened on, so the window is rounded to a
+        // whole number of ticks.
+        let tick_ms = replay.settings.tick_length.as_millis().max(1);
+        let window_ticks = (self.window_ms / tick_ms).max(1) as u64;
+        let window_secs = (window_ticks as u128 * tick_ms) as f64 / 1000.0;
+        let allowed = self.max_actions_per_second * window_secs;
+        let mut ticks: HashMap<UserId, Vec<u64>> = HashMap::new();
+        for action in &replay.actions {
+            ticks.entry(action.user_id).or_default().push(action.tick);
+        }
+        let mut suspicions: Vec<Suspicion> = ticks
+            .into_iter()
+            .filter_map(|(user_id, ticks)| {
+                // Actions are in the order they happened, so the ticks are already sorted.
+                let mut peak = 0;
+                let mut start = 0;
+                for (end, &tick) in ticks.iter().enumerate() {
+                    while ticks[start] + window_ticks <= tick {
+                        start += 1;
+                    }
+                    peak = peak.max(end + 1 - start);
+                }
+                (peak as f64 > allowed).then(|| Suspicion {
+                    user_id,
+                    score: peak as f64 / allowed,
+                    reason: format!("Took {} actions within {}s", peak, window_secs),
+                })
+            })
+            .collect();
+        suspicions.sort_by_key(|suspicion| suspicion.user_id);
+        suspicions
+    }
+}
+
+/// Flags players whose guesses went better than they had any right to, which is what it
+/// looks like when someone can see where the mines are.
+///
+/// Every reveal the player couldn't have known was safe counts as a guess, with the chance of
+/// it being safe worked out from what they could see at the time. If hitting as few mines as
+/// they did over all their guesses was too unlikely, they're suspicious.
+pub struct GuessLuck {
+    min_guesses: usize,
+    luck_threshold: f64,
+}
+
+impl GuessLuck {
+    pub fn new(settings: &AntiCheatSettings) -> Self {
+        Self {
+            min_guesses: settings.min_guesses,
+            luck_threshold: settings.luck_threshold,
+        }
+    }
+}
+
+impl Detector for GuessLuck {
+    fn name(&self) -> &'static str {
+        "guess_luck"
+    }
+
+    fn inspect(&self, evidence: &MatchEvidence) -> Vec<Suspicion> {
+        let replay = &evidence.replay;
+        let mut engine = match replay.engine() {
+            Ok(engine) => engine,
+            Err(_) => return vec![],
+        };
+        let options = SolverOptions {
+            max_technique: Technique::Enumeration,
+            ..SolverOptions::default()
+        };
+        // For every player, the chance each of their guesses had of being safe, and whether
+        // it hit a mine.
+        let mut guesses: HashMap<UserId, Vec<(f64, bool)>> = HashMap::new();
+        for action in &replay.actions {
+            while engine.current_tick() < action.tick && !engine.is_over() {
+                engine.tick();
+            }
+            if let Action::Reveal(position) = action.action {
+                let chance = engine
+                    .view(action.user_id)
+                    .and_then(|view| Solver::from_view(&view, options).ok())
+                    .and_then(|solver| solver.safe_chance(position));
+                if let Some(chance) = chance.filter(|&chance| chance < 1.0) {
+                    let hit = replay.mines.contains(&position);
+                    guesses
+                        .entry(action.user_id)
+                        .or_default()
+                        .push((chance, hit));
+                }
+            }
+            let _ = engine.apply(action.user_id, action.action);
+        }
+        let mut suspicions: Vec<Suspicion> = guesses
+            .into_iter()
+            .filter(|(_, guesses)| guesses.len() >= self.min_guesses)
+            .filter_map(|(user_id, guesses)| {
+                let hits = guesses.iter().filter(|(_, hit)| *hit).count();
+                let chances: Vec<f64> = guesses.iter().map(|(chance, _)| *chance).collect();
+                let likelihood = at_most_hits(&chances, hits).max(f64::MIN_POSITIVE);
+                (likelihood < self.luck_threshold).then(|| Suspicion {
+                    user_id,
+                    score: likelihood.ln() / self.luck_threshold.ln(),
+                    reason: format!(
+                        "Hit {} mines in {} guesses, which had a {:.2e} chance of happening",
+                        hits,
+                        guesses.len(),
+                        likelihood
+                    ),
+                })
+            })
+            .collect();
+        suspicions.sort_by_key(|suspicion| suspicion.user_id);
+        suspicions
+    }
+}
+
+/// The chance of hitting no more than `hits` mines over guesses that were each safe with the
+/// given chance.
+fn at_most_hits(safe_chances: &[f64], hits: usize) -> f64 {
+    // odds[k] is the chance of having hit exactly k mines so far.
+    let mut odds = vec![0.0; hits + 1];
+    odds[0] = 1.0;
+    for &safe in safe_chances {
+        for k in (0..=hits).rev() {
+            let from_below = if k > 0 {
+                odds[k - 1] * (1.0 - safe)
+            } else {
+                0.0
+            };
+            odds[k] = odds[k] * safe + from_below;
+        }
+    }
+    odds.iter().sum()
+}
+
+/// Flags players in the same match who have logged in from the same address, since they
+/// could be the same person playing on more than one account.
+pub struct SharedAddress {
+    score: f64,
+}
+
+impl SharedAddress {
+    pub fn new(settings: &AntiCheatSettings) -> Self {
+        Self {
+            score: settings.shared_address_score,
+        }
+    }
+}
+
+impl Detector for SharedAddress {
+    fn name(&self) -> &'static str {
+        "shared_address"
+    }
+
+    fn inspect(&self, evidence: &MatchEvidence) -> Vec<Suspicion> {
+        let players = &evidence.replay.players;
+        let no_addresses = vec![];
+        let addresses_of = |user_id| evidence.addresses.get(&user_id).unwrap_or(&no_addresses);
+        let mut suspicions = vec![];
+        for &user_id in players {
+            let shared_with: Vec<String> = players
+                .iter()
+                .filter(|&&other| other != user_id)
+                .filter(|&&other| {
+                    addresses_of(user_id)
+                        .iter()
+                        .any(|address| addresses_of(other).contains(address))
+                })
+                .map(|other| format!("player {}", other))
+                .collect();
+            if !shared_with.is_empty() {
+                suspicions.push(Suspicion {
+                    user_id,
+                    score: self.score,
+                    reason: format!(
+                        "Has logged in from the same address as {}",
+                        shared_with.join(", ")
+                    ),
+                });
+            }
+        }
+        suspicions
+    }
+}
@@ -0,0 +1,10 @@
+use snafu::prelude::*;
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+#[snafu(visibility(pub(crate)))]
+pub enum AntiCheatError {
+    #[snafu(display("There's no flag {flag_id}"))]
+    FlagNotFound { flag_id: i64 },
+    #[snafu(display("Flag {flag_id} has already been reviewed"))]
+    AlreadyReviewed { flag_id: i64 },
+}
@@ -0,0 +1,17 @@
+//! Spotting players who might be cheating.
+//!
+//! Every action goes through the server, so once a match is over its replay says everything
+//! about how it was played. A [`Pipeline`] runs each of its [`Detector`]s over the finished
+//! match and adds up how suspicious they found each player. Players who score too highly are
+//! flagged for a moderator to look at. Nothing here ever gets in the way of a match while
+//! it's being played.
+
+mod detectors;
+mod errors;
+mod pipeline;
+mod settings;
+
+pub use detectors::*;
+pub use errors::AntiCheatError;
+pub use pipeline::*;
+pub use settings::*;
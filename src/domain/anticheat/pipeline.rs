@@ -0,0 +1,126 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use super::{AntiCheatSettings, ClickRate, GuessLuck, SharedAddress};
+use crate::domain::{replay::Replay, UserId};
+
+/// Everything known about a finished match that detectors can go on.
+#[derive(Clone, Debug)]
+pub struct MatchEvidence {
+    pub match_id: i64,
+    pub replay: Replay,
+    /// The addresses each player has logged in from. Players without any aren't in here.
+    pub addresses: HashMap<UserId, Vec<String>>,
+}
+
+/// Something a detector found odd about how a player played.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suspicion {
+    pub user_id: UserId,
+    /// How suspicious it is. Findings for the same player add up, and the player is flagged
+    /// once they reach the pipeline's flag score.
+    pub score: f64,
+    /// What was found, for a moderator to read.
+    pub reason: String,
+}
+
+/// A [`Suspicion`], along with which detector raised it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    pub user_id: UserId,
+    pub detector: String,
+    pub score: f64,
+    pub reason: String,
+}
+
+/// One way of telling whether someone's cheating.
+pub trait Detector: Send + Sync {
+    /// What findings from this detector are labelled with.
+    fn name(&self) -> &'static str;
+
+    fn inspect(&self, evidence: &MatchEvidence) -> Vec<Suspicion>;
+}
+
+/// What the pipeline made of a match.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Review {
+    pub findings: Vec<Finding>,
+    /// The players whose findings added up to enough to flag them, with their total score.
+    pub flagged: Vec<(UserId, f64)>,
+}
+
+/// Runs a set of detectors over finished matches.
+pub struct Pipeline {
+    detectors: Vec<Box<dyn Detector>>,
+    flag_score: f64,
+}
+
+impl Pipeline {
+    /// A pipeline without any detectors, which never flags anyone.
+    pub fn new(flag_score: f64) -> Self {
+        Self {
+            detectors: vec![],
+            flag_score,
+        }
+    }
+
+    /// A pipeline with every detector there is.
+    pub fn standard(settings: &AntiCheatSettings) -> Self {
+        Self::new(settings.flag_score)
+            .with(ClickRate::new(settings))
+            .with(GuessLuck::new(settings))
+            .with(SharedAddress::new(settings))
+    }
+
+    pub fn with(mut self, detector: impl Detector + 'static) -> Self {
+        self.detectors.push(Box::new(detector));
+        self
+    }
+
+    pub fn review(&self, evidence: &MatchEvidence) -> Review {
+        let findings: Vec<Finding> = self
+            .detectors
+            .iter()
+            .flat_map(|detector| {
+                detector
+                    .inspect(evidence)
+                    .into_iter()
+                    .map(|suspicion| Finding {
+                        user_id: suspicion.user_id,
+                        detector: detector.name().to_string(),
+                        score: suspicion.score,
+                        reason: suspicion.reason,
+                    })
+            })
+            .collect();
+        let mut totals: BTreeMap<UserId, f64> = BTreeMap::new();
+        for finding in &findings {
+            *totals.entry(finding.user_id).or_default() += finding.score;
+        }
+        let flagged = totals
+            .into_iter()
+            .filter(|&(_, score)| score >= self.flag_score)
+            .collect();
+        Review { findings, flagged }
+    }
+}
+
+/// What a moderator decided about a flagged player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// They weren't cheating.
+    Cleared,
+    /// They were.
+    Confirmed,
+}
+
+impl Verdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cleared => "cleared",
+            Self::Confirmed => "confirmed",
+        }
+    }
+}
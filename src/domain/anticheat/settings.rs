@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AntiCheatSettings {
+    /// The most actions a person could plausibly take per second, sustained over
+    /// `rate_window`.
+    pub max_actions_per_second: f64,
+    pub rate_window: Duration,
+    /// Players who guessed fewer times than this in a match aren't judged on their luck.
+    pub min_guesses: usize,
+    /// How unlikely a player's guesses have to have been, from 0 to 1, before it's
+    /// suspicious.
+    pub luck_threshold: f64,
+    /// How suspicious it is for two players in a match to have logged in from the same
+    /// address. Players behind the same NAT share one too, so by default this isn't enough
+    /// to get anyone flagged without something else to go with it.
+    pub shared_address_score: f64,
+    /// Players whose findings in a match add up to this much get flagged.
+    pub flag_score: f64,
+}
+
+impl Default for AntiCheatSettings {
+    fn default() -> Self {
+        Self {
+            max_actions_per_second: 12.0,
+            rate_window: Duration::from_secs(2),
+            min_guesses: 8,
+            luck_threshold: 0.0001,
+            shared_address_score: 0.5,
+            flag_score: 1.0,
+        }
+    }
+}
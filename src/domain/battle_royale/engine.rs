@@ -0,0 +1,548 @@
+use std::{cmp::Reverse, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+
+use super::{
+    errors::*,
+    power_ups::{Arsenal, Regions},
+    EliminationReason, MatchResult, Outcome, PowerUp, PowerUpSettings, PowerUpUse, Standing,
+};
+use crate::domain::{
+    minesweeper::{
+        Board, BoardError, BoardView, CellState, GameStatus, MineLayout, Position, RevealOutcome,
+        RevealedCell, SeededRng,
+    },
+    protocol::FlagChange,
+    UserId,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BattleRoyaleSettings {
+    /// How much real time a tick stands for. The engine itself only counts ticks; this is for
+    /// whatever is driving the clock.
+    pub tick_length: Duration,
+    /// The tick the storm first closes in on.
+    pub first_storm: u64,
+    /// How many ticks there are between storms after the first one.
+    pub storm_interval: u64,
+    #[serde(default)]
+    pub power_ups: PowerUpSettings,
+}
+
+impl BattleRoyaleSettings {
+    /// How long `ticks` ticks take in real time.
+    pub fn duration_of(&self, ticks: u64) -> Duration {
+        self.tick_length
+            .saturating_mul(u32::try_from(ticks).unwrap_or(u32::MAX))
+    }
+}
+
+impl Default for BattleRoyaleSettings {
+    fn default() -> Self {
+        Self {
+            tick_length: Duration::from_millis(100),
+            first_storm: 600,
+            storm_interval: 300,
+            power_ups: PowerUpSettings::default(),
+        }
+    }
+}
+
+/// Something a player does in a match: a move on their board, or spending a power-up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", content = "position", rename_all = "snake_case")]
+pub enum Action {
+    Reveal(Position),
+    Flag(Position),
+    Chord(Position),
+    UsePowerUp(PowerUpUse),
+    /// Gives up on the match.
+    Forfeit,
+}
+
+/// Something that happened in a match, in the order it happened.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MatchEvent {
+    /// What changed on a player's own board because of their last action. Only that player
+    /// should see this.
+    BoardChanged {
+        user_id: UserId,
+        revealed: Vec<RevealedCell>,
+        exploded: Option<Position>,
+        flag: Option<FlagChange>,
+        status: GameStatus,
+    },
+    /// A player revealed more of their board.
+    Progress {
+        user_id: UserId,
+        revealed: usize,
+        safe_cells: usize,
+    },
+    Eliminated {
+        user_id: UserId,
+        placement: usize,
+        reason: EliminationReason,
+    },
+    /// When the storm closes in next.
+    StormScheduled {
+        tick: u64,
+    },
+    Finished(MatchResult),
+    /// A player cleared a region quickly enough to earn a power-up. Only that player should
+    /// see this.
+    PowerUpEarned {
+        user_id: UserId,
+        power_up: PowerUp,
+    },
+    PowerUpUsed {
+        user_id: UserId,
+        power_up: PowerUp,
+        target: Option<UserId>,
+    },
+    /// A player's shield stopped them being knocked out by the mine at `position`, which is
+    /// now flagged. Only that player should see this.
+    ShieldBroken {
+        user_id: UserId,
+        position: Position,
+    },
+    /// A player can see the numbers on their board again.
+    FogLifted {
+        user_id: UserId,
+    },
+}
+
+struct PlayerState {
+    user_id: UserId,
+    board: Board,
+    /// The last tick this player revealed something, used to break ties in the storm.
+    last_progress: u64,
+    /// Set once the player is out of the match, or the match is over.
+    finished: Option<(usize, Outcome)>,
+    arsenal: Arsenal,
+}
+
+impl PlayerState {
+    fn is_active(&self) -> bool {
+        self.finished.is_none()
+    }
+}
+
+/// A battle royale match.
+///
+/// Every player starts from the same revealed cell on the same board. A player is knocked out
+/// when they hit a mine, and whenever the storm closes in the active player with the least
+/// progress is knocked out too. The match ends as soon as someone clears their board or only
+/// one player is left, and everyone still playing at that point is placed by progress.
+pub struct BattleRoyale {
+    settings: BattleRoyaleSettings,
+    regions: Regions,
+    players: Vec<PlayerState>,
+    tick: u64,
+    next_storm: Option<u64>,
+    result: Option<MatchResult>,
+}
+
+impl BattleRoyale {
+    pub fn new(
+        layout: MineLayout,
+        start: Position,
+        players: &[UserId],
+        settings: BattleRoyaleSettings,
+    ) -> Result<Self, BoardError> {
+        let regions = Regions::new(&layout, settings.power_ups.region_size);
+        let mut board = Board::from_layout(layout);
+        let opening = board.reveal(start)?;
+        let players = players
+            .iter()
+            .map(|&user_id| PlayerState {
+                user_id,
+                board: board.clone(),
+                last_progress: 0,
+                finished: None,
+                arsenal: Arsenal::new(&regions, &opening.revealed),
+            })
+            .collect();
+        Ok(Self {
+            next_storm: Some(settings.first_storm),
+            regions,
+            settings,
+            players,
+            tick: 0,
+            result: None,
+        })
+    }
+
+    pub fn settings(&self) -> &BattleRoyaleSettings {
+        &self.settings
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = UserId> + '_ {
+        self.players.iter().map(|player| player.user_id)
+    }
+
+    /// The number of ticks that have passed since the match started.
+    pub fn current_tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// The tick the storm will next close in on, unless the match is over.
+    pub fn next_storm(&self) -> Option<u64> {
+        self.next_storm
+    }
+
+    pub fn is_active(&self, user_id: UserId) -> bool {
+        self.player(user_id).is_some_and(PlayerState::is_active)
+    }
+
+    /// A player's board as they see it, which is without its numbers while they're fogged.
+    pub fn view(&self, user_id: UserId) -> Option<BoardView> {
+        self.player(user_id).map(|player| {
+            let view = player.board.view();
+            if player.arsenal.is_fogged() {
+                view.fogged()
+            } else {
+                view
+            }
+        })
+    }
+
+    /// The power-ups a player is holding, in the order they earned them.
+    pub fn power_ups(&self, user_id: UserId) -> &[PowerUp] {
+        self.player(user_id)
+            .map_or(&[], |player| player.arsenal.held.as_slice())
+    }
+
+    pub fn is_shielded(&self, user_id: UserId) -> bool {
+        self.player(user_id)
+            .is_some_and(|player| player.arsenal.shielded)
+    }
+
+    pub fn is_fogged(&self, user_id: UserId) -> bool {
+        self.player(user_id)
+            .is_some_and(|player| player.arsenal.is_fogged())
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.result.is_some()
+    }
+
+    pub fn result(&self) -> Option<&MatchResult> {
+        self.result.as_ref()
+    }
+
+    /// Applies a player's action to their board. Players who are out of the match can't do
+    /// anything, and actions from someone who isn't in the match are ignored.
+    pub fn apply(
+        &mut self,
+        user_id: UserId,
+        action: Action,
+    ) -> Result<Vec<MatchEvent>, ActionError> {
+        let index = match self.players.iter().position(|p| p.user_id == user_id) {
+            Some(index) => index,
+            None => return Ok(vec![]),
+        };
+        if !self.players[index].is_active() {
+            return Err(BoardError::GameOver).context(InvalidMoveSnafu);
+        }
+        let board = &mut self.players[index].board;
+        let (outcome, flag) = match action {
+            Action::Reveal(position) => (board.reveal(position).context(InvalidMoveSnafu)?, None),
+            Action::Chord(position) => (board.chord(position).context(InvalidMoveSnafu)?, None),
+            Action::Flag(position) => {
+                let flagged =
+                    board.toggle_flag(position).context(InvalidMoveSnafu)? == CellState::Flagged;
+                (Default::default(), Some(FlagChange { position, flagged }))
+            }
+            Action::UsePowerUp(used) => return self.use_power_up(index, used),
+            Action::Forfeit => {
+                let user_id = self.players[index].user_id;
+                return Ok(self.eliminate(user_id, EliminationReason::Forfeit));
+            }
+        };
+        Ok(self.board_changed(index, outcome, flag, vec![]))
+    }
+
+    /// Reports what an action did to a player's board, along with anything that follows from
+    /// it. `events` go first.
+    fn board_changed(
+        &mut self,
+        index: usize,
+        outcome: RevealOutcome,
+        mut flag: Option<FlagChange>,
+        mut events: Vec<MatchEvent>,
+    ) -> Vec<MatchEvent> {
+        let tick = self.tick;
+        let power_ups = self.settings.power_ups;
+        let player = &mut self.players[index];
+        let user_id = player.user_id;
+        let mut shield_broken = None;
+        if outcome.exploded.is_some() && player.arsenal.shielded {
+            player.arsenal.shielded = false;
+            shield_broken = player.board.defuse();
+            flag = shield_broken.map(|position| FlagChange {
+                position,
+                flagged: true,
+            });
+        }
+        let status = player.board.status();
+        let progressed = !outcome.revealed.is_empty();
+        let earned = if power_ups.enabled {
+            player
+                .arsenal
+                .track(&self.regions, &power_ups, &outcome.revealed, tick)
+        } else {
+            vec![]
+        };
+        events.push(MatchEvent::BoardChanged {
+            user_id,
+            revealed: outcome.revealed,
+            exploded: outcome.exploded,
+            flag,
+            status,
+        });
+        if let Some(position) = shield_broken {
+            events.push(MatchEvent::ShieldBroken { user_id, position });
+        }
+        if progressed {
+            player.last_progress = tick;
+            events.push(MatchEvent::Progress {
+                user_id,
+                revealed: player.board.revealed_count(),
+                safe_cells: player.board.config().safe_cell_count(),
+            });
+        }
+        events.extend(
+            earned
+                .into_iter()
+                .map(|power_up| MatchEvent::PowerUpEarned { user_id, power_up }),
+        );
+        match status {
+            GameStatus::Won => events.extend(self.finish()),
+            GameStatus::Lost { .. } => {
+                events.extend(self.eliminate(user_id, EliminationReason::Mine));
+            }
+            GameStatus::Ready | GameStatus::Playing => {}
+        }
+        events
+    }
+
+    fn use_power_up(
+        &mut self,
+        index: usize,
+        used: PowerUpUse,
+    ) -> Result<Vec<MatchEvent>, ActionError> {
+        let PowerUpUse { power_up, target } = used;
+        let settings = self.settings.power_ups;
+        ensure!(settings.enabled, PowerUpsDisabledSnafu);
+        let user_id = self.players[index].user_id;
+        let arsenal = &self.players[index].arsenal;
+        ensure!(
+            arsenal.held.contains(&power_up),
+            NoPowerUpSnafu { power_up }
+        );
+        let event = MatchEvent::PowerUpUsed {
+            user_id,
+            power_up,
+            target,
+        };
+        match power_up {
+            PowerUp::Reveal => {
+                ensure!(target.is_none(), InvalidTargetSnafu);
+                let position = self.safe_cell_for(index).context(NothingToRevealSnafu)?;
+                let player = &mut self.players[index];
+                player.arsenal.spend(power_up);
+                let outcome = player
+                    .board
+                    .reveal(position)
+                    .expect("hidden safe cells can always be revealed");
+                Ok(self.board_changed(index, outcome, None, vec![event]))
+            }
+            PowerUp::Shield => {
+                ensure!(target.is_none(), InvalidTargetSnafu);
+                ensure!(!arsenal.shielded, AlreadyShieldedSnafu);
+                let arsenal = &mut self.players[index].arsenal;
+                arsenal.spend(power_up);
+                arsenal.shielded = true;
+                Ok(vec![event])
+            }
+            PowerUp::Fog => {
+                let until = self.tick + settings.fog_ticks.max(1);
+                let victim = self
+                    .players
+                    .iter()
+                    .position(|player| Some(player.user_id) == target && player.user_id != user_id);
+                let victim = match victim {
+                    Some(victim) if self.players[victim].is_active() => victim,
+                    _ => return InvalidTargetSnafu.fail(),
+                };
+                self.players[index].arsenal.spend(power_up);
+                let fogged_until = &mut self.players[victim].arsenal.fogged_until;
+                *fogged_until = Some(fogged_until.map_or(until, |current| current.max(until)));
+                Ok(vec![event])
+            }
+        }
+    }
+
+    /// Picks a hidden safe cell for a reveal power-up. Cells touching something the player
+    /// has already revealed come first, since a cell out in the open doesn't help much. The
+    /// pick is seeded from the tick so replays make the same one.
+    fn safe_cell_for(&self, index: usize) -> Option<Position> {
+        let player = &self.players[index];
+        let board = &player.board;
+        let config = board.config();
+        let layout = board.layout()?;
+        let state = |cell: usize| board.cell(config.position_of(cell));
+        let safe: Vec<usize> = (0..config.cell_count())
+            .filter(|&cell| state(cell) == Ok(CellState::Hidden) && !layout.is_mine(cell))
+            .collect();
+        let frontier: Vec<usize> = safe
+            .iter()
+            .copied()
+            .filter(|&cell| {
+                config
+                    .neighbours(cell)
+                    .any(|neighbour| state(neighbour) == Ok(CellState::Revealed))
+            })
+            .collect();
+        let candidates = if frontier.is_empty() { safe } else { frontier };
+        if candidates.is_empty() {
+            return None;
+        }
+        let mut rng = SeededRng::new(self.tick ^ (player.user_id as u64).rotate_left(32));
+        Some(config.position_of(candidates[rng.below(candidates.len())]))
+    }
+
+    /// Moves the clock forward one tick, bringing in the storm if it's due.
+    pub fn tick(&mut self) -> Vec<MatchEvent> {
+        if self.is_over() {
+            return vec![];
+        }
+        self.tick += 1;
+        let tick = self.tick;
+        let mut events = vec![];
+        for player in &mut self.players {
+            if player
+                .arsenal
+                .fogged_until
+                .is_some_and(|until| until <= tick)
+            {
+                player.arsenal.fogged_until = None;
+                events.push(MatchEvent::FogLifted {
+                    user_id: player.user_id,
+                });
+            }
+        }
+        if self.next_storm.is_some_and(|storm| storm <= tick) {
+            events.extend(self.storm());
+        }
+        events
+    }
+
+    fn storm(&mut self) -> Vec<MatchEvent> {
+        // Least progress goes first. Between players with the same progress, whoever got
+        // there last is behind, and after that whoever joined last.
+        let behind = self
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(_, player)| player.is_active())
+            .min_by_key(|(index, player)| {
+                (
+                    player.board.revealed_count(),
+                    Reverse(player.last_progress),
+                    Reverse(*index),
+                )
+            })
+            .map(|(_, player)| player.user_id);
+        let mut events = match behind {
+            Some(user_id) => self.eliminate(user_id, EliminationReason::Storm),
+            None => vec![],
+        };
+        if !self.is_over() {
+            let next = self.tick + self.settings.storm_interval.max(1);
+            self.next_storm = Some(next);
+            events.push(MatchEvent::StormScheduled { tick: next });
+        }
+        events
+    }
+
+    fn eliminate(&mut self, user_id: UserId, reason: EliminationReason) -> Vec<MatchEvent> {
+        let placement = self.active_count();
+        let tick = self.tick;
+        let player = self
+            .players
+            .iter_mut()
+            .find(|player| player.user_id == user_id)
+            .expect("only players in the match are eliminated");
+        player.finished = Some((placement, Outcome::Eliminated { reason, tick }));
+        let mut events = vec![MatchEvent::Eliminated {
+            user_id,
+            placement,
+            reason,
+        }];
+        let remaining = self.active_count();
+        if remaining == 0 || (remaining == 1 && self.players.len() > 1) {
+            events.extend(self.finish());
+        }
+        events
+    }
+
+    /// Places everyone still playing by their progress and wraps up the match.
+    fn finish(&mut self) -> Vec<MatchEvent> {
+        let tick = self.tick;
+        let mut active: Vec<&mut PlayerState> = self
+            .players
+            .iter_mut()
+            .filter(|player| player.is_active())
+            .collect();
+        active.sort_by_key(|player| {
+            (
+                Reverse(player.board.status() == GameStatus::Won),
+                Reverse(player.board.revealed_count()),
+                player.last_progress,
+            )
+        });
+        for (index, player) in active.into_iter().enumerate() {
+            let outcome = match player.board.status() {
+                GameStatus::Won => Outcome::Cleared { tick },
+                _ => Outcome::Survived,
+            };
+            player.finished = Some((index + 1, outcome));
+        }
+
+        let mut standings: Vec<Standing> = self
+            .players
+            .iter()
+            .map(|player| {
+                let (placement, outcome) = player.finished.expect("everyone has finished");
+                Standing {
+                    user_id: player.user_id,
+                    placement,
+                    revealed: player.board.revealed_count(),
+                    outcome,
+                }
+            })
+            .collect();
+        standings.sort_by_key(|standing| standing.placement);
+        let result = MatchResult {
+            standings,
+            ticks: tick,
+        };
+        self.next_storm = None;
+        self.result = Some(result.clone());
+        vec![MatchEvent::Finished(result)]
+    }
+
+    fn active_count(&self) -> usize {
+        self.players
+            .iter()
+            .filter(|player| player.is_active())
+            .count()
+    }
+
+    fn player(&self, user_id: UserId) -> Option<&PlayerState> {
+        self.players.iter().find(|player| player.user_id == user_id)
+    }
+}
@@ -0,0 +1,21 @@
+use snafu::prelude::*;
+
+use super::PowerUp;
+use crate::domain::minesweeper::BoardError;
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+#[snafu(visibility(pub(crate)))]
+pub enum ActionError {
+    #[snafu(display("{source}"))]
+    InvalidMove { source: BoardError },
+    #[snafu(display("Power-ups are turned off for this match"))]
+    PowerUpsDisabled,
+    #[snafu(display("You don't have a {} power-up", power_up.as_str()))]
+    NoPowerUp { power_up: PowerUp },
+    #[snafu(display("Fog has to be aimed at an opponent who's still playing"))]
+    InvalidTarget,
+    #[snafu(display("Your shield is already up"))]
+    AlreadyShielded,
+    #[snafu(display("There are no hidden safe cells left to reveal"))]
+    NothingToReveal,
+}
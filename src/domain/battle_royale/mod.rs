@@ -0,0 +1,21 @@
+//! Battle royale matches: every player gets a copy of the same seeded board, and players are
+//! knocked out by hitting mines or by the storm until one is left.
+//!
+//! Time only moves when [`BattleRoyale::tick`] is called, so a whole match can be played out
+//! in a test without waiting on a real clock.
+//!
+//! Lobbies can turn on power-ups, which players earn by clearing regions of their board
+//! quickly. Using one is just another [`Action`], so it's checked by the engine and ends up in
+//! the match log like any other move.
+
+mod engine;
+mod errors;
+mod power_ups;
+mod reconnects;
+mod standings;
+
+pub use engine::*;
+pub use errors::ActionError;
+pub use power_ups::{PowerUp, PowerUpSettings, PowerUpUse};
+pub use reconnects::*;
+pub use standings::*;
@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{
+    minesweeper::{BoardConfig, MineLayout, Position, RevealedCell},
+    UserId,
+};
+
+/// Something a player can earn by clearing part of their board quickly, and spend later.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerUp {
+    /// Reveals a safe cell for the player, next to what they've already revealed if possible.
+    Reveal,
+    /// The next mine the player hits gets flagged instead of knocking them out.
+    Shield,
+    /// Hides the numbers on an opponent's board for a while.
+    Fog,
+}
+
+impl PowerUp {
+    /// The order power-ups are handed out in.
+    pub const ALL: [PowerUp; 3] = [Self::Reveal, Self::Shield, Self::Fog];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Reveal => "reveal",
+            Self::Shield => "shield",
+            Self::Fog => "fog",
+        }
+    }
+}
+
+/// A player spending one of their power-ups. Only fog is aimed at someone else.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PowerUpUse {
+    pub power_up: PowerUp,
+    #[serde(default)]
+    pub target: Option<UserId>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PowerUpSettings {
+    /// Whether players earn and use power-ups at all. Lobbies turn this on for their own
+    /// matches.
+    pub enabled: bool,
+    /// Boards are split into square regions this many cells across. Clearing every safe cell
+    /// in a region quickly earns a power-up.
+    pub region_size: usize,
+    /// How many ticks a player has from their first reveal in a region to clear it.
+    pub region_ticks: u64,
+    /// How many power-ups a player can hold at once. Regions cleared with a full hand don't
+    /// earn anything.
+    pub max_held: usize,
+    /// How many ticks fog lasts.
+    pub fog_ticks: u64,
+}
+
+impl Default for PowerUpSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            region_size: 4,
+            region_ticks: 100,
+            max_held: 3,
+            fog_ticks: 100,
+        }
+    }
+}
+
+/// How a board is split into regions, and how many safe cells each one has.
+pub(super) struct Regions {
+    config: BoardConfig,
+    size: usize,
+    safe_cells: Vec<usize>,
+}
+
+impl Regions {
+    pub(super) fn new(layout: &MineLayout, size: usize) -> Self {
+        let config = layout.config();
+        let size = size.max(1);
+        let mut regions = Self {
+            config,
+            size,
+            safe_cells: vec![],
+        };
+        regions.safe_cells = vec![0; regions.columns() * config.height().div_ceil(size)];
+        for index in (0..config.cell_count()).filter(|&index| !layout.is_mine(index)) {
+            let region = regions.region_of(config.position_of(index));
+            regions.safe_cells[region] += 1;
+        }
+        regions
+    }
+
+    fn columns(&self) -> usize {
+        self.config.width().div_ceil(self.size)
+    }
+
+    fn region_of(&self, position: Position) -> usize {
+        (position.y / self.size) * self.columns() + position.x / self.size
+    }
+}
+
+/// One player's progress towards power-ups, and the ones they're holding or under.
+#[derive(Clone, Debug)]
+pub(super) struct Arsenal {
+    pub(super) held: Vec<PowerUp>,
+    /// How many power-ups they've earned so far, which decides which one comes next.
+    earned: usize,
+    pub(super) shielded: bool,
+    /// The tick their fog lifts on, if they're under some.
+    pub(super) fogged_until: Option<u64>,
+    /// Per region: how many safe cells they've revealed in it, and the tick they first did.
+    cleared: Vec<(usize, Option<u64>)>,
+}
+
+impl Arsenal {
+    /// `opening` is what was revealed for everyone at the start. It counts towards clearing
+    /// regions, but the clock on a region only starts with the player's own first reveal in it.
+    pub(super) fn new(regions: &Regions, opening: &[RevealedCell]) -> Self {
+        let mut cleared = vec![(0, None); regions.safe_cells.len()];
+        for cell in opening {
+            cleared[regions.region_of(cell.position)].0 += 1;
+        }
+        Self {
+            held: vec![],
+            earned: 0,
+            shielded: false,
+            fogged_until: None,
+            cleared,
+        }
+    }
+
+    /// Counts `revealed` towards the player's regions, and returns the power-ups it earned.
+    pub(super) fn track(
+        &mut self,
+        regions: &Regions,
+        settings: &PowerUpSettings,
+        revealed: &[RevealedCell],
+        tick: u64,
+    ) -> Vec<PowerUp> {
+        let mut touched = vec![];
+        for cell in revealed {
+            let region = regions.region_of(cell.position);
+            let (count, first) = &mut self.cleared[region];
+            *count += 1;
+            first.get_or_insert(tick);
+            if !touched.contains(&region) {
+                touched.push(region);
+            }
+        }
+        let mut earned = vec![];
+        for region in touched {
+            let (count, first) = self.cleared[region];
+            let quick = first.is_some_and(|first| tick - first <= settings.region_ticks);
+            if count == regions.safe_cells[region] && quick && self.held.len() < settings.max_held {
+                let power_up = PowerUp::ALL[self.earned % PowerUp::ALL.len()];
+                self.earned += 1;
+                self.held.push(power_up);
+                earned.push(power_up);
+            }
+        }
+        earned
+    }
+
+    /// Takes `power_up` out of their hand, if they have one.
+    pub(super) fn spend(&mut self, power_up: PowerUp) -> bool {
+        match self.held.iter().position(|&held| held == power_up) {
+            Some(index) => {
+                self.held.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(super) fn is_fogged(&self) -> bool {
+        self.fogged_until.is_some()
+    }
+}
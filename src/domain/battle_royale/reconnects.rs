@@ -0,0 +1,73 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::UserId;
+
+/// What happens to a player who's still gone when their grace period runs out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForfeitRule {
+    /// They forfeit the match, and are knocked out on the spot.
+    Eliminate,
+    /// Their slot is held for as long as the match lasts. They'll usually be caught by the
+    /// storm, since they can't make any progress while they're gone.
+    LeaveToStorm,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReconnectSettings {
+    /// How long a player who drops out of a match has to reconnect before the forfeit rule
+    /// kicks in.
+    pub grace_period: Duration,
+    pub forfeit: ForfeitRule,
+}
+
+impl Default for ReconnectSettings {
+    fn default() -> Self {
+        Self {
+            grace_period: Duration::from_secs(30),
+            forfeit: ForfeitRule::Eliminate,
+        }
+    }
+}
+
+/// The players who've dropped out of a match, and when their grace periods run out.
+#[derive(Clone, Debug, Default)]
+pub struct Absences {
+    away: Vec<(UserId, Instant)>,
+}
+
+impl Absences {
+    /// Starts a player's grace period, and returns when it runs out. A player who's already
+    /// away keeps the grace period they had.
+    pub fn left(&mut self, user_id: UserId, now: Instant, grace_period: Duration) -> Instant {
+        if let Some(&(_, deadline)) = self.away.iter().find(|(away, _)| *away == user_id) {
+            return deadline;
+        }
+        let deadline = now + grace_period;
+        self.away.push((user_id, deadline));
+        deadline
+    }
+
+    /// Ends a player's grace period. Returns whether they were away at all.
+    pub fn returned(&mut self, user_id: UserId) -> bool {
+        let before = self.away.len();
+        self.away.retain(|(away, _)| *away != user_id);
+        self.away.len() != before
+    }
+
+    pub fn is_away(&self, user_id: UserId) -> bool {
+        self.away.iter().any(|(away, _)| *away == user_id)
+    }
+
+    /// Takes out everyone whose grace period has run out by `now`, in the order they left.
+    pub fn overdue(&mut self, now: Instant) -> Vec<UserId> {
+        let (overdue, away) = self
+            .away
+            .drain(..)
+            .partition(|(_, deadline)| *deadline <= now);
+        self.away = away;
+        overdue.into_iter().map(|(user_id, _)| user_id).collect()
+    }
+}
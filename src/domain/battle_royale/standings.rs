@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::UserId;
+
+/// Why a player was knocked out of a match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EliminationReason {
+    /// They revealed a mine.
+    Mine,
+    /// They had the least progress when the storm closed in.
+    Storm,
+    /// They gave up, or were gone for too long.
+    Forfeit,
+}
+
+/// How a player's match ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum Outcome {
+    /// They cleared their whole board.
+    Cleared { tick: u64 },
+    /// They were still playing when the match ended.
+    Survived,
+    Eliminated {
+        reason: EliminationReason,
+        tick: u64,
+    },
+}
+
+/// Where a player finished in a match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Standing {
+    pub user_id: UserId,
+    /// 1 is first place.
+    pub placement: usize,
+    /// How many safe cells they had revealed when they finished.
+    pub revealed: usize,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+/// The final record of a finished match.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchResult {
+    /// First place first.
+    pub standings: Vec<Standing>,
+    /// How many ticks the match lasted.
+    pub ticks: u64,
+}
+
+impl MatchResult {
+    pub fn winner(&self) -> Option<UserId> {
+        self.standings.first().map(|standing| standing.user_id)
+    }
+}
@@ -0,0 +1,89 @@
+use crate::domain::{
+    battle_royale::Action,
+    minesweeper::{BoardView, CellView, Position, SeededRng, Solver},
+};
+
+use super::{BotSkill, GuessPolicy};
+
+/// Decides what a bot does next. Bots are deterministic for a given seed, so a bot's match
+/// plays out the same way every time it's given the same boards.
+#[derive(Clone, Debug)]
+pub struct Bot {
+    skill: BotSkill,
+    rng: SeededRng,
+}
+
+impl Bot {
+    pub fn new(skill: BotSkill, seed: u64) -> Self {
+        Self {
+            skill,
+            rng: SeededRng::new(seed),
+        }
+    }
+
+    pub fn skill(&self) -> &BotSkill {
+        &self.skill
+    }
+
+    /// What to do with `board` next, or `None` if there's nothing the bot is willing to do.
+    ///
+    /// Cells the bot can prove are safe get revealed, and mines it can prove get flagged so
+    /// it remembers them. Flags are only ever placed on proven mines, so every flag on the
+    /// board is taken to be right.
+    pub fn next_action(&mut self, board: &BoardView) -> Option<Action> {
+        if board.status.is_over() {
+            return None;
+        }
+        let mut solver = Solver::from_view(board, self.skill.solver_options()).ok()?;
+        for (index, cell) in board.cells.iter().enumerate() {
+            if *cell == CellView::Flagged {
+                solver.mark_mine(position_of(board, index));
+            }
+        }
+        let deduction = solver.deduce();
+        if let Some(safe) = deduction
+            .as_ref()
+            .and_then(|deduction| deduction.safe.first())
+        {
+            if self.fumbles() {
+                return self.any_hidden(board, &[]).map(Action::Reveal);
+            }
+            return Some(Action::Reveal(*safe));
+        }
+        if let Some(mine) = deduction.and_then(|deduction| deduction.mines.first().copied()) {
+            return Some(Action::Flag(mine));
+        }
+        match self.skill.guess_policy {
+            GuessPolicy::Never => None,
+            GuessPolicy::Random => self.any_hidden(board, &[]).map(Action::Reveal),
+            GuessPolicy::Cautious => self
+                .any_hidden(board, &solver.frontier())
+                .or_else(|| self.any_hidden(board, &[]))
+                .map(Action::Reveal),
+        }
+    }
+
+    fn fumbles(&mut self) -> bool {
+        (self.rng.next_u64() as f64 / u64::MAX as f64) < self.skill.error_rate
+    }
+
+    /// A random hidden cell that isn't flagged or in `avoid`.
+    fn any_hidden(&mut self, board: &BoardView, avoid: &[Position]) -> Option<Position> {
+        let hidden: Vec<Position> = board
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| **cell == CellView::Hidden)
+            .map(|(index, _)| position_of(board, index))
+            .filter(|position| !avoid.contains(position))
+            .collect();
+        if hidden.is_empty() {
+            return None;
+        }
+        Some(hidden[self.rng.below(hidden.len())])
+    }
+}
+
+fn position_of(board: &BoardView, index: usize) -> Position {
+    Position::new(index % board.width, index / board.width)
+}
@@ -0,0 +1,13 @@
+//! Computer players.
+//!
+//! Bots see exactly what a player would see and answer with the same actions a player would
+//! send, so the rest of the game can't tell them apart. They work out their moves with the
+//! [`Solver`](crate::domain::minesweeper::Solver), and how well they play is down to their
+//! [`BotSkill`]: how quickly they react, how often they fumble, and what they do when there's
+//! nothing left to work out.
+
+mod brain;
+mod settings;
+
+pub use brain::*;
+pub use settings::*;
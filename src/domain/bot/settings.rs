@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::minesweeper::{SolverOptions, Technique};
+
+/// What a bot does when it can't prove any cell is safe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuessPolicy {
+    /// Reveals any hidden cell.
+    Random,
+    /// Reveals a hidden cell away from the numbers if there is one, since those are
+    /// usually less likely to be mines than the cells the numbers are pointing at.
+    Cautious,
+    /// Never guesses, and waits for the storm instead.
+    Never,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BotSkill {
+    /// How long the bot takes over each move.
+    pub reaction_delay: Duration,
+    /// How often the bot fumbles a move it had worked out and reveals some other hidden cell
+    /// instead, from 0 (never) to 1 (always).
+    pub error_rate: f64,
+    pub guess_policy: GuessPolicy,
+    /// The hardest deductions the bot can make.
+    pub max_technique: Technique,
+}
+
+impl Default for BotSkill {
+    fn default() -> Self {
+        Self {
+            reaction_delay: Duration::from_millis(800),
+            error_rate: 0.02,
+            guess_policy: GuessPolicy::Cautious,
+            max_technique: Technique::Subset,
+        }
+    }
+}
+
+impl BotSkill {
+    pub fn solver_options(&self) -> SolverOptions {
+        SolverOptions {
+            max_technique: self.max_technique,
+            ..SolverOptions::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BotSettings {
+    /// How long a player waits in the matchmaking queue without finding a match before bots
+    /// are brought in to play against them.
+    pub fill_after: Duration,
+    pub skill: BotSkill,
+    /// The most bot-only matches a moderator can start in one go.
+    pub max_matches: usize,
+    /// The most bots that can play in one of those matches.
+    pub max_players: usize,
+}
+
+impl Default for BotSettings {
+    fn default() -> Self {
+        Self {
+            fill_after: Duration::from_secs(30),
+            skill: BotSkill::default(),
+            max_matches: 250,
+            max_players: 8,
+        }
+    }
+}
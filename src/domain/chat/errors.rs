@@ -0,0 +1,24 @@
+use snafu::prelude::*;
+
+use crate::domain::UserId;
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+#[snafu(visibility(pub(crate)))]
+pub enum ChatError {
+    #[snafu(display("Chat messages can't be empty"))]
+    EmptyMessage,
+    #[snafu(display("Chat messages can be at most {max} characters long"))]
+    MessageTooLong { max: usize },
+    #[snafu(display("You're sending messages too quickly, try again in {retry_after_ms}ms"))]
+    RateLimited { retry_after_ms: u64 },
+    #[snafu(display("You've been muted until {until}"))]
+    Muted { until: i64 },
+    #[snafu(display("You need to be in a lobby or a match to chat"))]
+    NoRoom,
+    #[snafu(display("There's no chat message {message_id}"))]
+    MessageNotFound { message_id: i64 },
+    #[snafu(display("There's no player {user_id}"))]
+    PlayerNotFound { user_id: UserId },
+    #[snafu(display("You can't mute or block yourself"))]
+    IgnoringSelf,
+}
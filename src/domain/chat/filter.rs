@@ -0,0 +1,45 @@
+/// Stars out words that aren't allowed in chat.
+///
+/// Words are matched whole and without caring about case, so banning "heck" leaves
+/// "checking" alone but catches "HECK".
+#[derive(Clone, Debug, Default)]
+pub struct WordFilter {
+    banned: Vec<String>,
+}
+
+impl WordFilter {
+    pub fn new<Word: AsRef<str>>(banned: &[Word]) -> Self {
+        Self {
+            banned: banned
+                .iter()
+                .map(|word| word.as_ref().trim().to_lowercase())
+                .filter(|word| !word.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn censor(&self, text: &str) -> String {
+        let mut censored = String::with_capacity(text.len());
+        let mut word = String::new();
+        for character in text.chars() {
+            if character.is_alphanumeric() {
+                word.push(character);
+            } else {
+                self.push_word(&mut censored, &mut word);
+                censored.push(character);
+            }
+        }
+        self.push_word(&mut censored, &mut word);
+        censored
+    }
+
+    /// Adds `word` to `censored`, starred out if it's banned, and empties it.
+    fn push_word(&self, censored: &mut String, word: &mut String) {
+        if self.banned.contains(&word.to_lowercase()) {
+            censored.extend(word.chars().map(|_| '*'));
+        } else {
+            censored.push_str(word);
+        }
+        word.clear();
+    }
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+/// How much a player doesn't want to hear from someone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IgnoreKind {
+    /// Their messages are hidden from the player.
+    Mute,
+    /// As well as being muted, they can't see the player's messages.
+    Block,
+}
+
+impl IgnoreKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Mute => "mute",
+            Self::Block => "block",
+        }
+    }
+}
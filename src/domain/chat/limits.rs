@@ -0,0 +1,97 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+
+use super::{errors::*, WordFilter};
+use crate::domain::UserId;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatSettings {
+    /// The longest a message can be, in characters.
+    pub max_length: usize,
+    /// Players can send at most `rate_limit` messages in any `rate_window`.
+    pub rate_limit: usize,
+    pub rate_window: Duration,
+    /// Words that are starred out of every message.
+    pub banned_words: Vec<String>,
+    /// How long messages are kept, so reports about them can be looked into.
+    pub retention: Duration,
+}
+
+impl Default for ChatSettings {
+    fn default() -> Self {
+        Self {
+            max_length: 200,
+            rate_limit: 5,
+            rate_window: Duration::from_secs(10),
+            banned_words: vec![],
+            retention: Duration::from_secs(30 * 24 * 60 * 60),
+        }
+    }
+}
+
+impl ChatSettings {
+    /// Tidies up a message and checks it's fine to send.
+    pub fn prepare(&self, text: &str, filter: &WordFilter) -> Result<String, ChatError> {
+        let text = text.trim();
+        ensure!(!text.is_empty(), EmptyMessageSnafu);
+        ensure!(
+            text.chars().count() <= self.max_length,
+            MessageTooLongSnafu {
+                max: self.max_length
+            }
+        );
+        Ok(filter.censor(text))
+    }
+}
+
+/// Keeps track of how many messages each player has sent recently.
+pub struct RateLimiter {
+    limit: usize,
+    window: Duration,
+    sent: HashMap<UserId, VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(settings: &ChatSettings) -> Self {
+        Self {
+            limit: settings.rate_limit,
+            window: settings.rate_window,
+            sent: HashMap::new(),
+        }
+    }
+
+    /// Counts a message `user_id` wants to send at `now`, unless they've already sent as
+    /// many as they're allowed to.
+    pub fn check(&mut self, user_id: UserId, now: Instant) -> Result<(), ChatError> {
+        let sent = self.sent.entry(user_id).or_default();
+        while matches!(sent.front(), Some(&at) if now.saturating_duration_since(at) >= self.window)
+        {
+            sent.pop_front();
+        }
+        if sent.len() >= self.limit {
+            let retry_after = sent.front().map_or(self.window, |&oldest| {
+                self.window - now.saturating_duration_since(oldest)
+            });
+            return RateLimitedSnafu {
+                retry_after_ms: retry_after.as_millis() as u64,
+            }
+            .fail();
+        }
+        sent.push_back(now);
+        Ok(())
+    }
+
+    /// Forgets about players who haven't sent anything within the window.
+    pub fn prune(&mut self, now: Instant) {
+        let window = self.window;
+        self.sent.retain(|_, sent| {
+            sent.back()
+                .is_some_and(|&at| now.saturating_duration_since(at) < window)
+        });
+    }
+}
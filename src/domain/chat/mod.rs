@@ -0,0 +1,18 @@
+//! Chat between players, and the rules that keep it civil.
+//!
+//! Players chat with whoever they're in a match with, or with their lobby between matches.
+//! Every message is length checked, rate limited and run through a word filter before anyone
+//! sees it. Players can mute or block each other, and moderators can delete messages and
+//! mute players for a while.
+
+mod errors;
+mod filter;
+mod ignore;
+mod limits;
+mod room;
+
+pub use errors::ChatError;
+pub use filter::*;
+pub use ignore::*;
+pub use limits::*;
+pub use room::*;
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::lobby::LobbyCode;
+
+/// Where a message was sent, which decides who gets to see it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "room", content = "id", rename_all = "snake_case")]
+pub enum ChatRoom {
+    Lobby(LobbyCode),
+    Match(i64),
+}
+
+impl ChatRoom {
+    /// The room's kind and ID, the way they're stored in the database.
+    pub fn to_parts(&self) -> (&'static str, String) {
+        match self {
+            Self::Lobby(code) => ("lobby", code.to_string()),
+            Self::Match(match_id) => ("match", match_id.to_string()),
+        }
+    }
+
+    /// Reads a room written by [`ChatRoom::to_parts`].
+    pub fn from_parts(kind: &str, id: &str) -> Option<Self> {
+        match kind {
+            "lobby" => Some(Self::Lobby(LobbyCode::from(id.to_string()))),
+            "match" => id.parse().ok().map(Self::Match),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use time::{Date, Month, OffsetDateTime};
+
+use crate::domain::{
+    battle_royale::BattleRoyaleSettings,
+    minesweeper::{BoardConfig, Position, SeededRng},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailySettings {
+    /// The time of day, in UTC, that a new challenge starts.
+    pub rollover: Duration,
+    pub board: BoardConfig,
+}
+
+impl Default for DailySettings {
+    fn default() -> Self {
+        Self {
+            rollover: Duration::ZERO,
+            board: BoardConfig::intermediate(),
+        }
+    }
+}
+
+impl DailySettings {
+    /// The date of the challenge being played at `now` (a unix timestamp). Before the
+    /// rollover time, that's still yesterday's.
+    pub fn date_at(&self, now: i64) -> Date {
+        OffsetDateTime::from_unix_timestamp(now - self.rollover.as_secs() as i64)
+            .expect("timestamps from the clock are in range")
+            .date()
+    }
+
+    /// When the challenge for `date` starts, as a unix timestamp.
+    pub fn opens_at(&self, date: Date) -> i64 {
+        date.midnight().assume_utc().unix_timestamp() + self.rollover.as_secs() as i64
+    }
+
+    /// When the challenge for `date` is over, which is when the next one starts.
+    pub fn closes_at(&self, date: Date) -> i64 {
+        self.opens_at(date.next_day().expect("dates this far out aren't used"))
+    }
+}
+
+/// One day's challenge.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DailyChallenge {
+    pub date: Date,
+    pub seed: u64,
+    pub config: BoardConfig,
+    /// Everyone starts with this cell revealed.
+    pub start: Position,
+}
+
+impl DailyChallenge {
+    pub fn new(date: Date, config: BoardConfig) -> Self {
+        Self {
+            date,
+            seed: seed_for(date),
+            config,
+            start: Position::new(config.width() / 2, config.height() / 2),
+        }
+    }
+
+    /// The settings attempts are played with: the same clock as a battle royale, but the
+    /// storm never comes.
+    pub fn match_settings() -> BattleRoyaleSettings {
+        BattleRoyaleSettings {
+            first_storm: u64::MAX,
+            storm_interval: u64::MAX,
+            ..BattleRoyaleSettings::default()
+        }
+    }
+}
+
+/// The seed a date's board is generated from.
+pub fn seed_for(date: Date) -> u64 {
+    // Mixed up so that boards on consecutive days don't start from similar seeds.
+    SeededRng::new(date.to_julian_day() as u64).next_u64()
+}
+
+/// Formats a date the way it shows up in URLs and the database: `2022-07-31`.
+pub fn format_date(date: Date) -> String {
+    format!(
+        "{:04}-{:02}-{:02}",
+        date.year(),
+        u8::from(date.month()),
+        date.day()
+    )
+}
+
+/// Reads a date written by [`format_date`].
+pub fn parse_date(text: &str) -> Option<Date> {
+    let mut parts = text.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = Month::try_from(parts.next()?.parse::<u8>().ok()?).ok()?;
+    let day = parts.next()?.parse().ok()?;
+    Date::from_calendar_date(year, month, day).ok()
+}
@@ -0,0 +1,20 @@
+use snafu::prelude::*;
+
+use crate::domain::battle_royale::ActionError;
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+#[snafu(visibility(pub(crate)))]
+pub enum DailyError {
+    #[snafu(display("You've already had your attempt at the {date} challenge"))]
+    AlreadyAttempted { date: String },
+    #[snafu(display("You haven't started today's challenge"))]
+    NoAttempt,
+    #[snafu(display("Your attempt at today's challenge is over"))]
+    AttemptOver,
+    #[snafu(display("{source}"))]
+    InvalidMove { source: ActionError },
+    #[snafu(display("{date:?} isn't a date like 2022-07-31"))]
+    InvalidDate { date: String },
+    #[snafu(display("There was no challenge on {date}"))]
+    ChallengeNotFound { date: String },
+}
@@ -0,0 +1,13 @@
+//! The daily challenge: one board a day that everyone gets a single attempt at.
+//!
+//! Each day's board comes from a seed worked out from the date, so it's the same for
+//! everyone. An attempt is played as a match with one player and no storm, which means its
+//! log and replay work just like any other match's.
+
+mod challenge;
+mod errors;
+mod streak;
+
+pub use challenge::*;
+pub use errors::DailyError;
+pub use streak::*;
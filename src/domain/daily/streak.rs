@@ -0,0 +1,39 @@
+use time::Date;
+
+/// How many daily challenges in a row a player has cleared.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Streak {
+    pub current: u32,
+    pub best: u32,
+    /// The last challenge the player cleared.
+    pub last_cleared: Option<Date>,
+}
+
+impl Streak {
+    /// The streak after clearing the challenge for `date`.
+    pub fn cleared(&self, date: Date) -> Self {
+        let current = match self.last_cleared {
+            Some(last) if last == date => return *self,
+            Some(last) if last.next_day() == Some(date) => self.current + 1,
+            _ => 1,
+        };
+        Self {
+            current,
+            best: self.best.max(current),
+            last_cleared: Some(date),
+        }
+    }
+
+    /// The streak as it stands on `today`. It's broken once a day goes by without a clear,
+    /// but today's challenge can still be played to keep it going.
+    pub fn as_of(&self, today: Date) -> Self {
+        let alive = self
+            .last_cleared
+            .and_then(Date::next_day)
+            .is_some_and(|day_after| day_after >= today);
+        Self {
+            current: if alive { self.current } else { 0 },
+            ..*self
+        }
+    }
+}
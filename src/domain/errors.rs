@@ -1,18 +1,118 @@
-use actix_web::{self, HttpResponse, ResponseError};
+use actix_web::{self, http::StatusCode, HttpResponse, ResponseError};
 use argon2::password_hash;
 use deadpool_sqlite::{rusqlite, InteractError, PoolError};
 use handlebars::RenderError;
 use snafu::{prelude::*, Backtrace};
 use tokio::task::JoinError;
+use tracing_subscriber::{filter::ParseError, reload};
+
+use super::{
+    anticheat::AntiCheatError, chat::ChatError, daily::DailyError, friends::FriendError,
+    leaderboard::LeaderboardError, lobby::LobbyError, notifications::NotificationError,
+    replay::ReplayError, spectate::SpectateError, stats::StatsError, tournament::TournamentError,
+};
 
 #[derive(Debug, Snafu)]
 pub(crate) struct ServerError(pub(crate) InnerError);
 
 impl ResponseError for ServerError {
+    fn status_code(&self) -> StatusCode {
+        match &self.0 {
+            InnerError::Unauthenticated | InnerError::InvalidAdminToken => StatusCode::UNAUTHORIZED,
+            InnerError::NotModerator => StatusCode::FORBIDDEN,
+            InnerError::MatchNotFound { .. } => StatusCode::NOT_FOUND,
+            InnerError::MatchNotFinished { .. } => StatusCode::CONFLICT,
+            InnerError::UnknownGameMode { .. } => StatusCode::NOT_FOUND,
+            InnerError::UserNotFound { .. } => StatusCode::NOT_FOUND,
+            InnerError::UsernameTaken { .. } => StatusCode::CONFLICT,
+            InnerError::ReservedUsername { .. }
+            | InnerError::TooManyBotMatches { .. }
+            | InnerError::InvalidBotMatchPlayers { .. } => StatusCode::BAD_REQUEST,
+            InnerError::LobbyError { source } => match source {
+                LobbyError::LobbyNotFound { .. } | LobbyError::NotInLobby => StatusCode::NOT_FOUND,
+                LobbyError::NotHost => StatusCode::FORBIDDEN,
+                LobbyError::InvalidSettings { .. } | LobbyError::InvalidBoard { .. } => {
+                    StatusCode::BAD_REQUEST
+                }
+                _ => StatusCode::CONFLICT,
+            },
+            InnerError::DailyError { source } => match source {
+                DailyError::AlreadyAttempted { .. } | DailyError::AttemptOver => {
+                    StatusCode::CONFLICT
+                }
+                DailyError::NoAttempt | DailyError::ChallengeNotFound { .. } => {
+                    StatusCode::NOT_FOUND
+                }
+                DailyError::InvalidMove { .. } | DailyError::InvalidDate { .. } => {
+                    StatusCode::BAD_REQUEST
+                }
+            },
+            InnerError::LeaderboardError { source } => match source {
+                LeaderboardError::InvalidCursor { .. } => StatusCode::BAD_REQUEST,
+                LeaderboardError::SeasonNotOver { .. } => StatusCode::CONFLICT,
+                _ => StatusCode::NOT_FOUND,
+            },
+            InnerError::SpectateError { source } => match source {
+                SpectateError::MatchNotLive { .. } => StatusCode::NOT_FOUND,
+                SpectateError::OwnMatch => StatusCode::FORBIDDEN,
+                SpectateError::NotInMatch { .. } => StatusCode::BAD_REQUEST,
+            },
+            InnerError::ChatError { source } => match source {
+                ChatError::Muted { .. } => StatusCode::FORBIDDEN,
+                ChatError::MessageNotFound { .. } | ChatError::PlayerNotFound { .. } => {
+                    StatusCode::NOT_FOUND
+                }
+                ChatError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+                ChatError::NoRoom => StatusCode::CONFLICT,
+                ChatError::EmptyMessage
+                | ChatError::MessageTooLong { .. }
+                | ChatError::IgnoringSelf => StatusCode::BAD_REQUEST,
+            },
+            InnerError::FriendError { source } => match source {
+                FriendError::UserNotFound { .. }
+                | FriendError::NoRequest { .. }
+                | FriendError::NotFriends { .. } => StatusCode::NOT_FOUND,
+                FriendError::Blocked => StatusCode::FORBIDDEN,
+                FriendError::AlreadyFriends | FriendError::AlreadyRequested => StatusCode::CONFLICT,
+                FriendError::FriendingSelf => StatusCode::BAD_REQUEST,
+            },
+            InnerError::AntiCheatError { source } => match source {
+                AntiCheatError::FlagNotFound { .. } => StatusCode::NOT_FOUND,
+                AntiCheatError::AlreadyReviewed { .. } => StatusCode::CONFLICT,
+            },
+            InnerError::TournamentError { source } => match source {
+                TournamentError::TournamentNotFound { .. } | TournamentError::NotRegistered => {
+                    StatusCode::NOT_FOUND
+                }
+                TournamentError::InvalidFormat { .. } | TournamentError::StartsInPast => {
+                    StatusCode::BAD_REQUEST
+                }
+                TournamentError::RegistrationClosed
+                | TournamentError::AlreadyRegistered
+                | TournamentError::TournamentFull { .. } => StatusCode::CONFLICT,
+            },
+            InnerError::NotificationError { source } => match source {
+                NotificationError::NotificationNotFound { .. } => StatusCode::NOT_FOUND,
+            },
+            InnerError::ReplayError {
+                source: ReplayError::IndexOutOfRange { .. },
+            } => StatusCode::BAD_REQUEST,
+            InnerError::InvalidLogFilter { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
     fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        let status_code = self.status_code();
+        if status_code.is_client_error() {
+            // These are the caller's fault, so tell them what they did wrong.
+            return HttpResponse::build(status_code)
+                .content_type("text/plain")
+                .body(self.0.to_string());
+        }
         // TODO: put more information here, such as some sort of Trace ID
         //       so I can look in the logs using that Trace ID later.
-        HttpResponse::new(self.status_code())
+        HttpResponse::new(status_code)
     }
 }
 
@@ -45,9 +145,75 @@ pub(crate) enum InnerError {
     },
     #[snafu(display("Failed to render the template {template_name}"))]
     TemplateRenderingError {
-        source: RenderError,
+        #[snafu(source(from(RenderError, Box::new)))]
+        source: Box<RenderError>,
+        backtrace: Backtrace,
+        template_name: String,
+    },
+    #[snafu(display("You need to be logged in to do that"))]
+    Unauthenticated,
+    #[snafu(display("The admin token is missing or wrong"))]
+    InvalidAdminToken,
+    #[snafu(display("Only moderators can do that"))]
+    NotModerator,
+    #[snafu(display("{source}"))]
+    LobbyError { source: LobbyError },
+    #[snafu(display("Match {match_id} doesn't exist"))]
+    MatchNotFound { match_id: i64 },
+    #[snafu(display("Match {match_id} isn't over yet"))]
+    MatchNotFinished { match_id: i64 },
+    #[snafu(display("There's no player {user_id}"))]
+    UserNotFound { user_id: i64 },
+    #[snafu(display("Someone's already called {username:?}"))]
+    UsernameTaken { username: String },
+    #[snafu(display("Names starting with {prefix:?} are kept for bots"))]
+    ReservedUsername { prefix: &'static str },
+    #[snafu(display("You can start between 1 and {max} bot matches at a time"))]
+    TooManyBotMatches { max: usize },
+    #[snafu(display("Bot matches need between {min} and {max} players"))]
+    InvalidBotMatchPlayers { min: usize, max: usize },
+    #[snafu(display("There's no game mode called {mode:?}"))]
+    UnknownGameMode { mode: String },
+    #[snafu(display("Failed to read an entry in a match's log"))]
+    MatchLogError {
+        source: serde_json::Error,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("{source}"))]
+    ReplayError { source: ReplayError },
+    #[snafu(display("{source}"))]
+    LeaderboardError { source: LeaderboardError },
+    #[snafu(display("{source}"))]
+    DailyError { source: DailyError },
+    #[snafu(display("{source}"))]
+    SpectateError { source: SpectateError },
+    #[snafu(display("{source}"))]
+    ChatError { source: ChatError },
+    #[snafu(display("{source}"))]
+    FriendError { source: FriendError },
+    #[snafu(display("{source}"))]
+    AntiCheatError { source: AntiCheatError },
+    #[snafu(display("{source}"))]
+    TournamentError { source: TournamentError },
+    #[snafu(display("Failed to read a tournament's saved state"))]
+    TournamentStateError {
+        source: serde_json::Error,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Failed to work out the stats for a match"))]
+    StatsError { source: StatsError },
+    #[snafu(display("{source}"))]
+    NotificationError { source: NotificationError },
+    #[snafu(display("Failed to read a saved notification"))]
+    NotificationBodyError {
+        source: serde_json::Error,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("That isn't a valid log filter: {source}"))]
+    InvalidLogFilter { source: ParseError },
+    #[snafu(display("Failed to get at the log filter"))]
+    LogFilterReloadError {
+        source: reload::Error,
         backtrace: Backtrace,
-        template_name: String
     },
-    InvalidUsernameOrPassword,
 }
@@ -0,0 +1,22 @@
+use snafu::prelude::*;
+
+use crate::domain::UserId;
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+#[snafu(visibility(pub(crate)))]
+pub enum FriendError {
+    #[snafu(display("You can't be friends with yourself"))]
+    FriendingSelf,
+    #[snafu(display("There's no player {user_id}"))]
+    UserNotFound { user_id: UserId },
+    #[snafu(display("You're already friends"))]
+    AlreadyFriends,
+    #[snafu(display("You've already sent them a friend request"))]
+    AlreadyRequested,
+    #[snafu(display("There's no friend request from player {user_id}"))]
+    NoRequest { user_id: UserId },
+    #[snafu(display("One of you has blocked the other"))]
+    Blocked,
+    #[snafu(display("Player {user_id} isn't your friend"))]
+    NotFriends { user_id: UserId },
+}
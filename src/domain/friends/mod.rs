@@ -0,0 +1,14 @@
+//! Friends, and seeing what they're up to.
+//!
+//! Two players become friends when one sends a request and the other accepts it. A block
+//! (the same one that hides chat) ends any friendship between two players and stops them
+//! sending each other requests. Friends can see each other's presence, and invite each
+//! other to their lobby.
+
+mod errors;
+mod presence;
+mod relationship;
+
+pub use errors::FriendError;
+pub use presence::*;
+pub use relationship::*;
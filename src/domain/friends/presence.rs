@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/// What a player is up to, as far as their friends can tell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Presence {
+    Offline,
+    Online,
+    InLobby,
+    InMatch { match_id: i64 },
+}
+
+impl Presence {
+    /// Players only count as around while they have the game channel open. A player who's
+    /// connected shows up in the most specific place they are.
+    pub fn new(connected: bool, in_lobby: bool, match_id: Option<i64>) -> Self {
+        match (connected, match_id) {
+            (false, _) => Self::Offline,
+            (true, Some(match_id)) => Self::InMatch { match_id },
+            (true, None) if in_lobby => Self::InLobby,
+            (true, None) => Self::Online,
+        }
+    }
+}
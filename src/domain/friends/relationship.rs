@@ -0,0 +1,54 @@
+use snafu::prelude::*;
+
+use super::errors::*;
+use crate::domain::UserId;
+
+/// How one player stands with another, from the first player's point of view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Relationship {
+    Strangers,
+    Friends,
+    /// The player asked the other to be friends, and is waiting to hear back.
+    RequestSent,
+    /// The other player asked to be friends.
+    RequestReceived,
+    /// Either of them blocked the other.
+    Blocked,
+}
+
+/// What sending a friend request ends up doing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestOutcome {
+    /// The request is waiting for the other player to answer it.
+    Sent,
+    /// The other player had already asked, so now they're friends.
+    Accepted,
+}
+
+impl Relationship {
+    /// Checks the player can send the other player a friend request.
+    pub fn send_request(self) -> Result<RequestOutcome, FriendError> {
+        match self {
+            Self::Strangers => Ok(RequestOutcome::Sent),
+            Self::RequestReceived => Ok(RequestOutcome::Accepted),
+            Self::Friends => AlreadyFriendsSnafu.fail(),
+            Self::RequestSent => AlreadyRequestedSnafu.fail(),
+            Self::Blocked => BlockedSnafu.fail(),
+        }
+    }
+
+    /// Checks there's a request from `other` for the player to accept or decline.
+    pub fn answer_request(self, other: UserId) -> Result<(), FriendError> {
+        ensure!(
+            self == Self::RequestReceived,
+            NoRequestSnafu { user_id: other }
+        );
+        Ok(())
+    }
+
+    /// Checks the player and `other` are friends, for the things only friends can do.
+    pub fn ensure_friends(self, other: UserId) -> Result<(), FriendError> {
+        ensure!(self == Self::Friends, NotFriendsSnafu { user_id: other });
+        Ok(())
+    }
+}
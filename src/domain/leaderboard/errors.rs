@@ -0,0 +1,16 @@
+use snafu::prelude::*;
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+#[snafu(visibility(pub(crate)))]
+pub enum LeaderboardError {
+    #[snafu(display("There's no leaderboard called {name:?}"))]
+    UnknownBoard { name: String },
+    #[snafu(display("{cursor:?} isn't a valid cursor"))]
+    InvalidCursor { cursor: String },
+    #[snafu(display("You aren't on this leaderboard yet"))]
+    NotRanked,
+    #[snafu(display("There's no season {season_id}"))]
+    SeasonNotFound { season_id: i64 },
+    #[snafu(display("Season {season_id} isn't over yet"))]
+    SeasonNotOver { season_id: i64 },
+}
@@ -0,0 +1,13 @@
+//! Leaderboards and seasons.
+//!
+//! Ranking everyone means sorting every rating, which is too much work to do on every read.
+//! Leaderboards are instead served from [`Leaderboard`] snapshots that are taken every so
+//! often. Pages are found with a [`Cursor`] pointing at the last entry of the previous page
+//! rather than an offset, so paging through stays consistent when a new snapshot is taken in
+//! the middle of it.
+
+mod errors;
+mod standings;
+
+pub use errors::LeaderboardError;
+pub use standings::*;
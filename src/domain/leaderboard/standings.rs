@@ -0,0 +1,235 @@
+use std::{cmp::Ordering, fmt, str::FromStr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+
+use super::errors::*;
+use crate::domain::{lobby::GameMode, UserId};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LeaderboardSettings {
+    /// How often new leaderboard snapshots are taken.
+    pub refresh_interval: Duration,
+    /// How long a season lasts. Ratings are partly reset and the final standings archived
+    /// when it's over.
+    pub season_length: Duration,
+    /// Players need to have played this many matches in a mode this season to show up on
+    /// its leaderboard.
+    pub min_matches: u32,
+}
+
+impl Default for LeaderboardSettings {
+    fn default() -> Self {
+        Self {
+            refresh_interval: Duration::from_secs(60),
+            season_length: Duration::from_secs(90 * 24 * 60 * 60),
+            min_matches: 1,
+        }
+    }
+}
+
+/// Which leaderboard to look at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Board {
+    /// Every player, ranked by their best rating in any mode.
+    Global,
+    Mode(GameMode),
+}
+
+impl Board {
+    /// Every leaderboard there is.
+    pub fn all() -> Vec<Self> {
+        let mut boards = vec![Self::Global];
+        boards.extend(GameMode::ALL.into_iter().map(Self::Mode));
+        boards
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Global => "global",
+            Self::Mode(mode) => mode.as_str(),
+        }
+    }
+
+    /// The board with the given [`Board::name`].
+    pub fn from_name(name: &str) -> Result<Self, LeaderboardError> {
+        Self::all()
+            .into_iter()
+            .find(|board| board.name() == name)
+            .context(UnknownBoardSnafu { name })
+    }
+}
+
+/// A player's rating, as it's shown on a leaderboard.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerRating {
+    pub user_id: UserId,
+    pub username: String,
+    pub rating: f64,
+    pub deviation: f64,
+    /// Matches played this season.
+    pub matches_played: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    /// Players with the same rating share a rank, and the ranks after them are skipped.
+    pub rank: usize,
+    #[serde(flatten)]
+    pub player: PlayerRating,
+}
+
+/// Where a page of a leaderboard starts: right after the entry it was taken from.
+///
+/// Its text form, used in URLs, is the entry's rating and user ID separated by an underscore.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cursor {
+    pub rating: f64,
+    pub user_id: UserId,
+}
+
+impl Cursor {
+    pub fn after(entry: &LeaderboardEntry) -> Self {
+        Self {
+            rating: entry.player.rating,
+            user_id: entry.player.user_id,
+        }
+    }
+
+    /// Whether `player` is placed before this cursor's entry (or is the entry itself).
+    fn is_past(&self, player: &PlayerRating) -> bool {
+        compare(player.rating, player.user_id, self.rating, self.user_id) != Ordering::Greater
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.rating, self.user_id)
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = LeaderboardError;
+
+    fn from_str(cursor: &str) -> Result<Self, Self::Err> {
+        cursor
+            .rsplit_once('_')
+            .and_then(|(rating, user_id)| {
+                Some(Self {
+                    rating: rating
+                        .parse()
+                        .ok()
+                        .filter(|rating: &f64| rating.is_finite())?,
+                    user_id: user_id.parse().ok()?,
+                })
+            })
+            .context(InvalidCursorSnafu { cursor })
+    }
+}
+
+/// Part of a leaderboard.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LeaderboardPage {
+    pub board: String,
+    pub season_id: i64,
+    /// When the snapshot the page came from was taken, as a unix timestamp.
+    pub taken_at: i64,
+    pub entries: Vec<LeaderboardEntry>,
+    /// Where the page after this one starts, if there is one.
+    pub next: Option<String>,
+}
+
+/// A snapshot of one leaderboard.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Leaderboard {
+    board: Board,
+    season_id: i64,
+    taken_at: i64,
+    entries: Vec<LeaderboardEntry>,
+}
+
+impl Leaderboard {
+    /// Ranks `players`, highest rating first. Ties are broken by user ID so every entry has a
+    /// fixed place to page from.
+    pub fn new(
+        board: Board,
+        season_id: i64,
+        taken_at: i64,
+        mut players: Vec<PlayerRating>,
+    ) -> Self {
+        players.sort_by(|a, b| compare(a.rating, a.user_id, b.rating, b.user_id));
+        let mut entries: Vec<LeaderboardEntry> = Vec::with_capacity(players.len());
+        for (index, player) in players.into_iter().enumerate() {
+            let rank = match entries.last() {
+                Some(last) if last.player.rating == player.rating => last.rank,
+                _ => index + 1,
+            };
+            entries.push(LeaderboardEntry { rank, player });
+        }
+        Self {
+            board,
+            season_id,
+            taken_at,
+            entries,
+        }
+    }
+
+    pub fn board(&self) -> Board {
+        self.board
+    }
+
+    pub fn season_id(&self) -> i64 {
+        self.season_id
+    }
+
+    pub fn taken_at(&self) -> i64 {
+        self.taken_at
+    }
+
+    pub fn entries(&self) -> &[LeaderboardEntry] {
+        &self.entries
+    }
+
+    /// Up to `limit` entries, starting right after `after` or at the top.
+    pub fn page(&self, after: Option<Cursor>, limit: usize) -> LeaderboardPage {
+        let start = after.map_or(0, |cursor| {
+            self.entries
+                .partition_point(|entry| cursor.is_past(&entry.player))
+        });
+        let end = (start + limit).min(self.entries.len());
+        self.page_of(start, end)
+    }
+
+    /// `player`'s entry, along with up to `radius` entries either side of it. Players who
+    /// aren't on the leaderboard get nothing.
+    pub fn around(&self, player: UserId, radius: usize) -> Option<LeaderboardPage> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.player.user_id == player)?;
+        let start = index.saturating_sub(radius);
+        let end = (index + radius + 1).min(self.entries.len());
+        Some(self.page_of(start, end))
+    }
+
+    fn page_of(&self, start: usize, end: usize) -> LeaderboardPage {
+        let entries = self.entries[start..end].to_vec();
+        LeaderboardPage {
+            board: self.board.name().to_string(),
+            season_id: self.season_id,
+            taken_at: self.taken_at,
+            next: entries
+                .last()
+                .filter(|_| end < self.entries.len())
+                .map(|last| Cursor::after(last).to_string()),
+            entries,
+        }
+    }
+}
+
+/// The order entries go in: highest rating first, then lowest user ID.
+fn compare(rating: f64, user_id: UserId, other_rating: f64, other_user_id: UserId) -> Ordering {
+    other_rating
+        .total_cmp(&rating)
+        .then(user_id.cmp(&other_user_id))
+}
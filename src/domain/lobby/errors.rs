@@ -0,0 +1,37 @@
+use snafu::prelude::*;
+
+use super::LobbyCode;
+use crate::domain::{minesweeper::BoardError, UserId};
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+#[snafu(visibility(pub(crate)))]
+pub enum LobbyError {
+    #[snafu(display("There is no lobby with the code {code}"))]
+    LobbyNotFound { code: LobbyCode },
+    #[snafu(display("You are already in lobby {code}"))]
+    AlreadyInLobby { code: LobbyCode },
+    #[snafu(display("You are not in a lobby"))]
+    NotInLobby,
+    #[snafu(display("Only the host of the lobby can do that"))]
+    NotHost,
+    #[snafu(display("The lobby is full"))]
+    LobbyFull,
+    #[snafu(display("The lobby is in the middle of a match"))]
+    MatchInProgress,
+    #[snafu(display("The lobby is not in a match"))]
+    NoMatchInProgress,
+    #[snafu(display("At least {min} players are needed to start a match"))]
+    NotEnoughPlayers { min: usize },
+    #[snafu(display("Player {user_id} is not in this lobby"))]
+    PlayerNotInLobby { user_id: UserId },
+    #[snafu(display("The host can't do that to themselves"))]
+    TargetIsHost,
+    #[snafu(display("Invalid lobby settings: {reason}"))]
+    InvalidSettings { reason: String },
+    #[snafu(display("Invalid board settings: {source}"))]
+    InvalidBoard { source: BoardError },
+    #[snafu(display("You are already in the matchmaking queue"))]
+    AlreadyQueued,
+    #[snafu(display("You are still playing match {match_id}"))]
+    StillPlaying { match_id: i64 },
+}
@@ -0,0 +1,483 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+
+use super::errors::*;
+use crate::domain::{
+    minesweeper::{BoardConfig, BoardTopology, SeededRng},
+    User, UserId,
+};
+
+/// The fewest players a match can be started with.
+pub const MIN_PLAYERS: usize = 2;
+/// The most players a lobby can be configured to hold.
+pub const MAX_PLAYERS: usize = 100;
+
+const CODE_LENGTH: usize = 6;
+/// No 0/O or 1/I, so codes can be read out loud without confusion.
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// The short code players use to find and join a lobby.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "String")]
+pub struct LobbyCode(String);
+
+impl LobbyCode {
+    fn random(rng: &mut SeededRng) -> Self {
+        Self(
+            (0..CODE_LENGTH)
+                .map(|_| CODE_ALPHABET[rng.below(CODE_ALPHABET.len())] as char)
+                .collect(),
+        )
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for LobbyCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Codes are case-insensitive, so they're normalised to upper case when parsed.
+impl From<String> for LobbyCode {
+    fn from(code: String) -> Self {
+        Self(code.trim().to_ascii_uppercase())
+    }
+}
+
+impl FromStr for LobbyCode {
+    type Err = std::convert::Infallible;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(code.to_string()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    /// Listed for anyone to join.
+    Public,
+    /// Only joinable with the code.
+    Private,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMode {
+    BattleRoyale,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 1] = [GameMode::BattleRoyale];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::BattleRoyale => "battle_royale",
+        }
+    }
+
+    /// The mode with the given [`GameMode::as_str`] name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.as_str() == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LobbySettings {
+    pub visibility: Visibility,
+    pub mode: GameMode,
+    pub width: usize,
+    pub height: usize,
+    /// Lobbies that don't say are played on square boards.
+    #[serde(default)]
+    pub topology: BoardTopology,
+    /// Whether players earn and use power-ups in the lobby's matches.
+    #[serde(default)]
+    pub power_ups: bool,
+    /// The percentage of cells that are mines.
+    pub mine_density: u8,
+    pub max_players: usize,
+}
+
+impl Default for LobbySettings {
+    fn default() -> Self {
+        Self {
+            visibility: Visibility::Public,
+            mode: GameMode::BattleRoyale,
+            width: 16,
+            height: 16,
+            topology: BoardTopology::Square,
+            power_ups: false,
+            mine_density: 15,
+            max_players: 8,
+        }
+    }
+}
+
+impl LobbySettings {
+    /// The board every player in the match will play on. Also validates the settings, since
+    /// settings that don't make a valid board are the main thing that can go wrong.
+    pub fn board_config(&self) -> Result<BoardConfig, LobbyError> {
+        ensure!(
+            (1..=50).contains(&self.mine_density),
+            InvalidSettingsSnafu {
+                reason: "mine density must be between 1% and 50%"
+            }
+        );
+        ensure!(
+            (MIN_PLAYERS..=MAX_PLAYERS).contains(&self.max_players),
+            InvalidSettingsSnafu {
+                reason: format!(
+                    "max players must be between {} and {}",
+                    MIN_PLAYERS, MAX_PLAYERS
+                )
+            }
+        );
+        ensure!(
+            self.width <= 100 && self.height <= 100,
+            InvalidSettingsSnafu {
+                reason: "boards can be at most 100x100"
+            }
+        );
+        let cells = self.width * self.height;
+        let mines = (cells * self.mine_density as usize / 100).max(1);
+        BoardConfig::with_topology(self.width, self.height, mines, self.topology)
+            .context(InvalidBoardSnafu)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LobbyMember {
+    pub user: User,
+    pub ready: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LobbyStatus {
+    Waiting,
+    InMatch,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lobby {
+    pub code: LobbyCode,
+    pub host: UserId,
+    pub settings: LobbySettings,
+    pub members: Vec<LobbyMember>,
+    pub status: LobbyStatus,
+}
+
+impl Lobby {
+    pub fn is_member(&self, user_id: UserId) -> bool {
+        self.members
+            .iter()
+            .any(|member| member.user.id() == user_id)
+    }
+
+    fn is_full(&self) -> bool {
+        self.members.len() >= self.settings.max_players
+    }
+
+    fn should_auto_start(&self) -> bool {
+        self.status == LobbyStatus::Waiting
+            && self.members.len() >= MIN_PLAYERS
+            && self.members.iter().all(|member| member.ready)
+    }
+
+    fn ensure_waiting(&self) -> Result<(), LobbyError> {
+        ensure!(self.status == LobbyStatus::Waiting, MatchInProgressSnafu);
+        Ok(())
+    }
+}
+
+/// Everything needed to set up a match for a lobby that just started.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MatchStart {
+    pub code: LobbyCode,
+    pub settings: LobbySettings,
+    pub board: BoardConfig,
+    pub players: Vec<User>,
+    /// The seed every player's board is generated from.
+    pub seed: u64,
+}
+
+/// Every open lobby, and which lobby each player is in. A player can be in at most one lobby
+/// at a time.
+pub struct Lobbies {
+    lobbies: HashMap<LobbyCode, Lobby>,
+    member_of: HashMap<UserId, LobbyCode>,
+    rng: SeededRng,
+}
+
+impl Lobbies {
+    /// `seed` drives lobby codes and match seeds. In production this should come from a
+    /// proper source of randomness so neither can be predicted.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            lobbies: HashMap::new(),
+            member_of: HashMap::new(),
+            rng: SeededRng::new(seed),
+        }
+    }
+
+    pub fn get(&self, code: &LobbyCode) -> Result<&Lobby, LobbyError> {
+        self.lobbies
+            .get(code)
+            .context(LobbyNotFoundSnafu { code: code.clone() })
+    }
+
+    /// The lobby `user_id` is currently in, if any.
+    pub fn lobby_of(&self, user_id: UserId) -> Option<&Lobby> {
+        self.member_of
+            .get(&user_id)
+            .and_then(|code| self.lobbies.get(code))
+    }
+
+    /// Public lobbies that are waiting for players and have room, oldest codes first.
+    pub fn open_public_lobbies(&self) -> Vec<&Lobby> {
+        let mut lobbies: Vec<&Lobby> = self
+            .lobbies
+            .values()
+            .filter(|lobby| lobby.settings.visibility == Visibility::Public)
+            .filter(|lobby| lobby.status == LobbyStatus::Waiting && !lobby.is_full())
+            .collect();
+        lobbies.sort_by(|a, b| a.code.cmp(&b.code));
+        lobbies
+    }
+
+    pub fn create(&mut self, host: User, settings: LobbySettings) -> Result<&Lobby, LobbyError> {
+        self.ensure_not_in_lobby(host.id())?;
+        settings.board_config()?;
+        let code = loop {
+            let code = LobbyCode::random(&mut self.rng);
+            if !self.lobbies.contains_key(&code) {
+                break code;
+            }
+        };
+        self.member_of.insert(host.id(), code.clone());
+        self.lobbies.insert(
+            code.clone(),
+            Lobby {
+                code: code.clone(),
+                host: host.id(),
+                settings,
+                members: vec![LobbyMember {
+                    user: host,
+                    ready: false,
+                }],
+                status: LobbyStatus::Waiting,
+            },
+        );
+        self.get(&code)
+    }
+
+    pub fn join(&mut self, code: &LobbyCode, user: User) -> Result<&Lobby, LobbyError> {
+        self.ensure_not_in_lobby(user.id())?;
+        let lobby = self
+            .lobbies
+            .get_mut(code)
+            .context(LobbyNotFoundSnafu { code: code.clone() })?;
+        lobby.ensure_waiting()?;
+        ensure!(!lobby.is_full(), LobbyFullSnafu);
+        self.member_of.insert(user.id(), code.clone());
+        lobby.members.push(LobbyMember { user, ready: false });
+        self.get(code)
+    }
+
+    /// Removes the player from their lobby. If they were the host, the longest-standing
+    /// member takes over. Empty lobbies are closed.
+    pub fn leave(&mut self, user_id: UserId) -> Result<(), LobbyError> {
+        let code = self.member_of.remove(&user_id).context(NotInLobbySnafu)?;
+        let lobby = self
+            .lobbies
+            .get_mut(&code)
+            .expect("member_of only points at open lobbies");
+        lobby.members.retain(|member| member.user.id() != user_id);
+        match lobby.members.first() {
+            None => {
+                self.lobbies.remove(&code);
+            }
+            Some(next_host) if lobby.host == user_id => lobby.host = next_host.user.id(),
+            Some(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Marks the player as (not) ready. If that makes everyone ready, the match starts.
+    pub fn set_ready(
+        &mut self,
+        user_id: UserId,
+        ready: bool,
+    ) -> Result<Option<MatchStart>, LobbyError> {
+        let lobby = self.lobby_of_mut(user_id)?;
+        lobby.ensure_waiting()?;
+        for member in lobby.members.iter_mut() {
+            if member.user.id() == user_id {
+                member.ready = ready;
+            }
+        }
+        if lobby.should_auto_start() {
+            let code = lobby.code.clone();
+            self.start_lobby(&code).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Changes the lobby's settings. Everyone has to ready up again afterwards, since they
+    /// agreed to the old settings.
+    pub fn update_settings(
+        &mut self,
+        host_id: UserId,
+        settings: LobbySettings,
+    ) -> Result<&Lobby, LobbyError> {
+        settings.board_config()?;
+        let lobby = self.hosted_lobby_mut(host_id)?;
+        lobby.ensure_waiting()?;
+        ensure!(
+            lobby.members.len() <= settings.max_players,
+            InvalidSettingsSnafu {
+                reason: "max players can't be lower than the number of players in the lobby"
+            }
+        );
+        lobby.settings = settings;
+        for member in lobby.members.iter_mut() {
+            member.ready = false;
+        }
+        let code = lobby.code.clone();
+        self.get(&code)
+    }
+
+    pub fn kick(&mut self, host_id: UserId, target: UserId) -> Result<(), LobbyError> {
+        let lobby = self.hosted_lobby_mut(host_id)?;
+        ensure!(target != host_id, TargetIsHostSnafu);
+        ensure!(
+            lobby.is_member(target),
+            PlayerNotInLobbySnafu { user_id: target }
+        );
+        self.leave(target)
+    }
+
+    pub fn transfer_host(&mut self, host_id: UserId, target: UserId) -> Result<(), LobbyError> {
+        let lobby = self.hosted_lobby_mut(host_id)?;
+        ensure!(target != host_id, TargetIsHostSnafu);
+        ensure!(
+            lobby.is_member(target),
+            PlayerNotInLobbySnafu { user_id: target }
+        );
+        lobby.host = target;
+        Ok(())
+    }
+
+    /// Lets the host start without waiting for everyone to be ready.
+    pub fn start(&mut self, host_id: UserId) -> Result<MatchStart, LobbyError> {
+        let lobby = self.hosted_lobby_mut(host_id)?;
+        lobby.ensure_waiting()?;
+        ensure!(
+            lobby.members.len() >= MIN_PLAYERS,
+            NotEnoughPlayersSnafu { min: MIN_PLAYERS }
+        );
+        let code = lobby.code.clone();
+        self.start_lobby(&code)
+    }
+
+    /// Creates a private lobby for a group of players that were matched together and starts
+    /// it straight away. The first player is made the host.
+    pub fn create_matched(
+        &mut self,
+        players: Vec<User>,
+        settings: LobbySettings,
+    ) -> Result<MatchStart, LobbyError> {
+        let mut players = players.into_iter();
+        let host = players
+            .next()
+            .context(NotEnoughPlayersSnafu { min: MIN_PLAYERS })?;
+        let host_id = host.id();
+        let code = self.create(host, settings)?.code.clone();
+        for player in players {
+            if let Err(error) = self.join(&code, player) {
+                self.close(&code);
+                return Err(error);
+            }
+        }
+        match self.start(host_id) {
+            Ok(start) => Ok(start),
+            Err(error) => {
+                self.close(&code);
+                Err(error)
+            }
+        }
+    }
+
+    /// Puts a lobby back into the waiting state once its match is over. Everyone has to
+    /// ready up again for the next one.
+    pub fn finish_match(&mut self, code: &LobbyCode) -> Result<&Lobby, LobbyError> {
+        let lobby = self
+            .lobbies
+            .get_mut(code)
+            .context(LobbyNotFoundSnafu { code: code.clone() })?;
+        ensure!(lobby.status == LobbyStatus::InMatch, NoMatchInProgressSnafu);
+        lobby.status = LobbyStatus::Waiting;
+        for member in lobby.members.iter_mut() {
+            member.ready = false;
+        }
+        self.get(code)
+    }
+
+    fn start_lobby(&mut self, code: &LobbyCode) -> Result<MatchStart, LobbyError> {
+        let seed = self.rng.next_u64();
+        let lobby = self
+            .lobbies
+            .get_mut(code)
+            .context(LobbyNotFoundSnafu { code: code.clone() })?;
+        let board = lobby.settings.board_config()?;
+        lobby.status = LobbyStatus::InMatch;
+        Ok(MatchStart {
+            code: code.clone(),
+            settings: lobby.settings.clone(),
+            board,
+            players: lobby
+                .members
+                .iter()
+                .map(|member| member.user.clone())
+                .collect(),
+            seed,
+        })
+    }
+
+    fn close(&mut self, code: &LobbyCode) {
+        if let Some(lobby) = self.lobbies.remove(code) {
+            for member in lobby.members {
+                self.member_of.remove(&member.user.id());
+            }
+        }
+    }
+
+    fn ensure_not_in_lobby(&self, user_id: UserId) -> Result<(), LobbyError> {
+        match self.member_of.get(&user_id) {
+            Some(code) => AlreadyInLobbySnafu { code: code.clone() }.fail(),
+            None => Ok(()),
+        }
+    }
+
+    fn lobby_of_mut(&mut self, user_id: UserId) -> Result<&mut Lobby, LobbyError> {
+        let code = self.member_of.get(&user_id).context(NotInLobbySnafu)?;
+        Ok(self
+            .lobbies
+            .get_mut(code)
+            .expect("member_of only points at open lobbies"))
+    }
+
+    fn hosted_lobby_mut(&mut self, host_id: UserId) -> Result<&mut Lobby, LobbyError> {
+        let lobby = self.lobby_of_mut(host_id)?;
+        ensure!(lobby.host == host_id, NotHostSnafu);
+        Ok(lobby)
+    }
+}
@@ -0,0 +1,203 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+
+use super::{errors::*, GameMode, LobbySettings, Visibility, MIN_PLAYERS};
+use crate::domain::{User, UserId};
+
+/// The rating a player without any rated matches is treated as having.
+pub const DEFAULT_RATING: f64 = 1500.0;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MatchmakingSettings {
+    /// How far apart two players' ratings can be when they first join the queue.
+    pub initial_window: f64,
+    /// How much the window grows for every second a player has been waiting.
+    pub widen_per_second: f64,
+    /// The window never grows past this.
+    pub max_window: f64,
+    /// Once a big enough group is found, how long to hold off starting it while waiting for
+    /// the group to fill up. Full groups start immediately.
+    pub fill_time: Duration,
+    /// The lobby settings used for matches made by the queue. The group size comes from
+    /// `max_players`.
+    pub lobby: LobbySettings,
+}
+
+impl Default for MatchmakingSettings {
+    fn default() -> Self {
+        Self {
+            initial_window: 100.0,
+            widen_per_second: 10.0,
+            max_window: 600.0,
+            fill_time: Duration::from_secs(15),
+            lobby: LobbySettings {
+                visibility: Visibility::Private,
+                ..LobbySettings::default()
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueueEntry {
+    pub player: User,
+    pub mode: GameMode,
+    pub rating: f64,
+    pub enqueued_at: Instant,
+}
+
+/// A group of players that should be put into a match together.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchedGroup {
+    pub mode: GameMode,
+    pub players: Vec<QueueEntry>,
+}
+
+/// Players waiting to be matched, grouped by rating.
+///
+/// Every player has a rating window that starts narrow and widens the longer they wait. Two
+/// players can only be matched if each is inside the other's window, so a newcomer can't
+/// drag a long-waiting player into a lopsided match (or the other way around).
+pub struct Matchmaker {
+    settings: MatchmakingSettings,
+    queue: Vec<QueueEntry>,
+}
+
+impl Matchmaker {
+    pub fn new(settings: MatchmakingSettings) -> Self {
+        Self {
+            settings,
+            queue: Vec::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &MatchmakingSettings {
+        &self.settings
+    }
+
+    pub fn enqueue(
+        &mut self,
+        player: User,
+        mode: GameMode,
+        rating: f64,
+        now: Instant,
+    ) -> Result<(), LobbyError> {
+        ensure!(!self.is_queued(player.id()), AlreadyQueuedSnafu);
+        self.queue.push(QueueEntry {
+            player,
+            mode,
+            rating,
+            enqueued_at: now,
+        });
+        Ok(())
+    }
+
+    /// Takes the player out of the queue. Returns whether they were in it.
+    pub fn dequeue(&mut self, user_id: UserId) -> bool {
+        let before = self.queue.len();
+        self.queue.retain(|entry| entry.player.id() != user_id);
+        before != self.queue.len()
+    }
+
+    pub fn is_queued(&self, user_id: UserId) -> bool {
+        self.queue.iter().any(|entry| entry.player.id() == user_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Pulls everyone out of the queue who's been waiting at least `wait`, so they can be
+    /// given a match some other way.
+    pub fn take_waiting(&mut self, wait: Duration, now: Instant) -> Vec<QueueEntry> {
+        let (waiting, rest) = std::mem::take(&mut self.queue)
+            .into_iter()
+            .partition(|entry| now.saturating_duration_since(entry.enqueued_at) >= wait);
+        self.queue = rest;
+        waiting
+    }
+
+    /// Puts an entry taken out by `find_matches` or `take_waiting` back in the queue, keeping
+    /// how long it had been waiting. Does nothing if the player has queued again since.
+    pub fn requeue(&mut self, entry: QueueEntry) {
+        if !self.is_queued(entry.player.id()) {
+            self.queue.push(entry);
+        }
+    }
+
+    /// The rating window of a player that has been waiting since `enqueued_at`.
+    pub fn window(&self, enqueued_at: Instant, now: Instant) -> f64 {
+        let waited = now.saturating_duration_since(enqueued_at).as_secs_f64();
+        (self.settings.initial_window + self.settings.widen_per_second * waited)
+            .min(self.settings.max_window)
+    }
+
+    /// Pulls every group that's ready to play out of the queue.
+    ///
+    /// Players are considered longest-waiting first. Each one gathers everyone whose rating is
+    /// mutually within range, closest ratings first. The group is matched if it's full, or if
+    /// it's big enough and its anchor has waited at least `fill_time`.
+    pub fn find_matches(&mut self, now: Instant) -> Vec<MatchedGroup> {
+        let group_size = self.settings.lobby.max_players.max(MIN_PLAYERS);
+        self.queue.sort_by_key(|entry| entry.enqueued_at);
+        let mut taken = vec![false; self.queue.len()];
+        let mut groups = Vec::new();
+        for anchor_index in 0..self.queue.len() {
+            if taken[anchor_index] {
+                continue;
+            }
+            let anchor = &self.queue[anchor_index];
+            let anchor_window = self.window(anchor.enqueued_at, now);
+            let mut candidates: Vec<usize> = (0..self.queue.len())
+                .filter(|&index| !taken[index] && index != anchor_index)
+                .filter(|&index| {
+                    let other = &self.queue[index];
+                    let distance = (other.rating - anchor.rating).abs();
+                    other.mode == anchor.mode
+                        && distance <= anchor_window
+                        && distance <= self.window(other.enqueued_at, now)
+                })
+                .collect();
+            candidates.sort_by(|&a, &b| {
+                let distance_a = (self.queue[a].rating - anchor.rating).abs();
+                let distance_b = (self.queue[b].rating - anchor.rating).abs();
+                distance_a
+                    .total_cmp(&distance_b)
+                    .then(self.queue[a].enqueued_at.cmp(&self.queue[b].enqueued_at))
+            });
+            candidates.truncate(group_size - 1);
+            let size = candidates.len() + 1;
+            let waited_long_enough =
+                now.saturating_duration_since(anchor.enqueued_at) >= self.settings.fill_time;
+            if size == group_size || (size >= MIN_PLAYERS && waited_long_enough) {
+                taken[anchor_index] = true;
+                for &index in &candidates {
+                    taken[index] = true;
+                }
+                groups.push((anchor.mode, anchor_index, candidates));
+            }
+        }
+        let groups = groups
+            .into_iter()
+            .map(|(mode, anchor_index, candidates)| MatchedGroup {
+                mode,
+                players: std::iter::once(anchor_index)
+                    .chain(candidates)
+                    .map(|index| self.queue[index].clone())
+                    .collect(),
+            })
+            .collect();
+        let mut index = 0;
+        self.queue.retain(|_| {
+            index += 1;
+            !taken[index - 1]
+        });
+        groups
+    }
+}
@@ -0,0 +1,7 @@
+mod errors;
+mod lobbies;
+mod matchmaking;
+
+pub use errors::LobbyError;
+pub use lobbies::*;
+pub use matchmaking::*;
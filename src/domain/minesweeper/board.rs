@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+
+use super::{errors::*, BoardConfig, MineLayout, Position};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CellState {
+    Hidden,
+    Flagged,
+    Revealed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum GameStatus {
+    /// No cell has been revealed yet, so (for boards created with `Board::new`) the mines
+    /// haven't been placed.
+    Ready,
+    Playing,
+    Won,
+    Lost {
+        exploded: Position,
+    },
+}
+
+impl GameStatus {
+    pub fn is_over(&self) -> bool {
+        matches!(self, Self::Won | Self::Lost { .. })
+    }
+}
+
+/// A single cell that was uncovered by an action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevealedCell {
+    pub position: Position,
+    pub adjacent_mines: u8,
+}
+
+/// Everything that changed on the board because of a reveal or a chord. Callers can forward
+/// this as-is to clients instead of re-sending the whole board.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevealOutcome {
+    pub revealed: Vec<RevealedCell>,
+    /// The mine that was hit, if any. When this is set, the game is lost.
+    pub exploded: Option<Position>,
+}
+
+impl RevealOutcome {
+    pub fn is_empty(&self) -> bool {
+        self.revealed.is_empty() && self.exploded.is_none()
+    }
+}
+
+/// A single player's game of minesweeper.
+///
+/// Mines are either placed up front (`Board::from_layout`) or lazily on the first reveal
+/// (`Board::new`), which guarantees that the first click is never a mine.
+#[derive(Clone, Debug)]
+pub struct Board {
+    config: BoardConfig,
+    seed: u64,
+    layout: Option<MineLayout>,
+    cells: Vec<CellState>,
+    status: GameStatus,
+    revealed: usize,
+    flags: usize,
+}
+
+impl Board {
+    /// Creates a board whose mines will be placed from `seed` when the first cell is revealed.
+    pub fn new(config: BoardConfig, seed: u64) -> Self {
+        Self {
+            config,
+            seed,
+            layout: None,
+            cells: vec![CellState::Hidden; config.cell_count()],
+            status: GameStatus::Ready,
+            revealed: 0,
+            flags: 0,
+        }
+    }
+
+    /// Creates a board with an already decided layout. There's no first click protection here,
+    /// that's up to whoever made the layout.
+    pub fn from_layout(layout: MineLayout) -> Self {
+        let config = layout.config();
+        Self {
+            config,
+            seed: 0,
+            layout: Some(layout),
+            cells: vec![CellState::Hidden; config.cell_count()],
+            status: GameStatus::Ready,
+            revealed: 0,
+            flags: 0,
+        }
+    }
+
+    pub fn config(&self) -> BoardConfig {
+        self.config
+    }
+
+    pub fn status(&self) -> GameStatus {
+        self.status
+    }
+
+    /// The layout of the board, or `None` if the mines haven't been placed yet.
+    pub fn layout(&self) -> Option<&MineLayout> {
+        self.layout.as_ref()
+    }
+
+    pub fn cell(&self, position: Position) -> Result<CellState, BoardError> {
+        Ok(self.cells[self.config.checked_index(position)?])
+    }
+
+    pub fn revealed_count(&self) -> usize {
+        self.revealed
+    }
+
+    pub fn flag_count(&self) -> usize {
+        self.flags
+    }
+
+    /// The number of mines minus the number of flags. Can go negative when a player
+    /// over-flags, just like the counter in the classic game.
+    pub fn mines_remaining(&self) -> isize {
+        self.config.mines() as isize - self.flags as isize
+    }
+
+    /// The number of mines touching a revealed cell. Hidden cells return `None` so that
+    /// callers can't accidentally leak information about them.
+    pub fn adjacent_mines(&self, position: Position) -> Result<Option<u8>, BoardError> {
+        let index = self.config.checked_index(position)?;
+        Ok(match (self.cells[index], &self.layout) {
+            (CellState::Revealed, Some(layout)) => Some(layout.adjacent_mines(index)),
+            _ => None,
+        })
+    }
+
+    /// Reveals the cell at `position`. Revealing a cell with no adjacent mines also reveals
+    /// all of its neighbours, flood filling outwards until it reaches numbered cells.
+    ///
+    /// Revealing an already revealed cell is not an error, it just doesn't change anything.
+    pub fn reveal(&mut self, position: Position) -> Result<RevealOutcome, BoardError> {
+        ensure!(!self.status.is_over(), GameOverSnafu);
+        let index = self.config.checked_index(position)?;
+        match self.cells[index] {
+            CellState::Revealed => return Ok(RevealOutcome::default()),
+            CellState::Flagged => return CellFlaggedSnafu { position }.fail(),
+            CellState::Hidden => {}
+        }
+        if self.layout.is_none() {
+            self.layout = Some(MineLayout::generate(self.config, self.seed, position)?);
+        }
+        self.status = GameStatus::Playing;
+        let mut outcome = RevealOutcome::default();
+        self.reveal_from(index, &mut outcome);
+        Ok(outcome)
+    }
+
+    /// Chording: clicking a revealed number whose adjacent flags match its number reveals all
+    /// of its other hidden neighbours at once. If the flags are wrong, this can hit a mine.
+    ///
+    /// Chording a cell that doesn't qualify is a no-op.
+    pub fn chord(&mut self, position: Position) -> Result<RevealOutcome, BoardError> {
+        ensure!(!self.status.is_over(), GameOverSnafu);
+        let index = self.config.checked_index(position)?;
+        let mut outcome = RevealOutcome::default();
+        let layout = match (&self.layout, self.cells[index]) {
+            (Some(layout), CellState::Revealed) => layout,
+            _ => return Ok(outcome),
+        };
+        let adjacent = layout.adjacent_mines(index);
+        let flagged = self
+            .config
+            .neighbours(index)
+            .filter(|&neighbour| self.cells[neighbour] == CellState::Flagged)
+            .count();
+        if adjacent == 0 || flagged != adjacent as usize {
+            return Ok(outcome);
+        }
+        let hidden: Vec<usize> = self
+            .config
+            .neighbours(index)
+            .filter(|&neighbour| self.cells[neighbour] == CellState::Hidden)
+            .collect();
+        for neighbour in hidden {
+            if self.status.is_over() {
+                break;
+            }
+            self.reveal_from(neighbour, &mut outcome);
+        }
+        Ok(outcome)
+    }
+
+    /// Flags a hidden cell or unflags a flagged one. Returns the new state of the cell.
+    pub fn toggle_flag(&mut self, position: Position) -> Result<CellState, BoardError> {
+        ensure!(!self.status.is_over(), GameOverSnafu);
+        let index = self.config.checked_index(position)?;
+        let new_state = match self.cells[index] {
+            CellState::Revealed => return CellRevealedSnafu { position }.fail(),
+            CellState::Hidden => {
+                self.flags += 1;
+                CellState::Flagged
+            }
+            CellState::Flagged => {
+                self.flags -= 1;
+                CellState::Hidden
+            }
+        };
+        self.cells[index] = new_state;
+        Ok(new_state)
+    }
+
+    /// Takes back hitting a mine: the mine is flagged instead, and the game carries on.
+    /// Returns the mine, or `None` if the game wasn't lost.
+    pub fn defuse(&mut self) -> Option<Position> {
+        let exploded = match self.status {
+            GameStatus::Lost { exploded } => exploded,
+            _ => return None,
+        };
+        let index = self
+            .config
+            .index_of(exploded)
+            .expect("the exploded mine is on the board");
+        self.cells[index] = CellState::Flagged;
+        self.flags += 1;
+        self.status = GameStatus::Playing;
+        Some(exploded)
+    }
+
+    /// Reveals `start` (and flood fills from it), recording everything into `outcome` and
+    /// updating the game status.
+    fn reveal_from(&mut self, start: usize, outcome: &mut RevealOutcome) {
+        let layout = self
+            .layout
+            .as_ref()
+            .expect("mines are always placed before revealing");
+        if layout.is_mine(start) {
+            let exploded = self.config.position_of(start);
+            self.cells[start] = CellState::Revealed;
+            self.status = GameStatus::Lost { exploded };
+            outcome.exploded = Some(exploded);
+            return;
+        }
+        let mut stack = vec![start];
+        while let Some(index) = stack.pop() {
+            if self.cells[index] != CellState::Hidden {
+                continue;
+            }
+            self.cells[index] = CellState::Revealed;
+            self.revealed += 1;
+            let adjacent_mines = layout.adjacent_mines(index);
+            outcome.revealed.push(RevealedCell {
+                position: self.config.position_of(index),
+                adjacent_mines,
+            });
+            if adjacent_mines == 0 {
+                stack.extend(
+                    self.config
+                        .neighbours(index)
+                        .filter(|&neighbour| self.cells[neighbour] == CellState::Hidden),
+                );
+            }
+        }
+        if self.revealed == self.config.safe_cell_count() {
+            self.status = GameStatus::Won;
+        }
+    }
+}
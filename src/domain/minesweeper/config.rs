@@ -0,0 +1,196 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use snafu::prelude::*;
+
+use super::{errors::*, BoardTopology, Topology};
+
+/// A cell coordinate on a board. `x` is the column and `y` is the row, both starting at zero
+/// in the top left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Position {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl Position {
+    pub fn new(x: usize, y: usize) -> Self {
+        Self { x, y }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+/// The shape of a board: how big it is, how its cells touch and how many mines are hidden in
+/// it.
+///
+/// Cells are addressed either by `Position` or by their index in row-major order. The index
+/// form is what the rest of the engine uses internally since it makes for cheap lookups.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawBoardConfig")]
+pub struct BoardConfig {
+    width: usize,
+    height: usize,
+    mines: usize,
+    topology: BoardTopology,
+}
+
+impl BoardConfig {
+    /// Creates a new square configuration, making sure there's at least one cell and that
+    /// there's always at least one safe cell to click.
+    pub fn new(width: usize, height: usize, mines: usize) -> Result<Self, BoardError> {
+        Self::with_topology(width, height, mines, BoardTopology::Square)
+    }
+
+    /// Same as `new`, but for any topology. The board also has to be big enough for it.
+    pub fn with_topology(
+        width: usize,
+        height: usize,
+        mines: usize,
+        topology: BoardTopology,
+    ) -> Result<Self, BoardError> {
+        ensure!(
+            width > 0 && height > 0,
+            InvalidDimensionsSnafu { width, height }
+        );
+        let min = topology.min_size();
+        ensure!(
+            width >= min && height >= min,
+            TooSmallForTopologySnafu { topology, min }
+        );
+        let cells = width
+            .checked_mul(height)
+            .context(InvalidDimensionsSnafu { width, height })?;
+        let max = cells - 1;
+        ensure!(mines <= max, TooManyMinesSnafu { mines, cells, max });
+        Ok(Self {
+            width,
+            height,
+            mines,
+            topology,
+        })
+    }
+
+    /// 9x9 with 10 mines.
+    pub fn beginner() -> Self {
+        Self {
+            width: 9,
+            height: 9,
+            mines: 10,
+            topology: BoardTopology::Square,
+        }
+    }
+
+    /// 16x16 with 40 mines.
+    pub fn intermediate() -> Self {
+        Self {
+            width: 16,
+            height: 16,
+            mines: 40,
+            topology: BoardTopology::Square,
+        }
+    }
+
+    /// 30x16 with 99 mines.
+    pub fn expert() -> Self {
+        Self {
+            width: 30,
+            height: 16,
+            mines: 99,
+            topology: BoardTopology::Square,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn mines(&self) -> usize {
+        self.mines
+    }
+
+    pub fn topology(&self) -> BoardTopology {
+        self.topology
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// The number of cells that have to be revealed to win.
+    pub fn safe_cell_count(&self) -> usize {
+        self.cell_count() - self.mines
+    }
+
+    /// Turns a position into its row-major index, or `None` if it's off the board.
+    pub fn index_of(&self, position: Position) -> Option<usize> {
+        if position.x < self.width && position.y < self.height {
+            Some(position.y * self.width + position.x)
+        } else {
+            None
+        }
+    }
+
+    /// Same as `index_of`, but with an error that can be handed straight back to the caller.
+    pub(crate) fn checked_index(&self, position: Position) -> Result<usize, BoardError> {
+        self.index_of(position)
+            .context(OutOfBoundsSnafu { position })
+    }
+
+    pub fn position_of(&self, index: usize) -> Position {
+        Position::new(index % self.width, index / self.width)
+    }
+
+    /// The indices of the cells touching the cell at `index`, which depends on the board's
+    /// topology.
+    pub fn neighbours(&self, index: usize) -> impl Iterator<Item = usize> {
+        let position = self.position_of(index);
+        let topology = self.topology;
+        let (width, height) = (self.width as isize, self.height as isize);
+        let (x, y) = (position.x as isize, position.y as isize);
+        topology
+            .steps(position)
+            .iter()
+            .filter_map(move |&(dx, dy)| {
+                let (mut nx, mut ny) = (x + dx, y + dy);
+                if topology.wraps() {
+                    nx = nx.rem_euclid(width);
+                    ny = ny.rem_euclid(height);
+                }
+                if nx >= 0 && nx < width && ny >= 0 && ny < height {
+                    Some((ny * width + nx) as usize)
+                } else {
+                    None
+                }
+            })
+    }
+}
+
+/// Deserialization goes through `BoardConfig::new` so a config coming off the wire can never
+/// describe an impossible board.
+#[derive(Deserialize)]
+struct RawBoardConfig {
+    width: usize,
+    height: usize,
+    mines: usize,
+    /// Boards saved before there were other topologies are all square.
+    #[serde(default)]
+    topology: BoardTopology,
+}
+
+impl TryFrom<RawBoardConfig> for BoardConfig {
+    type Error = BoardError;
+
+    fn try_from(raw: RawBoardConfig) -> Result<Self, Self::Error> {
+        Self::with_topology(raw.width, raw.height, raw.mines, raw.topology)
+    }
+}
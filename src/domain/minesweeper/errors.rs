@@ -0,0 +1,28 @@
+use snafu::prelude::*;
+
+use super::{BoardTopology, Position};
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+#[snafu(visibility(pub(crate)))]
+pub enum BoardError {
+    #[snafu(display("A board must be at least 1x1, but got {width}x{height}"))]
+    InvalidDimensions { width: usize, height: usize },
+    #[snafu(display(
+        "A board with {cells} cells can hold at most {max} mines, but {mines} were requested"
+    ))]
+    TooManyMines {
+        mines: usize,
+        cells: usize,
+        max: usize,
+    },
+    #[snafu(display("A {} board must be at least {min}x{min}", topology.as_str()))]
+    TooSmallForTopology { topology: BoardTopology, min: usize },
+    #[snafu(display("{position} is outside of the board"))]
+    OutOfBounds { position: Position },
+    #[snafu(display("{position} is flagged and must be unflagged before it can be revealed"))]
+    CellFlagged { position: Position },
+    #[snafu(display("{position} has already been revealed"))]
+    CellRevealed { position: Position },
+    #[snafu(display("The game is already over"))]
+    GameOver,
+}
@@ -0,0 +1,209 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+
+use super::{
+    errors::BoardError, Board, BoardConfig, MineLayout, Position, SeededRng, Solver, SolverOptions,
+    Technique,
+};
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+pub enum GenerationError {
+    #[snafu(display(
+        "Could not generate a no-guess board within {budget:?} ({attempts} attempts)"
+    ))]
+    BudgetExceeded { budget: Duration, attempts: usize },
+    #[snafu(display("Could not generate a no-guess board in {attempts} attempts"))]
+    AttemptsExhausted { attempts: usize },
+    #[snafu(display("The board could not be created"))]
+    InvalidBoard { source: BoardError },
+}
+
+/// How hard a board is to clear, based on the hardest technique the solver needed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl From<Technique> for Difficulty {
+    fn from(technique: Technique) -> Self {
+        match technique {
+            Technique::Single => Self::Easy,
+            Technique::Subset => Self::Medium,
+            Technique::Enumeration => Self::Hard,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DifficultyReport {
+    pub difficulty: Difficulty,
+    /// Harder techniques are weighted more heavily, so two boards with the same
+    /// `difficulty` can still be compared.
+    pub score: u32,
+    pub techniques: BTreeMap<Technique, usize>,
+}
+
+impl DifficultyReport {
+    fn from_techniques(techniques: BTreeMap<Technique, usize>) -> Self {
+        let difficulty = techniques
+            .keys()
+            .max()
+            .copied()
+            .map_or(Difficulty::Easy, Difficulty::from);
+        let score = techniques
+            .iter()
+            .map(|(technique, &count)| {
+                let weight = match technique {
+                    Technique::Single => 1,
+                    Technique::Subset => 3,
+                    Technique::Enumeration => 10,
+                };
+                weight * count as u32
+            })
+            .sum();
+        Self {
+            difficulty,
+            score,
+            techniques,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeneratorOptions {
+    /// How long to keep trying before giving up.
+    pub time_budget: Duration,
+    /// How many fresh layouts to try before giving up.
+    pub max_attempts: usize,
+    /// How many times a single layout can have a mine moved off of the point where the
+    /// solver got stuck before the layout is thrown away.
+    pub max_repairs: usize,
+    /// Options for the solver that has to clear the board. Lowering `max_technique` makes
+    /// for easier boards (that take longer to find).
+    pub solver: SolverOptions,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        Self {
+            time_budget: Duration::from_secs(2),
+            max_attempts: 1_000,
+            max_repairs: 50,
+            solver: SolverOptions::default(),
+        }
+    }
+}
+
+/// A board that can be cleared from `start` without guessing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeneratedBoard {
+    pub layout: MineLayout,
+    pub start: Position,
+    pub difficulty: DifficultyReport,
+    pub attempts: usize,
+    pub repairs: usize,
+}
+
+impl GeneratedBoard {
+    /// A fresh board for a player to start playing.
+    pub fn board(&self) -> Board {
+        Board::from_layout(self.layout.clone())
+    }
+}
+
+/// Generates a board that the solver can clear from `start` without ever guessing.
+///
+/// Each attempt starts from a random layout (with `start` and its neighbours kept clear). When
+/// the solver gets stuck, one of the mines it got stuck on is moved somewhere the solver
+/// hasn't looked at yet and the solve is retried. If that doesn't work out after
+/// `max_repairs`, a new layout is tried instead.
+///
+/// The result only depends on `config`, `seed`, `start` and `options` (unless the time budget
+/// runs out, which gives an error rather than a different board).
+pub fn generate_no_guess(
+    config: BoardConfig,
+    seed: u64,
+    start: Position,
+    options: &GeneratorOptions,
+) -> Result<GeneratedBoard, GenerationError> {
+    let started = Instant::now();
+    config.checked_index(start).context(InvalidBoardSnafu)?;
+    let mut rng = SeededRng::new(seed);
+    let mut total_repairs = 0;
+    for attempt in 1..=options.max_attempts {
+        let mut layout =
+            MineLayout::generate(config, rng.next_u64(), start).context(InvalidBoardSnafu)?;
+        for _ in 0..=options.max_repairs {
+            ensure!(
+                started.elapsed() <= options.time_budget,
+                BudgetExceededSnafu {
+                    budget: options.time_budget,
+                    attempts: attempt,
+                }
+            );
+            let mut board = Board::from_layout(layout.clone());
+            let report = Solver::solve(&mut board, start, options.solver);
+            if report.solved {
+                return Ok(GeneratedBoard {
+                    layout,
+                    start,
+                    difficulty: DifficultyReport::from_techniques(report.techniques),
+                    attempts: attempt,
+                    repairs: total_repairs,
+                });
+            }
+            match repair(&layout, &board, &report.stuck_frontier, &mut rng) {
+                Some(repaired) => {
+                    layout = repaired;
+                    total_repairs += 1;
+                }
+                None => break,
+            }
+        }
+    }
+    AttemptsExhaustedSnafu {
+        attempts: options.max_attempts,
+    }
+    .fail()
+}
+
+/// Moves one mine from the frontier the solver got stuck on to a hidden cell that isn't next
+/// to anything revealed. Returns `None` if there's nothing to move or nowhere to put it.
+fn repair(
+    layout: &MineLayout,
+    board: &Board,
+    stuck_frontier: &[Position],
+    rng: &mut SeededRng,
+) -> Option<MineLayout> {
+    let config = layout.config();
+    let frontier_mines: Vec<usize> = stuck_frontier
+        .iter()
+        .filter_map(|&position| config.index_of(position))
+        .filter(|&index| layout.is_mine(index))
+        .collect();
+    let is_revealed = |index: usize| board.adjacent_mines(config.position_of(index)) != Ok(None);
+    let destinations: Vec<usize> = (0..config.cell_count())
+        .filter(|&index| !layout.is_mine(index) && !is_revealed(index))
+        .filter(|&index| !config.neighbours(index).any(is_revealed))
+        .collect();
+    if frontier_mines.is_empty() || destinations.is_empty() {
+        return None;
+    }
+    let from = frontier_mines[rng.below(frontier_mines.len())];
+    let to = destinations[rng.below(destinations.len())];
+    let mut mines: Vec<bool> = (0..config.cell_count())
+        .map(|index| layout.is_mine(index))
+        .collect();
+    mines[from] = false;
+    mines[to] = true;
+    Some(MineLayout::from_mines(config, mines))
+}
@@ -0,0 +1,105 @@
+use super::{errors::*, BoardConfig, BoardTopology, Position, SeededRng};
+
+/// Where the mines are on a board, along with the pre-computed number of mines touching
+/// every cell.
+///
+/// A layout is immutable once created. Several `Board`s can share copies of the same layout,
+/// which is how every player in a match ends up playing the same board.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MineLayout {
+    config: BoardConfig,
+    mines: Vec<bool>,
+    adjacent: Vec<u8>,
+}
+
+impl MineLayout {
+    /// Randomly places `config.mines()` mines using `seed`, keeping `safe` clear. If there's
+    /// room, every neighbour of `safe` is kept clear as well so the first click opens up an
+    /// area instead of a lone number.
+    ///
+    /// The same config, seed and safe position always produce the same layout.
+    pub fn generate(config: BoardConfig, seed: u64, safe: Position) -> Result<Self, BoardError> {
+        let safe_index = config.checked_index(safe)?;
+        let mut excluded = vec![safe_index];
+        let neighbours: Vec<usize> = config.neighbours(safe_index).collect();
+        if config.cell_count() - 1 - neighbours.len() >= config.mines() {
+            excluded.extend(neighbours);
+        }
+        let mut candidates: Vec<usize> = (0..config.cell_count())
+            .filter(|index| !excluded.contains(index))
+            .collect();
+        SeededRng::new(seed).shuffle(&mut candidates);
+        let mut mines = vec![false; config.cell_count()];
+        for &index in candidates.iter().take(config.mines()) {
+            mines[index] = true;
+        }
+        Ok(Self::from_mines(config, mines))
+    }
+
+    /// Builds a square layout from an explicit list of mine positions. The config's mine count
+    /// is taken from the number of distinct positions given.
+    pub fn from_positions(
+        width: usize,
+        height: usize,
+        positions: &[Position],
+    ) -> Result<Self, BoardError> {
+        Self::from_positions_with_topology(width, height, BoardTopology::Square, positions)
+    }
+
+    /// Same as `from_positions`, but for any topology.
+    pub fn from_positions_with_topology(
+        width: usize,
+        height: usize,
+        topology: BoardTopology,
+        positions: &[Position],
+    ) -> Result<Self, BoardError> {
+        let unchecked = BoardConfig::with_topology(width, height, 0, topology)?;
+        let mut mines = vec![false; unchecked.cell_count()];
+        for &position in positions {
+            mines[unchecked.checked_index(position)?] = true;
+        }
+        let count = mines.iter().filter(|&&mine| mine).count();
+        let config = BoardConfig::with_topology(width, height, count, topology)?;
+        Ok(Self::from_mines(config, mines))
+    }
+
+    /// Builds a layout from a row-major mine mask that's already known to match `config`.
+    pub(crate) fn from_mines(config: BoardConfig, mines: Vec<bool>) -> Self {
+        debug_assert_eq!(mines.len(), config.cell_count());
+        let adjacent = (0..config.cell_count())
+            .map(|index| {
+                config
+                    .neighbours(index)
+                    .filter(|&neighbour| mines[neighbour])
+                    .count() as u8
+            })
+            .collect();
+        Self {
+            config,
+            mines,
+            adjacent,
+        }
+    }
+
+    pub fn config(&self) -> BoardConfig {
+        self.config
+    }
+
+    pub fn is_mine(&self, index: usize) -> bool {
+        self.mines[index]
+    }
+
+    /// The number of mines touching the cell at `index`.
+    pub fn adjacent_mines(&self, index: usize) -> u8 {
+        self.adjacent[index]
+    }
+
+    pub fn mine_positions(&self) -> Vec<Position> {
+        self.mines
+            .iter()
+            .enumerate()
+            .filter(|(_, &mine)| mine)
+            .map(|(index, _)| self.config.position_of(index))
+            .collect()
+    }
+}
@@ -0,0 +1,24 @@
+//! The rules of minesweeper, without any knowledge of players, matches or the network.
+//!
+//! Everything in here is plain, synchronous Rust so that it can be driven from the game
+//! server, from tests, or from anything else that wants to play a board.
+
+mod board;
+mod config;
+mod errors;
+mod generator;
+mod layout;
+mod rng;
+mod solver;
+mod topology;
+mod view;
+
+pub use board::*;
+pub use config::*;
+pub use errors::BoardError;
+pub use generator::*;
+pub use layout::*;
+pub use rng::*;
+pub use solver::*;
+pub use topology::*;
+pub use view::*;
@@ -0,0 +1,49 @@
+/// A small, deterministic pseudo-random number generator (SplitMix64).
+///
+/// Boards have to be reproducible from nothing but a seed so that every player in a match
+/// (and every replay of that match) sees exactly the same layout. Pulling in a general purpose
+/// RNG crate would tie our layouts to that crate's algorithm choices, which can change between
+/// versions, so we keep our own tiny implementation instead.
+#[derive(Clone, Debug)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a uniformly distributed number in `0..upper`.
+    ///
+    /// # Panics
+    /// If `upper` is zero.
+    pub fn below(&mut self, upper: usize) -> usize {
+        assert!(upper > 0, "upper bound must be greater than zero");
+        let upper = upper as u64;
+        // Reject the values that would bias the result towards the lower numbers.
+        let zone = u64::MAX - (u64::MAX % upper);
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return (value % upper) as usize;
+            }
+        }
+    }
+
+    /// Shuffles the slice in place using Fisher-Yates.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
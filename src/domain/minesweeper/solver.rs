@@ -0,0 +1,739 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Board, BoardConfig, BoardError, BoardView, CellState, CellView, GameStatus, Position};
+
+/// The deduction techniques the solver knows, from easiest to hardest. The derived ordering
+/// is used to compare how hard a board is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Technique {
+    /// A single number is either fully satisfied by known mines (the rest are safe) or has
+    /// exactly as many hidden neighbours as it has mines left (they're all mines).
+    Single,
+    /// One number's hidden neighbours are a subset of another's, so the difference between
+    /// them can be reasoned about on its own.
+    Subset,
+    /// Every possible arrangement of mines along the frontier is enumerated (along with the
+    /// total mine count) and cells that are the same in all of them are resolved.
+    Enumeration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SolverOptions {
+    /// The hardest technique the solver is allowed to use.
+    pub max_technique: Technique,
+    /// Frontier regions with more cells than this are not enumerated. Enumeration is
+    /// exponential in the worst case, so this keeps a single deduction bounded.
+    pub enumeration_cell_limit: usize,
+    /// The maximum number of partial assignments to visit while enumerating one region.
+    pub enumeration_node_limit: usize,
+}
+
+impl Default for SolverOptions {
+    fn default() -> Self {
+        Self {
+            max_technique: Technique::Enumeration,
+            enumeration_cell_limit: 32,
+            enumeration_node_limit: 200_000,
+        }
+    }
+}
+
+/// Cells the solver has proven to be safe or to be mines, and how it proved it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deduction {
+    pub technique: Technique,
+    pub safe: Vec<Position>,
+    pub mines: Vec<Position>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Knowledge {
+    Unknown,
+    Revealed(u8),
+    Mine,
+}
+
+/// A revealed number and the hidden cells around it that it's still constraining.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Constraint {
+    cells: BTreeSet<usize>,
+    mines: usize,
+}
+
+/// A minesweeper solver that only uses information a player could see.
+///
+/// The solver never guesses. Feed it what's been revealed with `reveal` and `mark_mine`, then
+/// ask it for the next thing it can prove with `deduce`.
+#[derive(Clone, Debug)]
+pub struct Solver {
+    config: BoardConfig,
+    options: SolverOptions,
+    knowledge: Vec<Knowledge>,
+}
+
+/// The result of running the solver against a board from its first click until it either
+/// clears it or gets stuck.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolveReport {
+    pub solved: bool,
+    /// How many deductions needed each technique.
+    pub techniques: BTreeMap<Technique, usize>,
+    /// The hidden cells next to revealed numbers when the solver got stuck. Empty if solved.
+    pub stuck_frontier: Vec<Position>,
+}
+
+impl Solver {
+    pub fn new(config: BoardConfig) -> Self {
+        Self::with_options(config, SolverOptions::default())
+    }
+
+    pub fn with_options(config: BoardConfig, options: SolverOptions) -> Self {
+        Self {
+            config,
+            options,
+            knowledge: vec![Knowledge::Unknown; config.cell_count()],
+        }
+    }
+
+    /// Builds a solver from what the player can currently see on `board`. Flags are ignored
+    /// since players can place them wrong.
+    pub fn from_board(board: &Board, options: SolverOptions) -> Self {
+        let mut solver = Self::with_options(board.config(), options);
+        for index in 0..solver.config.cell_count() {
+            let position = solver.config.position_of(index);
+            if let Ok(Some(adjacent_mines)) = board.adjacent_mines(position) {
+                solver.knowledge[index] = Knowledge::Revealed(adjacent_mines);
+            }
+        }
+        solver
+    }
+
+    /// Builds a solver from what a player was shown. Flags are ignored, for the same reason
+    /// as in `from_board`.
+    pub fn from_view(view: &BoardView, options: SolverOptions) -> Result<Self, BoardError> {
+        let config =
+            BoardConfig::with_topology(view.width, view.height, view.mines, view.topology)?;
+        let mut solver = Self::with_options(config, options);
+        for (index, cell) in view.cells.iter().enumerate() {
+            if let CellView::Revealed(adjacent_mines) = *cell {
+                solver.knowledge[index] = Knowledge::Revealed(adjacent_mines);
+            }
+        }
+        Ok(solver)
+    }
+
+    pub fn config(&self) -> BoardConfig {
+        self.config
+    }
+
+    /// Records that the cell at `position` was revealed and shows `adjacent_mines`.
+    pub fn reveal(&mut self, position: Position, adjacent_mines: u8) {
+        if let Some(index) = self.config.index_of(position) {
+            self.knowledge[index] = Knowledge::Revealed(adjacent_mines);
+        }
+    }
+
+    /// Records that the cell at `position` is known to be a mine.
+    pub fn mark_mine(&mut self, position: Position) {
+        if let Some(index) = self.config.index_of(position) {
+            self.knowledge[index] = Knowledge::Mine;
+        }
+    }
+
+    /// Hidden cells that border at least one revealed number.
+    pub fn frontier(&self) -> Vec<Position> {
+        let mut frontier: BTreeSet<usize> = BTreeSet::new();
+        for constraint in self.constraints() {
+            frontier.extend(constraint.cells);
+        }
+        frontier
+            .into_iter()
+            .map(|index| self.config.position_of(index))
+            .collect()
+    }
+
+    /// Finds the next cells that can be proven safe or mined, using the easiest technique
+    /// that gets anywhere. Returns `None` when nothing can be proven without guessing.
+    ///
+    /// Nothing is recorded by this, so the same deduction keeps coming back until the safe
+    /// cells are passed to `reveal` and the mines to `mark_mine`.
+    pub fn deduce(&self) -> Option<Deduction> {
+        let constraints = self.constraints();
+        let techniques = [Technique::Single, Technique::Subset, Technique::Enumeration];
+        techniques
+            .into_iter()
+            .filter(|&technique| technique <= self.options.max_technique)
+            .find_map(|technique| {
+                let (safe, mines) = match technique {
+                    Technique::Single => single_rule(&constraints),
+                    Technique::Subset => subset_rule(&constraints),
+                    Technique::Enumeration => self.enumerate(&constraints),
+                };
+                if safe.is_empty() && mines.is_empty() {
+                    None
+                } else {
+                    Some(Deduction {
+                        technique,
+                        safe: safe
+                            .into_iter()
+                            .map(|index| self.config.position_of(index))
+                            .collect(),
+                        mines: mines
+                            .into_iter()
+                            .map(|index| self.config.position_of(index))
+                            .collect(),
+                    })
+                }
+            })
+    }
+
+    /// How likely the hidden cell at `position` is to be safe, from 0 to 1, taking every
+    /// number on the board and the total mine count into account together. Every arrangement
+    /// of the remaining mines that fits what's been revealed is counted as equally likely, so
+    /// cells that can be proven either way come out as exactly 1 or 0.
+    ///
+    /// If the frontier is too big to enumerate (or enumeration isn't allowed), this falls back
+    /// to the share of safe cells around the tightest number next to the cell, or among all
+    /// the hidden cells if no number touches it. Returns `None` if the cell isn't hidden.
+    pub fn safe_chance(&self, position: Position) -> Option<f64> {
+        let index = self.config.index_of(position)?;
+        if self.knowledge[index] != Knowledge::Unknown {
+            return None;
+        }
+        let constraints = self.constraints();
+        if self.options.max_technique >= Technique::Enumeration {
+            if let Some(chance) = self.counted_safe_chance(&constraints, index) {
+                return Some(chance);
+            }
+        }
+        for (safe, mines) in [single_rule(&constraints), subset_rule(&constraints)] {
+            if safe.contains(&index) {
+                return Some(1.0);
+            }
+            if mines.contains(&index) {
+                return Some(0.0);
+            }
+        }
+        let tightest = constraints
+            .iter()
+            .filter(|constraint| constraint.cells.contains(&index))
+            .map(|constraint| 1.0 - constraint.mines as f64 / constraint.cells.len() as f64)
+            .reduce(f64::min);
+        if let Some(chance) = tightest {
+            return Some(chance);
+        }
+        let hidden = self
+            .knowledge
+            .iter()
+            .filter(|&&knowledge| knowledge == Knowledge::Unknown)
+            .count();
+        let mines_left = self.config.mines().saturating_sub(self.known_mines());
+        Some(1.0 - mines_left as f64 / hidden as f64)
+    }
+
+    /// The exact chance of the hidden cell at `index` being safe, found by counting the
+    /// arrangements of mines it's safe in. Returns `None` if a region of the frontier is too
+    /// big to enumerate, or nothing fits what's been revealed.
+    fn counted_safe_chance(&self, constraints: &[Constraint], index: usize) -> Option<f64> {
+        let regions = regions(constraints);
+        let mut outcomes = Vec::new();
+        for (cells, region_constraints) in &regions {
+            if cells.len() > self.options.enumeration_cell_limit {
+                return None;
+            }
+            outcomes.push(enumerate_region(
+                cells,
+                region_constraints,
+                self.options.enumeration_node_limit,
+            )?);
+        }
+        let interior = self
+            .knowledge
+            .iter()
+            .enumerate()
+            .filter(|&(cell, &knowledge)| {
+                knowledge == Knowledge::Unknown
+                    && !regions.iter().any(|(cells, _)| cells.contains(&cell))
+            })
+            .count();
+        let mines_left = self.config.mines().saturating_sub(self.known_mines());
+        // The ways of arranging `count` mines across some regions, and of putting the rest
+        // of them in the interior. These get huge, so they're kept as logarithms.
+        let ln_rest = |count: usize| {
+            (count <= mines_left && mines_left - count <= interior)
+                .then(|| ln_choose(interior, mines_left - count))
+        };
+        let region = regions.iter().position(|(cells, _)| cells.contains(&index));
+        // Every arrangement is split into the part in the cell's own region (if it has one)
+        // and the part everywhere else; only the first decides whether the cell's safe.
+        let (own, others): (Vec<(usize, f64, f64)>, Vec<f64>) = match region {
+            Some(region) => {
+                let position = regions[region]
+                    .0
+                    .iter()
+                    .position(|&cell| cell == index)
+                    .expect("the cell is in its region");
+                let own = outcomes[region]
+                    .by_count
+                    .iter()
+                    .map(|(&count, possibilities)| {
+                        let safe = possibilities.arrangements - possibilities.mine_in[position];
+                        (count, possibilities.arrangements, safe)
+                    })
+                    .collect();
+                let others = arrangements_by_count(
+                    outcomes
+                        .iter()
+                        .enumerate()
+                        .filter(|&(other, _)| other != region)
+                        .map(|(_, outcome)| outcome),
+                );
+                (own, others)
+            }
+            None => {
+                let own = vec![(0, 1.0, 1.0)];
+                (own, arrangements_by_count(outcomes.iter()))
+            }
+        };
+        let mut weights = Vec::new();
+        for &(count, arrangements, safe) in &own {
+            for (other_count, &other_arrangements) in others.iter().enumerate() {
+                if other_arrangements == 0.0 {
+                    continue;
+                }
+                let frontier_mines = count + other_count;
+                let rest = match ln_rest(frontier_mines) {
+                    Some(rest) => rest,
+                    None => continue,
+                };
+                let ln_others = other_arrangements.ln() + rest;
+                let safe = match region {
+                    Some(_) => safe,
+                    // Interior cells are mines in their share of whatever's left over.
+                    None => 1.0 - (mines_left - frontier_mines) as f64 / interior as f64,
+                };
+                weights.push((arrangements.ln() + ln_others, safe / arrangements));
+            }
+        }
+        let largest = weights
+            .iter()
+            .map(|&(ln_weight, _)| ln_weight)
+            .reduce(f64::max)?;
+        let (mut total, mut safe) = (0.0, 0.0);
+        for (ln_weight, safe_share) in weights {
+            let weight = (ln_weight - largest).exp();
+            total += weight;
+            safe += weight * safe_share;
+        }
+        Some(safe / total)
+    }
+
+    /// Plays `board` from `start` using nothing but deductions, revealing proven cells on the
+    /// board as it goes. The board should be fresh; it's consumed in the process.
+    pub fn solve(board: &mut Board, start: Position, options: SolverOptions) -> SolveReport {
+        let mut solver = Self::with_options(board.config(), options);
+        let mut techniques = BTreeMap::new();
+        match board.reveal(start) {
+            Ok(outcome) if outcome.exploded.is_none() => {
+                for cell in outcome.revealed {
+                    solver.reveal(cell.position, cell.adjacent_mines);
+                }
+            }
+            _ => {
+                return SolveReport {
+                    solved: false,
+                    techniques,
+                    stuck_frontier: vec![start],
+                }
+            }
+        }
+        while !board.status().is_over() {
+            let deduction = match solver.deduce() {
+                Some(deduction) => deduction,
+                None => {
+                    return SolveReport {
+                        solved: false,
+                        techniques,
+                        stuck_frontier: solver.frontier(),
+                    }
+                }
+            };
+            *techniques.entry(deduction.technique).or_insert(0) += 1;
+            for &mine in &deduction.mines {
+                solver.mark_mine(mine);
+            }
+            for &safe in &deduction.safe {
+                // Flood fills from earlier cells in this deduction may have got here first.
+                if board.cell(safe) != Ok(CellState::Hidden) {
+                    continue;
+                }
+                match board.reveal(safe) {
+                    Ok(outcome) => {
+                        for cell in outcome.revealed {
+                            solver.reveal(cell.position, cell.adjacent_mines);
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        }
+        SolveReport {
+            solved: board.status() == GameStatus::Won,
+            techniques,
+            stuck_frontier: vec![],
+        }
+    }
+
+    fn known_mines(&self) -> usize {
+        self.knowledge
+            .iter()
+            .filter(|&&knowledge| knowledge == Knowledge::Mine)
+            .count()
+    }
+
+    fn constraints(&self) -> Vec<Constraint> {
+        let mut constraints: Vec<Constraint> = Vec::new();
+        for (index, knowledge) in self.knowledge.iter().enumerate() {
+            let number = match knowledge {
+                Knowledge::Revealed(number) => *number as usize,
+                _ => continue,
+            };
+            let mut cells = BTreeSet::new();
+            let mut known_mines = 0;
+            for neighbour in self.config.neighbours(index) {
+                match self.knowledge[neighbour] {
+                    Knowledge::Unknown => {
+                        cells.insert(neighbour);
+                    }
+                    Knowledge::Mine => known_mines += 1,
+                    Knowledge::Revealed(_) => {}
+                }
+            }
+            if cells.is_empty() {
+                continue;
+            }
+            let constraint = Constraint {
+                cells,
+                mines: number.saturating_sub(known_mines),
+            };
+            if !constraints.contains(&constraint) {
+                constraints.push(constraint);
+            }
+        }
+        constraints
+    }
+
+    /// Enumerates every arrangement of mines in each frontier region, then combines the
+    /// regions with the number of mines left on the board to find the cells that are the same
+    /// in every arrangement that's still possible.
+    fn enumerate(&self, constraints: &[Constraint]) -> (BTreeSet<usize>, BTreeSet<usize>) {
+        let mut safe = BTreeSet::new();
+        let mut mines = BTreeSet::new();
+        let regions = regions(constraints);
+        let frontier_size: usize = regions.iter().map(|(cells, _)| cells.len()).sum();
+        let interior: Vec<usize> = (0..self.config.cell_count())
+            .filter(|&index| self.knowledge[index] == Knowledge::Unknown)
+            .filter(|index| !regions.iter().any(|(cells, _)| cells.contains(index)))
+            .collect();
+        debug_assert_eq!(
+            frontier_size + interior.len(),
+            self.knowledge
+                .iter()
+                .filter(|&&knowledge| knowledge == Knowledge::Unknown)
+                .count()
+        );
+        let mines_left = self.config.mines().saturating_sub(self.known_mines());
+
+        let mut outcomes = Vec::new();
+        for (cells, region_constraints) in &regions {
+            if cells.len() > self.options.enumeration_cell_limit {
+                return (safe, mines);
+            }
+            match enumerate_region(
+                cells,
+                region_constraints,
+                self.options.enumeration_node_limit,
+            ) {
+                Some(outcome) => outcomes.push(outcome),
+                None => return (safe, mines),
+            }
+        }
+
+        // For every region, the mine counts the *other* regions can add up to.
+        for (region_index, outcome) in outcomes.iter().enumerate() {
+            let others = outcomes
+                .iter()
+                .enumerate()
+                .filter(|&(other_index, _)| other_index != region_index)
+                .fold(BTreeSet::from([0usize]), |sums, (_, other)| {
+                    sums.iter()
+                        .flat_map(|sum| other.by_count.keys().map(move |count| sum + count))
+                        .collect()
+                });
+            let feasible = |count: usize| {
+                others.iter().any(|other| {
+                    let frontier_mines = count + other;
+                    frontier_mines <= mines_left && mines_left - frontier_mines <= interior.len()
+                })
+            };
+            let cells = &regions[region_index].0;
+            for (position, &cell) in cells.iter().enumerate() {
+                let (mut can_be_mine, mut can_be_safe) = (false, false);
+                for (&count, possibilities) in &outcome.by_count {
+                    if feasible(count) {
+                        can_be_mine |= possibilities.can_be_mine[position];
+                        can_be_safe |= possibilities.can_be_safe[position];
+                    }
+                }
+                if can_be_mine && !can_be_safe {
+                    mines.insert(cell);
+                } else if can_be_safe && !can_be_mine {
+                    safe.insert(cell);
+                }
+            }
+        }
+
+        // Cells away from the frontier are only resolved by the total mine count.
+        if !interior.is_empty() {
+            let frontier_counts =
+                outcomes
+                    .iter()
+                    .fold(BTreeSet::from([0usize]), |sums, outcome| {
+                        sums.iter()
+                            .flat_map(|sum| outcome.by_count.keys().map(move |count| sum + count))
+                            .collect()
+                    });
+            let interior_counts: BTreeSet<usize> = frontier_counts
+                .into_iter()
+                .filter(|&count| count <= mines_left && mines_left - count <= interior.len())
+                .map(|count| mines_left - count)
+                .collect();
+            if interior_counts == BTreeSet::from([0]) {
+                safe.extend(interior.iter().copied());
+            } else if interior_counts == BTreeSet::from([interior.len()]) {
+                mines.extend(interior.iter().copied());
+            }
+        }
+        (safe, mines)
+    }
+}
+
+fn single_rule(constraints: &[Constraint]) -> (BTreeSet<usize>, BTreeSet<usize>) {
+    let mut safe = BTreeSet::new();
+    let mut mines = BTreeSet::new();
+    for constraint in constraints {
+        if constraint.mines == 0 {
+            safe.extend(constraint.cells.iter().copied());
+        } else if constraint.mines == constraint.cells.len() {
+            mines.extend(constraint.cells.iter().copied());
+        }
+    }
+    (safe, mines)
+}
+
+fn subset_rule(constraints: &[Constraint]) -> (BTreeSet<usize>, BTreeSet<usize>) {
+    let mut safe = BTreeSet::new();
+    let mut mines = BTreeSet::new();
+    for small in constraints {
+        for large in constraints {
+            if small.cells.len() >= large.cells.len() || !small.cells.is_subset(&large.cells) {
+                continue;
+            }
+            if large.mines < small.mines {
+                continue;
+            }
+            let difference_mines = large.mines - small.mines;
+            let difference = large.cells.difference(&small.cells);
+            if difference_mines == 0 {
+                safe.extend(difference);
+            } else if difference_mines == large.cells.len() - small.cells.len() {
+                mines.extend(difference);
+            }
+        }
+    }
+    (safe, mines)
+}
+
+/// Splits the frontier into groups of cells that are linked through shared constraints.
+/// Cells in different regions can't affect each other except through the total mine count.
+fn regions(constraints: &[Constraint]) -> Vec<(Vec<usize>, Vec<Constraint>)> {
+    let mut remaining: Vec<&Constraint> = constraints.iter().collect();
+    let mut regions = Vec::new();
+    while let Some(seed) = remaining.pop() {
+        let mut cells: BTreeSet<usize> = seed.cells.clone();
+        let mut members = vec![seed.clone()];
+        loop {
+            let (linked, unlinked): (Vec<&Constraint>, Vec<&Constraint>) = remaining
+                .into_iter()
+                .partition(|constraint| !constraint.cells.is_disjoint(&cells));
+            remaining = unlinked;
+            if linked.is_empty() {
+                break;
+            }
+            for constraint in linked {
+                cells.extend(constraint.cells.iter().copied());
+                members.push(constraint.clone());
+            }
+        }
+        regions.push((cells.into_iter().collect(), members));
+    }
+    regions
+}
+
+/// Which cells can be mines and which can be safe, across all arrangements with a given
+/// number of mines.
+struct Possibilities {
+    can_be_mine: Vec<bool>,
+    can_be_safe: Vec<bool>,
+    /// How many arrangements there are, and how many of them have a mine in each cell.
+    arrangements: f64,
+    mine_in: Vec<f64>,
+}
+
+struct RegionOutcome {
+    by_count: BTreeMap<usize, Possibilities>,
+}
+
+/// How many arrangements of mines there are across all of `outcomes` together, indexed by
+/// how many mines they use.
+fn arrangements_by_count<'a>(outcomes: impl Iterator<Item = &'a RegionOutcome>) -> Vec<f64> {
+    outcomes.fold(vec![1.0], |totals, outcome| {
+        let most = outcome.by_count.keys().max().copied().unwrap_or(0);
+        let mut combined = vec![0.0; totals.len() + most];
+        for (count, &total) in totals.iter().enumerate() {
+            for (&more, possibilities) in &outcome.by_count {
+                combined[count + more] += total * possibilities.arrangements;
+            }
+        }
+        combined
+    })
+}
+
+/// The logarithm of `n` choose `k`.
+fn ln_choose(n: usize, k: usize) -> f64 {
+    let k = k.min(n - k);
+    (1..=k)
+        .map(|i| ((n - k + i) as f64).ln() - (i as f64).ln())
+        .sum()
+}
+
+/// Backtracks through every valid arrangement of mines in a region. Returns `None` if the
+/// node limit is hit before finishing.
+fn enumerate_region(
+    cells: &[usize],
+    constraints: &[Constraint],
+    node_limit: usize,
+) -> Option<RegionOutcome> {
+    // Per constraint: the positions (in `cells`) it covers and how many mines it needs.
+    let covered: Vec<Vec<usize>> = constraints
+        .iter()
+        .map(|constraint| {
+            cells
+                .iter()
+                .enumerate()
+                .filter(|(_, cell)| constraint.cells.contains(cell))
+                .map(|(position, _)| position)
+                .collect()
+        })
+        .collect();
+    let mut constraints_of: Vec<Vec<usize>> = vec![vec![]; cells.len()];
+    for (constraint_index, positions) in covered.iter().enumerate() {
+        for &position in positions {
+            constraints_of[position].push(constraint_index);
+        }
+    }
+
+    struct Search<'a> {
+        constraints: &'a [Constraint],
+        constraints_of: &'a [Vec<usize>],
+        assignment: Vec<bool>,
+        placed: Vec<usize>,
+        unassigned: Vec<usize>,
+        nodes: usize,
+        node_limit: usize,
+        by_count: BTreeMap<usize, Possibilities>,
+    }
+
+    impl Search<'_> {
+        fn run(&mut self, position: usize) -> bool {
+            self.nodes += 1;
+            if self.nodes > self.node_limit {
+                return false;
+            }
+            if position == self.assignment.len() {
+                let count = self.assignment.iter().filter(|&&mine| mine).count();
+                let size = self.assignment.len();
+                let possibilities = self.by_count.entry(count).or_insert_with(|| Possibilities {
+                    can_be_mine: vec![false; size],
+                    can_be_safe: vec![false; size],
+                    arrangements: 0.0,
+                    mine_in: vec![0.0; size],
+                });
+                possibilities.arrangements += 1.0;
+                for (cell, &mine) in self.assignment.iter().enumerate() {
+                    if mine {
+                        possibilities.can_be_mine[cell] = true;
+                        possibilities.mine_in[cell] += 1.0;
+                    } else {
+                        possibilities.can_be_safe[cell] = true;
+                    }
+                }
+                return true;
+            }
+            for mine in [false, true] {
+                if self.fits(position, mine) {
+                    self.assign(position, mine, true);
+                    let finished = self.run(position + 1);
+                    self.assign(position, mine, false);
+                    if !finished {
+                        return false;
+                    }
+                }
+            }
+            true
+        }
+
+        fn fits(&self, position: usize, mine: bool) -> bool {
+            self.constraints_of[position].iter().all(|&constraint| {
+                let placed = self.placed[constraint] + usize::from(mine);
+                let unassigned = self.unassigned[constraint] - 1;
+                let needed = self.constraints[constraint].mines;
+                placed <= needed && placed + unassigned >= needed
+            })
+        }
+
+        fn assign(&mut self, position: usize, mine: bool, entering: bool) {
+            self.assignment[position] = mine && entering;
+            for &constraint in &self.constraints_of[position] {
+                if entering {
+                    self.placed[constraint] += usize::from(mine);
+                    self.unassigned[constraint] -= 1;
+                } else {
+                    self.placed[constraint] -= usize::from(mine);
+                    self.unassigned[constraint] += 1;
+                }
+            }
+        }
+    }
+
+    let mut search = Search {
+        constraints,
+        constraints_of: &constraints_of,
+        assignment: vec![false; cells.len()],
+        placed: vec![0; constraints.len()],
+        unassigned: covered.iter().map(Vec::len).collect(),
+        nodes: 0,
+        node_limit,
+        by_count: BTreeMap::new(),
+    };
+    if search.run(0) {
+        Some(RegionOutcome {
+            by_count: search.by_count,
+        })
+    } else {
+        None
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+use super::Position;
+
+/// How the cells on a board touch each other, which decides what a cell's number counts and
+/// how far a reveal floods.
+///
+/// Cells are always laid out in a `width`x`height` grid of positions. A topology only decides
+/// which of those positions are next to each other.
+pub trait Topology {
+    /// The `(dx, dy)` steps from `position` to each of the cells touching it. These can depend
+    /// on the position, since not every row of a hex board lines up the same way.
+    fn steps(&self, position: Position) -> &'static [(isize, isize)];
+
+    /// Whether stepping off one edge of the board comes back on at the opposite edge.
+    fn wraps(&self) -> bool {
+        false
+    }
+
+    /// The narrowest (and shortest) a board can be. Wrapping boards need to be at least 3
+    /// cells across so that a cell's neighbours on either side aren't the same cell.
+    fn min_size(&self) -> usize {
+        if self.wraps() {
+            3
+        } else {
+            1
+        }
+    }
+}
+
+const EIGHT_WAY: &[(isize, isize)] = &[
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// The classic grid, where every cell touches the (up to) 8 cells around it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Square;
+
+impl Topology for Square {
+    fn steps(&self, _position: Position) -> &'static [(isize, isize)] {
+        EIGHT_WAY
+    }
+}
+
+/// A grid of hexagons, where every cell touches (up to) 6 others. Odd rows are pushed half a
+/// cell to the right, so they reach one further right above and below than even rows do.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Hex;
+
+impl Topology for Hex {
+    fn steps(&self, position: Position) -> &'static [(isize, isize)] {
+        if position.y.is_multiple_of(2) {
+            &[(-1, -1), (0, -1), (-1, 0), (1, 0), (-1, 1), (0, 1)]
+        } else {
+            &[(0, -1), (1, -1), (-1, 0), (1, 0), (0, 1), (1, 1)]
+        }
+    }
+}
+
+/// A square grid that wraps around at every edge, so every cell touches exactly 8 others and
+/// there are no corners to hide in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Torus;
+
+impl Topology for Torus {
+    fn steps(&self, _position: Position) -> &'static [(isize, isize)] {
+        EIGHT_WAY
+    }
+
+    fn wraps(&self) -> bool {
+        true
+    }
+}
+
+/// Which [`Topology`] a board uses. This is what's stored and sent over the wire.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BoardTopology {
+    #[default]
+    Square,
+    Hex,
+    Torus,
+}
+
+impl BoardTopology {
+    pub const ALL: [BoardTopology; 3] = [Self::Square, Self::Hex, Self::Torus];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Square => "square",
+            Self::Hex => "hex",
+            Self::Torus => "torus",
+        }
+    }
+
+    /// The topology with the given [`BoardTopology::as_str`] name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|topology| topology.as_str() == name)
+    }
+
+    fn topology(&self) -> &'static dyn Topology {
+        match self {
+            Self::Square => &Square,
+            Self::Hex => &Hex,
+            Self::Torus => &Torus,
+        }
+    }
+}
+
+impl Topology for BoardTopology {
+    fn steps(&self, position: Position) -> &'static [(isize, isize)] {
+        self.topology().steps(position)
+    }
+
+    fn wraps(&self) -> bool {
+        self.topology().wraps()
+    }
+
+    fn min_size(&self) -> usize {
+        self.topology().min_size()
+    }
+}
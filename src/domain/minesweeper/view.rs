@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Board, BoardTopology, CellState, GameStatus};
+
+/// What a single cell looks like to a player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "adjacent_mines", rename_all = "snake_case")]
+pub enum CellView {
+    Hidden,
+    Flagged,
+    Revealed(u8),
+    /// A revealed cell whose number is hidden by fog.
+    Fogged,
+    /// Only shown once the game is over.
+    Mine,
+    /// The mine that ended the game.
+    Exploded,
+}
+
+/// A snapshot of a board that is safe to send to the player. Mines are never included
+/// while the game is still going.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoardView {
+    pub width: usize,
+    pub height: usize,
+    /// How the cells touch, which clients need to know to draw the board.
+    #[serde(default)]
+    pub topology: BoardTopology,
+    pub mines: usize,
+    pub mines_remaining: isize,
+    pub status: GameStatus,
+    /// Row-major, so the cell at `(x, y)` is at `y * width + x`.
+    pub cells: Vec<CellView>,
+}
+
+impl BoardView {
+    /// The same board with the numbers on every revealed cell hidden.
+    pub fn fogged(mut self) -> Self {
+        for cell in &mut self.cells {
+            if let CellView::Revealed(_) = cell {
+                *cell = CellView::Fogged;
+            }
+        }
+        self
+    }
+}
+
+impl Board {
+    pub fn view(&self) -> BoardView {
+        let config = self.config();
+        let status = self.status();
+        let cells = (0..config.cell_count())
+            .map(|index| {
+                let position = config.position_of(index);
+                let state = self.cell(position).expect("index is always on the board");
+                let is_mine = self.layout().is_some_and(|layout| layout.is_mine(index));
+                match (state, status) {
+                    (_, GameStatus::Lost { exploded }) if exploded == position => {
+                        CellView::Exploded
+                    }
+                    (CellState::Hidden, GameStatus::Won | GameStatus::Lost { .. }) if is_mine => {
+                        CellView::Mine
+                    }
+                    (CellState::Hidden, _) => CellView::Hidden,
+                    (CellState::Flagged, _) => CellView::Flagged,
+                    (CellState::Revealed, _) => CellView::Revealed(
+                        self.layout()
+                            .expect("revealed cells always have a layout")
+                            .adjacent_mines(index),
+                    ),
+                }
+            })
+            .collect();
+        BoardView {
+            width: config.width(),
+            height: config.height(),
+            topology: config.topology(),
+            mines: config.mines(),
+            mines_remaining: self.mines_remaining(),
+            status,
+            cells,
+        }
+    }
+}
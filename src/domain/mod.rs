@@ -1,4 +1,21 @@
+pub mod anticheat;
+pub mod battle_royale;
+pub mod bot;
+pub mod chat;
+pub mod daily;
 pub(crate) mod errors;
+pub mod friends;
+pub mod leaderboard;
+pub mod lobby;
+pub mod minesweeper;
+pub mod notifications;
+pub mod practice;
+pub mod protocol;
+pub mod rating;
+pub mod replay;
+pub mod spectate;
+pub mod stats;
+pub mod tournament;
 mod user;
 
 pub use user::*;
@@ -0,0 +1,8 @@
+use snafu::prelude::*;
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+#[snafu(visibility(pub(crate)))]
+pub enum NotificationError {
+    #[snafu(display("There's no notification {notification_id}"))]
+    NotificationNotFound { notification_id: i64 },
+}
@@ -0,0 +1,11 @@
+//! Notifications: things worth telling a player about even when they aren't in the game.
+//!
+//! Every notification is saved, and stays unread until the player marks it as read. Players
+//! with `/events` open get them pushed as they happen, and everything still unread again
+//! whenever they reconnect.
+
+mod errors;
+mod notification;
+
+pub use errors::NotificationError;
+pub use notification::*;
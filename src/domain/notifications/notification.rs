@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{lobby::LobbyCode, User};
+
+/// What a player's being told about.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NotificationKind {
+    FriendRequest {
+        from: User,
+    },
+    /// A friend wants them in their lobby.
+    LobbyInvite {
+        from: User,
+        code: LobbyCode,
+    },
+    /// They've been drawn in a heat of a tournament's next round.
+    TournamentHeat {
+        tournament_id: i64,
+        /// Counting from 1, like the heat.
+        round: usize,
+        heat: usize,
+        /// When the heat's match starts, as a unix timestamp in milliseconds.
+        starts_at: i64,
+    },
+    AchievementUnlocked {
+        achievement_id: String,
+        name: String,
+        /// The match that unlocked it.
+        match_id: i64,
+    },
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FriendRequest { .. } => "friend_request",
+            Self::LobbyInvite { .. } => "lobby_invite",
+            Self::TournamentHeat { .. } => "tournament_heat",
+            Self::AchievementUnlocked { .. } => "achievement_unlocked",
+        }
+    }
+}
+
+/// A notification that's been saved for a player.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: i64,
+    /// A unix timestamp.
+    pub created_at: i64,
+    pub read: bool,
+    #[serde(flatten)]
+    pub kind: NotificationKind,
+}
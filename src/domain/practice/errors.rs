@@ -0,0 +1,18 @@
+use snafu::prelude::*;
+
+use crate::domain::minesweeper::BoardError;
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+#[snafu(visibility(pub(crate)))]
+pub enum PracticeError {
+    #[snafu(display("Custom boards can be at most {max}x{max}"))]
+    BoardTooLarge { max: usize },
+    #[snafu(display("You can't practice in the middle of a match"))]
+    InMatch,
+    #[snafu(display("You are not in a match"))]
+    NotInMatch,
+    #[snafu(display("{source}"))]
+    InvalidMove { source: BoardError },
+    #[snafu(display("Power-ups can only be used in matches"))]
+    NoPowerUps,
+}
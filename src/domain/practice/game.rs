@@ -0,0 +1,158 @@
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+
+use super::{errors::*, three_bv, GameStats};
+use crate::domain::{
+    battle_royale::Action,
+    minesweeper::{Board, BoardConfig, BoardView, CellState, GameStatus, Position, RevealedCell},
+    protocol::FlagChange,
+};
+
+/// Custom boards can't be any wider or taller than this.
+pub const MAX_CUSTOM_SIZE: usize = 100;
+
+/// Which board a practice game is played on. The classic sizes each have their own personal
+/// bests, as does every distinct custom board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "level", rename_all = "snake_case")]
+pub enum Level {
+    Beginner,
+    Intermediate,
+    Expert,
+    Custom(BoardConfig),
+}
+
+impl Level {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Beginner => "beginner",
+            Self::Intermediate => "intermediate",
+            Self::Expert => "expert",
+            Self::Custom(_) => "custom",
+        }
+    }
+
+    pub fn config(&self) -> Result<BoardConfig, PracticeError> {
+        Ok(match self {
+            Self::Beginner => BoardConfig::beginner(),
+            Self::Intermediate => BoardConfig::intermediate(),
+            Self::Expert => BoardConfig::expert(),
+            Self::Custom(config) => {
+                ensure!(
+                    config.width() <= MAX_CUSTOM_SIZE && config.height() <= MAX_CUSTOM_SIZE,
+                    BoardTooLargeSnafu {
+                        max: MAX_CUSTOM_SIZE
+                    }
+                );
+                *config
+            }
+        })
+    }
+}
+
+/// What changed on the board because of one action.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PracticeMove {
+    pub revealed: Vec<RevealedCell>,
+    pub exploded: Option<Position>,
+    pub flag: Option<FlagChange>,
+    pub status: GameStatus,
+}
+
+/// How a finished game went.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PracticeResult {
+    pub level: Level,
+    pub won: bool,
+    /// From the first click to the last, to the millisecond.
+    pub time_ms: u64,
+    pub stats: GameStats,
+}
+
+/// One player's practice game. Mines are placed on the first reveal, so it's never a mine.
+pub struct PracticeGame {
+    level: Level,
+    board: Board,
+    clicks: u32,
+    started: Option<Instant>,
+    finished: Option<Instant>,
+}
+
+impl PracticeGame {
+    pub fn new(level: Level, seed: u64) -> Result<Self, PracticeError> {
+        Ok(Self {
+            board: Board::new(level.config()?, seed),
+            level,
+            clicks: 0,
+            started: None,
+            finished: None,
+        })
+    }
+
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    pub fn view(&self) -> BoardView {
+        self.board.view()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.board.status().is_over()
+    }
+
+    /// Applies an action made at `now`. The clock starts with the first action that's
+    /// accepted, and stops with the one that ends the game.
+    pub fn apply(&mut self, action: Action, now: Instant) -> Result<PracticeMove, PracticeError> {
+        let board = &mut self.board;
+        let (outcome, flag) = match action {
+            Action::Reveal(position) => (board.reveal(position).context(InvalidMoveSnafu)?, None),
+            Action::Chord(position) => (board.chord(position).context(InvalidMoveSnafu)?, None),
+            Action::Flag(position) => {
+                let flagged =
+                    board.toggle_flag(position).context(InvalidMoveSnafu)? == CellState::Flagged;
+                (Default::default(), Some(FlagChange { position, flagged }))
+            }
+            Action::UsePowerUp(_) => return NoPowerUpsSnafu.fail(),
+            Action::Forfeit => return NotInMatchSnafu.fail(),
+        };
+        self.clicks += 1;
+        self.started.get_or_insert(now);
+        let status = self.board.status();
+        if status.is_over() {
+            self.finished = Some(now);
+        }
+        Ok(PracticeMove {
+            revealed: outcome.revealed,
+            exploded: outcome.exploded,
+            flag,
+            status,
+        })
+    }
+
+    /// How the game went, once it's over.
+    pub fn result(&self) -> Option<PracticeResult> {
+        let finished = self.finished?;
+        let started = self.started.unwrap_or(finished);
+        let layout = self.board.layout()?;
+        let config = self.board.config();
+        let time_ms = finished.duration_since(started).as_millis() as u64;
+        let solved = |index: usize| {
+            self.board.cell(config.position_of(index)) == Ok(CellState::Revealed)
+                && !layout.is_mine(index)
+        };
+        Some(PracticeResult {
+            level: self.level,
+            won: self.board.status() == GameStatus::Won,
+            time_ms,
+            stats: GameStats::new(
+                three_bv(layout, |_| true),
+                three_bv(layout, solved),
+                self.clicks,
+                time_ms,
+            ),
+        })
+    }
+}
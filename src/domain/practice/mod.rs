@@ -0,0 +1,13 @@
+//! Solo practice: classic minesweeper against the clock, with no opponents and no storm.
+//!
+//! Games are timed by the server from the first click to the last one, and every finished
+//! game is scored with its 3BV (the fewest clicks the board can be cleared in without flags)
+//! and how efficiently the player got through it.
+
+mod errors;
+mod game;
+mod stats;
+
+pub use errors::PracticeError;
+pub use game::*;
+pub use stats::*;
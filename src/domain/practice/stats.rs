@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::minesweeper::MineLayout;
+
+/// How well a game went, beyond whether it was won.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameStats {
+    /// The board's 3BV: the fewest clicks that clear it without flagging anything.
+    pub three_bv: usize,
+    /// How much of the board's 3BV the player got through. All of it, for a win.
+    pub solved_three_bv: usize,
+    /// Every action the player took, flags and chords included.
+    pub clicks: u32,
+    /// Solved 3BV per click, as a percentage. Over 100% is possible with chording.
+    pub efficiency: f64,
+    pub three_bv_per_second: f64,
+}
+
+impl GameStats {
+    pub fn new(three_bv: usize, solved_three_bv: usize, clicks: u32, time_ms: u64) -> Self {
+        Self {
+            three_bv,
+            solved_three_bv,
+            clicks,
+            efficiency: match clicks {
+                0 => 0.0,
+                clicks => solved_three_bv as f64 * 100.0 / clicks as f64,
+            },
+            three_bv_per_second: match time_ms {
+                0 => 0.0,
+                time_ms => solved_three_bv as f64 * 1000.0 / time_ms as f64,
+            },
+        }
+    }
+}
+
+/// Counts the 3BV of a board, only including the parts `solved` says were revealed.
+///
+/// Each opening (a connected patch of cells with no adjacent mines, along with the numbers
+/// around it) takes one click, and so does every other safe cell. An opening counts as solved
+/// once one of its empty cells is revealed, since that reveals all of it.
+pub fn three_bv(layout: &MineLayout, solved: impl Fn(usize) -> bool) -> usize {
+    let config = layout.config();
+    let is_empty = |index: usize| !layout.is_mine(index) && layout.adjacent_mines(index) == 0;
+    let mut in_opening = vec![false; config.cell_count()];
+    let mut count = 0;
+    for start in 0..config.cell_count() {
+        if !is_empty(start) || in_opening[start] {
+            continue;
+        }
+        if solved(start) {
+            count += 1;
+        }
+        in_opening[start] = true;
+        let mut stack = vec![start];
+        while let Some(index) = stack.pop() {
+            for neighbour in config.neighbours(index) {
+                if in_opening[neighbour] || layout.is_mine(neighbour) {
+                    continue;
+                }
+                in_opening[neighbour] = true;
+                if is_empty(neighbour) {
+                    stack.push(neighbour);
+                }
+            }
+        }
+    }
+    count
+        + (0..config.cell_count())
+            .filter(|&index| !layout.is_mine(index) && !in_opening[index] && solved(index))
+            .count()
+}
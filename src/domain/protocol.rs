@@ -0,0 +1,245 @@
+//! The messages sent over the real-time game channel (`/ws`), and to spectators
+//! (`/matches/{id}/spectate`). Everything is JSON, tagged with a `type` field in snake case.
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    battle_royale::{EliminationReason, PowerUp, Standing},
+    friends::Presence,
+    lobby::LobbyCode,
+    minesweeper::{BoardView, GameStatus, Position, RevealedCell},
+    practice::{GameStats, Level},
+    spectate::PlayerBoard,
+    tournament::TournamentStatus,
+    User, UserId,
+};
+
+/// Something a client asks the server to do.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Reveal {
+        position: Position,
+    },
+    Flag {
+        position: Position,
+    },
+    Chord {
+        position: Position,
+    },
+    Chat {
+        text: String,
+    },
+    /// Tells the server every event up to and including `seq` has been received, so it
+    /// doesn't need to keep them around for a resume any more.
+    Ack {
+        seq: u64,
+    },
+    /// Starts a solo practice game, giving up on any practice game already going. Reveals,
+    /// flags and chords go to the practice game whenever the player isn't in a match.
+    StartPractice {
+        #[serde(flatten)]
+        level: Level,
+    },
+    /// Spends one of the player's power-ups. Fog needs a `target` to be aimed at.
+    UsePowerUp {
+        power_up: PowerUp,
+        #[serde(default)]
+        target: Option<UserId>,
+    },
+    /// Gives up on the match the player's in.
+    Forfeit,
+}
+
+/// An event from the server, numbered so clients can resume from where they left off.
+///
+/// Sequence numbers belong to the player rather than the connection: they keep counting up
+/// across reconnects, and start at 1.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ServerMessage {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub event: ServerEvent,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerEvent {
+    /// Sent when a connection is opened. `resumed` is false if the client asked to resume
+    /// from an event that's no longer available, in which case it has to refresh its state
+    /// from scratch.
+    Connected { user_id: UserId, resumed: bool },
+    MatchStarted {
+        match_id: i64,
+        players: Vec<UserId>,
+        /// The cell everyone starts from. It's already revealed on `board`.
+        start: Position,
+        board: BoardView,
+    },
+    /// What changed on this player's board because of their last action.
+    BoardDelta {
+        revealed: Vec<RevealedCell>,
+        exploded: Option<Position>,
+        flag: Option<FlagChange>,
+        status: GameStatus,
+    },
+    /// The player's whole board. This is sent instead of `BoardDelta` while fog hides the
+    /// numbers on it, and once more when the fog lifts.
+    BoardRefreshed { board: BoardView },
+    /// The player cleared a region of their board quickly enough to earn a power-up.
+    PowerUpEarned { power_up: PowerUp },
+    /// Someone in the match used a power-up, aimed at `target` if it's fog.
+    PowerUpUsed {
+        user_id: UserId,
+        power_up: PowerUp,
+        target: Option<UserId>,
+    },
+    /// The player's shield took the hit from the mine at `position`, which is now flagged.
+    ShieldBroken { position: Position },
+    /// Sent on connecting in the middle of a match when the events the player missed couldn't
+    /// be replayed, with everything they need to pick the match back up.
+    MatchInProgress {
+        match_id: i64,
+        players: Vec<UserId>,
+        board: BoardView,
+        power_ups: Vec<PowerUp>,
+    },
+    /// Another player in the match lost their connection. Their place is held for them, and
+    /// unless they're back within `forfeit_in_ms` they forfeit. It's `None` if they get to
+    /// stay in the match however long they're gone.
+    PlayerDisconnected {
+        user_id: UserId,
+        forfeit_in_ms: Option<u64>,
+    },
+    /// A player who lost their connection is back.
+    PlayerReconnected { user_id: UserId },
+    /// How far along another player in the match is.
+    OpponentProgress {
+        user_id: UserId,
+        revealed: usize,
+        safe_cells: usize,
+    },
+    Eliminated {
+        user_id: UserId,
+        placement: usize,
+        reason: EliminationReason,
+    },
+    /// Time left until the storm next closes in on the match.
+    Timer { remaining_ms: u64 },
+    MatchFinished {
+        match_id: i64,
+        /// First place first.
+        standings: Vec<Standing>,
+    },
+    Chat {
+        /// What to report the message by.
+        message_id: i64,
+        from: UserId,
+        text: String,
+    },
+    /// A moderator deleted a chat message, so it shouldn't be shown any more.
+    ChatDeleted { message_id: i64 },
+    PracticeStarted {
+        #[serde(flatten)]
+        level: Level,
+        board: BoardView,
+    },
+    /// A practice game is over. Its moves are reported with `BoardDelta`, like a match's.
+    PracticeFinished {
+        won: bool,
+        time_ms: u64,
+        stats: GameStats,
+        /// Whether this was the player's fastest win on this board.
+        personal_best: bool,
+    },
+    /// How many people are watching the player's match, and how many of them are following
+    /// this player. Sent whenever either changes.
+    SpectatorCount { count: usize, following: usize },
+    /// Someone asked to be the player's friend.
+    FriendRequest { from: User },
+    /// The player and `user` just became friends.
+    FriendAdded { user: User, presence: Presence },
+    /// The player and `user_id` aren't friends any more.
+    FriendRemoved { user_id: UserId },
+    /// A friend came online, went offline, or moved between lobbies and matches.
+    FriendPresence { user_id: UserId, presence: Presence },
+    /// A friend wants the player to join their lobby.
+    LobbyInvite { from: User, code: LobbyCode },
+    /// The player has been drawn in a tournament heat, which starts at `starts_at` (a unix
+    /// timestamp in milliseconds). They need to be connected by then to play in it. `round`
+    /// and `heat` count from 1.
+    TournamentHeat {
+        tournament_id: i64,
+        round: usize,
+        heat: usize,
+        starts_at: i64,
+        players: Vec<User>,
+    },
+    /// A tournament the player entered is over, either because it was played out or because
+    /// it was cancelled.
+    TournamentOver {
+        tournament_id: i64,
+        status: TournamentStatus,
+        winner: Option<User>,
+    },
+    /// The last message couldn't be handled.
+    Error { message: String },
+}
+
+/// Something a spectator asks for. Spectators can only watch, so this is all there is.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SpectatorMessage {
+    /// Switches which player the spectator is following.
+    Focus { user_id: UserId },
+}
+
+/// An event sent to a spectator. Apart from the spectator count, everything is delayed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SpectatorEvent {
+    /// Sent first, with everyone's board as spectators currently see it.
+    Spectating {
+        match_id: i64,
+        boards: Vec<PlayerBoard>,
+        /// The player being followed, who starts out as the first one.
+        focus: UserId,
+        spectators: usize,
+        delay_ms: u64,
+    },
+    /// A change to one player's board.
+    BoardDelta {
+        user_id: UserId,
+        revealed: Vec<RevealedCell>,
+        exploded: Option<Position>,
+        flag: Option<FlagChange>,
+        status: GameStatus,
+    },
+    Eliminated {
+        user_id: UserId,
+        placement: usize,
+        reason: EliminationReason,
+    },
+    /// The spectator switched to following another player, whose board this is.
+    Focused {
+        user_id: UserId,
+        board: BoardView,
+    },
+    SpectatorCount {
+        count: usize,
+    },
+    /// The match is over, and the connection is about to be closed.
+    MatchFinished {
+        match_id: i64,
+        standings: Vec<Standing>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlagChange {
+    pub position: Position,
+    pub flagged: bool,
+}
@@ -0,0 +1,192 @@
+use std::{cmp::Ordering, f64::consts::PI, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{lobby::DEFAULT_RATING, UserId};
+
+/// Converts between the Glicko scale players see and the Glicko-2 scale the maths is done on.
+const SCALE: f64 = 173.7178;
+/// How close the volatility search has to get before it stops.
+const CONVERGENCE: f64 = 0.000_001;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rating {
+    pub rating: f64,
+    /// How unsure we are of `rating`. There's roughly a 95% chance the player's real skill is
+    /// within two deviations of it.
+    pub deviation: f64,
+    /// How erratic the player's results are.
+    pub volatility: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RatingSettings {
+    /// What players start out with.
+    pub initial: Rating,
+    /// Limits how quickly volatility can change. Glickman suggests something between 0.3
+    /// and 1.2.
+    pub tau: f64,
+    /// Every full period a player goes without playing makes their rating less certain.
+    pub rating_period: Duration,
+    /// Deviation never grows past this, no matter how long a player has been away.
+    pub max_deviation: f64,
+    /// Players are provisional while their deviation is at least this high.
+    pub provisional_deviation: f64,
+}
+
+impl Default for RatingSettings {
+    fn default() -> Self {
+        Self {
+            initial: Rating {
+                rating: DEFAULT_RATING,
+                deviation: 350.0,
+                volatility: 0.06,
+            },
+            tau: 0.5,
+            rating_period: Duration::from_secs(24 * 60 * 60),
+            max_deviation: 350.0,
+            provisional_deviation: 110.0,
+        }
+    }
+}
+
+impl RatingSettings {
+    /// How many whole rating periods fit in `elapsed`.
+    pub fn periods_in(&self, elapsed: Duration) -> u64 {
+        match self.rating_period.as_secs() {
+            0 => 0,
+            period => elapsed.as_secs() / period,
+        }
+    }
+}
+
+/// One player's result in a match, for rating it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RatedPlacement {
+    pub user_id: UserId,
+    pub rating: Rating,
+    /// 1 is first place. Players can share a place.
+    pub placement: usize,
+}
+
+impl Rating {
+    /// The rating after going `periods` rating periods without playing.
+    pub fn decayed(&self, periods: u64, settings: &RatingSettings) -> Self {
+        let phi = self.deviation / SCALE;
+        let phi = (phi * phi + periods as f64 * self.volatility * self.volatility).sqrt();
+        Self {
+            deviation: (phi * SCALE).min(settings.max_deviation),
+            ..*self
+        }
+    }
+
+    /// The rating a player carries into a new season: pulled halfway back to where new players
+    /// start, and uncertain enough that they're provisional again.
+    pub fn for_new_season(&self, settings: &RatingSettings) -> Self {
+        Self {
+            rating: (self.rating + settings.initial.rating) / 2.0,
+            deviation: self.deviation.max(settings.provisional_deviation),
+            volatility: self.volatility,
+        }
+    }
+
+    pub fn is_provisional(&self, settings: &RatingSettings) -> bool {
+        self.deviation >= settings.provisional_deviation
+    }
+
+    /// Updates the rating with the results of one rating period. Each result is an opponent's
+    /// rating going into the period and the score against them: 1 for a win, 0.5 for a draw
+    /// and 0 for a loss.
+    pub fn updated(&self, results: &[(Rating, f64)], settings: &RatingSettings) -> Self {
+        let mu = (self.rating - 1500.0) / SCALE;
+        let phi = self.deviation / SCALE;
+        if results.is_empty() {
+            return self.decayed(1, settings);
+        }
+
+        let mut inverse_variance = 0.0;
+        let mut improvement = 0.0;
+        for (opponent, score) in results {
+            let opponent_mu = (opponent.rating - 1500.0) / SCALE;
+            let g = g(opponent.deviation / SCALE);
+            let expected = 1.0 / (1.0 + (-g * (mu - opponent_mu)).exp());
+            inverse_variance += g * g * expected * (1.0 - expected);
+            improvement += g * (score - expected);
+        }
+        let variance = 1.0 / inverse_variance;
+        let delta = variance * improvement;
+
+        let volatility = self.new_volatility(phi, variance, delta, settings.tau);
+        let phi_star = (phi * phi + volatility * volatility).sqrt();
+        let new_phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / variance).sqrt();
+        let new_mu = mu + new_phi * new_phi * improvement;
+        Self {
+            rating: new_mu * SCALE + 1500.0,
+            deviation: (new_phi * SCALE).min(settings.max_deviation),
+            volatility,
+        }
+    }
+
+    /// Step 5 of Glickman's Glicko-2 paper, using the Illinois algorithm.
+    fn new_volatility(&self, phi: f64, variance: f64, delta: f64, tau: f64) -> f64 {
+        let a = (self.volatility * self.volatility).ln();
+        let f = |x: f64| {
+            let ex = x.exp();
+            let denominator = phi * phi + variance + ex;
+            ex * (delta * delta - phi * phi - variance - ex) / (2.0 * denominator * denominator)
+                - (x - a) / (tau * tau)
+        };
+        let mut low = a;
+        let mut high = if delta * delta > phi * phi + variance {
+            (delta * delta - phi * phi - variance).ln()
+        } else {
+            let mut k = 1.0;
+            while f(a - k * tau) < 0.0 {
+                k += 1.0;
+            }
+            a - k * tau
+        };
+        let mut f_low = f(low);
+        let mut f_high = f(high);
+        while (high - low).abs() > CONVERGENCE {
+            let next = low + (low - high) * f_low / (f_high - f_low);
+            let f_next = f(next);
+            if f_next * f_high <= 0.0 {
+                low = high;
+                f_low = f_high;
+            } else {
+                f_low /= 2.0;
+            }
+            high = next;
+            f_high = f_next;
+        }
+        (low / 2.0).exp()
+    }
+}
+
+fn g(phi: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * phi * phi / (PI * PI)).sqrt()
+}
+
+/// Works out everyone's new rating after a match. Everyone is rated against the ratings the
+/// others had going in, and the new ratings come back in the same order as `players`.
+pub fn rate_match(players: &[RatedPlacement], settings: &RatingSettings) -> Vec<Rating> {
+    players
+        .iter()
+        .map(|player| {
+            let results: Vec<(Rating, f64)> = players
+                .iter()
+                .filter(|opponent| opponent.user_id != player.user_id)
+                .map(|opponent| {
+                    let score = match player.placement.cmp(&opponent.placement) {
+                        Ordering::Less => 1.0,
+                        Ordering::Equal => 0.5,
+                        Ordering::Greater => 0.0,
+                    };
+                    (opponent.rating, score)
+                })
+                .collect();
+            player.rating.updated(&results, settings)
+        })
+        .collect()
+}
@@ -0,0 +1,9 @@
+//! Skill ratings, using Glicko-2.
+//!
+//! Glicko-2 only knows about one-on-one games, so a match with more players is treated as
+//! every player having played everyone else: beating those placed below them, losing to
+//! those placed above them, and drawing with anyone who finished in the same place.
+
+mod glicko;
+
+pub use glicko::*;
@@ -0,0 +1,24 @@
+use snafu::prelude::*;
+
+use crate::domain::{battle_royale::ActionError, minesweeper::BoardError};
+
+#[derive(Debug, PartialEq, Eq, Snafu)]
+#[snafu(visibility(pub(crate)))]
+pub enum ReplayError {
+    #[snafu(display("This isn't a replay file"))]
+    NotAReplay,
+    #[snafu(display("Replay files of version {version} aren't supported"))]
+    UnsupportedVersion { version: u8 },
+    #[snafu(display("The replay file ends too early"))]
+    Truncated,
+    #[snafu(display("The replay file is corrupt: {reason}"))]
+    Corrupt { reason: &'static str },
+    #[snafu(display("The match log doesn't start with the match starting"))]
+    MissingStart,
+    #[snafu(display("The replay's board is invalid: {source}"))]
+    InvalidBoard { source: BoardError },
+    #[snafu(display("Action {index} in the replay can't be played: {source}"))]
+    InvalidAction { index: usize, source: ActionError },
+    #[snafu(display("There is no event {index}, the replay only has {len}"))]
+    IndexOutOfRange { index: usize, len: usize },
+}
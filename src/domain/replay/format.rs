@@ -0,0 +1,388 @@
+use std::time::Duration;
+
+use snafu::prelude::*;
+
+use super::{errors::*, LogEntry, MatchRecord};
+use crate::domain::{
+    battle_royale::{Action, BattleRoyaleSettings, PowerUp, PowerUpSettings, PowerUpUse},
+    minesweeper::{BoardConfig, BoardTopology, Position},
+    UserId,
+};
+
+/// Every replay file starts with these bytes.
+pub const REPLAY_MAGIC: &[u8; 4] = b"MSRP";
+/// The version of the replay format [`Replay::encode`] writes. Version 1 files, from before
+/// there were other topologies, can still be read and are always square. Neither they nor
+/// version 2 files have power-ups, and only version 4 files can have forfeits in them.
+pub const REPLAY_VERSION: u8 = 4;
+
+/// An action in a replay, along with who took it and when.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReplayAction {
+    pub tick: u64,
+    pub user_id: UserId,
+    pub action: Action,
+}
+
+/// Everything needed to play a match back: the board, the players and every action they took.
+///
+/// Replays are stored in a compact binary format. After the magic bytes and a version byte,
+/// everything is an unsigned LEB128 varint (user IDs are zigzag encoded first):
+///
+/// ```text
+/// match id, width, height, topology, start cell, tick length in ms, first storm, storm interval,
+/// power-ups enabled, region size, region ticks, max held, fog ticks,
+/// last tick, player count, user id..., mine count, mine cell..., action count,
+/// (ticks since the last action, player index, kind, cell | power-up and target | nothing)...
+/// ```
+///
+/// Cells are indexes into the board, row by row. The topology is 0 for square, 1 for hex and 2
+/// for torus. An action's kind is 0 for a reveal, 1 for a flag, 2 for a chord and 3 for using
+/// a power-up, which is followed by the power-up instead of a cell. Power-ups are 0 for
+/// reveal, 1 for shield and 2 for fog, and their target is 0 for nobody or the target's player
+/// index plus 1. Kind 4 is forfeiting, which is followed by nothing at all.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    pub match_id: i64,
+    pub width: usize,
+    pub height: usize,
+    pub topology: BoardTopology,
+    pub mines: Vec<Position>,
+    pub start: Position,
+    pub players: Vec<UserId>,
+    pub settings: BattleRoyaleSettings,
+    pub actions: Vec<ReplayAction>,
+    /// The tick the match ended on, which can be after the last action if the storm ended it.
+    pub last_tick: u64,
+}
+
+impl Replay {
+    /// Builds a replay out of a match's log. Only the actions are kept.
+    pub fn from_log(match_id: i64, records: &[MatchRecord]) -> Result<Self, ReplayError> {
+        let (width, height, topology, mines, start, players, settings) = match records.first() {
+            Some(MatchRecord {
+                entry:
+                    LogEntry::Started {
+                        width,
+                        height,
+                        topology,
+                        mines,
+                        start,
+                        players,
+                        settings,
+                    },
+                ..
+            }) => (
+                *width,
+                *height,
+                *topology,
+                mines.clone(),
+                *start,
+                players.clone(),
+                settings.clone(),
+            ),
+            _ => return MissingStartSnafu.fail(),
+        };
+        let actions = records
+            .iter()
+            .filter_map(|record| match record.entry {
+                LogEntry::Action { user_id, action } => Some(ReplayAction {
+                    tick: record.tick,
+                    user_id,
+                    action,
+                }),
+                _ => None,
+            })
+            .collect();
+        Ok(Self {
+            match_id,
+            width,
+            height,
+            topology,
+            mines,
+            start,
+            players,
+            settings,
+            actions,
+            last_tick: records.last().map_or(0, |record| record.tick),
+        })
+    }
+
+    /// The replay file's name when it's downloaded.
+    pub fn file_name(&self) -> String {
+        format!("match-{}.replay", self.match_id)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let config = self.config();
+        let mut bytes = REPLAY_MAGIC.to_vec();
+        bytes.push(REPLAY_VERSION);
+        let mut write = |value: u64| write_varint(&mut bytes, value);
+        write(zigzag(self.match_id));
+        write(self.width as u64);
+        write(self.height as u64);
+        write(match self.topology {
+            BoardTopology::Square => 0,
+            BoardTopology::Hex => 1,
+            BoardTopology::Torus => 2,
+        });
+        write(cell_index(config, self.start));
+        write(self.settings.tick_length.as_millis() as u64);
+        write(self.settings.first_storm);
+        write(self.settings.storm_interval);
+        let power_ups = self.settings.power_ups;
+        write(u64::from(power_ups.enabled));
+        write(power_ups.region_size as u64);
+        write(power_ups.region_ticks);
+        write(power_ups.max_held as u64);
+        write(power_ups.fog_ticks);
+        write(self.last_tick);
+        write(self.players.len() as u64);
+        for &player in &self.players {
+            write(zigzag(player));
+        }
+        write(self.mines.len() as u64);
+        for &mine in &self.mines {
+            write(cell_index(config, mine));
+        }
+        write(self.actions.len() as u64);
+        let mut last_tick = 0;
+        let player_index = |user_id: UserId| {
+            self.players
+                .iter()
+                .position(|&player| player == user_id)
+                .expect("only players in the match take actions")
+        };
+        for action in &self.actions {
+            write(action.tick - last_tick);
+            write(player_index(action.user_id) as u64);
+            match action.action {
+                Action::Reveal(position) => {
+                    write(0);
+                    write(cell_index(config, position));
+                }
+                Action::Flag(position) => {
+                    write(1);
+                    write(cell_index(config, position));
+                }
+                Action::Chord(position) => {
+                    write(2);
+                    write(cell_index(config, position));
+                }
+                Action::UsePowerUp(PowerUpUse { power_up, target }) => {
+                    write(3);
+                    write(match power_up {
+                        PowerUp::Reveal => 0,
+                        PowerUp::Shield => 1,
+                        PowerUp::Fog => 2,
+                    });
+                    write(target.map_or(0, |target| player_index(target) as u64 + 1));
+                }
+                Action::Forfeit => write(4),
+            }
+            last_tick = action.tick;
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ReplayError> {
+        ensure!(bytes.starts_with(REPLAY_MAGIC), NotAReplaySnafu);
+        let mut reader = Reader {
+            bytes: &bytes[REPLAY_MAGIC.len()..],
+        };
+        let version = reader.byte()?;
+        ensure!(
+            (1..=REPLAY_VERSION).contains(&version),
+            UnsupportedVersionSnafu { version }
+        );
+        let match_id = unzigzag(reader.varint()?);
+        let width = reader.varint()? as usize;
+        let height = reader.varint()? as usize;
+        let topology = if version == 1 {
+            BoardTopology::Square
+        } else {
+            match reader.varint()? {
+                0 => BoardTopology::Square,
+                1 => BoardTopology::Hex,
+                2 => BoardTopology::Torus,
+                _ => {
+                    return CorruptSnafu {
+                        reason: "unknown topology",
+                    }
+                    .fail()
+                }
+            }
+        };
+        // The mine count doesn't matter here, only the dimensions.
+        let config =
+            BoardConfig::with_topology(width, height, 0, topology).context(InvalidBoardSnafu)?;
+        let start = reader.cell(config)?;
+        let settings = BattleRoyaleSettings {
+            tick_length: Duration::from_millis(reader.varint()?),
+            first_storm: reader.varint()?,
+            storm_interval: reader.varint()?,
+            power_ups: if version < 3 {
+                PowerUpSettings::default()
+            } else {
+                PowerUpSettings {
+                    enabled: reader.varint()? != 0,
+                    region_size: reader.varint()? as usize,
+                    region_ticks: reader.varint()?,
+                    max_held: reader.varint()? as usize,
+                    fog_ticks: reader.varint()?,
+                }
+            },
+        };
+        let last_tick = reader.varint()?;
+        let players = (0..reader.count()?)
+            .map(|_| reader.varint().map(unzigzag))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mines = (0..reader.count()?)
+            .map(|_| reader.cell(config))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut tick = 0u64;
+        let actions = (0..reader.count()?)
+            .map(|_| {
+                tick = tick.checked_add(reader.varint()?).context(CorruptSnafu {
+                    reason: "the ticks overflow",
+                })?;
+                let user_id = *players
+                    .get(reader.varint()? as usize)
+                    .context(CorruptSnafu {
+                        reason: "an action belongs to a player who isn't in the match",
+                    })?;
+                let action = match reader.varint()? {
+                    0 => Action::Reveal(reader.cell(config)?),
+                    1 => Action::Flag(reader.cell(config)?),
+                    2 => Action::Chord(reader.cell(config)?),
+                    3 => {
+                        let power_up = match reader.varint()? {
+                            0 => PowerUp::Reveal,
+                            1 => PowerUp::Shield,
+                            2 => PowerUp::Fog,
+                            _ => {
+                                return CorruptSnafu {
+                                    reason: "unknown power-up",
+                                }
+                                .fail()
+                            }
+                        };
+                        let target = match reader.varint()? {
+                            0 => None,
+                            index => {
+                                Some(*players.get(index as usize - 1).context(CorruptSnafu {
+                                    reason:
+                                        "a power-up is aimed at a player who isn't in the match",
+                                })?)
+                            }
+                        };
+                        Action::UsePowerUp(PowerUpUse { power_up, target })
+                    }
+                    4 => Action::Forfeit,
+                    _ => {
+                        return CorruptSnafu {
+                            reason: "unknown action kind",
+                        }
+                        .fail()
+                    }
+                };
+                Ok(ReplayAction {
+                    tick,
+                    user_id,
+                    action,
+                })
+            })
+            .collect::<Result<Vec<_>, ReplayError>>()?;
+        ensure!(
+            reader.bytes.is_empty(),
+            CorruptSnafu {
+                reason: "there's data after the last action",
+            }
+        );
+        Ok(Self {
+            match_id,
+            width,
+            height,
+            topology,
+            mines,
+            start,
+            players,
+            settings,
+            actions,
+            last_tick,
+        })
+    }
+
+    fn config(&self) -> BoardConfig {
+        BoardConfig::with_topology(self.width, self.height, 0, self.topology)
+            .expect("replays are only made from valid boards")
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, ReplayError> {
+        let (&byte, rest) = self.bytes.split_first().context(TruncatedSnafu)?;
+        self.bytes = rest;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u64, ReplayError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        CorruptSnafu {
+            reason: "a number is too long",
+        }
+        .fail()
+    }
+
+    /// A length, which can't be longer than what's left of the file since everything
+    /// takes at least a byte. This keeps corrupt files from making us allocate too much.
+    fn count(&mut self) -> Result<usize, ReplayError> {
+        let count = self.varint()?;
+        ensure!(count <= self.bytes.len() as u64, TruncatedSnafu);
+        Ok(count as usize)
+    }
+
+    fn cell(&mut self, config: BoardConfig) -> Result<Position, ReplayError> {
+        let index = self.varint()? as usize;
+        ensure!(
+            index < config.cell_count(),
+            CorruptSnafu {
+                reason: "a cell is off the board",
+            }
+        );
+        Ok(config.position_of(index))
+    }
+}
+
+fn cell_index(config: BoardConfig, position: Position) -> u64 {
+    config
+        .index_of(position)
+        .expect("everything in a replay is on its board") as u64
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{
+    battle_royale::{Action, BattleRoyaleSettings, MatchEvent},
+    minesweeper::{BoardTopology, Position},
+    UserId,
+};
+
+/// One entry in a match's log.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LogEntry {
+    /// Always the first entry. Has everything needed to set the match up again.
+    Started {
+        width: usize,
+        height: usize,
+        /// Logs from before there were other topologies are all square.
+        #[serde(default)]
+        topology: BoardTopology,
+        mines: Vec<Position>,
+        start: Position,
+        players: Vec<UserId>,
+        settings: BattleRoyaleSettings,
+    },
+    /// An action the engine accepted. Rejected actions aren't logged.
+    Action { user_id: UserId, action: Action },
+    /// Something the engine reported, either because of the action before it or because
+    /// time passed.
+    Event { event: MatchEvent },
+}
+
+impl LogEntry {
+    /// A short name for the kind of entry, for storing alongside it.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Started { .. } => "started",
+            Self::Action { .. } => "action",
+            Self::Event { .. } => "event",
+        }
+    }
+}
+
+/// An entry in a match's log, in the order it happened.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchRecord {
+    /// Starts at 0 for the [`LogEntry::Started`] entry and counts up without gaps.
+    pub seq: u64,
+    /// The match tick it happened on.
+    pub tick: u64,
+    pub entry: LogEntry,
+}
@@ -0,0 +1,16 @@
+//! Recording matches and playing them back.
+//!
+//! While a match is played, everything that happens in it is kept as an ordered log of
+//! [`MatchRecord`]s. A finished match's log can be turned into a [`Replay`], which only keeps
+//! the board and the players' actions since everything else can be worked out again by
+//! running them back through the engine. That's what [`Replayer`] does.
+
+mod errors;
+mod format;
+mod log;
+mod replayer;
+
+pub use errors::ReplayError;
+pub use format::*;
+pub use log::*;
+pub use replayer::*;
@@ -0,0 +1,157 @@
+use snafu::prelude::*;
+
+use super::{errors::*, LogEntry, MatchRecord, Replay};
+use crate::domain::{
+    battle_royale::{BattleRoyale, MatchResult},
+    minesweeper::{BoardView, MineLayout},
+    UserId,
+};
+
+/// What a match looked like at some point in its replay.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayState {
+    pub tick: u64,
+    /// Every player's board, in the order the players joined the match.
+    pub boards: Vec<(UserId, BoardView)>,
+    /// Set once the match is over.
+    pub result: Option<MatchResult>,
+}
+
+/// Plays a [`Replay`] back through the match engine.
+///
+/// The actions are played back once up front to rebuild the match's full log, including every
+/// event the engine reported. After that, the state of the match after any number of log
+/// entries can be looked at.
+pub struct Replayer {
+    replay: Replay,
+    timeline: Vec<MatchRecord>,
+}
+
+impl Replayer {
+    pub fn new(replay: Replay) -> Result<Self, ReplayError> {
+        let mut replayer = Self {
+            replay,
+            timeline: vec![],
+        };
+        let mut timeline = vec![MatchRecord {
+            seq: 0,
+            tick: 0,
+            entry: LogEntry::Started {
+                width: replayer.replay.width,
+                height: replayer.replay.height,
+                topology: replayer.replay.topology,
+                mines: replayer.replay.mines.clone(),
+                start: replayer.replay.start,
+                players: replayer.replay.players.clone(),
+                settings: replayer.replay.settings.clone(),
+            },
+        }];
+        let mut push = |tick: u64, entry: LogEntry| {
+            let seq = timeline.len() as u64;
+            timeline.push(MatchRecord { seq, tick, entry });
+        };
+        let mut engine = replayer.replay.engine()?;
+        for (index, action) in replayer.replay.actions.iter().enumerate() {
+            while engine.current_tick() < action.tick && !engine.is_over() {
+                for event in engine.tick() {
+                    push(engine.current_tick(), LogEntry::Event { event });
+                }
+            }
+            let events = engine
+                .apply(action.user_id, action.action)
+                .context(InvalidActionSnafu { index })?;
+            push(
+                engine.current_tick(),
+                LogEntry::Action {
+                    user_id: action.user_id,
+                    action: action.action,
+                },
+            );
+            for event in events {
+                push(engine.current_tick(), LogEntry::Event { event });
+            }
+        }
+        while engine.current_tick() < replayer.replay.last_tick && !engine.is_over() {
+            for event in engine.tick() {
+                push(engine.current_tick(), LogEntry::Event { event });
+            }
+        }
+        replayer.timeline = timeline;
+        Ok(replayer)
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// The match's full log, as it would have been recorded while it was played.
+    pub fn timeline(&self) -> &[MatchRecord] {
+        &self.timeline
+    }
+
+    /// The state of the match once the first `index` entries of the timeline have happened.
+    /// `0` is before the match started and `timeline().len()` is the very end.
+    pub fn state_at(&self, index: usize) -> Result<ReplayState, ReplayError> {
+        let len = self.timeline.len();
+        ensure!(index <= len, IndexOutOfRangeSnafu { index, len });
+        let mut engine = self.replay.engine()?;
+        let tick = index
+            .checked_sub(1)
+            .map_or(0, |last| self.timeline[last].tick);
+        for record in &self.timeline[..index] {
+            while engine.current_tick() < record.tick && !engine.is_over() {
+                engine.tick();
+            }
+            if let LogEntry::Action { user_id, action } = record.entry {
+                // Every action was already played back successfully in `new`.
+                engine
+                    .apply(user_id, action)
+                    .expect("replayed actions are valid");
+            }
+        }
+        while engine.current_tick() < tick && !engine.is_over() {
+            engine.tick();
+        }
+        Ok(ReplayState {
+            tick,
+            boards: engine
+                .players()
+                .map(|player| {
+                    let view = engine.view(player).expect("every player has a board");
+                    (player, view)
+                })
+                .collect(),
+            result: engine.result().cloned(),
+        })
+    }
+}
+
+impl Replay {
+    /// A fresh engine for the replay's match, before anyone has done anything.
+    pub fn engine(&self) -> Result<BattleRoyale, ReplayError> {
+        let layout = MineLayout::from_positions_with_topology(
+            self.width,
+            self.height,
+            self.topology,
+            &self.mines,
+        )
+        .context(InvalidBoardSnafu)?;
+        BattleRoyale::new(layout, self.start, &self.players, self.settings.clone())
+            .context(InvalidBoardSnafu)
+    }
+
+    /// An engine with every action in the replay played back, which is where the match was
+    /// at when its log was taken. This is how a match that isn't over can be picked back up.
+    pub fn resume(&self) -> Result<BattleRoyale, ReplayError> {
+        let mut engine = self.engine()?;
+        for (index, action) in self.actions.iter().enumerate() {
+            while engine.current_tick() < action.tick && !engine.is_over() {
+                engine.tick();
+            }
+            engine
+                .apply(action.user_id, action.action)
+                .context(InvalidActionSnafu { index })?;
+        }
+        Ok(engine)
+    }
+}
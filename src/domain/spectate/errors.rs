@@ -0,0 +1,14 @@
+use snafu::prelude::*;
+
+use crate::domain::UserId;
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+#[snafu(visibility(pub(crate)))]
+pub enum SpectateError {
+    #[snafu(display("Match {match_id} isn't being played right now"))]
+    MatchNotLive { match_id: i64 },
+    #[snafu(display("You can't spectate a match you're playing in"))]
+    OwnMatch,
+    #[snafu(display("Player {user_id} isn't in this match"))]
+    NotInMatch { user_id: UserId },
+}
@@ -0,0 +1,200 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{
+    battle_royale::MatchEvent,
+    minesweeper::{BoardView, CellView, GameStatus, Position, RevealedCell},
+    protocol::{FlagChange, SpectatorEvent},
+    UserId,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpectatorSettings {
+    /// How far behind the match spectators are.
+    pub delay: Duration,
+}
+
+impl Default for SpectatorSettings {
+    fn default() -> Self {
+        Self {
+            delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// One player's board, as spectators see it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerBoard {
+    pub user_id: UserId,
+    pub board: BoardView,
+}
+
+/// What spectators of one match get to see, held back by the spectator delay.
+pub struct SpectatorFeed {
+    match_id: i64,
+    delay: Duration,
+    /// Everyone's board as of `delay` ago.
+    boards: Vec<PlayerBoard>,
+    /// Events that happened too recently to be shown yet, oldest first.
+    pending: VecDeque<(Instant, MatchEvent)>,
+    finished: bool,
+}
+
+impl SpectatorFeed {
+    /// Starts a feed from everyone's board at the start of the match.
+    pub fn new(match_id: i64, boards: Vec<PlayerBoard>, settings: &SpectatorSettings) -> Self {
+        let boards = boards
+            .into_iter()
+            .map(|PlayerBoard { user_id, board }| PlayerBoard {
+                user_id,
+                board: hide_mines(board),
+            })
+            .collect();
+        Self {
+            match_id,
+            delay: settings.delay,
+            boards,
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    pub fn match_id(&self) -> i64 {
+        self.match_id
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    pub fn players(&self) -> impl Iterator<Item = UserId> + '_ {
+        self.boards.iter().map(|player| player.user_id)
+    }
+
+    pub fn boards(&self) -> &[PlayerBoard] {
+        &self.boards
+    }
+
+    pub fn board(&self, user_id: UserId) -> Option<&BoardView> {
+        self.boards
+            .iter()
+            .find(|player| player.user_id == user_id)
+            .map(|player| &player.board)
+    }
+
+    /// Whether the end of the match has been shown, after which there's nothing left to see.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Holds on to events that just happened in the match.
+    pub fn record(&mut self, events: impl IntoIterator<Item = MatchEvent>, now: Instant) {
+        self.pending
+            .extend(events.into_iter().map(|event| (now, event)));
+    }
+
+    /// Lets out every event that's been held back long enough, updating the boards with them.
+    pub fn release(&mut self, now: Instant) -> Vec<SpectatorEvent> {
+        let mut released = vec![];
+        while let Some((at, _)) = self.pending.front() {
+            if now.saturating_duration_since(*at) < self.delay {
+                break;
+            }
+            let (_, event) = self.pending.pop_front().expect("there's a front event");
+            released.extend(self.apply(event));
+        }
+        released
+    }
+
+    fn apply(&mut self, event: MatchEvent) -> Option<SpectatorEvent> {
+        match event {
+            MatchEvent::BoardChanged {
+                user_id,
+                revealed,
+                exploded,
+                flag,
+                status,
+            } => {
+                let player = self
+                    .boards
+                    .iter_mut()
+                    .find(|player| player.user_id == user_id)?;
+                apply_delta(&mut player.board, &revealed, exploded, flag, status);
+                Some(SpectatorEvent::BoardDelta {
+                    user_id,
+                    revealed,
+                    exploded,
+                    flag,
+                    status,
+                })
+            }
+            MatchEvent::Eliminated {
+                user_id,
+                placement,
+                reason,
+            } => Some(SpectatorEvent::Eliminated {
+                user_id,
+                placement,
+                reason,
+            }),
+            MatchEvent::Finished(result) => {
+                self.finished = true;
+                Some(SpectatorEvent::MatchFinished {
+                    match_id: self.match_id,
+                    standings: result.standings,
+                })
+            }
+            // Progress can be read off the boards, and the storm timer would be out of date.
+            MatchEvent::Progress { .. } | MatchEvent::StormScheduled { .. } => None,
+            // What power-ups do to a board shows up in its deltas. Spectators see through fog,
+            // since they're not playing.
+            MatchEvent::PowerUpEarned { .. }
+            | MatchEvent::PowerUpUsed { .. }
+            | MatchEvent::ShieldBroken { .. }
+            | MatchEvent::FogLifted { .. } => None,
+        }
+    }
+}
+
+/// Turns any mines on a board back into hidden cells.
+fn hide_mines(mut board: BoardView) -> BoardView {
+    for cell in &mut board.cells {
+        if *cell == CellView::Mine {
+            *cell = CellView::Hidden;
+        }
+    }
+    board
+}
+
+fn apply_delta(
+    board: &mut BoardView,
+    revealed: &[RevealedCell],
+    exploded: Option<Position>,
+    flag: Option<FlagChange>,
+    status: GameStatus,
+) {
+    let index_of = |position: Position| position.y * board.width + position.x;
+    for cell in revealed {
+        let index = index_of(cell.position);
+        board.cells[index] = CellView::Revealed(cell.adjacent_mines);
+    }
+    if let Some(position) = exploded {
+        let index = index_of(position);
+        board.cells[index] = CellView::Exploded;
+    }
+    if let Some(FlagChange { position, flagged }) = flag {
+        let index = index_of(position);
+        if flagged {
+            board.cells[index] = CellView::Flagged;
+            board.mines_remaining -= 1;
+        } else {
+            board.cells[index] = CellView::Hidden;
+            board.mines_remaining += 1;
+        }
+    }
+    board.status = status;
+}
@@ -0,0 +1,12 @@
+//! Watching other people's matches.
+//!
+//! Spectators see every player's board, but only as it was a little while ago, so nobody can
+//! feed a player what their opponents are up to. The boards they see are rebuilt from the
+//! same deltas the players were sent, which means a mine only ever shows up for a spectator
+//! once the player it belongs to has hit it.
+
+mod errors;
+mod feed;
+
+pub use errors::SpectateError;
+pub use feed::*;
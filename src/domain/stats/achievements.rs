@@ -0,0 +1,182 @@
+use serde::Serialize;
+
+use super::{MatchStats, PlayerStats};
+
+/// A lifetime total that achievements can be unlocked by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Total {
+    MatchesPlayed,
+    Wins,
+    CellsRevealed,
+    MinesFlagged,
+    Clears,
+}
+
+impl Total {
+    pub fn of(&self, stats: &PlayerStats) -> u64 {
+        match self {
+            Self::MatchesPlayed => stats.matches_played,
+            Self::Wins => stats.wins,
+            Self::CellsRevealed => stats.cells_revealed,
+            Self::MinesFlagged => stats.mines_flagged,
+            Self::Clears => stats.clears,
+        }
+    }
+}
+
+/// What it takes to unlock an achievement. Rules are checked after every match, against the
+/// player's totals (with that match already added on) and how they did in it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule {
+    /// A lifetime total reaching `at_least`.
+    Reached { total: Total, at_least: u64 },
+    /// Clearing a whole board within `within_ms`.
+    ClearedWithin { within_ms: u64 },
+    /// Having `at_least` mines flagged by the end of a single match.
+    FlaggedInMatch { at_least: usize },
+    /// Winning a match by clearing the board, rather than by outlasting everyone.
+    WonByClearing,
+}
+
+impl Rule {
+    pub fn is_met(&self, totals: &PlayerStats, latest: &MatchStats) -> bool {
+        match *self {
+            Self::Reached { total, at_least } => total.of(totals) >= at_least,
+            Self::ClearedWithin { within_ms } => {
+                latest.clear_ms.is_some_and(|time_ms| time_ms <= within_ms)
+            }
+            Self::FlaggedInMatch { at_least } => latest.mines_flagged >= at_least,
+            Self::WonByClearing => latest.won() && latest.clear_ms.is_some(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Achievement {
+    /// What the achievement is stored as, so it must never change.
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    #[serde(skip)]
+    pub rule: Rule,
+}
+
+impl Achievement {
+    pub fn find(id: &str) -> Option<&'static Achievement> {
+        ACHIEVEMENTS.iter().find(|achievement| achievement.id == id)
+    }
+}
+
+/// Every achievement there is, in the order they're shown.
+pub const ACHIEVEMENTS: &[Achievement] = &[
+    Achievement {
+        id: "first_match",
+        name: "Into the Fray",
+        description: "Finish your first match.",
+        rule: Rule::Reached {
+            total: Total::MatchesPlayed,
+            at_least: 1,
+        },
+    },
+    Achievement {
+        id: "regular",
+        name: "Regular",
+        description: "Finish 50 matches.",
+        rule: Rule::Reached {
+            total: Total::MatchesPlayed,
+            at_least: 50,
+        },
+    },
+    Achievement {
+        id: "veteran",
+        name: "Veteran",
+        description: "Finish 250 matches.",
+        rule: Rule::Reached {
+            total: Total::MatchesPlayed,
+            at_least: 250,
+        },
+    },
+    Achievement {
+        id: "first_win",
+        name: "Last One Standing",
+        description: "Win a match.",
+        rule: Rule::Reached {
+            total: Total::Wins,
+            at_least: 1,
+        },
+    },
+    Achievement {
+        id: "champion",
+        name: "Champion",
+        description: "Win 25 matches.",
+        rule: Rule::Reached {
+            total: Total::Wins,
+            at_least: 25,
+        },
+    },
+    Achievement {
+        id: "clean_sweep",
+        name: "Clean Sweep",
+        description: "Clear your whole board in a match.",
+        rule: Rule::Reached {
+            total: Total::Clears,
+            at_least: 1,
+        },
+    },
+    Achievement {
+        id: "finish_line",
+        name: "Finish Line",
+        description: "Win a match by clearing your whole board.",
+        rule: Rule::WonByClearing,
+    },
+    Achievement {
+        id: "speed_demon",
+        name: "Speed Demon",
+        description: "Clear your whole board in under a minute.",
+        rule: Rule::ClearedWithin { within_ms: 60_000 },
+    },
+    Achievement {
+        id: "lightning",
+        name: "Lightning",
+        description: "Clear your whole board in under 20 seconds.",
+        rule: Rule::ClearedWithin { within_ms: 20_000 },
+    },
+    Achievement {
+        id: "bomb_squad",
+        name: "Bomb Squad",
+        description: "Have 10 mines flagged at the end of a match.",
+        rule: Rule::FlaggedInMatch { at_least: 10 },
+    },
+    Achievement {
+        id: "demolition_expert",
+        name: "Demolition Expert",
+        description: "Flag 1,000 mines.",
+        rule: Rule::Reached {
+            total: Total::MinesFlagged,
+            at_least: 1_000,
+        },
+    },
+    Achievement {
+        id: "explorer",
+        name: "Explorer",
+        description: "Reveal 10,000 safe cells.",
+        rule: Rule::Reached {
+            total: Total::CellsRevealed,
+            at_least: 10_000,
+        },
+    },
+];
+
+/// The achievements a player has just unlocked with `latest`, leaving out the ones they
+/// already had. `totals` should already include `latest`.
+pub fn newly_unlocked(
+    totals: &PlayerStats,
+    latest: &MatchStats,
+    already: &[String],
+) -> Vec<&'static Achievement> {
+    ACHIEVEMENTS
+        .iter()
+        .filter(|achievement| !already.iter().any(|id| id == achievement.id))
+        .filter(|achievement| achievement.rule.is_met(totals, latest))
+        .collect()
+}
@@ -0,0 +1,10 @@
+use snafu::prelude::*;
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+#[snafu(visibility(pub(crate)))]
+pub enum StatsError {
+    #[snafu(display("The match log doesn't start with the match being set up"))]
+    MissingStart,
+    #[snafu(display("The match log doesn't say how the match finished"))]
+    MissingResult,
+}
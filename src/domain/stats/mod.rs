@@ -0,0 +1,14 @@
+//! Players' lifetime statistics, and the achievements they unlock along the way.
+//!
+//! Every finished match is boiled down to a [`MatchStats`] for each player, straight from the
+//! match's log, and added onto their [`PlayerStats`]. Achievements are a fixed list of
+//! [`Rule`]s that are checked against those after every match, so working them all out again
+//! from old matches gives the same answer as having recorded them as they were played.
+
+mod achievements;
+mod errors;
+mod totals;
+
+pub use achievements::*;
+pub use errors::StatsError;
+pub use totals::*;
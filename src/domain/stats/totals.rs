@@ -0,0 +1,141 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+
+use super::errors::*;
+use crate::domain::{
+    battle_royale::{MatchEvent, Outcome},
+    minesweeper::Position,
+    replay::{LogEntry, MatchRecord},
+    UserId,
+};
+
+/// How one player did in one match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchStats {
+    pub user_id: UserId,
+    /// 1 is first place.
+    pub placement: usize,
+    /// How many safe cells they revealed.
+    pub revealed: usize,
+    /// How many mines they had flagged when the match ended. Flags on safe cells don't count.
+    pub mines_flagged: usize,
+    /// How long it took them to clear their whole board, if they did.
+    pub clear_ms: Option<u64>,
+}
+
+impl MatchStats {
+    /// Works out how everyone did in a finished match from its log, first place first.
+    pub fn from_log(records: &[MatchRecord]) -> Result<Vec<Self>, StatsError> {
+        let (mines, settings) = match records.first() {
+            Some(MatchRecord {
+                entry: LogEntry::Started {
+                    mines, settings, ..
+                },
+                ..
+            }) => (
+                mines.iter().copied().collect::<HashSet<Position>>(),
+                settings,
+            ),
+            _ => return MissingStartSnafu.fail(),
+        };
+        let mut flags: HashMap<UserId, HashSet<Position>> = HashMap::new();
+        let mut result = None;
+        for record in records {
+            match &record.entry {
+                LogEntry::Event {
+                    event:
+                        MatchEvent::BoardChanged {
+                            user_id,
+                            flag: Some(change),
+                            ..
+                        },
+                } => {
+                    let flagged = flags.entry(*user_id).or_default();
+                    if change.flagged {
+                        flagged.insert(change.position);
+                    } else {
+                        flagged.remove(&change.position);
+                    }
+                }
+                LogEntry::Event {
+                    event: MatchEvent::Finished(finished),
+                } => result = Some(finished),
+                _ => {}
+            }
+        }
+        let result = result.context(MissingResultSnafu)?;
+        Ok(result
+            .standings
+            .iter()
+            .map(|standing| Self {
+                user_id: standing.user_id,
+                placement: standing.placement,
+                revealed: standing.revealed,
+                mines_flagged: flags.get(&standing.user_id).map_or(0, |flagged| {
+                    flagged
+                        .iter()
+                        .filter(|position| mines.contains(position))
+                        .count()
+                }),
+                clear_ms: match standing.outcome {
+                    Outcome::Cleared { tick } => {
+                        Some(settings.duration_of(tick).as_millis() as u64)
+                    }
+                    _ => None,
+                },
+            })
+            .collect())
+    }
+
+    pub fn won(&self) -> bool {
+        self.placement == 1
+    }
+}
+
+/// The quickest a player has cleared a whole board in a match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FastestClear {
+    pub match_id: i64,
+    pub time_ms: u64,
+}
+
+/// A player's totals over every match they've finished.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub matches_played: u64,
+    pub wins: u64,
+    /// Every placement they've had added up, for working out their average.
+    pub placement_total: u64,
+    pub cells_revealed: u64,
+    pub mines_flagged: u64,
+    /// How many times they've cleared their whole board.
+    pub clears: u64,
+    pub fastest_clear: Option<FastestClear>,
+}
+
+impl PlayerStats {
+    /// Adds a match onto the totals.
+    pub fn record(&mut self, match_id: i64, stats: &MatchStats) {
+        self.matches_played += 1;
+        self.wins += u64::from(stats.won());
+        self.placement_total += stats.placement as u64;
+        self.cells_revealed += stats.revealed as u64;
+        self.mines_flagged += stats.mines_flagged as u64;
+        if let Some(time_ms) = stats.clear_ms {
+            self.clears += 1;
+            if self
+                .fastest_clear
+                .is_none_or(|fastest| time_ms < fastest.time_ms)
+            {
+                self.fastest_clear = Some(FastestClear { match_id, time_ms });
+            }
+        }
+    }
+
+    /// `None` until they've played a match.
+    pub fn average_placement(&self) -> Option<f64> {
+        (self.matches_played > 0).then(|| self.placement_total as f64 / self.matches_played as f64)
+    }
+}
@@ -0,0 +1,545 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+
+use super::errors::*;
+use crate::domain::{
+    lobby::{LobbySettings, MIN_PLAYERS},
+    User, UserId,
+};
+
+/// How a tournament is played.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TournamentFormat {
+    /// What every heat is played with. `max_players` is how many players go in each heat.
+    pub lobby: LobbySettings,
+    /// How many players from each heat go through to the next round.
+    pub advance: usize,
+    pub max_entrants: usize,
+}
+
+impl TournamentFormat {
+    pub fn heat_size(&self) -> usize {
+        self.lobby.max_players
+    }
+
+    pub fn validate(&self) -> Result<(), TournamentError> {
+        if let Err(error) = self.lobby.board_config() {
+            return InvalidFormatSnafu {
+                reason: error.to_string(),
+            }
+            .fail();
+        }
+        ensure!(
+            (1..self.heat_size()).contains(&self.advance),
+            InvalidFormatSnafu {
+                reason: "at least one player has to go through from each heat, and at least one \
+                         has to be knocked out"
+            }
+        );
+        ensure!(
+            self.max_entrants >= MIN_PLAYERS,
+            InvalidFormatSnafu {
+                reason: format!("at least {} players have to be able to enter", MIN_PLAYERS)
+            }
+        );
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TournamentStatus {
+    Registration,
+    Running,
+    Finished,
+    /// Not enough players registered.
+    Cancelled,
+}
+
+impl TournamentStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Registration => "registration",
+            Self::Running => "running",
+            Self::Finished => "finished",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        matches!(self, Self::Finished | Self::Cancelled)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entrant {
+    pub user: User,
+    /// 1 is the top seed. Set once registration closes.
+    pub seed: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum HeatState {
+    /// Starts at `starts_at`, a unix timestamp in milliseconds.
+    Waiting {
+        starts_at: i64,
+    },
+    Playing {
+        match_id: i64,
+    },
+    Finished,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeatPlayer {
+    pub user: User,
+    /// 1 is first place. Set once the heat is over, unless they didn't turn up.
+    pub placement: Option<usize>,
+    pub no_show: bool,
+    /// Whether they went through to the next round.
+    pub advanced: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Heat {
+    pub players: Vec<HeatPlayer>,
+    pub state: HeatState,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Round {
+    pub heats: Vec<Heat>,
+}
+
+impl Round {
+    /// The final is the round that's played as a single heat.
+    pub fn is_final(&self) -> bool {
+        self.heats.len() == 1
+    }
+}
+
+/// A heat that's due to start, with everyone who's meant to play in it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DueHeat {
+    pub round: usize,
+    pub heat: usize,
+    pub players: Vec<User>,
+}
+
+/// Where a player finished in a tournament.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TournamentStanding {
+    pub place: usize,
+    pub user: User,
+    /// How many rounds they were drawn in.
+    pub rounds: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tournament {
+    id: i64,
+    name: String,
+    format: TournamentFormat,
+    /// When registration closes and the first round is drawn, as a unix timestamp in
+    /// milliseconds.
+    starts_at: i64,
+    status: TournamentStatus,
+    /// In the order they registered until registration closes, then in seed order.
+    entrants: Vec<Entrant>,
+    rounds: Vec<Round>,
+    /// Nobody wins if everyone who was left didn't turn up.
+    winner: Option<User>,
+}
+
+impl Tournament {
+    pub fn new(
+        id: i64,
+        name: String,
+        format: TournamentFormat,
+        starts_at: i64,
+        now: i64,
+    ) -> Result<Self, TournamentError> {
+        format.validate()?;
+        ensure!(starts_at > now, StartsInPastSnafu);
+        Ok(Self {
+            id,
+            name,
+            format,
+            starts_at,
+            status: TournamentStatus::Registration,
+            entrants: vec![],
+            rounds: vec![],
+            winner: None,
+        })
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn format(&self) -> &TournamentFormat {
+        &self.format
+    }
+
+    pub fn starts_at(&self) -> i64 {
+        self.starts_at
+    }
+
+    pub fn status(&self) -> TournamentStatus {
+        self.status
+    }
+
+    pub fn entrants(&self) -> &[Entrant] {
+        &self.entrants
+    }
+
+    pub fn rounds(&self) -> &[Round] {
+        &self.rounds
+    }
+
+    pub fn winner(&self) -> Option<&User> {
+        self.winner.as_ref()
+    }
+
+    pub fn is_registered(&self, user_id: UserId) -> bool {
+        self.entrants
+            .iter()
+            .any(|entrant| entrant.user.id() == user_id)
+    }
+
+    pub fn register(&mut self, user: User, now: i64) -> Result<(), TournamentError> {
+        self.ensure_registration_open(now)?;
+        ensure!(!self.is_registered(user.id()), AlreadyRegisteredSnafu);
+        ensure!(
+            self.entrants.len() < self.format.max_entrants,
+            TournamentFullSnafu {
+                max: self.format.max_entrants
+            }
+        );
+        self.entrants.push(Entrant { user, seed: None });
+        Ok(())
+    }
+
+    pub fn withdraw(&mut self, user_id: UserId, now: i64) -> Result<(), TournamentError> {
+        self.ensure_registration_open(now)?;
+        ensure!(self.is_registered(user_id), NotRegisteredSnafu);
+        self.entrants.retain(|entrant| entrant.user.id() != user_id);
+        Ok(())
+    }
+
+    fn ensure_registration_open(&self, now: i64) -> Result<(), TournamentError> {
+        ensure!(
+            self.status == TournamentStatus::Registration && now < self.starts_at,
+            RegistrationClosedSnafu
+        );
+        Ok(())
+    }
+
+    /// Whether it's time to call [`Tournament::close_registration`].
+    pub fn registration_due(&self, now: i64) -> bool {
+        self.status == TournamentStatus::Registration && now >= self.starts_at
+    }
+
+    /// Seeds everyone by their rating, highest first, and draws the first round, which
+    /// starts `round_break` milliseconds from now. Players without a rating in `ratings` are
+    /// seeded last, and players with the same rating keep the order they registered in.
+    ///
+    /// The tournament is cancelled if too few players registered.
+    pub fn close_registration(
+        &mut self,
+        ratings: &HashMap<UserId, f64>,
+        now: i64,
+        round_break: i64,
+    ) {
+        if self.status != TournamentStatus::Registration {
+            return;
+        }
+        if self.entrants.len() < MIN_PLAYERS {
+            self.status = TournamentStatus::Cancelled;
+            return;
+        }
+        let rating_of =
+            |entrant: &Entrant| ratings.get(&entrant.user.id()).copied().unwrap_or(f64::MIN);
+        self.entrants
+            .sort_by(|a, b| rating_of(b).total_cmp(&rating_of(a)));
+        for (index, entrant) in self.entrants.iter_mut().enumerate() {
+            entrant.seed = Some(index + 1);
+        }
+        self.status = TournamentStatus::Running;
+        let players = self
+            .entrants
+            .iter()
+            .map(|entrant| entrant.user.clone())
+            .collect();
+        self.draw(players, now + round_break);
+    }
+
+    /// Spreads `players`, best first, across as few heats as will hold them. They go across
+    /// the heats and then back again, so every heat gets a fair share of the strong players.
+    fn draw(&mut self, players: Vec<User>, starts_at: i64) {
+        let heat_count = players.len().div_ceil(self.format.heat_size());
+        let mut heats: Vec<Heat> = (0..heat_count)
+            .map(|_| Heat {
+                players: vec![],
+                state: HeatState::Waiting { starts_at },
+            })
+            .collect();
+        for (index, user) in players.into_iter().enumerate() {
+            let offset = index % heat_count;
+            let heat = if (index / heat_count).is_multiple_of(2) {
+                offset
+            } else {
+                heat_count - 1 - offset
+            };
+            heats[heat].players.push(HeatPlayer {
+                user,
+                placement: None,
+                no_show: false,
+                advanced: false,
+            });
+        }
+        self.rounds.push(Round { heats });
+    }
+
+    /// Heats in the current round that are waiting to start and are due to.
+    pub fn due_heats(&self, now: i64) -> Vec<DueHeat> {
+        if self.status != TournamentStatus::Running {
+            return vec![];
+        }
+        let round = self.rounds.len() - 1;
+        self.rounds[round]
+            .heats
+            .iter()
+            .enumerate()
+            .filter(|(_, heat)| {
+                matches!(heat.state, HeatState::Waiting { starts_at } if starts_at <= now)
+            })
+            .map(|(index, heat)| DueHeat {
+                round,
+                heat: index,
+                players: heat.players.iter().map(|player| player.user.clone()).collect(),
+            })
+            .collect()
+    }
+
+    /// The matches heats in the current round are being played in.
+    pub fn playing_matches(&self) -> Vec<i64> {
+        self.rounds
+            .last()
+            .map(|round| {
+                round
+                    .heats
+                    .iter()
+                    .filter_map(|heat| match heat.state {
+                        HeatState::Playing { match_id } => Some(match_id),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Records that a heat has started with only the `present` players, in the match with
+    /// `match_id`. Everyone else is a no-show.
+    ///
+    /// Without a match, which is what happens when fewer than two players turn up, the heat
+    /// is over straight away and whoever did turn up goes through.
+    pub fn heat_started(
+        &mut self,
+        round: usize,
+        heat: usize,
+        present: &[UserId],
+        match_id: Option<i64>,
+        now: i64,
+        round_break: i64,
+    ) {
+        if round + 1 != self.rounds.len() || self.status != TournamentStatus::Running {
+            return;
+        }
+        let heat_state = match self.rounds[round].heats.get_mut(heat) {
+            Some(heat_state) if matches!(heat_state.state, HeatState::Waiting { .. }) => heat_state,
+            _ => return,
+        };
+        for player in heat_state.players.iter_mut() {
+            player.no_show = !present.contains(&player.user.id());
+        }
+        match match_id {
+            Some(match_id) => heat_state.state = HeatState::Playing { match_id },
+            None => self.finish_heat(round, heat, present, now, round_break),
+        }
+    }
+
+    /// Records the result of a heat's match, with `placements` being the players in the order
+    /// they finished. Returns whether any heat was being played in the match.
+    pub fn heat_finished(
+        &mut self,
+        match_id: i64,
+        placements: &[UserId],
+        now: i64,
+        round_break: i64,
+    ) -> bool {
+        match self.heat_playing(match_id) {
+            Some((round, heat)) => {
+                self.finish_heat(round, heat, placements, now, round_break);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Puts a heat whose match was lost, to a restart say, back to waiting so it's played
+    /// again after another round break.
+    pub fn heat_abandoned(&mut self, match_id: i64, now: i64, round_break: i64) {
+        if let Some((round, heat)) = self.heat_playing(match_id) {
+            let heat = &mut self.rounds[round].heats[heat];
+            heat.state = HeatState::Waiting {
+                starts_at: now + round_break,
+            };
+            for player in heat.players.iter_mut() {
+                player.no_show = false;
+            }
+        }
+    }
+
+    fn heat_playing(&self, match_id: i64) -> Option<(usize, usize)> {
+        let round = self.rounds.len().checked_sub(1)?;
+        let heat = self.rounds[round]
+            .heats
+            .iter()
+            .position(|heat| heat.state == HeatState::Playing { match_id })?;
+        Some((round, heat))
+    }
+
+    fn finish_heat(
+        &mut self,
+        round: usize,
+        heat: usize,
+        placements: &[UserId],
+        now: i64,
+        round_break: i64,
+    ) {
+        let is_final = self.rounds[round].is_final();
+        let played = placements.len();
+        // Somebody always has to be knocked out, or the rounds would never get any smaller.
+        let going_through = match played {
+            _ if is_final => 0,
+            0 | 1 => played,
+            _ => self.format.advance.min(played - 1),
+        };
+        let heat_state = &mut self.rounds[round].heats[heat];
+        for player in heat_state.players.iter_mut() {
+            player.placement = placements
+                .iter()
+                .position(|&user_id| user_id == player.user.id())
+                .map(|index| index + 1);
+            player.advanced = player
+                .placement
+                .is_some_and(|placement| placement <= going_through);
+        }
+        heat_state.state = HeatState::Finished;
+        let round_over = self.rounds[round]
+            .heats
+            .iter()
+            .all(|heat| heat.state == HeatState::Finished);
+        if round_over {
+            self.next_round(now, round_break);
+        }
+    }
+
+    /// Draws the next round from everyone who went through, or finishes the tournament if
+    /// that was the final or there's nobody left to play.
+    fn next_round(&mut self, now: i64, round_break: i64) {
+        let round = self.rounds.last().expect("only called after a round");
+        if round.is_final() {
+            self.winner = round.heats[0]
+                .players
+                .iter()
+                .find(|player| player.placement == Some(1))
+                .map(|player| player.user.clone());
+            self.status = TournamentStatus::Finished;
+            return;
+        }
+        // Heat winners are drawn first, then the runners up and so on, with the seeds
+        // deciding between players who finished in the same place.
+        let seed_of = |user_id: UserId| {
+            self.entrants
+                .iter()
+                .find(|entrant| entrant.user.id() == user_id)
+                .and_then(|entrant| entrant.seed)
+                .unwrap_or(usize::MAX)
+        };
+        let mut advancing: Vec<(usize, usize, User)> = round
+            .heats
+            .iter()
+            .flat_map(|heat| heat.players.iter())
+            .filter(|player| player.advanced)
+            .map(|player| {
+                (
+                    player.placement.unwrap_or(usize::MAX),
+                    seed_of(player.user.id()),
+                    player.user.clone(),
+                )
+            })
+            .collect();
+        advancing.sort_by_key(|(placement, seed, _)| (*placement, *seed));
+        let players: Vec<User> = advancing.into_iter().map(|(_, _, user)| user).collect();
+        if players.len() < MIN_PLAYERS {
+            self.winner = players.into_iter().next();
+            self.status = TournamentStatus::Finished;
+            return;
+        }
+        self.draw(players, now + round_break);
+    }
+
+    /// Everyone who was drawn in a round, best first: the further they got, the better,
+    /// then by where they finished in the last heat they were drawn in. No-shows come after
+    /// everyone who played in the same round, and the seeds settle anything else.
+    pub fn standings(&self) -> Vec<TournamentStanding> {
+        let mut reached: Vec<(usize, bool, usize, usize, &User)> = self
+            .entrants
+            .iter()
+            .filter_map(|entrant| {
+                let user_id = entrant.user.id();
+                let (rounds, last) =
+                    self.rounds
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .find_map(|(index, round)| {
+                            round
+                                .heats
+                                .iter()
+                                .flat_map(|heat| heat.players.iter())
+                                .find(|player| player.user.id() == user_id)
+                                .map(|player| (index + 1, player))
+                        })?;
+                Some((
+                    rounds,
+                    last.no_show,
+                    last.placement.unwrap_or(usize::MAX),
+                    entrant.seed.unwrap_or(usize::MAX),
+                    &entrant.user,
+                ))
+            })
+            .collect();
+        reached.sort_by_key(|&(rounds, no_show, placement, seed, _)| {
+            (Reverse(rounds), no_show, placement, seed)
+        });
+        reached
+            .into_iter()
+            .enumerate()
+            .map(|(index, (rounds, _, _, _, user))| TournamentStanding {
+                place: index + 1,
+                user: user.clone(),
+                rounds,
+            })
+            .collect()
+    }
+}
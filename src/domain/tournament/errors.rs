@@ -0,0 +1,20 @@
+use snafu::prelude::*;
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+#[snafu(visibility(pub(crate)))]
+pub enum TournamentError {
+    #[snafu(display("There's no tournament {tournament_id}"))]
+    TournamentNotFound { tournament_id: i64 },
+    #[snafu(display("Registration for this tournament is closed"))]
+    RegistrationClosed,
+    #[snafu(display("You're already registered for this tournament"))]
+    AlreadyRegistered,
+    #[snafu(display("You aren't registered for this tournament"))]
+    NotRegistered,
+    #[snafu(display("This tournament is full at {max} players"))]
+    TournamentFull { max: usize },
+    #[snafu(display("Invalid tournament format: {reason}"))]
+    InvalidFormat { reason: String },
+    #[snafu(display("Tournaments have to start in the future"))]
+    StartsInPast,
+}
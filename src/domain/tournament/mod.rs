@@ -0,0 +1,17 @@
+//! Organised tournaments, played as rounds of battle royale heats.
+//!
+//! Players register while registration is open. When it closes, they're seeded by rating and
+//! spread across the first round's heats. The top finishers in each heat go through to the
+//! next round, until a round is small enough to be played as a single final heat. Players who
+//! don't turn up for their heat are knocked out.
+//!
+//! A [`Tournament`] is only ever changed by the calls here, and it can be saved and loaded
+//! whole, so a tournament carries on from where it was after a restart.
+
+mod bracket;
+mod errors;
+mod settings;
+
+pub use bracket::*;
+pub use errors::TournamentError;
+pub use settings::*;
@@ -0,0 +1,21 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TournamentSettings {
+    /// How long players have between a round being drawn and their heat starting. Anyone who
+    /// isn't connected by then is a no-show.
+    pub round_break: Duration,
+    /// How often tournaments are checked for heats to start and results to record.
+    pub check_interval: Duration,
+}
+
+impl Default for TournamentSettings {
+    fn default() -> Self {
+        Self {
+            round_break: Duration::from_secs(60),
+            check_interval: Duration::from_secs(1),
+        }
+    }
+}
@@ -49,14 +49,17 @@ impl UserInput {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+/// The primary key of the `User` table.
+pub type UserId = i64;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct User {
-    id: i64,
+    id: UserId,
     username: String,
 }
 
 impl User {
-    pub fn new(id: i64, username: String) -> Self {
+    pub fn new(id: UserId, username: String) -> Self {
         Self { id, username }
     }
 
@@ -64,7 +67,7 @@ impl User {
         &self.username
     }
 
-    pub fn id(&self) -> i64 {
+    pub fn id(&self) -> UserId {
         self.id
     }
 }
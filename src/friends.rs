@@ -0,0 +1,267 @@
+//! Storing friendships and friend requests. Whether a request makes sense is decided by
+//! [`crate::domain::friends`], and presence comes from the game server.
+
+use deadpool_sqlite::rusqlite::{params, Connection, Error, OptionalExtension};
+use snafu::ResultExt;
+
+use crate::{
+    db_handle::DbHandle,
+    domain::{
+        errors::*,
+        friends::{FriendError, Relationship, RequestOutcome},
+        User, UserId,
+    },
+    session::unix_timestamp,
+};
+
+/// A friend, and since when (a unix timestamp).
+pub(crate) struct Friend {
+    pub(crate) user: User,
+    pub(crate) since: i64,
+}
+
+/// Friend requests a player hasn't heard back about, both ways round.
+pub(crate) struct PendingRequests {
+    pub(crate) incoming: Vec<User>,
+    pub(crate) outgoing: Vec<User>,
+}
+
+/// Asks `other` to be friends. If they'd already asked, that's taken as a yes. Returns what
+/// happened, along with who `other` is.
+pub(crate) async fn send_request(
+    db_handle: &DbHandle,
+    user_id: UserId,
+    other: UserId,
+) -> Result<(RequestOutcome, User), InnerError> {
+    if user_id == other {
+        return Err(FriendError::FriendingSelf).context(FriendSnafu);
+    }
+    db_handle
+        .transaction(move |transaction| {
+            let other_user = match find_user(transaction, other)? {
+                Some(user) => user,
+                None => return Ok(Err(FriendError::UserNotFound { user_id: other })),
+            };
+            let outcome = match relationship(transaction, user_id, other)?.send_request() {
+                Ok(outcome) => outcome,
+                Err(error) => return Ok(Err(error)),
+            };
+            match outcome {
+                RequestOutcome::Sent => {
+                    transaction.execute(
+                        "INSERT INTO FriendRequest (SenderId, RecipientId, SentAt)
+                         VALUES (?1, ?2, ?3)",
+                        params![user_id, other, unix_timestamp()],
+                    )?;
+                }
+                RequestOutcome::Accepted => befriend(transaction, user_id, other)?,
+            }
+            Ok(Ok((outcome, other_user)))
+        })
+        .await?
+        .context(FriendSnafu)
+}
+
+/// Accepts (or declines) the friend request `other` sent. Returns who `other` is.
+pub(crate) async fn answer_request(
+    db_handle: &DbHandle,
+    user_id: UserId,
+    other: UserId,
+    accept: bool,
+) -> Result<User, InnerError> {
+    db_handle
+        .transaction(move |transaction| {
+            if let Err(error) = relationship(transaction, user_id, other)?.answer_request(other) {
+                return Ok(Err(error));
+            }
+            if accept {
+                befriend(transaction, user_id, other)?;
+            } else {
+                transaction.execute(
+                    "DELETE FROM FriendRequest WHERE SenderId = ?1 AND RecipientId = ?2",
+                    params![other, user_id],
+                )?;
+            }
+            let other_user = find_user(transaction, other)?.expect("requests are from real users");
+            Ok(Ok(other_user))
+        })
+        .await?
+        .context(FriendSnafu)
+}
+
+/// Ends a friendship, or takes back a request that hasn't been answered yet.
+pub(crate) async fn unfriend(
+    db_handle: &DbHandle,
+    user_id: UserId,
+    other: UserId,
+) -> Result<(), InnerError> {
+    let forgotten = db_handle
+        .transaction(move |transaction| {
+            match relationship(transaction, user_id, other)? {
+                Relationship::Friends | Relationship::RequestSent => {}
+                _ => return Ok(false),
+            }
+            forget(transaction, user_id, other)?;
+            Ok(true)
+        })
+        .await?;
+    if !forgotten {
+        return Err(FriendError::NotFriends { user_id: other }).context(FriendSnafu);
+    }
+    Ok(())
+}
+
+/// Checks the two players are friends.
+pub(crate) async fn ensure_friends(
+    db_handle: &DbHandle,
+    user_id: UserId,
+    other: UserId,
+) -> Result<(), InnerError> {
+    db_handle
+        .transaction(move |transaction| relationship(transaction, user_id, other))
+        .await?
+        .ensure_friends(other)
+        .context(FriendSnafu)
+}
+
+pub(crate) async fn friend_ids(
+    db_handle: &DbHandle,
+    user_id: UserId,
+) -> Result<Vec<UserId>, InnerError> {
+    db_handle
+        .transaction(move |transaction| {
+            transaction
+                .prepare("SELECT FriendId FROM Friendship WHERE UserId = ?1")?
+                .query_map(params![user_id], |row| row.get(0))?
+                .collect()
+        })
+        .await
+}
+
+pub(crate) async fn friends(
+    db_handle: &DbHandle,
+    user_id: UserId,
+) -> Result<Vec<Friend>, InnerError> {
+    db_handle
+        .transaction(move |transaction| {
+            transaction
+                .prepare(
+                    "SELECT User.Id, User.Username, Friendship.Since
+                     FROM Friendship JOIN User ON User.Id = Friendship.FriendId
+                     WHERE Friendship.UserId = ?1
+                     ORDER BY User.Username",
+                )?
+                .query_map(params![user_id], |row| {
+                    Ok(Friend {
+                        user: User::new(row.get(0)?, row.get(1)?),
+                        since: row.get(2)?,
+                    })
+                })?
+                .collect()
+        })
+        .await
+}
+
+pub(crate) async fn pending_requests(
+    db_handle: &DbHandle,
+    user_id: UserId,
+) -> Result<PendingRequests, InnerError> {
+    db_handle
+        .transaction(move |transaction| {
+            let incoming = transaction
+                .prepare(
+                    "SELECT User.Id, User.Username
+                     FROM FriendRequest JOIN User ON User.Id = FriendRequest.SenderId
+                     WHERE FriendRequest.RecipientId = ?1
+                     ORDER BY FriendRequest.SentAt, User.Id",
+                )?
+                .query_map(params![user_id], |row| {
+                    Ok(User::new(row.get(0)?, row.get(1)?))
+                })?
+                .collect::<Result<_, _>>()?;
+            let outgoing = transaction
+                .prepare(
+                    "SELECT User.Id, User.Username
+                     FROM FriendRequest JOIN User ON User.Id = FriendRequest.RecipientId
+                     WHERE FriendRequest.SenderId = ?1
+                     ORDER BY FriendRequest.SentAt, User.Id",
+                )?
+                .query_map(params![user_id], |row| {
+                    Ok(User::new(row.get(0)?, row.get(1)?))
+                })?
+                .collect::<Result<_, _>>()?;
+            Ok(PendingRequests { incoming, outgoing })
+        })
+        .await
+}
+
+/// Drops any friendship or friend requests between the two players. Returns whether they
+/// were friends.
+pub(crate) fn forget(
+    connection: &Connection,
+    user_id: UserId,
+    other: UserId,
+) -> Result<bool, Error> {
+    connection.execute(
+        "DELETE FROM FriendRequest
+         WHERE (SenderId = ?1 AND RecipientId = ?2) OR (SenderId = ?2 AND RecipientId = ?1)",
+        params![user_id, other],
+    )?;
+    let removed = connection.execute(
+        "DELETE FROM Friendship
+         WHERE (UserId = ?1 AND FriendId = ?2) OR (UserId = ?2 AND FriendId = ?1)",
+        params![user_id, other],
+    )?;
+    Ok(removed > 0)
+}
+
+fn befriend(connection: &Connection, user_id: UserId, other: UserId) -> Result<(), Error> {
+    forget(connection, user_id, other)?;
+    let now = unix_timestamp();
+    for (from, to) in [(user_id, other), (other, user_id)] {
+        connection.execute(
+            "INSERT INTO Friendship (UserId, FriendId, Since) VALUES (?1, ?2, ?3)",
+            params![from, to, now],
+        )?;
+    }
+    Ok(())
+}
+
+fn relationship(
+    connection: &Connection,
+    user_id: UserId,
+    other: UserId,
+) -> Result<Relationship, Error> {
+    let exists = |query: &str| {
+        connection
+            .query_row(query, params![user_id, other], |_| Ok(()))
+            .optional()
+            .map(|row| row.is_some())
+    };
+    Ok(
+        if exists(
+            "SELECT 1 FROM ChatIgnore WHERE Kind = 'block'
+             AND ((UserId = ?1 AND TargetId = ?2) OR (UserId = ?2 AND TargetId = ?1))",
+        )? {
+            Relationship::Blocked
+        } else if exists("SELECT 1 FROM Friendship WHERE UserId = ?1 AND FriendId = ?2")? {
+            Relationship::Friends
+        } else if exists("SELECT 1 FROM FriendRequest WHERE SenderId = ?1 AND RecipientId = ?2")? {
+            Relationship::RequestSent
+        } else if exists("SELECT 1 FROM FriendRequest WHERE SenderId = ?2 AND RecipientId = ?1")? {
+            Relationship::RequestReceived
+        } else {
+            Relationship::Strangers
+        },
+    )
+}
+
+fn find_user(connection: &Connection, user_id: UserId) -> Result<Option<User>, Error> {
+    connection
+        .query_row(
+            "SELECT Id, Username FROM User WHERE Id = ?1",
+            params![user_id],
+            |row| Ok(User::new(row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+}
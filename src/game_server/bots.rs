@@ -0,0 +1,128 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::domain::{
+    battle_royale::Action,
+    bot::{Bot, BotSettings},
+    minesweeper::BoardView,
+    User, UserId,
+};
+
+/// A bot that's in a match, and when it gets to move next.
+struct PlayingBot {
+    bot: Bot,
+    next_move_at: Instant,
+}
+
+/// Every bot account the server knows about, and what each of them is doing.
+pub(crate) struct Bots {
+    settings: BotSettings,
+    accounts: HashMap<UserId, User>,
+    /// Bots that aren't in a match, in the order they became free.
+    idle: Vec<UserId>,
+    playing: HashMap<UserId, PlayingBot>,
+    next_seed: u64,
+}
+
+impl Bots {
+    pub(crate) fn new(settings: BotSettings, seed: u64) -> Self {
+        Self {
+            settings,
+            accounts: HashMap::new(),
+            idle: vec![],
+            playing: HashMap::new(),
+            next_seed: seed,
+        }
+    }
+
+    pub(crate) fn settings(&self) -> &BotSettings {
+        &self.settings
+    }
+
+    /// Makes new bot accounts available to play.
+    pub(crate) fn add_accounts(&mut self, accounts: Vec<User>) {
+        for account in accounts {
+            if self
+                .accounts
+                .insert(account.id(), account.clone())
+                .is_none()
+            {
+                self.idle.push(account.id());
+            }
+        }
+    }
+
+    pub(crate) fn is_bot(&self, user_id: UserId) -> bool {
+        self.accounts.contains_key(&user_id)
+    }
+
+    /// Takes up to `count` bots that aren't doing anything. They're kept aside until they
+    /// start playing or are given back with `release`.
+    pub(crate) fn take_idle(&mut self, count: usize) -> Vec<User> {
+        let count = count.min(self.idle.len());
+        self.idle
+            .drain(..count)
+            .map(|user_id| self.accounts[&user_id].clone())
+            .collect()
+    }
+
+    /// Gives back bots from `take_idle` that didn't end up playing.
+    pub(crate) fn release(&mut self, bots: &[User]) {
+        self.idle.extend(bots.iter().map(User::id));
+    }
+
+    /// Gives every bot in `players` a brain for the match that's starting. Their first move
+    /// comes after one reaction delay, like anyone else's.
+    pub(crate) fn start_playing(&mut self, players: &[UserId], now: Instant) {
+        for &player in players {
+            if !self.is_bot(player) {
+                continue;
+            }
+            self.next_seed = self.next_seed.wrapping_add(1);
+            let bot = Bot::new(self.settings.skill.clone(), self.next_seed);
+            let next_move_at = now + bot.skill().reaction_delay;
+            self.playing
+                .insert(player, PlayingBot { bot, next_move_at });
+        }
+    }
+
+    /// Frees up the bots in `players` once their match is over. Returns which of them were
+    /// bots.
+    pub(crate) fn stop_playing(&mut self, players: &[UserId]) -> Vec<UserId> {
+        let stopped: Vec<UserId> = players
+            .iter()
+            .copied()
+            .filter(|player| self.playing.remove(player).is_some())
+            .collect();
+        self.idle.extend(&stopped);
+        stopped
+    }
+
+    /// The bots that are due to move at `now`.
+    pub(crate) fn due(&self, now: Instant) -> Vec<UserId> {
+        self.playing
+            .iter()
+            .filter(|(_, playing)| playing.next_move_at <= now)
+            .map(|(&user_id, _)| user_id)
+            .collect()
+    }
+
+    pub(crate) fn playing(&self) -> impl Iterator<Item = UserId> + '_ {
+        self.playing.keys().copied()
+    }
+
+    /// Works out the bot's next move on `board`, and when it'll be ready for the one after.
+    pub(crate) fn think(
+        &mut self,
+        user_id: UserId,
+        board: &BoardView,
+        now: Instant,
+    ) -> Option<Action> {
+        let playing = self.playing.get_mut(&user_id)?;
+        let delay: Duration = playing.bot.skill().reaction_delay;
+        playing.next_move_at = now + delay;
+        playing.bot.next_action(board)
+    }
+}
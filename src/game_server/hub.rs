@@ -0,0 +1,214 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use crate::domain::{
+    protocol::{ServerEvent, ServerMessage},
+    UserId,
+};
+
+/// Identifies one web socket connection, since a player can reconnect (and so have several
+/// connections over time).
+pub(crate) type ConnectionId = u64;
+
+/// A player's stream of events, which outlives any single connection.
+struct Channel {
+    /// The sequence number of the last event sent to this player.
+    last_seq: u64,
+    /// Events that haven't been acknowledged yet, oldest first.
+    unacked: VecDeque<ServerMessage>,
+    connection: Option<(ConnectionId, mpsc::Sender<ServerMessage>)>,
+    /// When the channel last lost its connection, or when it was opened if it's never had
+    /// one.
+    disconnected_at: Instant,
+}
+
+impl Channel {
+    fn new() -> Self {
+        Self {
+            last_seq: 0,
+            unacked: VecDeque::new(),
+            connection: None,
+            disconnected_at: Instant::now(),
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.connection = None;
+        self.disconnected_at = Instant::now();
+    }
+}
+
+/// What a newly opened connection should do before it starts streaming live events.
+pub(crate) struct Attached {
+    pub(crate) connection_id: ConnectionId,
+    pub(crate) receiver: mpsc::Receiver<ServerMessage>,
+    /// Events to send first, because the client missed them.
+    pub(crate) replay: Vec<ServerMessage>,
+    /// Whether the client's resume point was still available.
+    pub(crate) resumed: bool,
+}
+
+/// Routes server events to players' web socket connections.
+///
+/// Every event gets the next sequence number in its player's channel and is kept until the
+/// player acknowledges it (up to `replay_limit` events), so a client that reconnects can pick
+/// up where it left off. Each connection has a bounded queue of `send_buffer` events. A client
+/// that falls that far behind is disconnected instead of letting the queue grow forever; it
+/// can reconnect and resume. A player who stays away for longer than `replay_window` has
+/// their channel dropped by `prune`, and starts over with a full refresh if they come back.
+pub(crate) struct Hub {
+    channels: Mutex<HashMap<UserId, Channel>>,
+    next_connection_id: AtomicU64,
+    send_buffer: usize,
+    replay_limit: usize,
+    replay_window: Duration,
+}
+
+impl Hub {
+    pub(crate) fn new(send_buffer: usize, replay_limit: usize, replay_window: Duration) -> Self {
+        Self {
+            channels: Mutex::new(HashMap::new()),
+            next_connection_id: AtomicU64::new(1),
+            send_buffer,
+            replay_limit,
+            replay_window,
+        }
+    }
+
+    pub(crate) fn replay_window(&self) -> Duration {
+        self.replay_window
+    }
+
+    /// Opens a connection for `user_id`, replacing any connection they already had.
+    ///
+    /// `last_seq` is the last event the client saw, if it's resuming. Everything after that
+    /// is replayed, provided it's all still around.
+    pub(crate) fn attach(&self, user_id: UserId, last_seq: Option<u64>) -> Attached {
+        let connection_id = self.next_connection_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel(self.send_buffer);
+        let mut channels = self.channels.lock().expect("hub lock was poisoned");
+        let channel = channels.entry(user_id).or_insert_with(Channel::new);
+        channel.connection = Some((connection_id, sender));
+        let (replay, resumed) = match last_seq {
+            Some(last_seq) => {
+                let oldest_available = channel
+                    .unacked
+                    .front()
+                    .map_or(channel.last_seq + 1, |message| message.seq);
+                // Every event after `last_seq` has to still be here for the resume to work. It
+                // comes from the client, so it's checked against the channel before anything's
+                // added to it.
+                let resumed = last_seq <= channel.last_seq && last_seq + 1 >= oldest_available;
+                let replay = channel
+                    .unacked
+                    .iter()
+                    .filter(|message| message.seq > last_seq)
+                    .cloned()
+                    .collect();
+                (replay, resumed)
+            }
+            None => (vec![], false),
+        };
+        Attached {
+            connection_id,
+            receiver,
+            replay,
+            resumed,
+        }
+    }
+
+    /// Closes the connection, unless the player has already replaced it with a newer one.
+    pub(crate) fn detach(&self, user_id: UserId, connection_id: ConnectionId) {
+        let mut channels = self.channels.lock().expect("hub lock was poisoned");
+        if let Some(channel) = channels.get_mut(&user_id) {
+            if matches!(channel.connection, Some((id, _)) if id == connection_id) {
+                channel.disconnect();
+            }
+        }
+    }
+
+    /// Forgets every event up to and including `seq`.
+    pub(crate) fn acknowledge(&self, user_id: UserId, seq: u64) {
+        let mut channels = self.channels.lock().expect("hub lock was poisoned");
+        if let Some(channel) = channels.get_mut(&user_id) {
+            while matches!(channel.unacked.front(), Some(message) if message.seq <= seq) {
+                channel.unacked.pop_front();
+            }
+        }
+    }
+
+    /// Queues an event for the player. If they aren't connected right now, it's kept for
+    /// when they resume.
+    pub(crate) fn send(&self, user_id: UserId, event: ServerEvent) {
+        let mut channels = self.channels.lock().expect("hub lock was poisoned");
+        let channel = channels.entry(user_id).or_insert_with(Channel::new);
+        channel.last_seq += 1;
+        let message = ServerMessage {
+            seq: channel.last_seq,
+            event,
+        };
+        channel.unacked.push_back(message.clone());
+        while channel.unacked.len() > self.replay_limit {
+            channel.unacked.pop_front();
+        }
+        if let Some((connection_id, sender)) = &channel.connection {
+            match sender.try_send(message) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    tracing::warn!(
+                        user_id,
+                        connection_id,
+                        "Disconnecting a client that isn't keeping up"
+                    );
+                    channel.disconnect();
+                }
+                Err(TrySendError::Closed(_)) => channel.disconnect(),
+            }
+        }
+    }
+
+    /// Drops the channels of players who've been disconnected for the whole replay window,
+    /// along with the events they never picked up.
+    pub(crate) fn prune(&self, now: Instant) {
+        let mut channels = self.channels.lock().expect("hub lock was poisoned");
+        channels.retain(|_, channel| {
+            channel.connection.is_some()
+                || now.saturating_duration_since(channel.disconnected_at) < self.replay_window
+        });
+    }
+
+    /// Whether the player has the game channel open right now.
+    pub(crate) fn is_connected(&self, user_id: UserId) -> bool {
+        let channels = self.channels.lock().expect("hub lock was poisoned");
+        channels
+            .get(&user_id)
+            .is_some_and(|channel| channel.connection.is_some())
+    }
+
+    /// How many players have the game channel open right now.
+    pub(crate) fn connection_count(&self) -> usize {
+        let channels = self.channels.lock().expect("hub lock was poisoned");
+        channels
+            .values()
+            .filter(|channel| channel.connection.is_some())
+            .count()
+    }
+
+    pub(crate) fn send_all<'a>(
+        &self,
+        user_ids: impl IntoIterator<Item = &'a UserId>,
+        event: ServerEvent,
+    ) {
+        for &user_id in user_ids {
+            self.send(user_id, event.clone());
+        }
+    }
+}
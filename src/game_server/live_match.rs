@@ -0,0 +1,285 @@
+use std::time::Instant;
+
+use crate::domain::{
+    battle_royale::{
+        Absences, Action, ActionError, BattleRoyale, ForfeitRule, MatchEvent, MatchResult, PowerUp,
+        ReconnectSettings,
+    },
+    lobby::{GameMode, LobbyCode},
+    protocol::ServerEvent,
+    replay::{LogEntry, MatchRecord},
+    UserId,
+};
+
+/// Who an event produced by a match should be sent to.
+pub(crate) enum Recipients {
+    Player(UserId),
+    /// Everyone in the match except this player.
+    Opponents(UserId),
+    Everyone,
+}
+
+/// A match in progress, along with what's needed to report on it once it's over.
+pub(crate) struct LiveMatch {
+    pub(crate) id: i64,
+    pub(crate) code: LobbyCode,
+    pub(crate) mode: GameMode,
+    players: Vec<UserId>,
+    engine: BattleRoyale,
+    /// Players whose connection dropped, who are waiting to be forfeited.
+    absences: Absences,
+    /// Log entries that haven't been saved yet.
+    unsaved: Vec<MatchRecord>,
+    /// Events that haven't been passed on to spectators yet.
+    unwatched: Vec<MatchEvent>,
+    next_seq: u64,
+}
+
+impl LiveMatch {
+    /// `started` is the first entry in the match's log.
+    pub(crate) fn new(
+        id: i64,
+        code: LobbyCode,
+        mode: GameMode,
+        engine: BattleRoyale,
+        started: LogEntry,
+    ) -> Self {
+        let mut live_match = Self {
+            id,
+            code,
+            mode,
+            players: engine.players().collect(),
+            engine,
+            absences: Absences::default(),
+            unsaved: vec![],
+            unwatched: vec![],
+            next_seq: 0,
+        };
+        live_match.record(started);
+        live_match
+    }
+
+    pub(crate) fn players(&self) -> &[UserId] {
+        &self.players
+    }
+
+    pub(crate) fn engine(&self) -> &BattleRoyale {
+        &self.engine
+    }
+
+    pub(crate) fn result(&self) -> Option<&MatchResult> {
+        self.engine.result()
+    }
+
+    /// Applies a player's action and returns the events that should be sent out because of it.
+    pub(crate) fn apply(
+        &mut self,
+        player: UserId,
+        action: Action,
+    ) -> Result<Vec<(Recipients, ServerEvent)>, ActionError> {
+        let events = self.engine.apply(player, action)?;
+        self.record(LogEntry::Action {
+            user_id: player,
+            action,
+        });
+        Ok(self.route_events(events))
+    }
+
+    /// Moves the match clock forward one tick.
+    pub(crate) fn tick(&mut self) -> Vec<(Recipients, ServerEvent)> {
+        let events = self.engine.tick();
+        self.route_events(events)
+    }
+
+    /// Holds a player's place after they lose their connection, and lets everyone else know.
+    pub(crate) fn player_left(
+        &mut self,
+        user_id: UserId,
+        now: Instant,
+        settings: &ReconnectSettings,
+    ) -> Vec<(Recipients, ServerEvent)> {
+        if !self.engine.is_active(user_id) {
+            return vec![];
+        }
+        let deadline = self.absences.left(user_id, now, settings.grace_period);
+        let forfeit_in_ms = (settings.forfeit == ForfeitRule::Eliminate)
+            .then(|| deadline.saturating_duration_since(now).as_millis() as u64);
+        vec![(
+            Recipients::Opponents(user_id),
+            ServerEvent::PlayerDisconnected {
+                user_id,
+                forfeit_in_ms,
+            },
+        )]
+    }
+
+    /// Lets everyone know a player who lost their connection is back.
+    pub(crate) fn player_returned(&mut self, user_id: UserId) -> Vec<(Recipients, ServerEvent)> {
+        if !self.absences.returned(user_id) {
+            return vec![];
+        }
+        vec![(
+            Recipients::Opponents(user_id),
+            ServerEvent::PlayerReconnected { user_id },
+        )]
+    }
+
+    /// Forfeits everyone who's been gone for longer than their grace period.
+    pub(crate) fn forfeit_absent(&mut self, now: Instant) -> Vec<(Recipients, ServerEvent)> {
+        let mut routed = vec![];
+        for user_id in self.absences.overdue(now) {
+            // Anyone who was knocked out in the meantime has nothing left to forfeit.
+            if let Ok(events) = self.apply(user_id, Action::Forfeit) {
+                routed.extend(events);
+            }
+        }
+        routed
+    }
+
+    /// Everything a player needs to pick the match back up from scratch.
+    pub(crate) fn snapshot(&self, user_id: UserId) -> Option<ServerEvent> {
+        Some(ServerEvent::MatchInProgress {
+            match_id: self.id,
+            players: self.players.clone(),
+            board: self.engine.view(user_id)?,
+            power_ups: self.engine.power_ups(user_id).to_vec(),
+        })
+    }
+
+    /// Takes the log entries that still need saving.
+    pub(crate) fn take_unsaved(&mut self) -> Vec<MatchRecord> {
+        std::mem::take(&mut self.unsaved)
+    }
+
+    /// Takes the events that still need passing on to spectators.
+    pub(crate) fn take_unwatched(&mut self) -> Vec<MatchEvent> {
+        std::mem::take(&mut self.unwatched)
+    }
+
+    fn record(&mut self, entry: LogEntry) {
+        self.unsaved.push(MatchRecord {
+            seq: self.next_seq,
+            tick: self.engine.current_tick(),
+            entry,
+        });
+        self.next_seq += 1;
+    }
+
+    /// How long until the storm closes in next, in milliseconds.
+    pub(crate) fn storm_timer(&self) -> Option<ServerEvent> {
+        let storm = self.engine.next_storm()?;
+        let remaining = storm.saturating_sub(self.engine.current_tick());
+        Some(ServerEvent::Timer {
+            remaining_ms: self.engine.settings().duration_of(remaining).as_millis() as u64,
+        })
+    }
+
+    /// A player's whole board, as they see it.
+    fn board_refreshed(&self, user_id: UserId) -> Option<ServerEvent> {
+        let board = self.engine.view(user_id)?;
+        Some(ServerEvent::BoardRefreshed { board })
+    }
+
+    /// Works out who needs to hear about each event. Finishing the match is reported
+    /// separately, once its result has been saved.
+    ///
+    /// A fogged player can't be sent deltas, since those carry the numbers the fog is hiding,
+    /// so they get their whole (fogged) board instead.
+    fn route_events(&mut self, events: Vec<MatchEvent>) -> Vec<(Recipients, ServerEvent)> {
+        for event in &events {
+            self.record(LogEntry::Event {
+                event: event.clone(),
+            });
+        }
+        self.unwatched.extend(events.iter().cloned());
+        events
+            .into_iter()
+            .flat_map(|event| match event {
+                MatchEvent::BoardChanged { user_id, .. } if self.engine.is_fogged(user_id) => self
+                    .board_refreshed(user_id)
+                    .map(|refreshed| (Recipients::Player(user_id), refreshed))
+                    .into_iter()
+                    .collect(),
+                MatchEvent::BoardChanged {
+                    user_id,
+                    revealed,
+                    exploded,
+                    flag,
+                    status,
+                } => vec![(
+                    Recipients::Player(user_id),
+                    ServerEvent::BoardDelta {
+                        revealed,
+                        exploded,
+                        flag,
+                        status,
+                    },
+                )],
+                MatchEvent::Progress {
+                    user_id,
+                    revealed,
+                    safe_cells,
+                } => vec![(
+                    Recipients::Opponents(user_id),
+                    ServerEvent::OpponentProgress {
+                        user_id,
+                        revealed,
+                        safe_cells,
+                    },
+                )],
+                MatchEvent::Eliminated {
+                    user_id,
+                    placement,
+                    reason,
+                } => vec![(
+                    Recipients::Everyone,
+                    ServerEvent::Eliminated {
+                        user_id,
+                        placement,
+                        reason,
+                    },
+                )],
+                MatchEvent::StormScheduled { .. } => self
+                    .storm_timer()
+                    .map(|timer| (Recipients::Everyone, timer))
+                    .into_iter()
+                    .collect(),
+                MatchEvent::PowerUpEarned { user_id, power_up } => vec![(
+                    Recipients::Player(user_id),
+                    ServerEvent::PowerUpEarned { power_up },
+                )],
+                MatchEvent::PowerUpUsed {
+                    user_id,
+                    power_up,
+                    target,
+                } => {
+                    let mut routed = vec![(
+                        Recipients::Everyone,
+                        ServerEvent::PowerUpUsed {
+                            user_id,
+                            power_up,
+                            target,
+                        },
+                    )];
+                    if let (PowerUp::Fog, Some(target)) = (power_up, target) {
+                        routed.extend(
+                            self.board_refreshed(target)
+                                .map(|refreshed| (Recipients::Player(target), refreshed)),
+                        );
+                    }
+                    routed
+                }
+                MatchEvent::ShieldBroken { user_id, position } => vec![(
+                    Recipients::Player(user_id),
+                    ServerEvent::ShieldBroken { position },
+                )],
+                MatchEvent::FogLifted { user_id } => self
+                    .board_refreshed(user_id)
+                    .map(|refreshed| (Recipients::Player(user_id), refreshed))
+                    .into_iter()
+                    .collect(),
+                MatchEvent::Finished(_) => vec![],
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,1151 @@
+//! The live, in-memory side of the game: lobbies, the matchmaking queue and the matches
+//! being played. Only results are written to the database.
+
+mod bots;
+mod hub;
+mod live_match;
+mod spectators;
+mod tournaments;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use actix_web::web;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use deadpool_sqlite::rusqlite::{self, params, Transaction};
+use snafu::ResultExt;
+
+use crate::{
+    anticheat::spawn_match_review,
+    bots::{bot_accounts, create_bot_accounts},
+    chat::{hidden_from, save_message},
+    config::WebSocketSettings,
+    db_handle::DbHandle,
+    domain::{
+        anticheat::{AntiCheatSettings, Pipeline},
+        battle_royale::{
+            Action, BattleRoyale, BattleRoyaleSettings, ForfeitRule, PowerUpUse, ReconnectSettings,
+        },
+        bot::BotSettings,
+        chat::{ChatError, ChatRoom, ChatSettings, RateLimiter, WordFilter},
+        errors::*,
+        friends::Presence,
+        lobby::{
+            Lobbies, LobbyCode, LobbyError, LobbySettings, LobbyStatus, MatchStart, Matchmaker,
+            MatchmakingSettings, QueueEntry,
+        },
+        minesweeper::{generate_no_guess, GeneratorOptions, MineLayout, Position},
+        practice::{Level, PracticeError, PracticeGame},
+        protocol::SpectatorEvent,
+        protocol::{ClientMessage, ServerEvent},
+        rating::RatingSettings,
+        replay::{LogEntry, MatchRecord},
+        spectate::{PlayerBoard, SpectatorFeed, SpectatorSettings},
+        tournament::TournamentSettings,
+        User, UserId,
+    },
+    friends::friend_ids,
+    notifications::Notifications,
+    practice::record_practice_game,
+    ratings::record_match_ratings,
+    session::unix_timestamp,
+    stats::spawn_stats_update,
+};
+use bots::*;
+pub(crate) use hub::*;
+use live_match::*;
+pub(crate) use spectators::*;
+pub(crate) use tournaments::spawn_tournaments;
+
+/// Every match being played, and which match each player is in.
+#[derive(Default)]
+struct LiveMatches {
+    by_id: HashMap<i64, LiveMatch>,
+    by_player: HashMap<UserId, i64>,
+    /// Matches that are over and have been taken out of `by_id`, but haven't been saved yet.
+    finishing: HashSet<i64>,
+}
+
+impl LiveMatches {
+    /// Takes a match that's over out of play. It counts as live until `finished` is called.
+    fn remove(&mut self, match_id: i64) -> LiveMatch {
+        let live_match = self
+            .by_id
+            .remove(&match_id)
+            .expect("only live matches are removed");
+        for player in live_match.players() {
+            // The player may already be in another match, if this one was left running.
+            if self.by_player.get(player) == Some(&match_id) {
+                self.by_player.remove(player);
+            }
+        }
+        self.finishing.insert(match_id);
+        live_match
+    }
+
+    fn finished(&mut self, match_id: i64) {
+        self.finishing.remove(&match_id);
+    }
+
+    /// Whether the match is being played or saved on this server.
+    fn is_live(&self, match_id: i64) -> bool {
+        self.by_id.contains_key(&match_id) || self.finishing.contains(&match_id)
+    }
+}
+
+pub(crate) struct GameServer {
+    db_handle: DbHandle,
+    lobbies: Mutex<Lobbies>,
+    matchmaker: Mutex<Matchmaker>,
+    matches: Mutex<LiveMatches>,
+    /// Everyone's practice game, if they have one. Finished games stay until the next one
+    /// starts, so the board can still be looked at.
+    practice: Mutex<HashMap<UserId, PracticeGame>>,
+    battle_royale: BattleRoyaleSettings,
+    reconnects: ReconnectSettings,
+    ratings: RatingSettings,
+    spectating: SpectatorSettings,
+    chat: ChatSettings,
+    word_filter: WordFilter,
+    chat_limiter: Mutex<RateLimiter>,
+    hub: Hub,
+    notifications: Arc<Notifications>,
+    spectators: Spectators,
+    bots: Mutex<Bots>,
+    /// Reviews every match once it's over.
+    anticheat: Arc<Pipeline>,
+    /// The presence each player's friends were last told about. Offline players aren't in
+    /// here.
+    presence: Mutex<HashMap<UserId, Presence>>,
+    tournaments: TournamentSettings,
+}
+
+/// The settings for everything the game server runs.
+pub(crate) struct GameServerSettings {
+    pub(crate) matchmaking: MatchmakingSettings,
+    pub(crate) battle_royale: BattleRoyaleSettings,
+    pub(crate) reconnects: ReconnectSettings,
+    pub(crate) ratings: RatingSettings,
+    pub(crate) spectating: SpectatorSettings,
+    pub(crate) chat: ChatSettings,
+    pub(crate) bots: BotSettings,
+    pub(crate) anticheat: AntiCheatSettings,
+    pub(crate) tournaments: TournamentSettings,
+}
+
+impl GameServer {
+    pub(crate) fn new(
+        db_handle: DbHandle,
+        settings: GameServerSettings,
+        websocket: &WebSocketSettings,
+        notifications: Arc<Notifications>,
+    ) -> Self {
+        let GameServerSettings {
+            matchmaking,
+            battle_royale,
+            reconnects,
+            ratings,
+            spectating,
+            chat,
+            bots,
+            anticheat,
+            tournaments,
+        } = settings;
+        Self {
+            db_handle,
+            lobbies: Mutex::new(Lobbies::new(OsRng.next_u64())),
+            matchmaker: Mutex::new(Matchmaker::new(matchmaking)),
+            matches: Mutex::new(LiveMatches::default()),
+            practice: Mutex::new(HashMap::new()),
+            battle_royale,
+            reconnects,
+            ratings,
+            spectating,
+            word_filter: WordFilter::new(&chat.banned_words),
+            chat_limiter: Mutex::new(RateLimiter::new(&chat)),
+            chat,
+            hub: Hub::new(
+                websocket.send_buffer,
+                websocket.replay_limit,
+                websocket.replay_window,
+            ),
+            notifications,
+            spectators: Spectators::new(websocket.send_buffer),
+            bots: Mutex::new(Bots::new(bots, OsRng.next_u64())),
+            anticheat: Arc::new(Pipeline::standard(&anticheat)),
+            presence: Mutex::new(HashMap::new()),
+            tournaments,
+        }
+    }
+
+    pub(crate) fn lobbies(&self) -> MutexGuard<'_, Lobbies> {
+        self.lobbies.lock().expect("lobby lock was poisoned")
+    }
+
+    pub(crate) fn matchmaker(&self) -> MutexGuard<'_, Matchmaker> {
+        self.matchmaker
+            .lock()
+            .expect("matchmaker lock was poisoned")
+    }
+
+    fn matches(&self) -> MutexGuard<'_, LiveMatches> {
+        self.matches.lock().expect("match lock was poisoned")
+    }
+
+    fn chat_limiter(&self) -> MutexGuard<'_, RateLimiter> {
+        self.chat_limiter
+            .lock()
+            .expect("chat rate limiter lock was poisoned")
+    }
+
+    fn bots(&self) -> MutexGuard<'_, Bots> {
+        self.bots.lock().expect("bot lock was poisoned")
+    }
+
+    fn practice(&self) -> MutexGuard<'_, HashMap<UserId, PracticeGame>> {
+        self.practice.lock().expect("practice lock was poisoned")
+    }
+
+    /// Fails if the player is in a match, which they have to see out before doing anything
+    /// else with lobbies or the queue.
+    pub(crate) fn ensure_not_playing(&self, user_id: UserId) -> Result<(), LobbyError> {
+        if let Some(&match_id) = self.matches().by_player.get(&user_id) {
+            return Err(LobbyError::StillPlaying { match_id });
+        }
+        let starting = self
+            .lobbies()
+            .lobby_of(user_id)
+            .is_some_and(|lobby| lobby.status == LobbyStatus::InMatch);
+        if starting {
+            return Err(LobbyError::MatchInProgress);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn hub(&self) -> &Hub {
+        &self.hub
+    }
+
+    /// How many matches are being played right now.
+    pub(crate) fn live_match_count(&self) -> usize {
+        self.matches().by_id.len()
+    }
+
+    pub(crate) fn spectator_count(&self) -> usize {
+        self.spectators.count()
+    }
+
+    pub(crate) fn notifications(&self) -> &Arc<Notifications> {
+        &self.notifications
+    }
+
+    pub(crate) fn battle_royale(&self) -> &BattleRoyaleSettings {
+        &self.battle_royale
+    }
+
+    pub(crate) fn ratings(&self) -> &RatingSettings {
+        &self.ratings
+    }
+
+    pub(crate) fn tournament_settings(&self) -> &TournamentSettings {
+        &self.tournaments
+    }
+
+    pub(crate) fn bot_settings(&self) -> BotSettings {
+        self.bots().settings().clone()
+    }
+
+    /// Records a match that a lobby just started, deals out the boards and lets the players
+    /// know it's on.
+    #[tracing::instrument(name = "Starting a match", skip(self, start), fields(lobby = %start.code))]
+    pub(crate) async fn start_match(&self, start: MatchStart) -> Result<i64, InnerError> {
+        let MatchStart {
+            code,
+            settings,
+            board,
+            players,
+            seed,
+        } = start;
+        let player_ids: Vec<UserId> = players.iter().map(|player| player.id()).collect();
+        let playing = {
+            let matches = self.matches();
+            player_ids
+                .iter()
+                .find_map(|player| matches.by_player.get(player).copied())
+        };
+        if let Some(match_id) = playing {
+            // Nobody can play two matches at once, so the lobby goes back to waiting.
+            let _ = self.lobbies().finish_match(&code);
+            return Err(InnerError::LobbyError {
+                source: LobbyError::StillPlaying { match_id },
+            });
+        }
+        let match_id = {
+            let code = code.clone();
+            let player_ids = player_ids.clone();
+            self.db_handle
+                .transaction(move |transaction| {
+                    transaction.execute(
+                        "INSERT INTO GameMatch
+                             (LobbyCode, Mode, Width, Height, Mines, Topology, Seed, StartedAt)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                        params![
+                            code.as_str(),
+                            settings.mode.as_str(),
+                            board.width(),
+                            board.height(),
+                            board.mines(),
+                            board.topology().as_str(),
+                            seed as i64,
+                            unix_timestamp(),
+                        ],
+                    )?;
+                    let match_id = transaction.last_insert_rowid();
+                    for player in &player_ids {
+                        transaction.execute(
+                            "INSERT INTO GameMatchPlayer (MatchId, UserId) VALUES (?1, ?2)",
+                            params![match_id, player],
+                        )?;
+                    }
+                    Ok(match_id)
+                })
+                .await?
+        };
+
+        let start = Position::new(board.width() / 2, board.height() / 2);
+        let layout = tokio::task::spawn_blocking(move || {
+            match generate_no_guess(board, seed, start, &GeneratorOptions::default()) {
+                Ok(generated) => generated.layout,
+                Err(error) => {
+                    // A board that might need a guess is better than no match at all.
+                    tracing::warn!(%error, "Falling back to a random board");
+                    MineLayout::generate(board, seed, start)
+                        .expect("the start position is on the board")
+                }
+            }
+        })
+        .await
+        .context(JoinSnafu)?;
+
+        let mut match_settings = self.battle_royale.clone();
+        match_settings.power_ups.enabled = settings.power_ups;
+        let started = LogEntry::Started {
+            width: board.width(),
+            height: board.height(),
+            topology: board.topology(),
+            mines: layout.mine_positions(),
+            start,
+            players: player_ids.clone(),
+            settings: match_settings.clone(),
+        };
+        let engine = BattleRoyale::new(layout, start, &player_ids, match_settings)
+            .expect("the start position is on the board");
+        let mut live_match = LiveMatch::new(match_id, code, settings.mode, engine, started);
+        self.save_log(match_id, live_match.take_unsaved()).await?;
+        for &player in &player_ids {
+            self.hub.send(
+                player,
+                ServerEvent::MatchStarted {
+                    match_id,
+                    players: player_ids.clone(),
+                    start,
+                    board: live_match
+                        .engine()
+                        .view(player)
+                        .expect("every player has a board"),
+                },
+            );
+        }
+        if let Some(timer) = live_match.storm_timer() {
+            self.hub.send_all(&player_ids, timer);
+        }
+        let boards = player_ids
+            .iter()
+            .map(|&user_id| PlayerBoard {
+                user_id,
+                board: live_match
+                    .engine()
+                    .view(user_id)
+                    .expect("every player has a board"),
+            })
+            .collect();
+        self.spectators
+            .open(SpectatorFeed::new(match_id, boards, &self.spectating));
+        {
+            let mut matches = self.matches();
+            for &player in &player_ids {
+                matches.by_player.insert(player, match_id);
+            }
+            matches.by_id.insert(match_id, live_match);
+        }
+        self.bots().start_playing(&player_ids, Instant::now());
+        self.refresh_presence(&player_ids).await;
+        Ok(match_id)
+    }
+
+    /// Records the final placements of a match and everyone's new ratings, tells the players
+    /// how it went and puts the lobby back into the waiting state.
+    #[tracing::instrument(name = "Finishing a match", skip(self, live_match), fields(match_id = live_match.id))]
+    async fn finish_match(&self, mut live_match: LiveMatch) -> Result<(), InnerError> {
+        let match_id = live_match.id;
+        let mode = live_match.mode;
+        let unsaved = live_match.take_unsaved();
+        let standings = live_match
+            .result()
+            .expect("only finished matches are finished")
+            .standings
+            .clone();
+        // The lobby may have emptied out during the match, which is fine.
+        let _ = self.lobbies().finish_match(&live_match.code);
+        // Bots don't stick around for a rematch. They leave the lobby before going back to the
+        // idle pool, so another match can't pick one up while it's still in this lobby.
+        let bots: Vec<UserId> = {
+            let pool = self.bots();
+            live_match
+                .players()
+                .iter()
+                .copied()
+                .filter(|&player| pool.is_bot(player))
+                .collect()
+        };
+        {
+            let mut lobbies = self.lobbies();
+            for &bot in &bots {
+                let _ = lobbies.leave(bot);
+            }
+        }
+        self.bots().stop_playing(&bots);
+        let saved = {
+            let standings = standings.clone();
+            let ratings = self.ratings.clone();
+            self.db_handle
+                .transaction(move |transaction| {
+                    insert_records(transaction, match_id, &unsaved)?;
+                    transaction.execute(
+                        "UPDATE GameMatch SET FinishedAt = ?1 WHERE Id = ?2",
+                        params![unix_timestamp(), match_id],
+                    )?;
+                    for standing in &standings {
+                        transaction.execute(
+                            "UPDATE GameMatchPlayer SET Placement = ?1
+                             WHERE MatchId = ?2 AND UserId = ?3",
+                            params![standing.placement, match_id, standing.user_id],
+                        )?;
+                    }
+                    record_match_ratings(transaction, match_id, mode, &standings, &ratings)
+                })
+                .await
+        };
+        // Players hear about it once the result is saved, so anything they ask for about the
+        // match afterwards (like its replay) is already there. They still need to hear about
+        // it if saving failed though.
+        self.hub.send_all(
+            live_match.players(),
+            ServerEvent::MatchFinished {
+                match_id,
+                standings,
+            },
+        );
+        self.refresh_presence(live_match.players()).await;
+        self.matches().finished(match_id);
+        if saved.is_ok() {
+            spawn_match_review(
+                self.db_handle.clone(),
+                self.anticheat.clone(),
+                match_id,
+                bots,
+            );
+            spawn_stats_update(self.db_handle.clone(), self.notifications.clone(), match_id);
+        }
+        saved
+    }
+
+    /// Handles a message a player sent over their web socket.
+    #[tracing::instrument(name = "Handling a client message", skip(self, message))]
+    pub(crate) async fn handle_client_message(
+        &self,
+        user_id: UserId,
+        message: ClientMessage,
+    ) -> Result<(), InnerError> {
+        let action = match message {
+            ClientMessage::Ack { seq } => {
+                self.hub.acknowledge(user_id, seq);
+                return Ok(());
+            }
+            ClientMessage::Chat { text } => return self.chat(user_id, text).await,
+            ClientMessage::StartPractice { level } => {
+                self.start_practice(user_id, level);
+                return Ok(());
+            }
+            ClientMessage::Reveal { position } => Action::Reveal(position),
+            ClientMessage::Flag { position } => Action::Flag(position),
+            ClientMessage::Chord { position } => Action::Chord(position),
+            ClientMessage::UsePowerUp { power_up, target } => {
+                Action::UsePowerUp(PowerUpUse { power_up, target })
+            }
+            ClientMessage::Forfeit => Action::Forfeit,
+        };
+        // Players who aren't in a match might be practicing instead.
+        let played = {
+            let mut matches = self.matches();
+            match matches.by_player.get(&user_id) {
+                Some(&match_id) => {
+                    let live_match = matches
+                        .by_id
+                        .get_mut(&match_id)
+                        .expect("by_player only points at live matches");
+                    match live_match.apply(user_id, action) {
+                        Ok(events) => self.dispatch(live_match, events),
+                        Err(error) => self.send_error(user_id, &error.to_string()),
+                    }
+                    self.spectators
+                        .record(match_id, live_match.take_unwatched(), Instant::now());
+                    if live_match.result().is_some() {
+                        Some((match_id, vec![], Some(matches.remove(match_id))))
+                    } else {
+                        Some((match_id, live_match.take_unsaved(), None))
+                    }
+                }
+                None => None,
+            }
+        };
+        let (match_id, unsaved, finished) = match played {
+            Some(played) => played,
+            None => return self.play_practice(user_id, action).await,
+        };
+        if let Some(live_match) = finished {
+            return self.finish_match(live_match).await;
+        }
+        self.save_log(match_id, unsaved).await
+    }
+
+    /// Deals a player a new practice game, unless they're busy with a match.
+    fn start_practice(&self, user_id: UserId, level: Level) {
+        if self.matches().by_player.contains_key(&user_id) {
+            self.send_error(user_id, &PracticeError::InMatch.to_string());
+            return;
+        }
+        let game = match PracticeGame::new(level, OsRng.next_u64()) {
+            Ok(game) => game,
+            Err(error) => {
+                self.send_error(user_id, &error.to_string());
+                return;
+            }
+        };
+        self.hub.send(
+            user_id,
+            ServerEvent::PracticeStarted {
+                level,
+                board: game.view(),
+            },
+        );
+        self.practice().insert(user_id, game);
+    }
+
+    /// Applies an action to a player's practice game, and records the game once it's over.
+    async fn play_practice(&self, user_id: UserId, action: Action) -> Result<(), InnerError> {
+        let now = Instant::now();
+        let result = {
+            let mut practice = self.practice();
+            let game = match practice.get_mut(&user_id) {
+                Some(game) => game,
+                None => {
+                    self.send_error(user_id, &PracticeError::NotInMatch.to_string());
+                    return Ok(());
+                }
+            };
+            match game.apply(action, now) {
+                Ok(change) => self.hub.send(
+                    user_id,
+                    ServerEvent::BoardDelta {
+                        revealed: change.revealed,
+                        exploded: change.exploded,
+                        flag: change.flag,
+                        status: change.status,
+                    },
+                ),
+                Err(error) => {
+                    self.send_error(user_id, &error.to_string());
+                    return Ok(());
+                }
+            }
+            match game.result() {
+                Some(result) => result,
+                None => return Ok(()),
+            }
+        };
+        let personal_best = self
+            .db_handle
+            .transaction(move |transaction| record_practice_game(transaction, user_id, &result))
+            .await?;
+        self.hub.send(
+            user_id,
+            ServerEvent::PracticeFinished {
+                won: result.won,
+                time_ms: result.time_ms,
+                stats: result.stats,
+                personal_best,
+            },
+        );
+        Ok(())
+    }
+
+    /// Moves every match's clock forward one tick, and finishes the ones that ended because
+    /// of it.
+    #[tracing::instrument(name = "Ticking matches", level = "trace", skip(self))]
+    pub(crate) async fn tick_matches(&self) {
+        let (unsaved, finished): (Vec<_>, Vec<LiveMatch>) = {
+            let mut matches = self.matches();
+            let mut unsaved = vec![];
+            let mut finished_ids = vec![];
+            let now = Instant::now();
+            for (&match_id, live_match) in matches.by_id.iter_mut() {
+                let events = live_match.tick();
+                self.dispatch(live_match, events);
+                if self.reconnects.forfeit == ForfeitRule::Eliminate {
+                    let events = live_match.forfeit_absent(now);
+                    self.dispatch(live_match, events);
+                }
+                self.spectators
+                    .record(match_id, live_match.take_unwatched(), now);
+                if live_match.result().is_some() {
+                    finished_ids.push(match_id);
+                } else {
+                    unsaved.push((match_id, live_match.take_unsaved()));
+                }
+            }
+            let finished = finished_ids
+                .into_iter()
+                .map(|match_id| matches.remove(match_id))
+                .collect();
+            (unsaved, finished)
+        };
+        self.spectators.release(Instant::now());
+        // Each match is saved on its own, so one that can't be doesn't hold up the rest.
+        for (match_id, records) in unsaved {
+            if let Err(error) = self.save_log(match_id, records).await {
+                tracing::error!(?error, match_id, "Saving a match's log failed");
+            }
+        }
+        for live_match in finished {
+            let match_id = live_match.id;
+            if let Err(error) = self.finish_match(live_match).await {
+                tracing::error!(?error, match_id, "Finishing a match failed");
+            }
+        }
+    }
+
+    /// Adds entries to a match's log in the database.
+    async fn save_log(&self, match_id: i64, records: Vec<MatchRecord>) -> Result<(), InnerError> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        self.db_handle
+            .transaction(move |transaction| insert_records(transaction, match_id, &records))
+            .await
+    }
+
+    /// Holds the player's place in their match once their last connection has closed.
+    pub(crate) fn player_disconnected(&self, user_id: UserId) {
+        if self.hub.is_connected(user_id) {
+            return;
+        }
+        let mut matches = self.matches();
+        let live_match = match matches.by_player.get(&user_id).copied() {
+            Some(match_id) => matches
+                .by_id
+                .get_mut(&match_id)
+                .expect("by_player only points at live matches"),
+            None => return,
+        };
+        let events = live_match.player_left(user_id, Instant::now(), &self.reconnects);
+        self.dispatch(live_match, events);
+    }
+
+    /// Catches a player up with their match when they connect. `resumed` is whether the events
+    /// they missed were replayed, and if they weren't the player gets the whole match again.
+    pub(crate) fn player_connected(&self, user_id: UserId, resumed: bool) {
+        let mut matches = self.matches();
+        let live_match = match matches.by_player.get(&user_id).copied() {
+            Some(match_id) => matches
+                .by_id
+                .get_mut(&match_id)
+                .expect("by_player only points at live matches"),
+            None => return,
+        };
+        let events = live_match.player_returned(user_id);
+        self.dispatch(live_match, events);
+        if !resumed {
+            let catch_up = live_match.snapshot(user_id).into_iter();
+            for event in catch_up.chain(live_match.storm_timer()) {
+                self.hub.send(user_id, event);
+            }
+        }
+    }
+
+    /// Sends events from a match to whoever they're meant for.
+    fn dispatch(&self, live_match: &LiveMatch, events: Vec<(Recipients, ServerEvent)>) {
+        for (recipients, event) in events {
+            match recipients {
+                Recipients::Player(player) => self.hub.send(player, event),
+                Recipients::Opponents(player) => self.hub.send_all(
+                    live_match
+                        .players()
+                        .iter()
+                        .filter(|&&other| other != player),
+                    event,
+                ),
+                Recipients::Everyone => self.hub.send_all(live_match.players(), event),
+            }
+        }
+    }
+
+    /// Starts watching a live match, and lets its players know.
+    pub(crate) fn watch_match(
+        &self,
+        match_id: i64,
+        user_id: UserId,
+    ) -> Result<Watching, InnerError> {
+        let (watching, viewers) = self
+            .spectators
+            .watch(match_id, user_id)
+            .context(SpectateSnafu)?;
+        self.tell_players(viewers);
+        Ok(watching)
+    }
+
+    pub(crate) fn stop_watching(&self, match_id: i64, spectator_id: SpectatorId) {
+        if let Some(viewers) = self.spectators.stop_watching(match_id, spectator_id) {
+            self.tell_players(viewers);
+        }
+    }
+
+    /// Switches who a spectator is following. Anything that goes wrong is the spectator's
+    /// doing, so it's sent back to them.
+    pub(crate) fn focus_spectator(
+        &self,
+        match_id: i64,
+        spectator_id: SpectatorId,
+        user_id: UserId,
+    ) {
+        match self.spectators.focus(match_id, spectator_id, user_id) {
+            Ok(viewers) => self.tell_players(viewers),
+            Err(error) => self.send_spectator_error(match_id, spectator_id, &error.to_string()),
+        }
+    }
+
+    pub(crate) fn send_spectator_error(
+        &self,
+        match_id: i64,
+        spectator_id: SpectatorId,
+        message: &str,
+    ) {
+        self.spectators.send(
+            match_id,
+            spectator_id,
+            SpectatorEvent::Error {
+                message: message.to_string(),
+            },
+        );
+    }
+
+    fn tell_players(&self, viewers: Viewers) {
+        for (player, following) in viewers.following {
+            self.hub.send(
+                player,
+                ServerEvent::SpectatorCount {
+                    count: viewers.count,
+                    following,
+                },
+            );
+        }
+    }
+
+    /// Sends a chat message to the player's match, or their lobby between matches, once
+    /// it's passed the chat rules. Anyone who muted or blocked the player (or who the
+    /// player blocked) doesn't get it.
+    async fn chat(&self, user_id: UserId, text: String) -> Result<(), InnerError> {
+        let text = match self
+            .chat
+            .prepare(&text, &self.word_filter)
+            .and_then(|text| {
+                self.chat_limiter()
+                    .check(user_id, Instant::now())
+                    .map(|()| text)
+            }) {
+            Ok(text) => text,
+            Err(error) => {
+                self.send_error(user_id, &error.to_string());
+                return Ok(());
+            }
+        };
+        let room = match self.chat_room_of(user_id) {
+            Some(room) => room,
+            None => {
+                self.send_error(user_id, &ChatError::NoRoom.to_string());
+                return Ok(());
+            }
+        };
+        let message_id = match save_message(&self.db_handle, &room, user_id, &text).await {
+            Ok(message_id) => message_id,
+            Err(InnerError::ChatError { source }) => {
+                self.send_error(user_id, &source.to_string());
+                return Ok(());
+            }
+            Err(error) => return Err(error),
+        };
+        let hidden = hidden_from(&self.db_handle, user_id).await?;
+        let members: Vec<UserId> = self
+            .chat_members(&room)
+            .into_iter()
+            .filter(|member| !hidden.contains(member))
+            .collect();
+        self.hub.send_all(
+            &members,
+            ServerEvent::Chat {
+                message_id,
+                from: user_id,
+                text,
+            },
+        );
+        Ok(())
+    }
+
+    /// Where a player's messages go: their match if they're playing one, otherwise their
+    /// lobby.
+    fn chat_room_of(&self, user_id: UserId) -> Option<ChatRoom> {
+        if let Some(&match_id) = self.matches().by_player.get(&user_id) {
+            return Some(ChatRoom::Match(match_id));
+        }
+        self.lobbies()
+            .lobby_of(user_id)
+            .map(|lobby| ChatRoom::Lobby(lobby.code.clone()))
+    }
+
+    /// Everyone who can see messages sent to `room`.
+    fn chat_members(&self, room: &ChatRoom) -> Vec<UserId> {
+        match room {
+            ChatRoom::Match(match_id) => self
+                .matches()
+                .by_id
+                .get(match_id)
+                .map(|live_match| live_match.players().to_vec())
+                .unwrap_or_default(),
+            ChatRoom::Lobby(code) => self
+                .lobbies()
+                .get(code)
+                .map(|lobby| {
+                    lobby
+                        .members
+                        .iter()
+                        .map(|member| member.user.id())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Lets everyone who could have seen a message know a moderator deleted it.
+    pub(crate) fn announce_deleted_message(&self, room: &ChatRoom, message_id: i64) {
+        let members = self.chat_members(room);
+        self.hub
+            .send_all(&members, ServerEvent::ChatDeleted { message_id });
+    }
+
+    pub(crate) fn forget_quiet_chatters(&self) {
+        self.chat_limiter().prune(Instant::now());
+    }
+
+    pub(crate) fn chat_settings(&self) -> &ChatSettings {
+        &self.chat
+    }
+
+    /// What the player is up to right now.
+    pub(crate) fn presence_of(&self, user_id: UserId) -> Presence {
+        let match_id = self.matches().by_player.get(&user_id).copied();
+        let in_lobby = self.lobbies().lobby_of(user_id).is_some();
+        Presence::new(self.hub.is_connected(user_id), in_lobby, match_id)
+    }
+
+    /// Tells the friends of each of these players about it if what they're up to has
+    /// changed. Friends who aren't connected find out when they next list their friends.
+    pub(crate) async fn refresh_presence(&self, user_ids: &[UserId]) {
+        let changed: Vec<(UserId, Presence)> = {
+            let mut announced = self.presence.lock().expect("presence lock was poisoned");
+            user_ids
+                .iter()
+                .filter_map(|&user_id| {
+                    let presence = self.presence_of(user_id);
+                    let previous = match presence {
+                        Presence::Offline => announced.remove(&user_id),
+                        _ => announced.insert(user_id, presence),
+                    };
+                    (previous.unwrap_or(Presence::Offline) != presence)
+                        .then_some((user_id, presence))
+                })
+                .collect()
+        };
+        for (user_id, presence) in changed {
+            let friends = match friend_ids(&self.db_handle, user_id).await {
+                Ok(friends) => friends,
+                Err(error) => {
+                    tracing::error!(
+                        ?error,
+                        user_id,
+                        "Could not look up who to tell about a presence change"
+                    );
+                    continue;
+                }
+            };
+            for friend in friends {
+                if self.hub.is_connected(friend) {
+                    self.hub
+                        .send(friend, ServerEvent::FriendPresence { user_id, presence });
+                }
+            }
+        }
+    }
+
+    pub(crate) fn send_error(&self, user_id: UserId, message: &str) {
+        self.hub.send(
+            user_id,
+            ServerEvent::Error {
+                message: message.to_string(),
+            },
+        );
+    }
+
+    /// Turns every group the matchmaker can form right now into a started match.
+    #[tracing::instrument(name = "Running matchmaking", level = "debug", skip(self))]
+    pub(crate) async fn run_matchmaking(&self) {
+        let groups = self.matchmaker().find_matches(Instant::now());
+        for group in groups {
+            let settings = self.matchmaker().settings().lobby.clone();
+            let players = group
+                .players
+                .iter()
+                .map(|entry| entry.player.clone())
+                .collect();
+            // Someone in the group might have joined a lobby in the meantime. That only
+            // affects this group, so it shouldn't stop the rest from being started.
+            let start = match self.lobbies().create_matched(players, settings) {
+                Ok(start) => start,
+                Err(error) => {
+                    tracing::warn!(%error, "Could not create a lobby for a matched group");
+                    self.requeue(group.players, None);
+                    continue;
+                }
+            };
+            let code = start.code.clone();
+            if let Err(error) = self.start_match(start).await {
+                tracing::error!(?error, "Could not start a match for a matched group");
+                self.requeue(group.players, Some(&code));
+            }
+        }
+        let fill_after = self.bots().settings().fill_after;
+        let waiting = self.matchmaker().take_waiting(fill_after, Instant::now());
+        for entry in waiting {
+            let size = self.matchmaker().settings().lobby.max_players;
+            if let Err(error) = self.start_bot_match(vec![entry.player.clone()], size).await {
+                tracing::warn!(
+                    ?error,
+                    "Could not start a match with bots for a waiting player"
+                );
+                self.requeue(vec![entry], None);
+            }
+        }
+    }
+
+    /// Puts players whose match couldn't be started back in the queue, taking them out of the
+    /// lobby that was made for it. Anyone who's gone off to a lobby or a match of their own is
+    /// left out.
+    fn requeue(&self, entries: Vec<QueueEntry>, code: Option<&LobbyCode>) {
+        let entries: Vec<QueueEntry> = {
+            let mut lobbies = self.lobbies();
+            entries
+                .into_iter()
+                .filter(|entry| {
+                    let user_id = entry.player.id();
+                    let in_match_lobby = lobbies
+                        .lobby_of(user_id)
+                        .is_some_and(|lobby| Some(&lobby.code) == code);
+                    if in_match_lobby {
+                        let _ = lobbies.leave(user_id);
+                    }
+                    lobbies.lobby_of(user_id).is_none()
+                })
+                .collect()
+        };
+        let entries: Vec<QueueEntry> = {
+            let matches = self.matches();
+            entries
+                .into_iter()
+                .filter(|entry| !matches.by_player.contains_key(&entry.player.id()))
+                .collect()
+        };
+        let mut matchmaker = self.matchmaker();
+        for entry in entries {
+            matchmaker.requeue(entry);
+        }
+    }
+
+    /// Loads the bot accounts from earlier runs, so they can be reused.
+    pub(crate) async fn load_bots(&self) -> Result<(), InnerError> {
+        let accounts = bot_accounts(&self.db_handle).await?;
+        self.bots().add_accounts(accounts);
+        Ok(())
+    }
+
+    /// Starts a match between `players` and enough bots to make `size` players in all, using
+    /// the matchmaking lobby settings.
+    #[tracing::instrument(name = "Starting a match with bots", skip(self, players), fields(players = players.len()))]
+    pub(crate) async fn start_bot_match(
+        &self,
+        mut players: Vec<User>,
+        size: usize,
+    ) -> Result<i64, InnerError> {
+        let needed = size.saturating_sub(players.len());
+        let mut bots = self.bots().take_idle(needed);
+        if bots.len() < needed {
+            let accounts = create_bot_accounts(&self.db_handle, needed - bots.len()).await?;
+            let mut pool = self.bots();
+            pool.add_accounts(accounts);
+            bots.extend(pool.take_idle(needed - bots.len()));
+        }
+        players.extend(bots.iter().cloned());
+        let settings = LobbySettings {
+            max_players: size,
+            ..self.matchmaker().settings().lobby.clone()
+        };
+        let player_ids: Vec<UserId> = players.iter().map(User::id).collect();
+        let start = self.lobbies().create_matched(players, settings);
+        let start = match start {
+            Ok(start) => start,
+            Err(error) => {
+                self.bots().release(&bots);
+                return Err(InnerError::LobbyError { source: error });
+            }
+        };
+        let code = start.code.clone();
+        let started = self.start_match(start).await;
+        if started.is_err() {
+            // Break the lobby up again, so the bots can be used elsewhere and the players
+            // are free to queue or be queued again.
+            {
+                let mut lobbies = self.lobbies();
+                for &player in &player_ids {
+                    if lobbies
+                        .lobby_of(player)
+                        .is_some_and(|lobby| lobby.code == code)
+                    {
+                        let _ = lobbies.leave(player);
+                    }
+                }
+            }
+            self.bots().release(&bots);
+        }
+        started
+    }
+
+    /// Lets every bot that's due to move make its move, through the same path as a
+    /// player's messages.
+    pub(crate) async fn play_bots(&self) {
+        let now = Instant::now();
+        let moves: Vec<(UserId, Action)> = {
+            let matches = self.matches();
+            let mut bots = self.bots();
+            bots.due(now)
+                .into_iter()
+                .filter_map(|bot| {
+                    let board = matches
+                        .by_player
+                        .get(&bot)
+                        .and_then(|match_id| matches.by_id.get(match_id))
+                        .and_then(|live_match| live_match.engine().view(bot))?;
+                    bots.think(bot, &board, now).map(|action| (bot, action))
+                })
+                .collect()
+        };
+        for (bot, action) in moves {
+            let message = match action {
+                Action::Reveal(position) => ClientMessage::Reveal { position },
+                Action::Flag(position) => ClientMessage::Flag { position },
+                Action::Chord(position) => ClientMessage::Chord { position },
+                Action::UsePowerUp(PowerUpUse { power_up, target }) => {
+                    ClientMessage::UsePowerUp { power_up, target }
+                }
+                Action::Forfeit => ClientMessage::Forfeit,
+            };
+            if let Err(error) = self.handle_client_message(bot, message).await {
+                tracing::error!(?error, bot, "A bot's move failed");
+            }
+        }
+        // Nobody's listening to what bots are sent, so there's no point keeping it around.
+        let playing: Vec<UserId> = self.bots().playing().collect();
+        for bot in playing {
+            self.hub.acknowledge(bot, u64::MAX);
+        }
+    }
+}
+
+/// Runs matchmaking every `period` for as long as the server is up, so that waiting
+/// players' rating windows keep widening even when nobody new joins the queue.
+pub(crate) fn spawn_matchmaking(server: web::Data<GameServer>, period: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            server.run_matchmaking().await;
+        }
+    });
+}
+
+/// Drops the game channels of players who've been gone too long to resume, checking once
+/// every replay window.
+pub(crate) fn spawn_hub_pruning(server: web::Data<GameServer>) {
+    let period = server.hub().replay_window();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            server.hub().prune(Instant::now());
+        }
+    });
+}
+
+pub(crate) fn insert_records(
+    transaction: &Transaction<'_>,
+    match_id: i64,
+    records: &[MatchRecord],
+) -> Result<(), rusqlite::Error> {
+    for record in records {
+        transaction.execute(
+            "INSERT INTO GameMatchEvent (MatchId, Seq, Tick, Kind, Payload)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                match_id,
+                record.seq as i64,
+                record.tick as i64,
+                record.entry.kind(),
+                serde_json::to_string(&record.entry).expect("log entries always serialize"),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Drives the clock of every live match, one tick every `tick_length`.
+pub(crate) fn spawn_match_clock(server: web::Data<GameServer>) {
+    let tick_length = server.battle_royale().tick_length;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tick_length);
+        loop {
+            interval.tick().await;
+            server.tick_matches().await;
+            server.play_bots().await;
+        }
+    });
+}
@@ -0,0 +1,244 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::Instant,
+};
+
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use crate::domain::{
+    battle_royale::MatchEvent,
+    protocol::SpectatorEvent,
+    spectate::{SpectateError, SpectatorFeed},
+    UserId,
+};
+
+/// Identifies one spectator connection.
+pub(crate) type SpectatorId = u64;
+
+struct Spectator {
+    focus: UserId,
+    sender: mpsc::Sender<SpectatorEvent>,
+}
+
+/// A match's feed and everyone watching it.
+struct Audience {
+    feed: SpectatorFeed,
+    spectators: HashMap<SpectatorId, Spectator>,
+}
+
+impl Audience {
+    /// Sends an event to one spectator, dropping them if they can't keep up.
+    fn send(&mut self, spectator_id: SpectatorId, event: SpectatorEvent) {
+        let sent = match self.spectators.get(&spectator_id) {
+            Some(spectator) => spectator.sender.try_send(event),
+            None => return,
+        };
+        match sent {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                tracing::warn!(
+                    spectator_id,
+                    "Disconnecting a spectator that isn't keeping up"
+                );
+                self.spectators.remove(&spectator_id);
+            }
+            Err(TrySendError::Closed(_)) => {
+                self.spectators.remove(&spectator_id);
+            }
+        }
+    }
+
+    /// How many spectators there are, and how many are following each player.
+    fn viewers(&self) -> Viewers {
+        Viewers {
+            count: self.spectators.len(),
+            following: self
+                .feed
+                .players()
+                .map(|player| {
+                    let following = self
+                        .spectators
+                        .values()
+                        .filter(|spectator| spectator.focus == player)
+                        .count();
+                    (player, following)
+                })
+                .collect(),
+        }
+    }
+
+    fn send_all(&mut self, event: SpectatorEvent) {
+        let ids: Vec<SpectatorId> = self.spectators.keys().copied().collect();
+        for spectator_id in ids {
+            self.send(spectator_id, event.clone());
+        }
+    }
+}
+
+/// Who's watching a match, for telling its players.
+pub(crate) struct Viewers {
+    pub(crate) count: usize,
+    /// Each player, along with how many spectators are following them.
+    pub(crate) following: Vec<(UserId, usize)>,
+}
+
+/// A newly opened spectator connection.
+pub(crate) struct Watching {
+    pub(crate) spectator_id: SpectatorId,
+    pub(crate) receiver: mpsc::Receiver<SpectatorEvent>,
+}
+
+/// Everyone spectating a live match. Matches are only watchable from here once they've
+/// started, and stop being watchable once spectators have seen them finish.
+pub(crate) struct Spectators {
+    audiences: Mutex<HashMap<i64, Audience>>,
+    next_spectator_id: AtomicU64,
+    send_buffer: usize,
+}
+
+impl Spectators {
+    pub(crate) fn new(send_buffer: usize) -> Self {
+        Self {
+            audiences: Mutex::new(HashMap::new()),
+            next_spectator_id: AtomicU64::new(1),
+            send_buffer,
+        }
+    }
+
+    fn audiences(&self) -> MutexGuard<'_, HashMap<i64, Audience>> {
+        self.audiences.lock().expect("spectator lock was poisoned")
+    }
+
+    /// How many spectators are watching matches right now.
+    pub(crate) fn count(&self) -> usize {
+        self.audiences()
+            .values()
+            .map(|audience| audience.spectators.len())
+            .sum()
+    }
+
+    /// Makes a match watchable.
+    pub(crate) fn open(&self, feed: SpectatorFeed) {
+        self.audiences().insert(
+            feed.match_id(),
+            Audience {
+                feed,
+                spectators: HashMap::new(),
+            },
+        );
+    }
+
+    /// Holds on to events from a match until they're old enough to show.
+    pub(crate) fn record(&self, match_id: i64, events: Vec<MatchEvent>, now: Instant) {
+        if let Some(audience) = self.audiences().get_mut(&match_id) {
+            audience.feed.record(events, now);
+        }
+    }
+
+    /// Sends spectators everything that's been held back long enough. Matches whose end has
+    /// been shown are closed, which disconnects their spectators.
+    pub(crate) fn release(&self, now: Instant) {
+        let mut audiences = self.audiences();
+        for audience in audiences.values_mut() {
+            for event in audience.feed.release(now) {
+                audience.send_all(event);
+            }
+        }
+        audiences.retain(|_, audience| !audience.feed.is_finished());
+    }
+
+    /// Starts watching a match. Returns the connection along with who's watching now, so
+    /// the players can be told about it.
+    pub(crate) fn watch(
+        &self,
+        match_id: i64,
+        user_id: UserId,
+    ) -> Result<(Watching, Viewers), SpectateError> {
+        let mut audiences = self.audiences();
+        let audience = audiences
+            .get_mut(&match_id)
+            .ok_or(SpectateError::MatchNotLive { match_id })?;
+        if audience.feed.players().any(|player| player == user_id) {
+            return Err(SpectateError::OwnMatch);
+        }
+        let spectator_id = self.next_spectator_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel(self.send_buffer);
+        let focus = audience
+            .feed
+            .players()
+            .next()
+            .expect("matches always have players");
+        audience
+            .spectators
+            .insert(spectator_id, Spectator { focus, sender });
+        let count = audience.spectators.len();
+        audience.send(
+            spectator_id,
+            SpectatorEvent::Spectating {
+                match_id,
+                boards: audience.feed.boards().to_vec(),
+                focus,
+                spectators: count,
+                delay_ms: audience.feed.delay().as_millis() as u64,
+            },
+        );
+        audience.send_all(SpectatorEvent::SpectatorCount { count });
+        Ok((
+            Watching {
+                spectator_id,
+                receiver,
+            },
+            audience.viewers(),
+        ))
+    }
+
+    /// Stops watching a match. Returns who's still watching, unless the match can't be
+    /// watched any more.
+    pub(crate) fn stop_watching(
+        &self,
+        match_id: i64,
+        spectator_id: SpectatorId,
+    ) -> Option<Viewers> {
+        let mut audiences = self.audiences();
+        let audience = audiences.get_mut(&match_id)?;
+        audience.spectators.remove(&spectator_id)?;
+        let count = audience.spectators.len();
+        audience.send_all(SpectatorEvent::SpectatorCount { count });
+        Some(audience.viewers())
+    }
+
+    /// Switches who a spectator is following, and sends them that player's board. Returns
+    /// who's following whom now.
+    pub(crate) fn focus(
+        &self,
+        match_id: i64,
+        spectator_id: SpectatorId,
+        user_id: UserId,
+    ) -> Result<Viewers, SpectateError> {
+        let mut audiences = self.audiences();
+        let audience = audiences
+            .get_mut(&match_id)
+            .ok_or(SpectateError::MatchNotLive { match_id })?;
+        let board = audience
+            .feed
+            .board(user_id)
+            .ok_or(SpectateError::NotInMatch { user_id })?
+            .clone();
+        if let Some(spectator) = audience.spectators.get_mut(&spectator_id) {
+            spectator.focus = user_id;
+        }
+        audience.send(spectator_id, SpectatorEvent::Focused { user_id, board });
+        Ok(audience.viewers())
+    }
+
+    /// Sends a spectator an event outside of the match's feed.
+    pub(crate) fn send(&self, match_id: i64, spectator_id: SpectatorId, event: SpectatorEvent) {
+        if let Some(audience) = self.audiences().get_mut(&match_id) {
+            audience.send(spectator_id, event);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::time::Duration;
+
+use actix_web::web;
+use snafu::ResultExt;
+
+use super::GameServer;
+use crate::{
+    domain::{
+        errors::*,
+        lobby::{LobbySettings, Visibility, MIN_PLAYERS},
+        notifications::NotificationKind,
+        protocol::ServerEvent,
+        tournament::{DueHeat, HeatState, Tournament, TournamentFormat},
+        UserId,
+    },
+    session::unix_timestamp_millis,
+    tournaments::{
+        active_tournaments, heat_match, seeding_ratings, tournament, update_tournament, HeatMatch,
+    },
+};
+
+impl GameServer {
+    /// Moves every tournament that isn't over along: closing registration, recording the
+    /// results of heats and starting the heats that are due.
+    #[tracing::instrument(name = "Running tournaments", level = "debug", skip(self))]
+    pub(crate) async fn run_tournaments(&self) -> Result<(), InnerError> {
+        for tournament_id in active_tournaments(&self.db_handle).await? {
+            // One tournament going wrong shouldn't hold up the others.
+            if let Err(error) = self.run_tournament(tournament_id).await {
+                tracing::error!(?error, tournament_id, "Running a tournament failed");
+            }
+        }
+        Ok(())
+    }
+
+    async fn run_tournament(&self, tournament_id: i64) -> Result<(), InnerError> {
+        let round_break = self.tournaments.round_break.as_millis() as i64;
+        let before = tournament(&self.db_handle, tournament_id).await?;
+        let mut current = before.clone();
+
+        if current.registration_due(unix_timestamp_millis()) {
+            let players = current
+                .entrants()
+                .iter()
+                .map(|entrant| entrant.user.id())
+                .collect();
+            let mode = current.format().lobby.mode;
+            let ratings = seeding_ratings(&self.db_handle, players, mode, &self.ratings).await?;
+            current = update_tournament(&self.db_handle, tournament_id, move |tournament| {
+                tournament.close_registration(&ratings, unix_timestamp_millis(), round_break);
+                Ok(())
+            })
+            .await?
+            .1;
+        }
+
+        for match_id in current.playing_matches() {
+            if self.matches().is_live(match_id) {
+                continue;
+            }
+            match heat_match(&self.db_handle, match_id).await? {
+                HeatMatch::Finished(placements) => {
+                    current =
+                        update_tournament(&self.db_handle, tournament_id, move |tournament| {
+                            let now = unix_timestamp_millis();
+                            tournament.heat_finished(match_id, &placements, now, round_break);
+                            Ok(())
+                        })
+                        .await?
+                        .1;
+                }
+                HeatMatch::Lost => {
+                    tracing::warn!(
+                        tournament_id,
+                        match_id,
+                        "Replaying a heat whose match was lost"
+                    );
+                    current =
+                        update_tournament(&self.db_handle, tournament_id, move |tournament| {
+                            tournament.heat_abandoned(
+                                match_id,
+                                unix_timestamp_millis(),
+                                round_break,
+                            );
+                            Ok(())
+                        })
+                        .await?
+                        .1;
+                }
+            }
+        }
+
+        for due in current.due_heats(unix_timestamp_millis()) {
+            let (present, match_id) = match self.start_heat(current.format(), &due).await {
+                Ok(started) => started,
+                Err(error) => {
+                    // It's still due, so it'll be tried again next time.
+                    tracing::error!(?error, tournament_id, "Starting a tournament heat failed");
+                    continue;
+                }
+            };
+            current = update_tournament(&self.db_handle, tournament_id, move |tournament| {
+                let now = unix_timestamp_millis();
+                tournament.heat_started(due.round, due.heat, &present, match_id, now, round_break);
+                Ok(())
+            })
+            .await?
+            .1;
+        }
+
+        self.announce_tournament(&before, &current);
+        Ok(())
+    }
+
+    /// Starts a heat's match between the players who've turned up for it: the ones who are
+    /// connected and not already playing. They're taken out of any lobby or queue they're in
+    /// first. Returns who turned up, and the match if there were enough of them for one.
+    async fn start_heat(
+        &self,
+        format: &TournamentFormat,
+        due: &DueHeat,
+    ) -> Result<(Vec<UserId>, Option<i64>), InnerError> {
+        let present: Vec<_> = {
+            let matches = self.matches();
+            due.players
+                .iter()
+                .filter(|player| {
+                    self.hub.is_connected(player.id())
+                        && !matches.by_player.contains_key(&player.id())
+                })
+                .cloned()
+                .collect()
+        };
+        let present_ids: Vec<UserId> = present.iter().map(|player| player.id()).collect();
+        if present.len() < MIN_PLAYERS {
+            return Ok((present_ids, None));
+        }
+        for &player in &present_ids {
+            self.matchmaker().dequeue(player);
+            // Most players won't be in a lobby.
+            let _ = self.lobbies().leave(player);
+        }
+        let settings = LobbySettings {
+            visibility: Visibility::Private,
+            ..format.lobby.clone()
+        };
+        let start = self
+            .lobbies()
+            .create_matched(present, settings)
+            .context(LobbySnafu)?;
+        let match_id = self.start_match(start).await?;
+        Ok((present_ids, Some(match_id)))
+    }
+
+    /// Tells players about anything that happened to a tournament between `before` and
+    /// `after`: the heats they've been drawn in, and the tournament being over.
+    fn announce_tournament(&self, before: &Tournament, after: &Tournament) {
+        for (round, drawn) in after
+            .rounds()
+            .iter()
+            .enumerate()
+            .skip(before.rounds().len())
+        {
+            for (heat, drawn_heat) in drawn.heats.iter().enumerate() {
+                let starts_at = match drawn_heat.state {
+                    HeatState::Waiting { starts_at } => starts_at,
+                    _ => continue,
+                };
+                let players: Vec<_> = drawn_heat
+                    .players
+                    .iter()
+                    .map(|player| player.user.clone())
+                    .collect();
+                let player_ids: Vec<UserId> = players.iter().map(|player| player.id()).collect();
+                self.notifications.notify(
+                    player_ids.clone(),
+                    NotificationKind::TournamentHeat {
+                        tournament_id: after.id(),
+                        round: round + 1,
+                        heat: heat + 1,
+                        starts_at,
+                    },
+                );
+                self.hub.send_all(
+                    &player_ids,
+                    ServerEvent::TournamentHeat {
+                        tournament_id: after.id(),
+                        round: round + 1,
+                        heat: heat + 1,
+                        starts_at,
+                        players,
+                    },
+                );
+            }
+        }
+        if !before.status().is_over() && after.status().is_over() {
+            let entrants: Vec<UserId> = after
+                .entrants()
+                .iter()
+                .map(|entrant| entrant.user.id())
+                .collect();
+            self.hub.send_all(
+                &entrants,
+                ServerEvent::TournamentOver {
+                    tournament_id: after.id(),
+                    status: after.status(),
+                    winner: after.winner().cloned(),
+                },
+            );
+        }
+    }
+}
+
+/// Runs tournaments every `period` for as long as the server is up.
+pub(crate) fn spawn_tournaments(server: web::Data<GameServer>, period: Duration) {
+    tokio::spawn(async move {
+        // The database is busy with everything else starting up at first.
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        loop {
+            interval.tick().await;
+            if let Err(error) = server.run_tournaments().await {
+                tracing::error!(?error, "Running tournaments failed");
+            }
+        }
+    });
+}
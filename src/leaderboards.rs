@@ -0,0 +1,347 @@
+//! Leaderboard snapshots and seasons. The ranking itself lives in
+//! [`crate::domain::leaderboard`].
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use actix_web::web;
+use deadpool_sqlite::rusqlite::{params, Connection, Error, OptionalExtension, Row};
+use serde::Serialize;
+use snafu::ResultExt;
+
+use crate::{
+    db_handle::DbHandle,
+    domain::{
+        errors::*,
+        leaderboard::{
+            Board, Cursor, Leaderboard, LeaderboardEntry, LeaderboardError, LeaderboardPage,
+            LeaderboardSettings, PlayerRating,
+        },
+        rating::{Rating, RatingSettings},
+    },
+    session::unix_timestamp,
+};
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct Season {
+    pub(crate) id: i64,
+    pub(crate) started_at: i64,
+    pub(crate) ends_at: i64,
+    pub(crate) ended_at: Option<i64>,
+}
+
+/// The latest snapshot of every leaderboard. Reading a leaderboard never touches the
+/// database, only [`Leaderboards::refresh`] does.
+pub(crate) struct Leaderboards {
+    db_handle: DbHandle,
+    settings: LeaderboardSettings,
+    ratings: RatingSettings,
+    snapshots: RwLock<HashMap<Board, Arc<Leaderboard>>>,
+}
+
+impl Leaderboards {
+    pub(crate) fn new(
+        db_handle: DbHandle,
+        settings: LeaderboardSettings,
+        ratings: RatingSettings,
+    ) -> Self {
+        Self {
+            db_handle,
+            settings,
+            ratings,
+            snapshots: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn settings(&self) -> &LeaderboardSettings {
+        &self.settings
+    }
+
+    /// The latest snapshot of `board`. Before the first refresh, this is an empty board.
+    pub(crate) fn snapshot(&self, board: Board) -> Arc<Leaderboard> {
+        self.snapshots
+            .read()
+            .expect("leaderboard lock was poisoned")
+            .get(&board)
+            .cloned()
+            .unwrap_or_else(|| Arc::new(Leaderboard::new(board, 0, 0, vec![])))
+    }
+
+    /// Ends the current season if it's due to, and takes a new snapshot of every leaderboard.
+    #[tracing::instrument(name = "Refreshing the leaderboards", level = "debug", skip(self))]
+    pub(crate) async fn refresh(&self) -> Result<(), InnerError> {
+        let settings = self.settings.clone();
+        let ratings = self.ratings.clone();
+        let snapshots = self
+            .db_handle
+            .transaction(move |transaction| {
+                let now = unix_timestamp();
+                let season = current_season(transaction, &settings, &ratings, now)?;
+                Board::all()
+                    .into_iter()
+                    .map(|board| {
+                        let players = load_players(transaction, board, settings.min_matches)?;
+                        Ok((board, Leaderboard::new(board, season.id, now, players)))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
+            })
+            .await?;
+        let mut current = self
+            .snapshots
+            .write()
+            .expect("leaderboard lock was poisoned");
+        for (board, snapshot) in snapshots {
+            current.insert(board, Arc::new(snapshot));
+        }
+        Ok(())
+    }
+}
+
+/// Refreshes the leaderboards every `refresh_interval` for as long as the server is up.
+pub(crate) fn spawn_leaderboard_refresh(leaderboards: web::Data<Leaderboards>) {
+    let period = leaderboards.settings().refresh_interval;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            if let Err(error) = leaderboards.refresh().await {
+                tracing::error!(?error, "Refreshing the leaderboards failed");
+            }
+        }
+    });
+}
+
+/// The season being played, starting the first one or moving on to the next one if needed.
+fn current_season(
+    connection: &Connection,
+    settings: &LeaderboardSettings,
+    ratings: &RatingSettings,
+    now: i64,
+) -> Result<Season, Error> {
+    let season = connection
+        .query_row(
+            "SELECT Id, StartedAt, EndsAt, EndedAt FROM Season WHERE EndedAt IS NULL",
+            [],
+            season_from_row,
+        )
+        .optional()?;
+    match season {
+        Some(season) if season.ends_at > now => Ok(season),
+        Some(season) => {
+            end_season(connection, &season, settings, ratings, now)?;
+            start_season(connection, settings, now)
+        }
+        None => start_season(connection, settings, now),
+    }
+}
+
+fn start_season(
+    connection: &Connection,
+    settings: &LeaderboardSettings,
+    started_at: i64,
+) -> Result<Season, Error> {
+    let ends_at = started_at + settings.season_length.as_secs() as i64;
+    connection.execute(
+        "INSERT INTO Season (StartedAt, EndsAt) VALUES (?1, ?2)",
+        params![started_at, ends_at],
+    )?;
+    Ok(Season {
+        id: connection.last_insert_rowid(),
+        started_at,
+        ends_at,
+        ended_at: None,
+    })
+}
+
+/// Archives a season's final standings, and gives everyone their rating for the next one.
+#[tracing::instrument(name = "Ending a season", skip(connection, settings, ratings))]
+fn end_season(
+    connection: &Connection,
+    season: &Season,
+    settings: &LeaderboardSettings,
+    ratings: &RatingSettings,
+    now: i64,
+) -> Result<(), Error> {
+    for board in Board::all() {
+        let players = load_players(connection, board, settings.min_matches)?;
+        let standings = Leaderboard::new(board, season.id, now, players);
+        for entry in standings.entries() {
+            connection.execute(
+                "INSERT INTO SeasonStanding
+                     (SeasonId, Board, UserId, Rank, Rating, Deviation, MatchesPlayed)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    season.id,
+                    board.name(),
+                    entry.player.user_id,
+                    entry.rank,
+                    entry.player.rating,
+                    entry.player.deviation,
+                    entry.player.matches_played,
+                ],
+            )?;
+        }
+    }
+    let current = connection
+        .prepare("SELECT UserId, Mode, Rating, Deviation, Volatility FROM Rating")?
+        .query_map([], |row| {
+            let rating = Rating {
+                rating: row.get(2)?,
+                deviation: row.get(3)?,
+                volatility: row.get(4)?,
+            };
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, rating))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (user_id, mode, rating) in current {
+        let reset = rating.for_new_season(ratings);
+        connection.execute(
+            "UPDATE Rating SET Rating = ?1, Deviation = ?2, SeasonMatchesPlayed = 0
+             WHERE UserId = ?3 AND Mode = ?4",
+            params![reset.rating, reset.deviation, user_id, mode],
+        )?;
+    }
+    connection.execute(
+        "UPDATE Season SET EndedAt = ?1 WHERE Id = ?2",
+        params![now, season.id],
+    )?;
+    Ok(())
+}
+
+/// Everyone who belongs on `board`. For the global board, that's each player's best rating
+/// in any mode.
+fn load_players(
+    connection: &Connection,
+    board: Board,
+    min_matches: u32,
+) -> Result<Vec<PlayerRating>, Error> {
+    let player_from_row = |row: &Row<'_>| {
+        Ok(PlayerRating {
+            user_id: row.get(0)?,
+            username: row.get(1)?,
+            rating: row.get(2)?,
+            deviation: row.get(3)?,
+            matches_played: row.get(4)?,
+        })
+    };
+    match board {
+        // With a single MAX(), SQLite takes the other columns from the row with the maximum.
+        Board::Global => connection
+            .prepare(
+                "SELECT Rating.UserId, User.Username, MAX(Rating.Rating), Rating.Deviation,
+                     SUM(Rating.SeasonMatchesPlayed)
+                 FROM Rating JOIN User ON User.Id = Rating.UserId
+                 WHERE Rating.SeasonMatchesPlayed >= ?1
+                 GROUP BY Rating.UserId",
+            )?
+            .query_map(params![min_matches], player_from_row)?
+            .collect(),
+        Board::Mode(mode) => connection
+            .prepare(
+                "SELECT Rating.UserId, User.Username, Rating.Rating, Rating.Deviation,
+                     Rating.SeasonMatchesPlayed
+                 FROM Rating JOIN User ON User.Id = Rating.UserId
+                 WHERE Rating.Mode = ?1 AND Rating.SeasonMatchesPlayed >= ?2",
+            )?
+            .query_map(params![mode.as_str(), min_matches], player_from_row)?
+            .collect(),
+    }
+}
+
+fn season_from_row(row: &Row<'_>) -> Result<Season, Error> {
+    Ok(Season {
+        id: row.get(0)?,
+        started_at: row.get(1)?,
+        ends_at: row.get(2)?,
+        ended_at: row.get(3)?,
+    })
+}
+
+/// Every season so far, the latest first.
+pub(crate) async fn seasons(db_handle: &DbHandle) -> Result<Vec<Season>, InnerError> {
+    db_handle
+        .transaction(|transaction| {
+            transaction
+                .prepare("SELECT Id, StartedAt, EndsAt, EndedAt FROM Season ORDER BY Id DESC")?
+                .query_map([], season_from_row)?
+                .collect()
+        })
+        .await
+}
+
+/// A page of a finished season's final standings, read straight from the archive.
+pub(crate) async fn archived_page(
+    db_handle: &DbHandle,
+    season_id: i64,
+    board: Board,
+    after: Option<Cursor>,
+    limit: usize,
+) -> Result<LeaderboardPage, InnerError> {
+    let ended_at = db_handle
+        .query_row(
+            "SELECT EndedAt FROM Season WHERE Id = ?1",
+            [season_id],
+            |row| row.get::<_, Option<i64>>(0),
+        )
+        .await?;
+    let taken_at = match ended_at {
+        Some(Some(ended_at)) => ended_at,
+        Some(None) => {
+            Err(LeaderboardError::SeasonNotOver { season_id }).context(LeaderboardSnafu)?
+        }
+        None => Err(LeaderboardError::SeasonNotFound { season_id }).context(LeaderboardSnafu)?,
+    };
+    // One more than asked for, to find out whether there's a next page.
+    let mut entries = db_handle
+        .transaction(move |transaction| {
+            transaction
+                .prepare(
+                    "SELECT SeasonStanding.Rank, SeasonStanding.UserId, User.Username,
+                         SeasonStanding.Rating, SeasonStanding.Deviation,
+                         SeasonStanding.MatchesPlayed
+                     FROM SeasonStanding JOIN User ON User.Id = SeasonStanding.UserId
+                     WHERE SeasonStanding.SeasonId = ?1 AND SeasonStanding.Board = ?2
+                         AND (?3 IS NULL OR SeasonStanding.Rating < ?3
+                             OR (SeasonStanding.Rating = ?3 AND SeasonStanding.UserId > ?4))
+                     ORDER BY SeasonStanding.Rating DESC, SeasonStanding.UserId
+                     LIMIT ?5",
+                )?
+                .query_map(
+                    params![
+                        season_id,
+                        board.name(),
+                        after.map(|cursor| cursor.rating),
+                        after.map(|cursor| cursor.user_id),
+                        limit + 1,
+                    ],
+                    |row| {
+                        Ok(LeaderboardEntry {
+                            rank: row.get(0)?,
+                            player: PlayerRating {
+                                user_id: row.get(1)?,
+                                username: row.get(2)?,
+                                rating: row.get(3)?,
+                                deviation: row.get(4)?,
+                                matches_played: row.get(5)?,
+                            },
+                        })
+                    },
+                )?
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .await?;
+    let has_more = entries.len() > limit;
+    entries.truncate(limit);
+    Ok(LeaderboardPage {
+        board: board.name().to_string(),
+        season_id,
+        taken_at,
+        next: entries
+            .last()
+            .filter(|_| has_more)
+            .map(|last| Cursor::after(last).to_string()),
+        entries,
+    })
+}
@@ -1,17 +1,50 @@
+mod anticheat;
+mod bots;
+mod chat;
 pub mod config;
+mod daily;
 mod db_handle;
 pub mod domain;
+mod friends;
+mod game_server;
+mod leaderboards;
+mod matches;
+mod metrics;
+mod notifications;
+mod practice;
+mod ratings;
 mod routes;
+mod session;
+mod stats;
 pub mod telemetry;
+mod tournaments;
 
-use std::path::PathBuf;
+use std::{
+    net::TcpListener,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use actix_web::{dev::Server, web, App, HttpServer};
-use config::ApplicationConfiguration;
+use actix_web::{
+    dev::{Server, Service},
+    web, App, HttpServer,
+};
+use chat::spawn_chat_cleanup;
+use config::{AdminSettings, ApplicationConfiguration};
+use daily::spawn_daily_rollover;
 use db_handle::DbHandle;
+use game_server::{
+    spawn_hub_pruning, spawn_match_clock, spawn_matchmaking, spawn_tournaments, GameServer,
+    GameServerSettings,
+};
 use handlebars::Handlebars;
-use routes::{health_check, login, login_page, sign_up, sign_up_page};
+use leaderboards::{spawn_leaderboard_refresh, Leaderboards};
+use metrics::Metrics;
+use notifications::Notifications;
+use routes::*;
 use snafu::{prelude::*, Whatever};
+use telemetry::LogFilter;
 use tracing_actix_web::TracingLogger;
 
 mod embedded {
@@ -22,24 +55,252 @@ mod embedded {
 pub async fn run<Path: Into<PathBuf>>(
     app_config: ApplicationConfiguration<Path>,
 ) -> Result<Server, Whatever> {
-    let ApplicationConfiguration { listener, db_path } = app_config;
-    let db_handle = DbHandle::from_path(db_path).await?;
+    let ApplicationConfiguration {
+        listener,
+        db_path,
+        matchmaking,
+        battle_royale,
+        reconnects,
+        ratings,
+        leaderboards,
+        daily,
+        spectators,
+        chat,
+        bots,
+        anticheat,
+        tournaments,
+        websocket,
+        events,
+        proxies,
+        admin,
+        log_filter,
+    } = app_config;
+    let AdminSettings {
+        token: admin_token,
+        listener: admin_listener,
+    } = admin;
+    let metrics = web::Data::new(Metrics::default());
+    let db_handle = DbHandle::from_path(db_path, metrics.clone().into_inner()).await?;
+    let notifications = Arc::new(Notifications::new(db_handle.clone(), events.send_buffer));
+    let game_server = web::Data::new(GameServer::new(
+        db_handle.clone(),
+        GameServerSettings {
+            matchmaking,
+            battle_royale,
+            reconnects,
+            ratings: ratings.clone(),
+            spectating: spectators,
+            chat,
+            bots,
+            anticheat,
+            tournaments,
+        },
+        &websocket,
+        notifications,
+    ));
+    game_server
+        .load_bots()
+        .await
+        .with_whatever_context(|error| format!("Could not load the bots: {:?}", error))?;
+    spawn_matchmaking(game_server.clone(), Duration::from_secs(1));
+    spawn_match_clock(game_server.clone());
+    spawn_hub_pruning(game_server.clone());
+    spawn_chat_cleanup(db_handle.clone(), game_server.clone());
+    let tournament_interval = game_server.tournament_settings().check_interval;
+    spawn_tournaments(game_server.clone(), tournament_interval);
+    let leaderboards = web::Data::new(Leaderboards::new(db_handle.clone(), leaderboards, ratings));
+    leaderboards
+        .refresh()
+        .await
+        .with_whatever_context(|error| format!("Could not load the leaderboards: {:?}", error))?;
+    spawn_leaderboard_refresh(leaderboards.clone());
+    let daily = web::Data::new(daily);
+    let proxies = web::Data::new(proxies);
+    spawn_daily_rollover(db_handle.clone(), daily.clone());
     let mut handlebars = Handlebars::new();
     handlebars
         .register_templates_directory(".html", "./static")
         .with_whatever_context(|e| format!("Could not register templates folder: {:?}", e))?;
+    let admin_token = web::Data::new(AdminToken(admin_token));
+    let log_filter = web::Data::new(log_filter);
+    // With a listener of their own, the operator endpoints are only served there.
+    let admin_apart = admin_listener.is_some();
+    if let Some(admin_listener) = admin_listener {
+        spawn_admin_server(
+            admin_listener,
+            metrics.clone(),
+            admin_token.clone(),
+            log_filter.clone(),
+            db_handle.clone(),
+            game_server.clone(),
+        )?;
+    }
     Ok(HttpServer::new(move || {
+        let request_metrics = metrics.clone();
         App::new()
             .wrap(TracingLogger::default())
+            .wrap_fn(move |request, service| {
+                let started = Instant::now();
+                let metrics = request_metrics.clone();
+                let response = service.call(request);
+                async move {
+                    let response = response.await?;
+                    metrics.record_response(&response, started.elapsed());
+                    Ok(response)
+                }
+            })
             .service(health_check)
             .service(sign_up)
             .service(login)
             .service(login_page)
             .service(sign_up_page)
+            .service(list_lobbies)
+            .service(create_lobby)
+            // has to come before `get_lobby`, otherwise "current" is treated as a lobby code
+            .service(current_lobby)
+            .service(get_lobby)
+            .service(join_lobby)
+            .service(leave_lobby)
+            .service(set_ready)
+            .service(update_lobby_settings)
+            .service(kick_player)
+            .service(transfer_host)
+            .service(start_lobby)
+            .service(join_queue)
+            .service(queue_status_route)
+            .service(leave_queue)
+            .service(download_replay)
+            .service(view_replay)
+            .service(user_ratings)
+            .service(user_rating_history)
+            .service(user_personal_bests)
+            .service(user_stats)
+            .service(user_profile)
+            .service(leaderboard_around_me)
+            .service(view_leaderboard)
+            .service(get_leaderboard)
+            .service(list_seasons)
+            .service(season_leaderboard)
+            .service(todays_challenge)
+            .service(start_daily_attempt)
+            .service(get_daily_attempt)
+            .service(play_daily_move)
+            .service(daily_history)
+            .service(get_daily_leaderboard)
+            .service(web_socket)
+            .service(spectate)
+            .service(chat_ignores)
+            .service(mute_player)
+            .service(unmute_player)
+            .service(block_player)
+            .service(unblock_player)
+            .service(report_chat_message)
+            .service(delete_chat_message)
+            .service(moderation_mute)
+            .service(moderation_unmute)
+            .service(moderation_reports)
+            .service(list_friends)
+            .service(send_friend_request)
+            .service(accept_friend_request)
+            .service(decline_friend_request)
+            .service(remove_friend)
+            .service(invite_friend)
+            .service(start_bot_matches)
+            .service(cheat_flags)
+            .service(review_cheat_flag)
+            .service(list_tournaments)
+            .service(create_tournament)
+            .service(get_tournament)
+            .service(tournament_bracket)
+            .service(register_for_tournament)
+            .service(withdraw_from_tournament)
+            .service(event_stream)
+            .service(list_notifications)
+            .service(mark_all_notifications_read)
+            .service(mark_notification_read)
+            .configure(|config| {
+                if !admin_apart {
+                    config
+                        .service(scrape_metrics)
+                        .service(current_log_filter)
+                        .service(set_log_filter);
+                }
+            })
             .app_data(web::Data::new(db_handle.clone()))
+            .app_data(game_server.clone())
+            .app_data(leaderboards.clone())
+            .app_data(daily.clone())
+            .app_data(web::Data::new(websocket.clone()))
+            .app_data(web::Data::new(events.clone()))
+            .app_data(proxies.clone())
+            .app_data(metrics.clone())
+            .app_data(admin_token.clone())
+            .app_data(log_filter.clone())
             .app_data(web::Data::new(handlebars.clone()))
     })
     .listen(listener)
     .with_whatever_context(|error| format!("Encountered error running `listen`: {:?}", error))?
     .run())
 }
+
+/// Serves the operator endpoints on their own listener, for as long as the process is running.
+fn spawn_admin_server(
+    listener: TcpListener,
+    metrics: web::Data<Metrics>,
+    token: web::Data<AdminToken>,
+    log_filter: web::Data<LogFilter>,
+    db_handle: DbHandle,
+    game_server: web::Data<GameServer>,
+) -> Result<(), Whatever> {
+    let server = HttpServer::new(move || {
+        App::new()
+            .service(scrape_metrics)
+            .service(current_log_filter)
+            .service(set_log_filter)
+            .app_data(metrics.clone())
+            .app_data(token.clone())
+            .app_data(log_filter.clone())
+            .app_data(web::Data::new(db_handle.clone()))
+            .app_data(game_server.clone())
+    })
+    .workers(1)
+    .listen(listener)
+    .with_whatever_context(|error| {
+        format!(
+            "Encountered error running `listen` for the admin endpoints: {:?}",
+            error
+        )
+    })?
+    .run();
+    tokio::spawn(async move {
+        if let Err(error) = server.await {
+            tracing::error!(?error, "The admin server stopped");
+        }
+    });
+    Ok(())
+}
+
+/// Lets the player called `username` moderate chat, bot matches, anti-cheat flags and
+/// tournaments, using the database at `db_path`.
+pub async fn grant_moderator<Path: Into<PathBuf>>(
+    db_path: Path,
+    username: &str,
+) -> Result<(), Whatever> {
+    let db_handle = DbHandle::from_path(db_path, Arc::new(Metrics::default())).await?;
+    let found = chat::grant_moderator(&db_handle, username)
+        .await
+        .with_whatever_context(|error| format!("Could not grant moderator: {:?}", error))?;
+    if !found {
+        whatever!("There's no player called {:?}", username);
+    }
+    Ok(())
+}
+
+/// Works every player's stats and achievements out again from the matches in the database
+/// at `db_path`. Returns how many matches were counted.
+pub async fn backfill_stats<Path: Into<PathBuf>>(db_path: Path) -> Result<usize, Whatever> {
+    let db_handle = DbHandle::from_path(db_path, Arc::new(Metrics::default())).await?;
+    stats::backfill_stats(&db_handle)
+        .await
+        .with_whatever_context(|error| format!("Could not backfill the stats: {:?}", error))
+}
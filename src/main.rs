@@ -1,20 +1,78 @@
 use snafu::{prelude::*, Whatever};
 use std::net::TcpListener;
 use testcontainers_test::{
-    config::ApplicationConfiguration,
-    run,
-    telemetry::{get_subscriber, init_subscriber},
+    backfill_stats,
+    config::{
+        AdminSettings, ApplicationConfiguration, EventStreamSettings, ProxySettings,
+        TelemetrySettings, WebSocketSettings,
+    },
+    domain::{
+        anticheat::AntiCheatSettings,
+        battle_royale::{BattleRoyaleSettings, ReconnectSettings},
+        bot::BotSettings,
+        chat::ChatSettings,
+        daily::DailySettings,
+        leaderboard::LeaderboardSettings,
+        lobby::MatchmakingSettings,
+        rating::RatingSettings,
+        spectate::SpectatorSettings,
+        tournament::TournamentSettings,
+    },
+    grant_moderator, run,
+    telemetry::{get_subscriber, init_subscriber, shutdown_tracing},
 };
 
+const DB_PATH: &str = "app.sqlite3";
+
 #[tokio::main]
 async fn main() -> Result<(), Whatever> {
-    let subscriber = get_subscriber("app", "info", std::io::stdout);
+    let (subscriber, log_filter, log_guard) = get_subscriber(
+        "app",
+        "info",
+        std::io::stdout,
+        &TelemetrySettings::from_env()?,
+    )?;
     init_subscriber(subscriber)?;
+    // `backfill-stats` works everyone's stats out again from their old matches instead of
+    // starting the server.
+    if std::env::args().nth(1).as_deref() == Some("backfill-stats") {
+        let counted = backfill_stats(DB_PATH).await?;
+        tracing::info!(counted, "Backfilled stats");
+        shutdown_tracing(log_guard).await;
+        return Ok(());
+    }
+    // `grant-moderator <username>` lets that player moderate.
+    if std::env::args().nth(1).as_deref() == Some("grant-moderator") {
+        let username = match std::env::args().nth(2) {
+            Some(username) => username,
+            None => whatever!("Usage: grant-moderator <username>"),
+        };
+        grant_moderator(DB_PATH, &username).await?;
+        tracing::info!(%username, "Granted moderator");
+        shutdown_tracing(log_guard).await;
+        return Ok(());
+    }
     let listener = TcpListener::bind(("127.0.0.1", 8080))
         .with_whatever_context(|error| format!("Failed to create TCP Listener: {:?}", error))?;
     run(ApplicationConfiguration {
         listener,
-        db_path: "app.sqlite3",
+        db_path: DB_PATH,
+        matchmaking: MatchmakingSettings::default(),
+        battle_royale: BattleRoyaleSettings::default(),
+        reconnects: ReconnectSettings::default(),
+        ratings: RatingSettings::default(),
+        leaderboards: LeaderboardSettings::default(),
+        daily: DailySettings::default(),
+        spectators: SpectatorSettings::default(),
+        chat: ChatSettings::default(),
+        bots: BotSettings::default(),
+        anticheat: AntiCheatSettings::default(),
+        tournaments: TournamentSettings::default(),
+        websocket: WebSocketSettings::default(),
+        events: EventStreamSettings::default(),
+        proxies: ProxySettings::default(),
+        admin: AdminSettings::default(),
+        log_filter,
     })
     .await?
     .await
@@ -24,5 +82,6 @@ async fn main() -> Result<(), Whatever> {
             error
         )
     })?;
+    shutdown_tracing(log_guard).await;
     Ok(())
 }
@@ -0,0 +1,72 @@
+//! Reading finished matches back out of the database, for replays, stats and the anti-cheat
+//! pipeline.
+
+use std::collections::HashMap;
+
+use deadpool_sqlite::rusqlite::{params, Connection, Error};
+use snafu::ResultExt;
+
+use crate::{
+    db_handle::DbHandle,
+    domain::{errors::*, replay::MatchRecord, UserId},
+};
+
+/// A match's log entries as they're stored: sequence number, tick and the entry's JSON.
+pub(crate) type StoredLog = Vec<(i64, i64, String)>;
+
+/// Loads a finished match's log and the names of everyone who played in it.
+pub(crate) async fn load_finished_match(
+    db_handle: &DbHandle,
+    match_id: i64,
+) -> Result<(Vec<MatchRecord>, HashMap<UserId, String>), InnerError> {
+    let finished_at = db_handle
+        .query_row(
+            "SELECT FinishedAt FROM GameMatch WHERE Id = ?1",
+            [match_id],
+            |row| row.get::<_, Option<i64>>(0),
+        )
+        .await?;
+    match finished_at {
+        None => return MatchNotFoundSnafu { match_id }.fail(),
+        Some(None) => return MatchNotFinishedSnafu { match_id }.fail(),
+        Some(Some(_)) => {}
+    }
+    let (rows, usernames) = db_handle
+        .transaction(move |transaction| {
+            let rows = read_match_log(transaction, match_id)?;
+            let usernames = transaction
+                .prepare(
+                    "SELECT User.Id, User.Username FROM GameMatchPlayer
+                     JOIN User ON User.Id = GameMatchPlayer.UserId
+                     WHERE GameMatchPlayer.MatchId = ?1",
+                )?
+                .query_map(params![match_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<HashMap<UserId, String>, _>>()?;
+            Ok((rows, usernames))
+        })
+        .await?;
+    Ok((parse_match_log(rows)?, usernames))
+}
+
+/// Reads a match's log in order, without parsing it. That's left to [`parse_match_log`], so a
+/// log that can't be read doesn't fail the transaction it's read in.
+pub(crate) fn read_match_log(connection: &Connection, match_id: i64) -> Result<StoredLog, Error> {
+    connection
+        .prepare("SELECT Seq, Tick, Payload FROM GameMatchEvent WHERE MatchId = ?1 ORDER BY Seq")?
+        .query_map(params![match_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get(2)?))
+        })?
+        .collect()
+}
+
+pub(crate) fn parse_match_log(rows: StoredLog) -> Result<Vec<MatchRecord>, InnerError> {
+    rows.into_iter()
+        .map(|(seq, tick, payload)| {
+            Ok(MatchRecord {
+                seq: seq as u64,
+                tick: tick as u64,
+                entry: serde_json::from_str(&payload).context(MatchLogSnafu)?,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,259 @@
+//! Metrics for Prometheus to scrape from `/metrics`.
+//!
+//! Counters and histograms are recorded as things happen. Anything that's just the current
+//! state of the server (like how many matches are live) is looked up when it's scraped
+//! instead, and passed to [`Metrics::render`] as a [`Gauge`].
+
+use std::{collections::BTreeMap, fmt::Write, sync::Mutex, time::Duration};
+
+use actix_web::dev::ServiceResponse;
+
+/// The upper bounds of every histogram's buckets, in seconds. These are Prometheus' defaults,
+/// which suit anything from a quick query up to a slow password hash.
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Clone, Debug, Default)]
+struct Histogram {
+    /// How many observations fell in each bucket (and not an earlier one).
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        if let Some(bucket) = BUCKETS.iter().position(|&bound| seconds <= bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+/// One metric, with a value for each combination of its labels that's been seen.
+struct Family<T> {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    values: Mutex<BTreeMap<Vec<String>, T>>,
+}
+
+impl<T: Default> Family<T> {
+    fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Self {
+            name,
+            help,
+            labels,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn update(&self, labels: &[&str], update: impl FnOnce(&mut T)) {
+        debug_assert_eq!(labels.len(), self.labels.len());
+        let mut values = self.values.lock().expect("metrics lock was poisoned");
+        let key = labels.iter().map(|label| label.to_string()).collect();
+        update(values.entry(key).or_default());
+    }
+
+    fn label_set(&self, values: &[String], extra: Option<(&str, &str)>) -> String {
+        let labels = self.labels.iter().zip(values);
+        label_set(
+            labels
+                .map(|(name, value)| (*name, value.as_str()))
+                .chain(extra),
+        )
+    }
+}
+
+impl Family<u64> {
+    fn render(&self, out: &mut String) {
+        header(out, self.name, self.help, "counter");
+        let values = self.values.lock().expect("metrics lock was poisoned");
+        for (labels, value) in values.iter() {
+            let _ = writeln!(
+                out,
+                "{}{} {}",
+                self.name,
+                self.label_set(labels, None),
+                value
+            );
+        }
+    }
+}
+
+impl Family<Histogram> {
+    fn render(&self, out: &mut String) {
+        header(out, self.name, self.help, "histogram");
+        let values = self.values.lock().expect("metrics lock was poisoned");
+        for (labels, histogram) in values.iter() {
+            let mut cumulative = 0;
+            for (bound, count) in BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                let le = bound.to_string();
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{} {}",
+                    self.name,
+                    self.label_set(labels, Some(("le", &le))),
+                    cumulative
+                );
+            }
+            let label_set = self.label_set(labels, None);
+            let _ = writeln!(
+                out,
+                "{}_bucket{} {}",
+                self.name,
+                self.label_set(labels, Some(("le", "+Inf"))),
+                histogram.count
+            );
+            let _ = writeln!(out, "{}_sum{} {}", self.name, label_set, histogram.sum);
+            let _ = writeln!(out, "{}_count{} {}", self.name, label_set, histogram.count);
+        }
+    }
+}
+
+/// A value looked up at scrape time.
+pub(crate) struct Gauge {
+    pub(crate) name: &'static str,
+    pub(crate) help: &'static str,
+    /// Each value, along with its labels.
+    pub(crate) values: Vec<(Vec<(&'static str, String)>, f64)>,
+}
+
+impl Gauge {
+    /// A gauge with a single, unlabelled value.
+    pub(crate) fn single(name: &'static str, help: &'static str, value: f64) -> Self {
+        Self {
+            name,
+            help,
+            values: vec![(vec![], value)],
+        }
+    }
+
+    fn render(&self, out: &mut String) {
+        header(out, self.name, self.help, "gauge");
+        for (labels, value) in &self.values {
+            let labels = label_set(labels.iter().map(|(name, value)| (*name, value.as_str())));
+            let _ = writeln!(out, "{}{} {}", self.name, labels, value);
+        }
+    }
+}
+
+/// Everything recorded about the server as it runs.
+pub(crate) struct Metrics {
+    http_requests: Family<u64>,
+    http_request_duration: Family<Histogram>,
+    db_pool_wait: Family<Histogram>,
+    db_query_duration: Family<Histogram>,
+    password_hash_duration: Family<Histogram>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            http_requests: Family::new(
+                "http_requests_total",
+                "HTTP requests handled, by route and status.",
+                &["method", "route", "status"],
+            ),
+            http_request_duration: Family::new(
+                "http_request_duration_seconds",
+                "How long HTTP requests took to handle, by route and status.",
+                &["method", "route", "status"],
+            ),
+            db_pool_wait: Family::new(
+                "db_pool_wait_seconds",
+                "How long it took to get a database connection from the pool.",
+                &[],
+            ),
+            db_query_duration: Family::new(
+                "db_query_duration_seconds",
+                "How long database work took once it had a connection.",
+                &["operation"],
+            ),
+            password_hash_duration: Family::new(
+                "password_hash_duration_seconds",
+                "How long hashing and verifying passwords took.",
+                &["operation"],
+            ),
+        }
+    }
+}
+
+impl Metrics {
+    /// Counts a handled request. Requests are labelled by the route that matched them rather
+    /// than their path, so a path with an ID in it doesn't make a new series for every ID.
+    pub(crate) fn record_response<B>(&self, response: &ServiceResponse<B>, elapsed: Duration) {
+        let request = response.request();
+        let route = request
+            .match_pattern()
+            .unwrap_or_else(|| String::from("unmatched"));
+        let method = request.method().as_str();
+        let status = response.status();
+        let labels = [method, route.as_str(), status.as_str()];
+        self.http_requests.update(&labels, |count| *count += 1);
+        self.http_request_duration.update(&labels, |histogram| {
+            histogram.observe(elapsed.as_secs_f64())
+        });
+    }
+
+    pub(crate) fn record_pool_wait(&self, elapsed: Duration) {
+        self.db_pool_wait
+            .update(&[], |histogram| histogram.observe(elapsed.as_secs_f64()));
+    }
+
+    /// `operation` is the `DbHandle` method that did the work.
+    pub(crate) fn record_query(&self, operation: &str, elapsed: Duration) {
+        self.db_query_duration.update(&[operation], |histogram| {
+            histogram.observe(elapsed.as_secs_f64())
+        });
+    }
+
+    /// `operation` is either `hash` or `verify`.
+    pub(crate) fn record_password_hash(&self, operation: &str, elapsed: Duration) {
+        self.password_hash_duration
+            .update(&[operation], |histogram| {
+                histogram.observe(elapsed.as_secs_f64())
+            });
+    }
+
+    /// Everything in the Prometheus text format, followed by `gauges`.
+    pub(crate) fn render(&self, gauges: &[Gauge]) -> String {
+        let mut out = String::new();
+        self.http_requests.render(&mut out);
+        self.http_request_duration.render(&mut out);
+        self.db_pool_wait.render(&mut out);
+        self.db_query_duration.render(&mut out);
+        self.password_hash_duration.render(&mut out);
+        for gauge in gauges {
+            gauge.render(&mut out);
+        }
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Formats labels as `{name="value",...}`, or nothing at all if there aren't any.
+fn label_set<'a>(labels: impl Iterator<Item = (&'a str, &'a str)>) -> String {
+    let pairs: Vec<String> = labels
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape(value)))
+        .collect();
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
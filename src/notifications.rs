@@ -0,0 +1,195 @@
+//! Saving notifications and pushing them to whoever's listening on `/events`. What players
+//! get notified about is in [`crate::domain::notifications`].
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use deadpool_sqlite::rusqlite::params;
+use snafu::ResultExt;
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use crate::{
+    db_handle::DbHandle,
+    domain::{
+        errors::*,
+        notifications::{Notification, NotificationError, NotificationKind},
+        UserId,
+    },
+    session::unix_timestamp,
+};
+
+/// An in-process pub/sub hub with a topic per player. Every open `/events` stream is a
+/// subscriber to its player's topic.
+///
+/// Subscribers have a bounded queue of `send_buffer` notifications. One that falls that far
+/// behind is dropped, which ends its stream; nothing's lost, since everything unread is sent
+/// again when it reconnects.
+pub(crate) struct Notifications {
+    db_handle: DbHandle,
+    topics: Mutex<HashMap<UserId, Vec<mpsc::Sender<Notification>>>>,
+    send_buffer: usize,
+}
+
+impl Notifications {
+    pub(crate) fn new(db_handle: DbHandle, send_buffer: usize) -> Self {
+        Self {
+            db_handle,
+            topics: Mutex::new(HashMap::new()),
+            send_buffer,
+        }
+    }
+
+    /// Starts listening for the player's notifications.
+    pub(crate) fn subscribe(&self, user_id: UserId) -> mpsc::Receiver<Notification> {
+        let (sender, receiver) = mpsc::channel(self.send_buffer);
+        let mut topics = self.topics.lock().expect("notification lock was poisoned");
+        topics.entry(user_id).or_default().push(sender);
+        receiver
+    }
+
+    /// Saves a notification for each of the players and pushes it to them, in the
+    /// background. Anything that goes wrong is only logged, since nobody's waiting on it.
+    pub(crate) fn notify(self: &Arc<Self>, user_ids: Vec<UserId>, kind: NotificationKind) {
+        if user_ids.is_empty() {
+            return;
+        }
+        let notifications = self.clone();
+        tokio::spawn(async move {
+            let kind_name = kind.as_str();
+            match save_notifications(&notifications.db_handle, user_ids, kind).await {
+                Ok(saved) => {
+                    for (user_id, notification) in saved {
+                        notifications.publish(user_id, notification);
+                    }
+                }
+                Err(error) => {
+                    tracing::error!(?error, kind = kind_name, "Failed to save a notification")
+                }
+            }
+        });
+    }
+
+    fn publish(&self, user_id: UserId, notification: Notification) {
+        let mut topics = self.topics.lock().expect("notification lock was poisoned");
+        let subscribers = match topics.get_mut(&user_id) {
+            Some(subscribers) => subscribers,
+            None => return,
+        };
+        subscribers.retain(
+            |subscriber| match subscriber.try_send(notification.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    tracing::warn!(user_id, "Dropping an event stream that isn't keeping up");
+                    false
+                }
+                Err(TrySendError::Closed(_)) => false,
+            },
+        );
+        if subscribers.is_empty() {
+            topics.remove(&user_id);
+        }
+    }
+}
+
+async fn save_notifications(
+    db_handle: &DbHandle,
+    user_ids: Vec<UserId>,
+    kind: NotificationKind,
+) -> Result<Vec<(UserId, Notification)>, InnerError> {
+    let body = serde_json::to_string(&kind).expect("notifications always serialize");
+    db_handle
+        .transaction(move |transaction| {
+            let created_at = unix_timestamp();
+            let mut saved = vec![];
+            for user_id in user_ids {
+                transaction.execute(
+                    "INSERT INTO Notification (UserId, Kind, Body, CreatedAt)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![user_id, kind.as_str(), body, created_at],
+                )?;
+                let notification = Notification {
+                    id: transaction.last_insert_rowid(),
+                    created_at,
+                    read: false,
+                    kind: kind.clone(),
+                };
+                saved.push((user_id, notification));
+            }
+            Ok(saved)
+        })
+        .await
+}
+
+/// The player's unread notifications, oldest first.
+pub(crate) async fn unread_notifications(
+    db_handle: &DbHandle,
+    user_id: UserId,
+) -> Result<Vec<Notification>, InnerError> {
+    let rows = db_handle
+        .transaction(move |transaction| {
+            transaction
+                .prepare(
+                    "SELECT Id, CreatedAt, Body FROM Notification
+                     WHERE UserId = ?1 AND ReadAt IS NULL
+                     ORDER BY Id",
+                )?
+                .query_map(params![user_id], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                })?
+                .collect::<Result<Vec<(i64, i64, String)>, _>>()
+        })
+        .await?;
+    rows.into_iter()
+        .map(|(id, created_at, body)| {
+            Ok(Notification {
+                id,
+                created_at,
+                read: false,
+                kind: serde_json::from_str(&body).context(NotificationBodySnafu)?,
+            })
+        })
+        .collect()
+}
+
+/// Marks one of the player's notifications as read, or all of them if `notification_id` is
+/// `None`. Notifications that were already read are left alone.
+pub(crate) async fn mark_read(
+    db_handle: &DbHandle,
+    user_id: UserId,
+    notification_id: Option<i64>,
+) -> Result<(), InnerError> {
+    let found = db_handle
+        .transaction(move |transaction| {
+            let now = unix_timestamp();
+            match notification_id {
+                Some(notification_id) => {
+                    transaction.execute(
+                        "UPDATE Notification SET ReadAt = ?3
+                         WHERE Id = ?1 AND UserId = ?2 AND ReadAt IS NULL",
+                        params![notification_id, user_id, now],
+                    )?;
+                    transaction
+                        .prepare("SELECT 1 FROM Notification WHERE Id = ?1 AND UserId = ?2")?
+                        .exists(params![notification_id, user_id])
+                }
+                None => {
+                    transaction.execute(
+                        "UPDATE Notification SET ReadAt = ?2
+                         WHERE UserId = ?1 AND ReadAt IS NULL",
+                        params![user_id, now],
+                    )?;
+                    Ok(true)
+                }
+            }
+        })
+        .await?;
+    match (found, notification_id) {
+        (false, Some(notification_id)) => {
+            Err(NotificationError::NotificationNotFound { notification_id })
+                .context(NotificationSnafu)
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,142 @@
+//! Storing finished practice games and personal bests. The games themselves live in
+//! [`crate::domain::practice`].
+
+use deadpool_sqlite::rusqlite::{params, Connection, Error, OptionalExtension};
+use serde::Serialize;
+
+use crate::{
+    db_handle::DbHandle,
+    domain::{errors::*, minesweeper::BoardTopology, practice::PracticeResult, UserId},
+    session::unix_timestamp,
+};
+
+/// A player's fastest win on one board.
+#[derive(Serialize)]
+pub(crate) struct PersonalBest {
+    pub(crate) level: String,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) mines: usize,
+    pub(crate) topology: BoardTopology,
+    pub(crate) time_ms: u64,
+    pub(crate) three_bv: usize,
+    pub(crate) clicks: u32,
+    pub(crate) efficiency: f64,
+    pub(crate) three_bv_per_second: f64,
+    pub(crate) achieved_at: i64,
+}
+
+/// Records a finished practice game. Returns whether it's a new personal best.
+pub(crate) fn record_practice_game(
+    connection: &Connection,
+    user_id: UserId,
+    result: &PracticeResult,
+) -> Result<bool, Error> {
+    let config = result
+        .level
+        .config()
+        .expect("games are only played on valid boards");
+    let now = unix_timestamp();
+    let stats = result.stats;
+    connection.execute(
+        "INSERT INTO PracticeGame (UserId, Level, Width, Height, Mines, Topology, Won, TimeMs,
+             ThreeBv, SolvedThreeBv, Clicks, Efficiency, ThreeBvPerSecond, FinishedAt)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        params![
+            user_id,
+            result.level.name(),
+            config.width(),
+            config.height(),
+            config.mines(),
+            config.topology().as_str(),
+            result.won,
+            result.time_ms as i64,
+            stats.three_bv,
+            stats.solved_three_bv,
+            stats.clicks,
+            stats.efficiency,
+            stats.three_bv_per_second,
+            now,
+        ],
+    )?;
+    if !result.won {
+        return Ok(false);
+    }
+    let game_id = connection.last_insert_rowid();
+    let best = connection
+        .query_row(
+            "SELECT TimeMs FROM PersonalBest
+             WHERE UserId = ?1 AND Level = ?2 AND Width = ?3 AND Height = ?4 AND Mines = ?5
+                 AND Topology = ?6",
+            params![
+                user_id,
+                result.level.name(),
+                config.width(),
+                config.height(),
+                config.mines(),
+                config.topology().as_str(),
+            ],
+            |row| row.get::<_, i64>(0),
+        )
+        .optional()?;
+    if best.is_some_and(|best| best <= result.time_ms as i64) {
+        return Ok(false);
+    }
+    connection.execute(
+        "INSERT OR REPLACE INTO PersonalBest
+             (UserId, Level, Width, Height, Mines, Topology, GameId, TimeMs, AchievedAt)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            user_id,
+            result.level.name(),
+            config.width(),
+            config.height(),
+            config.mines(),
+            config.topology().as_str(),
+            game_id,
+            result.time_ms as i64,
+            now,
+        ],
+    )?;
+    Ok(true)
+}
+
+/// Every personal best a player has, the classic boards first.
+pub(crate) async fn personal_bests(
+    db_handle: &DbHandle,
+    user_id: UserId,
+) -> Result<Vec<PersonalBest>, InnerError> {
+    db_handle
+        .transaction(move |transaction| {
+            transaction
+                .prepare(
+                    "SELECT PersonalBest.Level, PersonalBest.Width, PersonalBest.Height,
+                         PersonalBest.Mines, PersonalBest.Topology, PersonalBest.TimeMs,
+                         PracticeGame.ThreeBv,
+                         PracticeGame.Clicks, PracticeGame.Efficiency,
+                         PracticeGame.ThreeBvPerSecond, PersonalBest.AchievedAt
+                     FROM PersonalBest JOIN PracticeGame ON PracticeGame.Id = PersonalBest.GameId
+                     WHERE PersonalBest.UserId = ?1
+                     ORDER BY PersonalBest.Level = 'custom', PersonalBest.Width * PersonalBest.Height,
+                         PersonalBest.Mines, PersonalBest.Topology",
+                )?
+                .query_map(params![user_id], |row| {
+                    Ok(PersonalBest {
+                        level: row.get(0)?,
+                        width: row.get(1)?,
+                        height: row.get(2)?,
+                        mines: row.get(3)?,
+                        topology: BoardTopology::from_name(&row.get::<_, String>(4)?)
+                            .unwrap_or_default(),
+                        time_ms: row.get::<_, i64>(5)? as u64,
+                        three_bv: row.get(6)?,
+                        clicks: row.get(7)?,
+                        efficiency: row.get(8)?,
+                        three_bv_per_second: row.get(9)?,
+                        achieved_at: row.get(10)?,
+                    })
+                })?
+                .collect()
+        })
+        .await
+}
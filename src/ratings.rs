@@ -0,0 +1,214 @@
+//! Storing players' ratings. The maths lives in [`crate::domain::rating`].
+
+use deadpool_sqlite::rusqlite::{params, Connection, Error, OptionalExtension};
+use serde::Serialize;
+
+use crate::{
+    db_handle::DbHandle,
+    domain::{
+        battle_royale::Standing,
+        errors::*,
+        lobby::GameMode,
+        rating::{rate_match, RatedPlacement, Rating, RatingSettings},
+        UserId,
+    },
+    session::unix_timestamp,
+};
+
+/// A player's rating in one mode.
+#[derive(Serialize)]
+pub(crate) struct RatingSummary {
+    pub(crate) mode: GameMode,
+    #[serde(flatten)]
+    pub(crate) rating: Rating,
+    pub(crate) matches_played: u32,
+    pub(crate) provisional: bool,
+}
+
+/// How one match changed a player's rating.
+#[derive(Serialize)]
+pub(crate) struct RatingChange {
+    pub(crate) match_id: i64,
+    pub(crate) placement: usize,
+    pub(crate) rating_before: f64,
+    pub(crate) rating_after: f64,
+    pub(crate) deviation_before: f64,
+    pub(crate) deviation_after: f64,
+    pub(crate) recorded_at: i64,
+}
+
+/// A player's rating as it stands right now, with decay for time away already applied.
+/// Players who have never played the mode get the initial rating.
+pub(crate) fn current_rating(
+    connection: &Connection,
+    user_id: UserId,
+    mode: GameMode,
+    settings: &RatingSettings,
+    now: i64,
+) -> Result<(Rating, u32), Error> {
+    let stored = connection
+        .query_row(
+            "SELECT Rating, Deviation, Volatility, MatchesPlayed, LastPlayedAt FROM Rating
+             WHERE UserId = ?1 AND Mode = ?2",
+            params![user_id, mode.as_str()],
+            |row| {
+                let rating = Rating {
+                    rating: row.get(0)?,
+                    deviation: row.get(1)?,
+                    volatility: row.get(2)?,
+                };
+                Ok((rating, row.get(3)?, row.get::<_, i64>(4)?))
+            },
+        )
+        .optional()?;
+    Ok(match stored {
+        Some((rating, matches_played, last_played_at)) => {
+            let away = std::time::Duration::from_secs(now.saturating_sub(last_played_at) as u64);
+            (
+                rating.decayed(settings.periods_in(away), settings),
+                matches_played,
+            )
+        }
+        None => (settings.initial, 0),
+    })
+}
+
+/// Rates a finished match and records everyone's new ratings. This is meant to be run in the
+/// same transaction that records the match's result.
+pub(crate) fn record_match_ratings(
+    connection: &Connection,
+    match_id: i64,
+    mode: GameMode,
+    standings: &[Standing],
+    settings: &RatingSettings,
+) -> Result<(), Error> {
+    let now = unix_timestamp();
+    let mut placements = Vec::with_capacity(standings.len());
+    let mut matches_played = Vec::with_capacity(standings.len());
+    for standing in standings {
+        let (rating, played) = current_rating(connection, standing.user_id, mode, settings, now)?;
+        placements.push(RatedPlacement {
+            user_id: standing.user_id,
+            rating,
+            placement: standing.placement,
+        });
+        matches_played.push(played);
+    }
+    let new_ratings = rate_match(&placements, settings);
+    for ((placement, after), played) in placements.iter().zip(new_ratings).zip(matches_played) {
+        connection.execute(
+            "INSERT INTO Rating (UserId, Mode, Rating, Deviation, Volatility, MatchesPlayed,
+                 SeasonMatchesPlayed, LastPlayedAt)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, ?7)
+             ON CONFLICT (UserId, Mode) DO UPDATE SET
+                 Rating = excluded.Rating,
+                 Deviation = excluded.Deviation,
+                 Volatility = excluded.Volatility,
+                 MatchesPlayed = excluded.MatchesPlayed,
+                 SeasonMatchesPlayed = Rating.SeasonMatchesPlayed + 1,
+                 LastPlayedAt = excluded.LastPlayedAt",
+            params![
+                placement.user_id,
+                mode.as_str(),
+                after.rating,
+                after.deviation,
+                after.volatility,
+                played + 1,
+                now,
+            ],
+        )?;
+        connection.execute(
+            "INSERT INTO RatingHistory (UserId, Mode, MatchId, Placement, RatingBefore,
+                 DeviationBefore, RatingAfter, DeviationAfter, VolatilityAfter, RecordedAt)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                placement.user_id,
+                mode.as_str(),
+                match_id,
+                placement.placement,
+                placement.rating.rating,
+                placement.rating.deviation,
+                after.rating,
+                after.deviation,
+                after.volatility,
+                now,
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// A player's current rating in a mode.
+pub(crate) async fn rating_of(
+    db_handle: &DbHandle,
+    user_id: UserId,
+    mode: GameMode,
+    settings: &RatingSettings,
+) -> Result<Rating, InnerError> {
+    let settings = settings.clone();
+    db_handle
+        .transaction(move |transaction| {
+            current_rating(transaction, user_id, mode, &settings, unix_timestamp())
+                .map(|(rating, _)| rating)
+        })
+        .await
+}
+
+/// A player's current rating in every mode, including ones they haven't played yet.
+pub(crate) async fn ratings_of(
+    db_handle: &DbHandle,
+    user_id: UserId,
+    settings: &RatingSettings,
+) -> Result<Vec<RatingSummary>, InnerError> {
+    let settings = settings.clone();
+    db_handle
+        .transaction(move |transaction| {
+            let now = unix_timestamp();
+            GameMode::ALL
+                .into_iter()
+                .map(|mode| {
+                    let (rating, matches_played) =
+                        current_rating(transaction, user_id, mode, &settings, now)?;
+                    Ok(RatingSummary {
+                        mode,
+                        rating,
+                        matches_played,
+                        provisional: rating.is_provisional(&settings),
+                    })
+                })
+                .collect()
+        })
+        .await
+}
+
+/// How a player's rating in a mode has changed, most recent match first.
+pub(crate) async fn rating_history(
+    db_handle: &DbHandle,
+    user_id: UserId,
+    mode: GameMode,
+    limit: usize,
+) -> Result<Vec<RatingChange>, InnerError> {
+    db_handle
+        .transaction(move |transaction| {
+            transaction
+                .prepare(
+                    "SELECT MatchId, Placement, RatingBefore, RatingAfter, DeviationBefore,
+                         DeviationAfter, RecordedAt
+                     FROM RatingHistory WHERE UserId = ?1 AND Mode = ?2
+                     ORDER BY RecordedAt DESC, Id DESC LIMIT ?3",
+                )?
+                .query_map(params![user_id, mode.as_str(), limit], |row| {
+                    Ok(RatingChange {
+                        match_id: row.get(0)?,
+                        placement: row.get(1)?,
+                        rating_before: row.get(2)?,
+                        rating_after: row.get(3)?,
+                        deviation_before: row.get(4)?,
+                        deviation_after: row.get(5)?,
+                        recorded_at: row.get(6)?,
+                    })
+                })?
+                .collect()
+        })
+        .await
+}
@@ -0,0 +1,56 @@
+use crate::{domain::errors::*, telemetry::LogFilter};
+use actix_web::{get, http::header, put, web, HttpRequest, HttpResponse};
+use subtle::ConstantTimeEq;
+
+/// The token the operator endpoints need, if there is one.
+pub(crate) struct AdminToken(pub(crate) Option<String>);
+
+impl AdminToken {
+    /// Checks the request's `Authorization: Bearer` header against the token. Without a token,
+    /// everything is let through unless `required` is set, in which case nothing is.
+    pub(crate) fn check(&self, request: &HttpRequest, required: bool) -> Result<(), ServerError> {
+        let token = match &self.0 {
+            Some(token) => token,
+            None if required => return Err(ServerError(InnerError::InvalidAdminToken)),
+            None => return Ok(()),
+        };
+        let given = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        // Compared in constant time, so the response time doesn't give the token away.
+        let matches =
+            given.is_some_and(|given| bool::from(given.as_bytes().ct_eq(token.as_bytes())));
+        if !matches {
+            return Err(ServerError(InnerError::InvalidAdminToken));
+        }
+        Ok(())
+    }
+}
+
+#[get("/admin/log-filter")]
+#[tracing::instrument(name = "Reading the log filter", skip(request, token, log_filter))]
+pub(crate) async fn current_log_filter(
+    request: HttpRequest,
+    token: web::Data<AdminToken>,
+    log_filter: web::Data<LogFilter>,
+) -> Result<String, ServerError> {
+    token.check(&request, true)?;
+    Ok(log_filter.current()?)
+}
+
+/// Takes the new filter as the body, in the same form `RUST_LOG` takes.
+#[put("/admin/log-filter")]
+#[tracing::instrument(name = "Changing the log filter", skip(request, token, log_filter))]
+pub(crate) async fn set_log_filter(
+    request: HttpRequest,
+    token: web::Data<AdminToken>,
+    log_filter: web::Data<LogFilter>,
+    directives: String,
+) -> Result<HttpResponse, ServerError> {
+    token.check(&request, true)?;
+    log_filter.set(directives.trim())?;
+    tracing::info!("Changed the log filter");
+    Ok(HttpResponse::NoContent().finish())
+}
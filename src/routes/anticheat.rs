@@ -0,0 +1,36 @@
+use crate::{
+    anticheat::{self, CheatFlag},
+    db_handle::DbHandle,
+    domain::{anticheat::Verdict, errors::*},
+    session::AuthenticatedUser,
+};
+use actix_web::{get, post, web, HttpResponse};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub(crate) struct FlagReviewInput {
+    verdict: Verdict,
+}
+
+#[get("/moderation/flags")]
+#[tracing::instrument(name = "Listing flagged players", skip(db_handle, user), fields(user_id = user.id()))]
+pub(crate) async fn cheat_flags(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+) -> Result<web::Json<Vec<CheatFlag>>, ServerError> {
+    user.ensure_moderator()?;
+    Ok(web::Json(anticheat::open_flags(&db_handle).await?))
+}
+
+#[post("/moderation/flags/{flag_id}/review")]
+#[tracing::instrument(name = "Reviewing a flagged player", skip(db_handle, user, input), fields(user_id = user.id()))]
+pub(crate) async fn review_cheat_flag(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+    flag_id: web::Path<i64>,
+    input: web::Json<FlagReviewInput>,
+) -> Result<HttpResponse, ServerError> {
+    user.ensure_moderator()?;
+    anticheat::review_flag(&db_handle, flag_id.into_inner(), user.id(), input.verdict).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
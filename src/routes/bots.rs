@@ -0,0 +1,50 @@
+use crate::{
+    domain::{errors::*, lobby::MIN_PLAYERS},
+    game_server::GameServer,
+    session::AuthenticatedUser,
+};
+use actix_web::{post, web};
+use serde::{Deserialize, Serialize};
+use snafu::ensure;
+
+#[derive(Deserialize)]
+pub(crate) struct BotMatchesInput {
+    matches: usize,
+    /// How many bots play in each match.
+    players: usize,
+}
+
+#[derive(Serialize)]
+pub(crate) struct BotMatches {
+    match_ids: Vec<i64>,
+}
+
+/// Starts matches with nobody but bots in them, for seeing how the server holds up.
+#[post("/moderation/bot_matches")]
+#[tracing::instrument(name = "Starting bot matches", skip(server, user, input), fields(user_id = user.id(), matches = input.matches))]
+pub(crate) async fn start_bot_matches(
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+    input: web::Json<BotMatchesInput>,
+) -> Result<web::Json<BotMatches>, ServerError> {
+    user.ensure_moderator()?;
+    let settings = server.bot_settings();
+    ensure!(
+        (1..=settings.max_matches).contains(&input.matches),
+        TooManyBotMatchesSnafu {
+            max: settings.max_matches
+        }
+    );
+    ensure!(
+        (MIN_PLAYERS..=settings.max_players).contains(&input.players),
+        InvalidBotMatchPlayersSnafu {
+            min: MIN_PLAYERS,
+            max: settings.max_players
+        }
+    );
+    let mut match_ids = Vec::with_capacity(input.matches);
+    for _ in 0..input.matches {
+        match_ids.push(server.start_bot_match(vec![], input.players).await?);
+    }
+    Ok(web::Json(BotMatches { match_ids }))
+}
@@ -0,0 +1,162 @@
+use crate::{
+    chat::{self, ChatReport, IgnoreList},
+    db_handle::DbHandle,
+    domain::{chat::IgnoreKind, errors::*, protocol::ServerEvent, UserId},
+    game_server::GameServer,
+    session::{unix_timestamp, AuthenticatedUser},
+};
+use actix_web::{delete, get, post, web, HttpResponse};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub(crate) struct ReportInput {
+    #[serde(default)]
+    reason: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct MuteInput {
+    user_id: UserId,
+    /// How long the player can't chat for.
+    minutes: u32,
+    #[serde(default)]
+    reason: String,
+}
+
+#[get("/chat/ignores")]
+#[tracing::instrument(name = "Getting a player's ignore list", skip(db_handle, user), fields(user_id = user.id()))]
+pub(crate) async fn chat_ignores(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+) -> Result<web::Json<IgnoreList>, ServerError> {
+    Ok(web::Json(chat::ignore_list(&db_handle, user.id()).await?))
+}
+
+#[post("/users/{target}/mute")]
+#[tracing::instrument(name = "Muting a player", skip(db_handle, user), fields(user_id = user.id()))]
+pub(crate) async fn mute_player(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+    target: web::Path<UserId>,
+) -> Result<HttpResponse, ServerError> {
+    chat::ignore(&db_handle, user.id(), target.into_inner(), IgnoreKind::Mute).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[delete("/users/{target}/mute")]
+#[tracing::instrument(name = "Unmuting a player", skip(db_handle, user), fields(user_id = user.id()))]
+pub(crate) async fn unmute_player(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+    target: web::Path<UserId>,
+) -> Result<HttpResponse, ServerError> {
+    chat::unignore(&db_handle, user.id(), target.into_inner(), IgnoreKind::Mute).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Blocking someone also ends any friendship with them.
+#[post("/users/{target}/block")]
+#[tracing::instrument(name = "Blocking a player", skip(db_handle, server, user), fields(user_id = user.id()))]
+pub(crate) async fn block_player(
+    db_handle: web::Data<DbHandle>,
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+    target: web::Path<UserId>,
+) -> Result<HttpResponse, ServerError> {
+    let target = target.into_inner();
+    let were_friends = chat::ignore(&db_handle, user.id(), target, IgnoreKind::Block).await?;
+    if were_friends {
+        server
+            .hub()
+            .send(user.id(), ServerEvent::FriendRemoved { user_id: target });
+        server
+            .hub()
+            .send(target, ServerEvent::FriendRemoved { user_id: user.id() });
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[delete("/users/{target}/block")]
+#[tracing::instrument(name = "Unblocking a player", skip(db_handle, user), fields(user_id = user.id()))]
+pub(crate) async fn unblock_player(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+    target: web::Path<UserId>,
+) -> Result<HttpResponse, ServerError> {
+    chat::unignore(
+        &db_handle,
+        user.id(),
+        target.into_inner(),
+        IgnoreKind::Block,
+    )
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/chat/messages/{message_id}/report")]
+#[tracing::instrument(name = "Reporting a chat message", skip(db_handle, user, input), fields(user_id = user.id()))]
+pub(crate) async fn report_chat_message(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+    message_id: web::Path<i64>,
+    input: web::Json<ReportInput>,
+) -> Result<HttpResponse, ServerError> {
+    let ReportInput { reason } = input.into_inner();
+    chat::report_message(&db_handle, message_id.into_inner(), user.id(), reason).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[delete("/chat/messages/{message_id}")]
+#[tracing::instrument(name = "Deleting a chat message", skip(db_handle, server, user), fields(user_id = user.id()))]
+pub(crate) async fn delete_chat_message(
+    db_handle: web::Data<DbHandle>,
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+    message_id: web::Path<i64>,
+) -> Result<HttpResponse, ServerError> {
+    user.ensure_moderator()?;
+    let message_id = message_id.into_inner();
+    let room = chat::delete_message(&db_handle, message_id, user.id()).await?;
+    server.announce_deleted_message(&room, message_id);
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/moderation/mutes")]
+#[tracing::instrument(name = "Muting a player from chat", skip(db_handle, user, input), fields(user_id = user.id(), target = input.user_id))]
+pub(crate) async fn moderation_mute(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+    input: web::Json<MuteInput>,
+) -> Result<HttpResponse, ServerError> {
+    user.ensure_moderator()?;
+    let MuteInput {
+        user_id,
+        minutes,
+        reason,
+    } = input.into_inner();
+    let until = unix_timestamp() + i64::from(minutes) * 60;
+    chat::add_chat_mute(&db_handle, user_id, user.id(), reason, until).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[delete("/moderation/mutes/{target}")]
+#[tracing::instrument(name = "Unmuting a player from chat", skip(db_handle, user), fields(user_id = user.id()))]
+pub(crate) async fn moderation_unmute(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+    target: web::Path<UserId>,
+) -> Result<HttpResponse, ServerError> {
+    user.ensure_moderator()?;
+    chat::remove_chat_mute(&db_handle, target.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/moderation/reports")]
+#[tracing::instrument(name = "Listing chat reports", skip(db_handle, user), fields(user_id = user.id()))]
+pub(crate) async fn moderation_reports(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+) -> Result<web::Json<Vec<ChatReport>>, ServerError> {
+    user.ensure_moderator()?;
+    Ok(web::Json(chat::reports(&db_handle).await?))
+}
@@ -0,0 +1,189 @@
+use crate::{
+    daily::{
+        current_attempt, daily_leaderboard, ensure_challenge, past_challenges, play_move,
+        start_attempt, streak_of, AttemptView, DailyResult, StreakView,
+    },
+    db_handle::DbHandle,
+    domain::{
+        battle_royale::Action,
+        daily::{format_date, parse_date, DailyError, DailySettings},
+        errors::*,
+        minesweeper::Position,
+    },
+    session::{unix_timestamp, AuthenticatedUser},
+};
+use actix_web::{get, http::header::ContentType, post, web, HttpResponse};
+use handlebars::Handlebars;
+use serde::Serialize;
+use snafu::ResultExt;
+
+/// How many players a day's leaderboard shows.
+const LEADERBOARD_SIZE: usize = 100;
+/// How many past challenges the history page shows.
+const HISTORY_DAYS: usize = 30;
+/// How many of each past challenge's fastest clears the history page shows.
+const HISTORY_TOP: usize = 3;
+
+#[derive(Serialize)]
+pub(crate) struct TodaysChallenge {
+    date: String,
+    width: usize,
+    height: usize,
+    mines: usize,
+    start: Position,
+    /// When the challenge started and when it's over, as unix timestamps.
+    opens_at: i64,
+    closes_at: i64,
+    /// The player's attempt, if they've started it.
+    attempt: Option<AttemptView>,
+    streak: StreakView,
+}
+
+#[get("/daily")]
+#[tracing::instrument(name = "Getting today's challenge", skip(db_handle, settings, user), fields(user_id = user.id()))]
+pub(crate) async fn todays_challenge(
+    db_handle: web::Data<DbHandle>,
+    settings: web::Data<DailySettings>,
+    user: AuthenticatedUser,
+) -> Result<web::Json<TodaysChallenge>, ServerError> {
+    let date = settings.date_at(unix_timestamp());
+    let stored = ensure_challenge(&db_handle, &settings, date).await?;
+    let attempt = match current_attempt(&db_handle, &settings, user.id()).await {
+        Ok(attempt) => Some(attempt),
+        Err(InnerError::DailyError {
+            source: DailyError::NoAttempt,
+        }) => None,
+        Err(error) => return Err(error.into()),
+    };
+    let streak = streak_of(&db_handle, &settings, user.id()).await?;
+    Ok(web::Json(TodaysChallenge {
+        date: format_date(date),
+        width: stored.challenge.config.width(),
+        height: stored.challenge.config.height(),
+        mines: stored.challenge.config.mines(),
+        start: stored.challenge.start,
+        opens_at: stored.opens_at,
+        closes_at: stored.closes_at,
+        attempt,
+        streak: streak.into(),
+    }))
+}
+
+#[post("/daily/attempt")]
+#[tracing::instrument(name = "Starting today's challenge", skip(db_handle, settings, user), fields(user_id = user.id()))]
+pub(crate) async fn start_daily_attempt(
+    db_handle: web::Data<DbHandle>,
+    settings: web::Data<DailySettings>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ServerError> {
+    let attempt = start_attempt(&db_handle, &settings, user.id()).await?;
+    Ok(HttpResponse::Created().json(attempt))
+}
+
+#[get("/daily/attempt")]
+#[tracing::instrument(name = "Getting a daily attempt", skip(db_handle, settings, user), fields(user_id = user.id()))]
+pub(crate) async fn get_daily_attempt(
+    db_handle: web::Data<DbHandle>,
+    settings: web::Data<DailySettings>,
+    user: AuthenticatedUser,
+) -> Result<web::Json<AttemptView>, ServerError> {
+    Ok(web::Json(
+        current_attempt(&db_handle, &settings, user.id()).await?,
+    ))
+}
+
+#[post("/daily/attempt/moves")]
+#[tracing::instrument(name = "Playing a daily move", skip(db_handle, settings, user), fields(user_id = user.id()))]
+pub(crate) async fn play_daily_move(
+    db_handle: web::Data<DbHandle>,
+    settings: web::Data<DailySettings>,
+    user: AuthenticatedUser,
+    action: web::Json<Action>,
+) -> Result<web::Json<AttemptView>, ServerError> {
+    Ok(web::Json(
+        play_move(&db_handle, &settings, user.id(), action.into_inner()).await?,
+    ))
+}
+
+#[get("/daily/{date}/leaderboard")]
+#[tracing::instrument(name = "Getting a daily leaderboard", skip(db_handle, _user))]
+pub(crate) async fn get_daily_leaderboard(
+    db_handle: web::Data<DbHandle>,
+    _user: AuthenticatedUser,
+    date: web::Path<String>,
+) -> Result<web::Json<Vec<DailyResult>>, ServerError> {
+    let date = date.into_inner();
+    let date = parse_date(&date)
+        .ok_or(DailyError::InvalidDate { date })
+        .context(DailySnafu)?;
+    Ok(web::Json(
+        daily_leaderboard(&db_handle, date, LEADERBOARD_SIZE).await?,
+    ))
+}
+
+#[get("/daily/history")]
+#[tracing::instrument(
+    name = "Viewing past daily challenges",
+    skip(db_handle, settings, hb, _user)
+)]
+pub(crate) async fn daily_history(
+    db_handle: web::Data<DbHandle>,
+    settings: web::Data<DailySettings>,
+    hb: web::Data<Handlebars<'static>>,
+    _user: AuthenticatedUser,
+) -> Result<HttpResponse, ServerError> {
+    let today = settings.date_at(unix_timestamp());
+    let mut days = vec![];
+    for summary in past_challenges(&db_handle, today, HISTORY_DAYS).await? {
+        let date = parse_date(&summary.date).expect("saved dates are valid");
+        let fastest = daily_leaderboard(&db_handle, date, HISTORY_TOP)
+            .await?
+            .into_iter()
+            .map(|result| FastestModel {
+                time: format_time(result.time_ms),
+                result,
+            })
+            .collect();
+        days.push(DayModel {
+            date: summary.date,
+            attempts: summary.attempts,
+            clears: summary.clears,
+            fastest,
+        });
+    }
+    let html =
+        tokio::task::spawn_blocking(move || hb.render("daily_history", &HistoryModel { days }))
+            .await
+            .context(JoinSnafu)?
+            .context(TemplateRenderingSnafu {
+                template_name: String::from("daily_history"),
+            })?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html))
+}
+
+#[derive(Serialize)]
+struct HistoryModel {
+    days: Vec<DayModel>,
+}
+
+#[derive(Serialize)]
+struct DayModel {
+    date: String,
+    attempts: u32,
+    clears: u32,
+    fastest: Vec<FastestModel>,
+}
+
+#[derive(Serialize)]
+struct FastestModel {
+    #[serde(flatten)]
+    result: DailyResult,
+    time: String,
+}
+
+/// Formats a clear time like `1:07.250`.
+fn format_time(ms: i64) -> String {
+    format!("{}:{:02}.{:03}", ms / 60_000, ms / 1000 % 60, ms % 1000)
+}
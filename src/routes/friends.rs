@@ -0,0 +1,182 @@
+use crate::{
+    db_handle::DbHandle,
+    domain::{
+        errors::*,
+        friends::{Presence, RequestOutcome},
+        lobby::{LobbyCode, LobbyError},
+        notifications::NotificationKind,
+        protocol::ServerEvent,
+        User, UserId,
+    },
+    friends,
+    game_server::GameServer,
+    session::AuthenticatedUser,
+};
+use actix_web::{delete, get, post, web, HttpResponse};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub(crate) struct FriendList {
+    friends: Vec<FriendEntry>,
+    /// Requests waiting for the player to answer them.
+    incoming: Vec<User>,
+    /// Requests the player is waiting to hear back about.
+    outgoing: Vec<User>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct FriendEntry {
+    user: User,
+    since: i64,
+    presence: Presence,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub(crate) enum RequestStatus {
+    /// Waiting for the other player to answer.
+    Sent,
+    /// The other player had already asked, so now they're friends.
+    Friends,
+}
+
+#[get("/friends")]
+#[tracing::instrument(name = "Listing friends", skip(db_handle, server, user), fields(user_id = user.id()))]
+pub(crate) async fn list_friends(
+    db_handle: web::Data<DbHandle>,
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+) -> Result<web::Json<FriendList>, ServerError> {
+    let friends = friends::friends(&db_handle, user.id())
+        .await?
+        .into_iter()
+        .map(|friend| FriendEntry {
+            presence: server.presence_of(friend.user.id()),
+            user: friend.user,
+            since: friend.since,
+        })
+        .collect();
+    let pending = friends::pending_requests(&db_handle, user.id()).await?;
+    Ok(web::Json(FriendList {
+        friends,
+        incoming: pending.incoming,
+        outgoing: pending.outgoing,
+    }))
+}
+
+#[post("/friends/{other}")]
+#[tracing::instrument(name = "Sending a friend request", skip(db_handle, server, user), fields(user_id = user.id()))]
+pub(crate) async fn send_friend_request(
+    db_handle: web::Data<DbHandle>,
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+    other: web::Path<UserId>,
+) -> Result<web::Json<RequestStatus>, ServerError> {
+    let (outcome, other) = friends::send_request(&db_handle, user.id(), *other).await?;
+    Ok(web::Json(match outcome {
+        RequestOutcome::Sent => {
+            server.notifications().notify(
+                vec![other.id()],
+                NotificationKind::FriendRequest {
+                    from: user.0.clone(),
+                },
+            );
+            server
+                .hub()
+                .send(other.id(), ServerEvent::FriendRequest { from: user.0 });
+            RequestStatus::Sent
+        }
+        RequestOutcome::Accepted => {
+            announce_friendship(&server, user.0, other);
+            RequestStatus::Friends
+        }
+    }))
+}
+
+#[post("/friends/{other}/accept")]
+#[tracing::instrument(name = "Accepting a friend request", skip(db_handle, server, user), fields(user_id = user.id()))]
+pub(crate) async fn accept_friend_request(
+    db_handle: web::Data<DbHandle>,
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+    other: web::Path<UserId>,
+) -> Result<HttpResponse, ServerError> {
+    let other = friends::answer_request(&db_handle, user.id(), *other, true).await?;
+    announce_friendship(&server, user.0, other);
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/friends/{other}/decline")]
+#[tracing::instrument(name = "Declining a friend request", skip(db_handle, user), fields(user_id = user.id()))]
+pub(crate) async fn decline_friend_request(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+    other: web::Path<UserId>,
+) -> Result<HttpResponse, ServerError> {
+    friends::answer_request(&db_handle, user.id(), *other, false).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Ends a friendship, or takes back a friend request.
+#[delete("/friends/{other}")]
+#[tracing::instrument(name = "Removing a friend", skip(db_handle, server, user), fields(user_id = user.id()))]
+pub(crate) async fn remove_friend(
+    db_handle: web::Data<DbHandle>,
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+    other: web::Path<UserId>,
+) -> Result<HttpResponse, ServerError> {
+    friends::unfriend(&db_handle, user.id(), *other).await?;
+    server
+        .hub()
+        .send(*other, ServerEvent::FriendRemoved { user_id: user.id() });
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/friends/{other}/invite")]
+#[tracing::instrument(name = "Inviting a friend to a lobby", skip(db_handle, server, user), fields(user_id = user.id()))]
+pub(crate) async fn invite_friend(
+    db_handle: web::Data<DbHandle>,
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+    other: web::Path<UserId>,
+) -> Result<HttpResponse, ServerError> {
+    friends::ensure_friends(&db_handle, user.id(), *other).await?;
+    let code: LobbyCode = server
+        .lobbies()
+        .lobby_of(user.id())
+        .map(|lobby| lobby.code.clone())
+        .ok_or(InnerError::LobbyError {
+            source: LobbyError::NotInLobby,
+        })?;
+    server.notifications().notify(
+        vec![*other],
+        NotificationKind::LobbyInvite {
+            from: user.0.clone(),
+            code: code.clone(),
+        },
+    );
+    server
+        .hub()
+        .send(*other, ServerEvent::LobbyInvite { from: user.0, code });
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Lets two new friends know about each other.
+fn announce_friendship(server: &GameServer, user: User, other: User) {
+    let (user_id, other_id) = (user.id(), other.id());
+    server.hub().send(
+        user_id,
+        ServerEvent::FriendAdded {
+            presence: server.presence_of(other_id),
+            user: other,
+        },
+    );
+    server.hub().send(
+        other_id,
+        ServerEvent::FriendAdded {
+            presence: server.presence_of(user_id),
+            user,
+        },
+    );
+}
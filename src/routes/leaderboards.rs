@@ -0,0 +1,172 @@
+use crate::{
+    db_handle::DbHandle,
+    domain::{
+        errors::*,
+        leaderboard::{Board, Cursor, LeaderboardError, LeaderboardPage},
+    },
+    leaderboards::{archived_page, seasons, Leaderboards},
+    session::AuthenticatedUser,
+};
+use actix_web::{get, http::header::ContentType, web, HttpResponse};
+use handlebars::Handlebars;
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+
+const DEFAULT_PAGE_SIZE: usize = 25;
+const MAX_PAGE_SIZE: usize = 100;
+/// How many entries either side of the player "around me" shows by default.
+const DEFAULT_RADIUS: usize = 5;
+
+#[derive(Deserialize)]
+pub(crate) struct PageQuery {
+    /// The `next` cursor of the previous page.
+    after: Option<String>,
+    limit: Option<usize>,
+}
+
+impl PageQuery {
+    fn cursor(&self) -> Result<Option<Cursor>, InnerError> {
+        self.after
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+            .context(LeaderboardSnafu)
+    }
+
+    fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct AroundQuery {
+    radius: Option<usize>,
+}
+
+#[get("/leaderboards/{board}")]
+#[tracing::instrument(name = "Getting a leaderboard", skip(leaderboards, _user, query))]
+pub(crate) async fn get_leaderboard(
+    leaderboards: web::Data<Leaderboards>,
+    _user: AuthenticatedUser,
+    board: web::Path<String>,
+    query: web::Query<PageQuery>,
+) -> Result<web::Json<LeaderboardPage>, ServerError> {
+    let board = Board::from_name(&board).context(LeaderboardSnafu)?;
+    let page = leaderboards
+        .snapshot(board)
+        .page(query.cursor()?, query.limit());
+    Ok(web::Json(page))
+}
+
+#[get("/leaderboards/{board}/me")]
+#[tracing::instrument(name = "Getting the leaderboard around a player", skip(leaderboards, user, query), fields(user_id = user.id()))]
+pub(crate) async fn leaderboard_around_me(
+    leaderboards: web::Data<Leaderboards>,
+    user: AuthenticatedUser,
+    board: web::Path<String>,
+    query: web::Query<AroundQuery>,
+) -> Result<web::Json<LeaderboardPage>, ServerError> {
+    let board = Board::from_name(&board).context(LeaderboardSnafu)?;
+    let radius = query
+        .radius
+        .unwrap_or(DEFAULT_RADIUS)
+        .min(MAX_PAGE_SIZE / 2);
+    let page = leaderboards
+        .snapshot(board)
+        .around(user.id(), radius)
+        .ok_or(LeaderboardError::NotRanked)
+        .context(LeaderboardSnafu)?;
+    Ok(web::Json(page))
+}
+
+#[get("/leaderboards/{board}/view")]
+#[tracing::instrument(name = "Viewing a leaderboard", skip(leaderboards, hb, user, query), fields(user_id = user.id()))]
+pub(crate) async fn view_leaderboard(
+    leaderboards: web::Data<Leaderboards>,
+    hb: web::Data<Handlebars<'static>>,
+    user: AuthenticatedUser,
+    board: web::Path<String>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let board = Board::from_name(&board).context(LeaderboardSnafu)?;
+    let page = leaderboards
+        .snapshot(board)
+        .page(query.cursor()?, query.limit());
+    let model = LeaderboardModel::new(page, user.id());
+    let html = tokio::task::spawn_blocking(move || hb.render("leaderboard", &model))
+        .await
+        .context(JoinSnafu)?
+        .context(TemplateRenderingSnafu {
+            template_name: String::from("leaderboard"),
+        })?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html))
+}
+
+#[get("/seasons")]
+#[tracing::instrument(name = "Listing seasons", skip(db_handle, _user))]
+pub(crate) async fn list_seasons(
+    db_handle: web::Data<DbHandle>,
+    _user: AuthenticatedUser,
+) -> Result<HttpResponse, ServerError> {
+    Ok(HttpResponse::Ok().json(seasons(&db_handle).await?))
+}
+
+#[get("/seasons/{season_id}/leaderboards/{board}")]
+#[tracing::instrument(
+    name = "Getting a season's final standings",
+    skip(db_handle, _user, query)
+)]
+pub(crate) async fn season_leaderboard(
+    db_handle: web::Data<DbHandle>,
+    _user: AuthenticatedUser,
+    path: web::Path<(i64, String)>,
+    query: web::Query<PageQuery>,
+) -> Result<web::Json<LeaderboardPage>, ServerError> {
+    let (season_id, board) = path.into_inner();
+    let board = Board::from_name(&board).context(LeaderboardSnafu)?;
+    let page = archived_page(&db_handle, season_id, board, query.cursor()?, query.limit()).await?;
+    Ok(web::Json(page))
+}
+
+#[derive(Serialize)]
+struct LeaderboardModel {
+    board: String,
+    season_id: i64,
+    rows: Vec<RowModel>,
+    next: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RowModel {
+    rank: usize,
+    username: String,
+    rating: String,
+    matches_played: u32,
+    /// Set on the row of the player looking at the page.
+    is_me: bool,
+}
+
+impl LeaderboardModel {
+    fn new(page: LeaderboardPage, viewer: i64) -> Self {
+        Self {
+            board: page.board,
+            season_id: page.season_id,
+            rows: page
+                .entries
+                .into_iter()
+                .map(|entry| RowModel {
+                    rank: entry.rank,
+                    is_me: entry.player.user_id == viewer,
+                    username: entry.player.username,
+                    rating: format!("{:.0}", entry.player.rating),
+                    matches_played: entry.player.matches_played,
+                })
+                .collect(),
+            next: page.next,
+        }
+    }
+}
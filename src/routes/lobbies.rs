@@ -0,0 +1,191 @@
+use crate::{
+    domain::{
+        errors::*,
+        lobby::{Lobby, LobbyCode, LobbyError, LobbySettings},
+        UserId,
+    },
+    game_server::GameServer,
+    session::AuthenticatedUser,
+};
+use actix_web::{get, post, put, web, HttpResponse};
+use serde::Deserialize;
+use snafu::ResultExt;
+
+#[derive(Deserialize)]
+pub(crate) struct ReadyInput {
+    ready: bool,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct TargetPlayer {
+    user_id: UserId,
+}
+
+#[get("/lobbies")]
+#[tracing::instrument(name = "Listing public lobbies", skip(server, _user))]
+pub(crate) async fn list_lobbies(
+    server: web::Data<GameServer>,
+    _user: AuthenticatedUser,
+) -> web::Json<Vec<Lobby>> {
+    web::Json(
+        server
+            .lobbies()
+            .open_public_lobbies()
+            .into_iter()
+            .cloned()
+            .collect(),
+    )
+}
+
+#[post("/lobbies")]
+#[tracing::instrument(name = "Creating a lobby", skip(server, user), fields(user_id = user.id()))]
+pub(crate) async fn create_lobby(
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+    settings: web::Json<LobbySettings>,
+) -> Result<web::Json<Lobby>, ServerError> {
+    server.ensure_not_playing(user.id()).context(LobbySnafu)?;
+    server.matchmaker().dequeue(user.id());
+    let lobby = server
+        .lobbies()
+        .create(user.0, settings.into_inner())
+        .context(LobbySnafu)?
+        .clone();
+    server.refresh_presence(&[lobby.host]).await;
+    Ok(web::Json(lobby))
+}
+
+#[get("/lobbies/current")]
+#[tracing::instrument(name = "Getting the current lobby", skip(server, user), fields(user_id = user.id()))]
+pub(crate) async fn current_lobby(
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+) -> Result<web::Json<Lobby>, ServerError> {
+    current_lobby_of(&server, user.id())
+}
+
+#[get("/lobbies/{code}")]
+#[tracing::instrument(name = "Getting a lobby", skip(server, _user))]
+pub(crate) async fn get_lobby(
+    server: web::Data<GameServer>,
+    _user: AuthenticatedUser,
+    code: web::Path<LobbyCode>,
+) -> Result<web::Json<Lobby>, ServerError> {
+    let lobby = server.lobbies().get(&code).context(LobbySnafu)?.clone();
+    Ok(web::Json(lobby))
+}
+
+#[post("/lobbies/{code}/join")]
+#[tracing::instrument(name = "Joining a lobby", skip(server, user), fields(user_id = user.id()))]
+pub(crate) async fn join_lobby(
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+    code: web::Path<LobbyCode>,
+) -> Result<web::Json<Lobby>, ServerError> {
+    let user_id = user.id();
+    server.ensure_not_playing(user_id).context(LobbySnafu)?;
+    server.matchmaker().dequeue(user_id);
+    let lobby = server
+        .lobbies()
+        .join(&code, user.0)
+        .context(LobbySnafu)?
+        .clone();
+    server.refresh_presence(&[user_id]).await;
+    Ok(web::Json(lobby))
+}
+
+#[post("/lobbies/current/leave")]
+#[tracing::instrument(name = "Leaving a lobby", skip(server, user), fields(user_id = user.id()))]
+pub(crate) async fn leave_lobby(
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ServerError> {
+    server.ensure_not_playing(user.id()).context(LobbySnafu)?;
+    server.lobbies().leave(user.id()).context(LobbySnafu)?;
+    server.refresh_presence(&[user.id()]).await;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Readying up can start the match, in which case the returned lobby is already in it.
+#[post("/lobbies/current/ready")]
+#[tracing::instrument(name = "Setting ready state", skip(server, user, input), fields(user_id = user.id()))]
+pub(crate) async fn set_ready(
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+    input: web::Json<ReadyInput>,
+) -> Result<web::Json<Lobby>, ServerError> {
+    let start = server
+        .lobbies()
+        .set_ready(user.id(), input.ready)
+        .context(LobbySnafu)?;
+    if let Some(start) = start {
+        server.start_match(start).await?;
+    }
+    current_lobby_of(&server, user.id())
+}
+
+#[put("/lobbies/current/settings")]
+#[tracing::instrument(name = "Updating lobby settings", skip(server, user), fields(user_id = user.id()))]
+pub(crate) async fn update_lobby_settings(
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+    settings: web::Json<LobbySettings>,
+) -> Result<web::Json<Lobby>, ServerError> {
+    let lobby = server
+        .lobbies()
+        .update_settings(user.id(), settings.into_inner())
+        .context(LobbySnafu)?
+        .clone();
+    Ok(web::Json(lobby))
+}
+
+#[post("/lobbies/current/kick")]
+#[tracing::instrument(name = "Kicking a player", skip(server, user, target), fields(user_id = user.id(), target = target.user_id))]
+pub(crate) async fn kick_player(
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+    target: web::Json<TargetPlayer>,
+) -> Result<web::Json<Lobby>, ServerError> {
+    server
+        .lobbies()
+        .kick(user.id(), target.user_id)
+        .context(LobbySnafu)?;
+    server.refresh_presence(&[target.user_id]).await;
+    current_lobby_of(&server, user.id())
+}
+
+#[post("/lobbies/current/host")]
+#[tracing::instrument(name = "Transferring host", skip(server, user, target), fields(user_id = user.id(), target = target.user_id))]
+pub(crate) async fn transfer_host(
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+    target: web::Json<TargetPlayer>,
+) -> Result<web::Json<Lobby>, ServerError> {
+    server
+        .lobbies()
+        .transfer_host(user.id(), target.user_id)
+        .context(LobbySnafu)?;
+    current_lobby_of(&server, user.id())
+}
+
+#[post("/lobbies/current/start")]
+#[tracing::instrument(name = "Starting a lobby", skip(server, user), fields(user_id = user.id()))]
+pub(crate) async fn start_lobby(
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+) -> Result<web::Json<Lobby>, ServerError> {
+    let start = server.lobbies().start(user.id()).context(LobbySnafu)?;
+    server.start_match(start).await?;
+    current_lobby_of(&server, user.id())
+}
+
+fn current_lobby_of(server: &GameServer, user_id: UserId) -> Result<web::Json<Lobby>, ServerError> {
+    let lobby = server
+        .lobbies()
+        .lobby_of(user_id)
+        .cloned()
+        .ok_or(InnerError::LobbyError {
+            source: LobbyError::NotInLobby,
+        })?;
+    Ok(web::Json(lobby))
+}
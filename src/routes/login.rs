@@ -1,8 +1,13 @@
+use std::{net::IpAddr, time::Instant};
+
 use crate::{
+    config::ProxySettings,
     db_handle::DbHandle,
     domain::{errors::*, Login, User},
+    metrics::Metrics,
+    session::create_session,
 };
-use actix_web::{get, http::header::ContentType, post, web, HttpResponse};
+use actix_web::{get, http::header::ContentType, post, web, HttpRequest, HttpResponse};
 use argon2::{Argon2, PasswordHash, PasswordVerifier};
 use handlebars::Handlebars;
 use serde::Serialize;
@@ -26,16 +31,19 @@ pub(crate) async fn login_page(
 }
 
 #[post("/")]
-#[tracing::instrument(name = "Logging In", skip(input, db_handle), fields(username = %input.username()))]
+#[tracing::instrument(name = "Logging In", skip(request, input, db_handle, metrics, proxies), fields(username = %input.username()))]
 pub(crate) async fn login(
+    request: HttpRequest,
     input: web::Form<Login>,
     db_handle: web::Data<DbHandle>,
     hb: web::Data<Handlebars<'static>>,
+    metrics: web::Data<Metrics>,
+    proxies: web::Data<ProxySettings>,
 ) -> Result<HttpResponse, ServerError> {
     let username = input.username().to_string();
     let user_option = db_handle
         .query_row(
-            "SELECT Id, Username, PasswordHash FROM User WHERE Username = ?1",
+            "SELECT Id, Username, PasswordHash FROM User WHERE Username = ?1 AND IsBot = 0",
             [username],
             |row| {
                 let id: i64 = row.get(0)?;
@@ -49,17 +57,22 @@ pub(crate) async fn login(
     match user_option {
         Some((user, password_hash)) => {
             let matches = tokio::task::spawn_blocking(move || {
-                argon
+                let started = Instant::now();
+                let matches = argon
                     .verify_password(
                         input.password().as_bytes(),
                         &PasswordHash::new(&password_hash).unwrap(),
                     )
-                    .is_ok()
+                    .is_ok();
+                metrics.record_password_hash("verify", started.elapsed());
+                matches
             })
             .await
             .context(JoinSnafu)?;
             if matches {
-                Ok(HttpResponse::Ok().json(user))
+                let address = client_address(&request, &proxies).map(|ip| ip.to_string());
+                let cookie = create_session(&db_handle, user.id(), address).await?;
+                Ok(HttpResponse::Ok().cookie(cookie).json(user))
             } else {
                 let html = tokio::task::spawn_blocking(move || {
                     hb.render(
@@ -109,3 +122,30 @@ enum MessageToClient {
 struct LoginModel {
     message: Option<MessageToClient>,
 }
+
+/// Where a request came from. Proxies we trust add the address they got the request from to
+/// the end of `X-Forwarded-For`, so the last address in it that isn't one of theirs is the
+/// client's; anything before that could've been made up by the client.
+fn client_address(request: &HttpRequest, proxies: &ProxySettings) -> Option<IpAddr> {
+    let peer = request.peer_addr()?.ip();
+    if !proxies.trusted.contains(&peer) {
+        return Some(peer);
+    }
+    let forwarded = request
+        .headers()
+        .get_all("X-Forwarded-For")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|address| address.trim().parse::<IpAddr>().ok())
+        .collect::<Vec<_>>();
+    let mut client = peer;
+    for address in forwarded.into_iter().rev() {
+        match address {
+            Some(address) if proxies.trusted.contains(&address) => client = address,
+            Some(address) => return Some(address),
+            // Whatever came before this can't be trusted either.
+            None => break,
+        }
+    }
+    Some(client)
+}
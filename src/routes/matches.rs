@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+
+use crate::{
+    db_handle::DbHandle,
+    domain::{
+        battle_royale::{Action, EliminationReason, MatchEvent, PowerUpUse},
+        errors::*,
+        minesweeper::{BoardTopology, CellView, GameStatus, Topology},
+        replay::{LogEntry, Replay, Replayer},
+        UserId,
+    },
+    matches::load_finished_match,
+    session::AuthenticatedUser,
+};
+use actix_web::{
+    get,
+    http::header::{ContentDisposition, ContentType, DispositionParam, DispositionType},
+    web, HttpResponse,
+};
+use handlebars::Handlebars;
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+
+#[derive(Deserialize)]
+pub(crate) struct ReplayViewQuery {
+    /// How many log entries to play. Defaults to all of them.
+    index: Option<usize>,
+}
+
+#[get("/matches/{match_id}/replay")]
+#[tracing::instrument(name = "Downloading a replay", skip(db_handle, _user))]
+pub(crate) async fn download_replay(
+    db_handle: web::Data<DbHandle>,
+    _user: AuthenticatedUser,
+    match_id: web::Path<i64>,
+) -> Result<HttpResponse, ServerError> {
+    let match_id = match_id.into_inner();
+    let (records, _) = load_finished_match(&db_handle, match_id).await?;
+    let replay = Replay::from_log(match_id, &records).context(ReplaySnafu)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::octet_stream())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(replay.file_name())],
+        })
+        .body(replay.encode()))
+}
+
+#[get("/matches/{match_id}/replay/view")]
+#[tracing::instrument(name = "Viewing a replay", skip(db_handle, hb, _user, query))]
+pub(crate) async fn view_replay(
+    db_handle: web::Data<DbHandle>,
+    hb: web::Data<Handlebars<'static>>,
+    _user: AuthenticatedUser,
+    match_id: web::Path<i64>,
+    query: web::Query<ReplayViewQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let match_id = match_id.into_inner();
+    let (records, usernames) = load_finished_match(&db_handle, match_id).await?;
+    let html = tokio::task::spawn_blocking(move || -> Result<String, InnerError> {
+        let replay = Replay::from_log(match_id, &records).context(ReplaySnafu)?;
+        let replayer = Replayer::new(replay).context(ReplaySnafu)?;
+        let model = ReplayModel::new(&replayer, query.index, &usernames)?;
+        hb.render("replay", &model).context(TemplateRenderingSnafu {
+            template_name: String::from("replay"),
+        })
+    })
+    .await
+    .context(JoinSnafu)??;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html))
+}
+
+#[derive(Serialize)]
+struct ReplayModel {
+    match_id: i64,
+    index: usize,
+    steps: usize,
+    previous: Option<StepModel>,
+    next: Option<StepModel>,
+    tick: u64,
+    /// What the last entry played was.
+    description: Option<String>,
+    boards: Vec<BoardModel>,
+}
+
+/// Another step in the replay that can be linked to.
+#[derive(Serialize)]
+struct StepModel {
+    index: usize,
+}
+
+#[derive(Serialize)]
+struct BoardModel {
+    player: String,
+    status: &'static str,
+    /// Set for boards that wrap around at the edges, which look the same as square ones.
+    wraps: bool,
+    rows: Vec<RowModel>,
+}
+
+#[derive(Serialize)]
+struct RowModel {
+    /// Odd rows of hex boards sit half a cell to the right.
+    shifted: bool,
+    cells: Vec<CellModel>,
+}
+
+#[derive(Serialize)]
+struct CellModel {
+    text: String,
+    background: &'static str,
+    round: bool,
+}
+
+impl ReplayModel {
+    fn new(
+        replayer: &Replayer,
+        index: Option<usize>,
+        usernames: &HashMap<UserId, String>,
+    ) -> Result<Self, InnerError> {
+        let len = replayer.timeline().len();
+        let index = index.unwrap_or(len);
+        let state = replayer.state_at(index).context(ReplaySnafu)?;
+        let name = |user_id: UserId| {
+            usernames
+                .get(&user_id)
+                .cloned()
+                .unwrap_or_else(|| format!("Player {}", user_id))
+        };
+        let boards = state
+            .boards
+            .into_iter()
+            .map(|(user_id, view)| BoardModel {
+                player: name(user_id),
+                status: match view.status {
+                    GameStatus::Ready | GameStatus::Playing => "Playing",
+                    GameStatus::Won => "Cleared",
+                    GameStatus::Lost { .. } => "Hit a mine",
+                },
+                wraps: view.topology.wraps(),
+                rows: view
+                    .cells
+                    .chunks(view.width)
+                    .enumerate()
+                    .map(|(y, row)| {
+                        let hex = view.topology == BoardTopology::Hex;
+                        RowModel {
+                            shifted: hex && y % 2 == 1,
+                            cells: row.iter().map(|cell| CellModel::new(cell, hex)).collect(),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+        Ok(Self {
+            match_id: replayer.replay().match_id,
+            index,
+            steps: len,
+            previous: index.checked_sub(1).map(|index| StepModel { index }),
+            next: Some(index + 1)
+                .filter(|&next| next <= len)
+                .map(|index| StepModel { index }),
+            tick: state.tick,
+            description: index
+                .checked_sub(1)
+                .map(|last| describe(&replayer.timeline()[last].entry, &name)),
+            boards,
+        })
+    }
+}
+
+impl CellModel {
+    fn new(cell: &CellView, round: bool) -> Self {
+        let (text, background) = match cell {
+            CellView::Hidden => (String::new(), "bg-light-silver"),
+            CellView::Flagged => (String::from("F"), "bg-light-silver"),
+            CellView::Revealed(0) => (String::new(), "bg-near-white"),
+            CellView::Revealed(count) => (count.to_string(), "bg-near-white"),
+            CellView::Fogged => (String::from("?"), "bg-near-white"),
+            CellView::Mine => (String::from("*"), "bg-near-white"),
+            CellView::Exploded => (String::from("*"), "bg-red"),
+        };
+        Self {
+            text,
+            background,
+            round,
+        }
+    }
+}
+
+fn describe(entry: &LogEntry, name: &impl Fn(UserId) -> String) -> String {
+    match entry {
+        LogEntry::Started { .. } => String::from("The match started"),
+        LogEntry::Action { user_id, action } => match action {
+            Action::Reveal(position) => format!("{} revealed {}", name(*user_id), position),
+            Action::Flag(position) => format!("{} flagged {}", name(*user_id), position),
+            Action::Chord(position) => format!("{} chorded {}", name(*user_id), position),
+            Action::UsePowerUp(PowerUpUse {
+                power_up,
+                target: Some(target),
+            }) => format!(
+                "{} used {} on {}",
+                name(*user_id),
+                power_up.as_str(),
+                name(*target)
+            ),
+            Action::UsePowerUp(PowerUpUse { power_up, .. }) => {
+                format!("{} used {}", name(*user_id), power_up.as_str())
+            }
+            Action::Forfeit => format!("{} forfeited", name(*user_id)),
+        },
+        LogEntry::Event { event } => match event {
+            MatchEvent::BoardChanged {
+                user_id, revealed, ..
+            } => format!("{} revealed {} cells", name(*user_id), revealed.len()),
+            MatchEvent::Progress {
+                user_id,
+                revealed,
+                safe_cells,
+            } => format!(
+                "{} has revealed {} of {} safe cells",
+                name(*user_id),
+                revealed,
+                safe_cells
+            ),
+            MatchEvent::Eliminated {
+                user_id,
+                placement,
+                reason,
+            } => format!(
+                "{} was knocked out by {} in place {}",
+                name(*user_id),
+                match reason {
+                    EliminationReason::Mine => "a mine",
+                    EliminationReason::Storm => "the storm",
+                    EliminationReason::Forfeit => "forfeiting",
+                },
+                placement
+            ),
+            MatchEvent::StormScheduled { tick } => {
+                format!("The storm will close in on tick {}", tick)
+            }
+            MatchEvent::PowerUpEarned { user_id, power_up } => {
+                format!("{} earned {}", name(*user_id), power_up.as_str())
+            }
+            MatchEvent::PowerUpUsed {
+                user_id, power_up, ..
+            } => format!("{}'s {} took effect", name(*user_id), power_up.as_str()),
+            MatchEvent::ShieldBroken { user_id, position } => {
+                format!(
+                    "{}'s shield stopped the mine at {}",
+                    name(*user_id),
+                    position
+                )
+            }
+            MatchEvent::FogLifted { user_id } => {
+                format!("The fog lifted from {}'s board", name(*user_id))
+            }
+            MatchEvent::Finished(result) => match result.winner() {
+                Some(winner) => format!("The match is over, {} won", name(winner)),
+                None => String::from("The match is over"),
+            },
+        },
+    }
+}
@@ -0,0 +1,92 @@
+use std::time::Instant;
+
+use crate::{
+    db_handle::DbHandle,
+    domain::{
+        errors::*,
+        lobby::{GameMode, LobbyCode, LobbyError, LobbyStatus},
+        UserId,
+    },
+    game_server::GameServer,
+    ratings::rating_of,
+    session::AuthenticatedUser,
+};
+use actix_web::{delete, get, post, web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+
+#[derive(Deserialize)]
+pub(crate) struct QueueInput {
+    mode: GameMode,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub(crate) enum QueueStatus {
+    /// Not queued and not in a match.
+    Idle,
+    Queued,
+    /// A match was found (or the player is in a match some other way).
+    Matched {
+        lobby: LobbyCode,
+    },
+}
+
+#[post("/matchmaking/queue")]
+#[tracing::instrument(name = "Joining the matchmaking queue", skip(db_handle, server, user, input), fields(user_id = user.id()))]
+pub(crate) async fn join_queue(
+    db_handle: web::Data<DbHandle>,
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+    input: web::Json<QueueInput>,
+) -> Result<HttpResponse, ServerError> {
+    let user_id = user.id();
+    server.ensure_not_playing(user_id).context(LobbySnafu)?;
+    if let Some(lobby) = server.lobbies().lobby_of(user_id) {
+        return Err(InnerError::LobbyError {
+            source: LobbyError::AlreadyInLobby {
+                code: lobby.code.clone(),
+            },
+        }
+        .into());
+    }
+    let rating = rating_of(&db_handle, user_id, input.mode, server.ratings()).await?;
+    server
+        .matchmaker()
+        .enqueue(user.0, input.mode, rating.rating, Instant::now())
+        .context(LobbySnafu)?;
+    // Don't make the player wait for the next scheduled run if a match is already there.
+    server.run_matchmaking().await;
+    Ok(HttpResponse::Accepted().json(queue_status(&server, user_id)))
+}
+
+#[get("/matchmaking/queue")]
+#[tracing::instrument(name = "Checking the matchmaking queue", skip(server, user), fields(user_id = user.id()))]
+pub(crate) async fn queue_status_route(
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+) -> web::Json<QueueStatus> {
+    web::Json(queue_status(&server, user.id()))
+}
+
+#[delete("/matchmaking/queue")]
+#[tracing::instrument(name = "Leaving the matchmaking queue", skip(server, user), fields(user_id = user.id()))]
+pub(crate) async fn leave_queue(
+    server: web::Data<GameServer>,
+    user: AuthenticatedUser,
+) -> HttpResponse {
+    server.matchmaker().dequeue(user.id());
+    HttpResponse::NoContent().finish()
+}
+
+fn queue_status(server: &GameServer, user_id: UserId) -> QueueStatus {
+    if server.matchmaker().is_queued(user_id) {
+        return QueueStatus::Queued;
+    }
+    match server.lobbies().lobby_of(user_id) {
+        Some(lobby) if lobby.status == LobbyStatus::InMatch => QueueStatus::Matched {
+            lobby: lobby.code.clone(),
+        },
+        _ => QueueStatus::Idle,
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{
+    db_handle::DbHandle,
+    domain::errors::*,
+    game_server::GameServer,
+    metrics::{Gauge, Metrics},
+};
+use actix_web::{get, web, HttpRequest, HttpResponse};
+
+use super::AdminToken;
+
+#[get("/metrics")]
+#[tracing::instrument(
+    name = "Scraping metrics",
+    level = "debug",
+    skip(request, token, metrics, db_handle, server)
+)]
+pub(crate) async fn scrape_metrics(
+    request: HttpRequest,
+    token: web::Data<AdminToken>,
+    metrics: web::Data<Metrics>,
+    db_handle: web::Data<DbHandle>,
+    server: web::Data<GameServer>,
+) -> Result<HttpResponse, ServerError> {
+    token.check(&request, false)?;
+    let pool = db_handle.pool_status();
+    let gauges = [
+        Gauge::single(
+            "db_pool_max_size",
+            "The most connections the database pool will open.",
+            pool.max_size as f64,
+        ),
+        Gauge::single(
+            "db_pool_size",
+            "How many connections the database pool has open.",
+            pool.size as f64,
+        ),
+        Gauge::single(
+            "db_pool_available",
+            "How many open database connections are free. Negative when callers are waiting.",
+            pool.available as f64,
+        ),
+        Gauge {
+            name: "websocket_connections",
+            help: "Open web socket connections, for playing and for spectating.",
+            values: vec![
+                (
+                    vec![("kind", String::from("player"))],
+                    server.hub().connection_count() as f64,
+                ),
+                (
+                    vec![("kind", String::from("spectator"))],
+                    server.spectator_count() as f64,
+                ),
+            ],
+        },
+        Gauge::single(
+            "live_matches",
+            "Matches being played right now.",
+            server.live_match_count() as f64,
+        ),
+    ];
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics.render(&gauges)))
+}
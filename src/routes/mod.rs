@@ -1,7 +1,41 @@
+mod admin;
+mod anticheat;
+mod bots;
+mod chat;
+mod daily;
+mod friends;
 mod health_check;
+mod leaderboards;
+mod lobbies;
 mod login;
+mod matches;
+mod matchmaking;
+mod metrics;
+mod notifications;
+mod practice;
+mod ratings;
 mod sign_up;
+mod stats;
+mod tournaments;
+mod ws;
 
+pub(crate) use admin::*;
+pub(crate) use anticheat::*;
+pub(crate) use bots::*;
+pub(crate) use chat::*;
+pub(crate) use daily::*;
+pub(crate) use friends::*;
 pub(crate) use health_check::*;
+pub(crate) use leaderboards::*;
+pub(crate) use lobbies::*;
 pub(crate) use login::*;
+pub(crate) use matches::*;
+pub(crate) use matchmaking::*;
+pub(crate) use metrics::*;
+pub(crate) use notifications::*;
+pub(crate) use practice::*;
+pub(crate) use ratings::*;
 pub(crate) use sign_up::*;
+pub(crate) use stats::*;
+pub(crate) use tournaments::*;
+pub(crate) use ws::*;
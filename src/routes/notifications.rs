@@ -0,0 +1,122 @@
+use std::{collections::HashSet, convert::Infallible};
+
+use crate::{
+    config::EventStreamSettings,
+    db_handle::DbHandle,
+    domain::{errors::*, notifications::Notification},
+    game_server::GameServer,
+    notifications::{mark_read, unread_notifications},
+    session::AuthenticatedUser,
+};
+use actix_web::{get, http::header, post, web, HttpResponse};
+use futures_util::stream;
+use serde::Serialize;
+use tokio::{
+    sync::mpsc,
+    time::{self, Interval},
+};
+
+#[derive(Serialize)]
+pub(crate) struct NotificationList {
+    /// Oldest first.
+    notifications: Vec<Notification>,
+}
+
+/// Streams the player's notifications as server-sent events: everything still unread first,
+/// then new ones as they happen.
+#[get("/events")]
+#[tracing::instrument(name = "Opening an event stream", skip(db_handle, server, settings, user), fields(user_id = user.id()))]
+pub(crate) async fn event_stream(
+    db_handle: web::Data<DbHandle>,
+    server: web::Data<GameServer>,
+    settings: web::Data<EventStreamSettings>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ServerError> {
+    // Subscribed before looking up what's unread, so nothing saved in between is missed.
+    let receiver = server.notifications().subscribe(user.id());
+    let unread = unread_notifications(&db_handle, user.id()).await?;
+    let keep_alive = time::interval_at(
+        time::Instant::now() + settings.keep_alive,
+        settings.keep_alive,
+    );
+    let events = EventStream {
+        already_sent: unread.iter().map(|notification| notification.id).collect(),
+        unread: unread.into_iter(),
+        receiver,
+        keep_alive,
+    };
+    let body = stream::unfold(events, |mut events| async move {
+        let frame = events.next_frame().await?;
+        Some((Ok::<_, Infallible>(web::Bytes::from(frame)), events))
+    });
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(body))
+}
+
+#[get("/notifications")]
+#[tracing::instrument(name = "Listing notifications", skip(db_handle, user), fields(user_id = user.id()))]
+pub(crate) async fn list_notifications(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+) -> Result<web::Json<NotificationList>, ServerError> {
+    let notifications = unread_notifications(&db_handle, user.id()).await?;
+    Ok(web::Json(NotificationList { notifications }))
+}
+
+#[post("/notifications/read")]
+#[tracing::instrument(name = "Marking every notification as read", skip(db_handle, user), fields(user_id = user.id()))]
+pub(crate) async fn mark_all_notifications_read(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+) -> Result<HttpResponse, ServerError> {
+    mark_read(&db_handle, user.id(), None).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/notifications/{notification_id}/read")]
+#[tracing::instrument(name = "Marking a notification as read", skip(db_handle, user), fields(user_id = user.id()))]
+pub(crate) async fn mark_notification_read(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+    notification_id: web::Path<i64>,
+) -> Result<HttpResponse, ServerError> {
+    mark_read(&db_handle, user.id(), Some(*notification_id)).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Where one `/events` stream is up to.
+struct EventStream {
+    unread: std::vec::IntoIter<Notification>,
+    /// The unread notifications, which might come through the subscription as well.
+    already_sent: HashSet<i64>,
+    receiver: mpsc::Receiver<Notification>,
+    keep_alive: Interval,
+}
+
+impl EventStream {
+    /// The next bit of the stream to write, or `None` once the subscription's been dropped.
+    async fn next_frame(&mut self) -> Option<String> {
+        if let Some(notification) = self.unread.next() {
+            return Some(event_frame(&notification));
+        }
+        loop {
+            tokio::select! {
+                notification = self.receiver.recv() => match notification? {
+                    notification if self.already_sent.contains(&notification.id) => continue,
+                    notification => return Some(event_frame(&notification)),
+                },
+                _ = self.keep_alive.tick() => return Some(String::from(": keep-alive\n\n")),
+            }
+        }
+    }
+}
+
+fn event_frame(notification: &Notification) -> String {
+    format!(
+        "id: {}\nevent: notification\ndata: {}\n\n",
+        notification.id,
+        serde_json::to_string(notification).expect("notifications always serialize")
+    )
+}
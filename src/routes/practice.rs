@@ -0,0 +1,18 @@
+use crate::{
+    db_handle::DbHandle,
+    domain::{errors::*, UserId},
+    practice::personal_bests,
+    session::AuthenticatedUser,
+};
+use actix_web::{get, web, HttpResponse};
+
+#[get("/users/{user_id}/personal_bests")]
+#[tracing::instrument(name = "Getting a player's personal bests", skip(db_handle, _user))]
+pub(crate) async fn user_personal_bests(
+    db_handle: web::Data<DbHandle>,
+    _user: AuthenticatedUser,
+    user_id: web::Path<UserId>,
+) -> Result<HttpResponse, ServerError> {
+    let bests = personal_bests(&db_handle, user_id.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(bests))
+}
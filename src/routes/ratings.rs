@@ -0,0 +1,52 @@
+use crate::{
+    db_handle::DbHandle,
+    domain::{errors::*, lobby::GameMode, UserId},
+    game_server::GameServer,
+    ratings::{rating_history, ratings_of},
+    session::AuthenticatedUser,
+};
+use actix_web::{get, web, HttpResponse};
+use serde::Deserialize;
+use snafu::OptionExt;
+
+/// How many matches of history are returned when no limit is given.
+const DEFAULT_HISTORY_LIMIT: usize = 20;
+const MAX_HISTORY_LIMIT: usize = 100;
+
+#[derive(Deserialize)]
+pub(crate) struct HistoryQuery {
+    limit: Option<usize>,
+}
+
+#[get("/users/{user_id}/ratings")]
+#[tracing::instrument(name = "Getting a player's ratings", skip(db_handle, server, _user))]
+pub(crate) async fn user_ratings(
+    db_handle: web::Data<DbHandle>,
+    server: web::Data<GameServer>,
+    _user: AuthenticatedUser,
+    user_id: web::Path<UserId>,
+) -> Result<HttpResponse, ServerError> {
+    let ratings = ratings_of(&db_handle, user_id.into_inner(), server.ratings()).await?;
+    Ok(HttpResponse::Ok().json(ratings))
+}
+
+#[get("/users/{user_id}/ratings/{mode}/history")]
+#[tracing::instrument(
+    name = "Getting a player's rating history",
+    skip(db_handle, _user, query)
+)]
+pub(crate) async fn user_rating_history(
+    db_handle: web::Data<DbHandle>,
+    _user: AuthenticatedUser,
+    path: web::Path<(UserId, String)>,
+    query: web::Query<HistoryQuery>,
+) -> Result<HttpResponse, ServerError> {
+    let (user_id, mode) = path.into_inner();
+    let mode = GameMode::from_name(&mode).context(UnknownGameModeSnafu { mode })?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .min(MAX_HISTORY_LIMIT);
+    let history = rating_history(&db_handle, user_id, mode, limit).await?;
+    Ok(HttpResponse::Ok().json(history))
+}
@@ -1,6 +1,10 @@
+use std::time::Instant;
+
 use crate::{
+    bots::{is_bot_name, BOT_NAME_PREFIX},
     db_handle::{DbHandle, ExecuteResult},
     domain::{errors::*, User, UserInput},
+    metrics::Metrics,
 };
 use actix_files::NamedFile;
 use actix_web::{get, post, web, Responder};
@@ -8,7 +12,7 @@ use argon2::{
     password_hash::{rand_core::OsRng, SaltString},
     Argon2, PasswordHasher,
 };
-use snafu::ResultExt;
+use snafu::{ensure, ResultExt};
 
 #[get("/signup")]
 #[tracing::instrument(name = "Navigating to the sign up page")]
@@ -16,13 +20,36 @@ pub(crate) async fn sign_up_page() -> impl Responder {
     NamedFile::open_async("./static/signup.html").await
 }
 
-#[tracing::instrument(name = "Creating a user", skip(input, db_handle), fields(username = %input.username()))]
+#[tracing::instrument(name = "Creating a user", skip(input, db_handle, metrics), fields(username = %input.username()))]
 #[post("/signup")]
 pub(crate) async fn sign_up(
     input: web::Form<UserInput>,
     db_handle: web::Data<DbHandle>,
+    metrics: web::Data<Metrics>,
 ) -> Result<web::Json<User>, ServerError> {
-    let password_hash = hash_and_salt_password(input.password().into()).await?;
+    ensure!(
+        !is_bot_name(input.username()),
+        ReservedUsernameSnafu {
+            prefix: BOT_NAME_PREFIX
+        }
+    );
+    // The index on usernames would turn away a duplicate too, but not with an error that
+    // says so.
+    let taken = db_handle
+        .query_row(
+            "SELECT 1 FROM User WHERE Username = ?1",
+            [input.username().to_string()],
+            |_| Ok(()),
+        )
+        .await?
+        .is_some();
+    ensure!(
+        !taken,
+        UsernameTakenSnafu {
+            username: input.username()
+        }
+    );
+    let password_hash = hash_and_salt_password(input.password().into(), metrics).await?;
     let ExecuteResult { last_insert_rowid } = db_handle
         .execute(
             "INSERT INTO User (Username, PasswordHash) VALUES (?1, ?2)",
@@ -35,15 +62,21 @@ pub(crate) async fn sign_up(
     )))
 }
 
-#[tracing::instrument(name = "Hashing and salting a password", skip(password))]
-async fn hash_and_salt_password(password: String) -> Result<String, InnerError> {
+#[tracing::instrument(name = "Hashing and salting a password", skip(password, metrics))]
+async fn hash_and_salt_password(
+    password: String,
+    metrics: web::Data<Metrics>,
+) -> Result<String, InnerError> {
     tokio::task::spawn_blocking(move || {
         let argon = Argon2::default(); // TODO: may want to use a custom instance
         let salt = SaltString::generate(&mut OsRng);
-        argon
+        let started = Instant::now();
+        let hashed = argon
             .hash_password(password.as_bytes(), &salt)
             .map_err(|error_source| InnerError::PasswordHashingError { error_source })
-            .map(|hashed_password| hashed_password.to_string())
+            .map(|hashed_password| hashed_password.to_string());
+        metrics.record_password_hash("hash", started.elapsed());
+        hashed
     })
     .await
     .context(JoinSnafu)?
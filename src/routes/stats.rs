@@ -0,0 +1,107 @@
+use crate::{
+    db_handle::DbHandle,
+    domain::{errors::*, stats::ACHIEVEMENTS, UserId},
+    session::AuthenticatedUser,
+    stats::{profile_of, ProfileStats, UnlockedAchievement},
+};
+use actix_web::{get, http::header::ContentType, web, HttpResponse};
+use handlebars::Handlebars;
+use serde::Serialize;
+use snafu::ResultExt;
+
+#[derive(Serialize)]
+pub(crate) struct StatsResponse {
+    stats: ProfileStats,
+    achievements: Vec<UnlockedAchievement>,
+}
+
+#[get("/users/{user_id}/stats")]
+#[tracing::instrument(name = "Getting a player's stats", skip(db_handle, _user))]
+pub(crate) async fn user_stats(
+    db_handle: web::Data<DbHandle>,
+    _user: AuthenticatedUser,
+    user_id: web::Path<UserId>,
+) -> Result<web::Json<StatsResponse>, ServerError> {
+    let (_, stats, achievements) = profile_of(&db_handle, user_id.into_inner()).await?;
+    Ok(web::Json(StatsResponse {
+        stats,
+        achievements,
+    }))
+}
+
+#[get("/users/{user_id}/profile")]
+#[tracing::instrument(name = "Viewing a player's profile", skip(db_handle, hb, user), fields(user_id = user.id()))]
+pub(crate) async fn user_profile(
+    db_handle: web::Data<DbHandle>,
+    hb: web::Data<Handlebars<'static>>,
+    user: AuthenticatedUser,
+    profile_id: web::Path<UserId>,
+) -> Result<HttpResponse, ServerError> {
+    let (player, stats, achievements) = profile_of(&db_handle, profile_id.into_inner()).await?;
+    let model = ProfileModel::new(player.username(), &stats, &achievements);
+    let html = tokio::task::spawn_blocking(move || hb.render("profile", &model))
+        .await
+        .context(JoinSnafu)?
+        .context(TemplateRenderingSnafu {
+            template_name: String::from("profile"),
+        })?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html))
+}
+
+#[derive(Serialize)]
+struct ProfileModel {
+    username: String,
+    matches_played: u64,
+    wins: u64,
+    average_placement: Option<String>,
+    cells_revealed: u64,
+    mines_flagged: u64,
+    clears: u64,
+    fastest_clear: Option<String>,
+    achievements: Vec<AchievementModel>,
+    unlocked: usize,
+    total: usize,
+}
+
+#[derive(Serialize)]
+struct AchievementModel {
+    name: &'static str,
+    description: &'static str,
+    unlocked: bool,
+}
+
+impl ProfileModel {
+    fn new(username: &str, stats: &ProfileStats, unlocked: &[UnlockedAchievement]) -> Self {
+        // Every achievement is listed, so players can see what's left to unlock.
+        let achievements = ACHIEVEMENTS
+            .iter()
+            .map(|achievement| AchievementModel {
+                name: achievement.name,
+                description: achievement.description,
+                unlocked: unlocked
+                    .iter()
+                    .any(|unlocked| unlocked.achievement.id == achievement.id),
+            })
+            .collect();
+        Self {
+            username: username.to_string(),
+            matches_played: stats.stats.matches_played,
+            wins: stats.stats.wins,
+            average_placement: stats
+                .average_placement
+                .map(|average| format!("{:.2}", average)),
+            cells_revealed: stats.stats.cells_revealed,
+            mines_flagged: stats.stats.mines_flagged,
+            clears: stats.stats.clears,
+            fastest_clear: stats
+                .stats
+                .fastest_clear
+                .map(|fastest| format!("{:.1}s", fastest.time_ms as f64 / 1000.0)),
+            achievements,
+            unlocked: unlocked.len(),
+            total: ACHIEVEMENTS.len(),
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{
+    db_handle::DbHandle,
+    domain::{
+        errors::*,
+        lobby::{GameMode, LobbySettings, Visibility},
+        minesweeper::BoardTopology,
+        tournament::{HeatState, Tournament, TournamentFormat, TournamentStanding},
+    },
+    session::{unix_timestamp_millis, AuthenticatedUser},
+    tournaments::{self, update_tournament, TournamentSummary},
+};
+use actix_web::{delete, get, http::header::ContentType, post, web, HttpResponse};
+use handlebars::Handlebars;
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+
+#[derive(Deserialize)]
+pub(crate) struct TournamentInput {
+    name: String,
+    /// When registration closes and the first round is drawn, as a unix timestamp in
+    /// milliseconds.
+    starts_at: i64,
+    width: usize,
+    height: usize,
+    #[serde(default)]
+    topology: BoardTopology,
+    #[serde(default)]
+    power_ups: bool,
+    mine_density: u8,
+    heat_size: usize,
+    advance: usize,
+    max_entrants: usize,
+}
+
+/// A tournament along with where everyone's finished so far.
+#[derive(Serialize)]
+pub(crate) struct TournamentDetails {
+    #[serde(flatten)]
+    tournament: Tournament,
+    standings: Vec<TournamentStanding>,
+}
+
+#[get("/tournaments")]
+#[tracing::instrument(name = "Listing tournaments", skip(db_handle, _user))]
+pub(crate) async fn list_tournaments(
+    db_handle: web::Data<DbHandle>,
+    _user: AuthenticatedUser,
+) -> Result<web::Json<Vec<TournamentSummary>>, ServerError> {
+    Ok(web::Json(tournaments::tournaments(&db_handle).await?))
+}
+
+#[post("/tournaments")]
+#[tracing::instrument(name = "Creating a tournament", skip(db_handle, user, input), fields(user_id = user.id()))]
+pub(crate) async fn create_tournament(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+    input: web::Json<TournamentInput>,
+) -> Result<web::Json<Tournament>, ServerError> {
+    user.ensure_moderator()?;
+    let TournamentInput {
+        name,
+        starts_at,
+        width,
+        height,
+        topology,
+        power_ups,
+        mine_density,
+        heat_size,
+        advance,
+        max_entrants,
+    } = input.into_inner();
+    let format = TournamentFormat {
+        lobby: LobbySettings {
+            visibility: Visibility::Private,
+            mode: GameMode::BattleRoyale,
+            width,
+            height,
+            topology,
+            power_ups,
+            mine_density,
+            max_players: heat_size,
+        },
+        advance,
+        max_entrants,
+    };
+    let tournament =
+        tournaments::create_tournament(&db_handle, user.id(), name, format, starts_at).await?;
+    Ok(web::Json(tournament))
+}
+
+#[get("/tournaments/{tournament_id}")]
+#[tracing::instrument(name = "Getting a tournament", skip(db_handle, _user))]
+pub(crate) async fn get_tournament(
+    db_handle: web::Data<DbHandle>,
+    _user: AuthenticatedUser,
+    tournament_id: web::Path<i64>,
+) -> Result<web::Json<TournamentDetails>, ServerError> {
+    let tournament = tournaments::tournament(&db_handle, tournament_id.into_inner()).await?;
+    Ok(web::Json(TournamentDetails {
+        standings: tournament.standings(),
+        tournament,
+    }))
+}
+
+#[get("/tournaments/{tournament_id}/bracket")]
+#[tracing::instrument(name = "Viewing a tournament bracket", skip(db_handle, hb, user), fields(user_id = user.id()))]
+pub(crate) async fn tournament_bracket(
+    db_handle: web::Data<DbHandle>,
+    hb: web::Data<Handlebars<'static>>,
+    user: AuthenticatedUser,
+    tournament_id: web::Path<i64>,
+) -> Result<HttpResponse, ServerError> {
+    let tournament = tournaments::tournament(&db_handle, tournament_id.into_inner()).await?;
+    let model = BracketModel::new(&tournament, user.id());
+    let html = tokio::task::spawn_blocking(move || hb.render("tournament", &model))
+        .await
+        .context(JoinSnafu)?
+        .context(TemplateRenderingSnafu {
+            template_name: String::from("tournament"),
+        })?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html))
+}
+
+#[post("/tournaments/{tournament_id}/registration")]
+#[tracing::instrument(name = "Registering for a tournament", skip(db_handle, user), fields(user_id = user.id()))]
+pub(crate) async fn register_for_tournament(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+    tournament_id: web::Path<i64>,
+) -> Result<HttpResponse, ServerError> {
+    let player = user.0.clone();
+    update_tournament(&db_handle, tournament_id.into_inner(), move |tournament| {
+        tournament.register(player, unix_timestamp_millis())
+    })
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[delete("/tournaments/{tournament_id}/registration")]
+#[tracing::instrument(name = "Withdrawing from a tournament", skip(db_handle, user), fields(user_id = user.id()))]
+pub(crate) async fn withdraw_from_tournament(
+    db_handle: web::Data<DbHandle>,
+    user: AuthenticatedUser,
+    tournament_id: web::Path<i64>,
+) -> Result<HttpResponse, ServerError> {
+    let user_id = user.id();
+    update_tournament(&db_handle, tournament_id.into_inner(), move |tournament| {
+        tournament.withdraw(user_id, unix_timestamp_millis())
+    })
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[derive(Serialize)]
+struct BracketModel {
+    name: String,
+    status: &'static str,
+    entrants: Vec<EntrantModel>,
+    rounds: Vec<RoundModel>,
+    standings: Vec<StandingModel>,
+    winner: Option<String>,
+}
+
+#[derive(Serialize)]
+struct EntrantModel {
+    seed: Option<usize>,
+    username: String,
+    is_me: bool,
+}
+
+#[derive(Serialize)]
+struct RoundModel {
+    number: usize,
+    is_final: bool,
+    heats: Vec<HeatModel>,
+}
+
+#[derive(Serialize)]
+struct HeatModel {
+    number: usize,
+    state: String,
+    players: Vec<HeatPlayerModel>,
+}
+
+#[derive(Serialize)]
+struct HeatPlayerModel {
+    username: String,
+    placement: Option<usize>,
+    no_show: bool,
+    advanced: bool,
+    is_me: bool,
+}
+
+#[derive(Serialize)]
+struct StandingModel {
+    place: usize,
+    username: String,
+    rounds: usize,
+    is_me: bool,
+}
+
+impl BracketModel {
+    fn new(tournament: &Tournament, viewer: i64) -> Self {
+        Self {
+            name: tournament.name().to_string(),
+            status: tournament.status().as_str(),
+            entrants: tournament
+                .entrants()
+                .iter()
+                .map(|entrant| EntrantModel {
+                    seed: entrant.seed,
+                    username: entrant.user.username().to_string(),
+                    is_me: entrant.user.id() == viewer,
+                })
+                .collect(),
+            rounds: tournament
+                .rounds()
+                .iter()
+                .enumerate()
+                .map(|(index, round)| RoundModel {
+                    number: index + 1,
+                    is_final: round.is_final(),
+                    heats: round
+                        .heats
+                        .iter()
+                        .enumerate()
+                        .map(|(index, heat)| HeatModel {
+                            number: index + 1,
+                            state: match heat.state {
+                                HeatState::Waiting { .. } => String::from("waiting"),
+                                HeatState::Playing { match_id } => {
+                                    format!("playing match {}", match_id)
+                                }
+                                HeatState::Finished => String::from("finished"),
+                            },
+                            players: heat
+                                .players
+                                .iter()
+                                .map(|player| HeatPlayerModel {
+                                    username: player.user.username().to_string(),
+                                    placement: player.placement,
+                                    no_show: player.no_show,
+                                    advanced: player.advanced,
+                                    is_me: player.user.id() == viewer,
+                                })
+                                .collect(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+            standings: tournament
+                .standings()
+                .into_iter()
+                .map(|standing| StandingModel {
+                    place: standing.place,
+                    is_me: standing.user.id() == viewer,
+                    username: standing.user.username().to_string(),
+                    rounds: standing.rounds,
+                })
+                .collect(),
+            winner: tournament
+                .winner()
+                .map(|winner| winner.username().to_string()),
+        }
+    }
+}
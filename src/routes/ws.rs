@@ -0,0 +1,268 @@
+use std::time::Instant;
+
+use crate::{
+    config::WebSocketSettings,
+    domain::{
+        errors::ServerError,
+        protocol::{ClientMessage, ServerEvent, ServerMessage, SpectatorEvent, SpectatorMessage},
+        UserId,
+    },
+    game_server::{Attached, GameServer, SpectatorId, Watching},
+    session::AuthenticatedUser,
+};
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream, Session};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub(crate) struct ConnectQuery {
+    /// The last event the client received before it was disconnected, if it's resuming.
+    last_seq: Option<u64>,
+}
+
+#[get("/ws")]
+#[tracing::instrument(name = "Opening a web socket", skip(req, body, server, settings, user, query), fields(user_id = user.id()))]
+pub(crate) async fn web_socket(
+    req: HttpRequest,
+    body: web::Payload,
+    server: web::Data<GameServer>,
+    settings: web::Data<WebSocketSettings>,
+    user: AuthenticatedUser,
+    query: web::Query<ConnectQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (response, session, stream) = actix_ws::handle(&req, body)?;
+    let attached = server.hub().attach(user.id(), query.last_seq);
+    actix_web::rt::spawn(run_connection(
+        server,
+        settings.get_ref().clone(),
+        user.id(),
+        attached,
+        session,
+        stream,
+    ));
+    Ok(response)
+}
+
+/// Runs a connection until either side goes away, then cleans up after it.
+async fn run_connection(
+    server: web::Data<GameServer>,
+    settings: WebSocketSettings,
+    user_id: UserId,
+    attached: Attached,
+    mut session: Session,
+    stream: MessageStream,
+) {
+    let connection_id = attached.connection_id;
+    let resumed = attached.resumed;
+    server
+        .hub()
+        .send(user_id, ServerEvent::Connected { user_id, resumed });
+    server.player_connected(user_id, resumed);
+    server.refresh_presence(&[user_id]).await;
+    let close_reason = pump(&server, &settings, user_id, attached, &mut session, stream).await;
+    server.hub().detach(user_id, connection_id);
+    server.player_disconnected(user_id);
+    server.refresh_presence(&[user_id]).await;
+    let _ = session.close(close_reason).await;
+}
+
+/// Sends events to the client and handles messages from it. Returns why the connection
+/// should be closed.
+async fn pump(
+    server: &GameServer,
+    settings: &WebSocketSettings,
+    user_id: UserId,
+    attached: Attached,
+    session: &mut Session,
+    mut stream: MessageStream,
+) -> Option<CloseReason> {
+    let Attached {
+        mut receiver,
+        replay,
+        ..
+    } = attached;
+    for message in replay {
+        send(session, &message).await.ok()?;
+    }
+    let mut heartbeat = tokio::time::interval(settings.heartbeat_interval);
+    let mut last_heard_from = Instant::now();
+    loop {
+        tokio::select! {
+            outgoing = receiver.recv() => match outgoing {
+                Some(message) => send(session, &message).await.ok()?,
+                // The hub dropped us, either for being too slow or for a newer connection.
+                None => return Some(CloseReason {
+                    code: CloseCode::Policy,
+                    description: Some(String::from("Connection replaced or too far behind")),
+                }),
+            },
+            incoming = stream.recv() => {
+                last_heard_from = Instant::now();
+                match incoming? {
+                    Ok(Message::Text(text)) => handle_text(server, user_id, &text).await,
+                    Ok(Message::Ping(bytes)) => session.pong(&bytes).await.ok()?,
+                    Ok(Message::Close(reason)) => return reason,
+                    Ok(_) => {}
+                    Err(error) => {
+                        tracing::debug!(?error, "Web socket protocol error");
+                        return Some(CloseCode::Protocol.into());
+                    }
+                }
+            },
+            _ = heartbeat.tick() => {
+                if last_heard_from.elapsed() > settings.client_timeout {
+                    return Some(CloseReason {
+                        code: CloseCode::Away,
+                        description: Some(String::from("Heartbeat timed out")),
+                    });
+                }
+                session.ping(b"").await.ok()?;
+            },
+        }
+    }
+}
+
+async fn handle_text(server: &GameServer, user_id: UserId, text: &str) {
+    match serde_json::from_str::<ClientMessage>(text) {
+        Ok(message) => {
+            if let Err(error) = server.handle_client_message(user_id, message).await {
+                tracing::error!(?error, "Failed to handle a client message");
+                server.send_error(user_id, "Something went wrong handling that message");
+            }
+        }
+        Err(error) => server.send_error(user_id, &format!("Invalid message: {}", error)),
+    }
+}
+
+async fn send(session: &mut Session, message: &ServerMessage) -> Result<(), actix_ws::Closed> {
+    let json = serde_json::to_string(message).expect("server messages always serialize");
+    session.text(json).await
+}
+
+#[get("/matches/{match_id}/spectate")]
+#[tracing::instrument(name = "Spectating a match", skip(req, body, server, settings, user), fields(user_id = user.id()))]
+pub(crate) async fn spectate(
+    req: HttpRequest,
+    body: web::Payload,
+    server: web::Data<GameServer>,
+    settings: web::Data<WebSocketSettings>,
+    user: AuthenticatedUser,
+    match_id: web::Path<i64>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let match_id = match_id.into_inner();
+    let (response, session, stream) = actix_ws::handle(&req, body)?;
+    let watching = server
+        .watch_match(match_id, user.id())
+        .map_err(ServerError)?;
+    actix_web::rt::spawn(run_spectator(
+        server,
+        settings.get_ref().clone(),
+        match_id,
+        watching,
+        session,
+        stream,
+    ));
+    Ok(response)
+}
+
+/// Runs a spectator's connection until either side goes away or the match is over.
+async fn run_spectator(
+    server: web::Data<GameServer>,
+    settings: WebSocketSettings,
+    match_id: i64,
+    watching: Watching,
+    mut session: Session,
+    stream: MessageStream,
+) {
+    let spectator_id = watching.spectator_id;
+    let close_reason =
+        pump_spectator(&server, &settings, match_id, watching, &mut session, stream).await;
+    server.stop_watching(match_id, spectator_id);
+    let _ = session.close(close_reason).await;
+}
+
+/// Sends spectator events to the client, and handles its requests to switch focus.
+async fn pump_spectator(
+    server: &GameServer,
+    settings: &WebSocketSettings,
+    match_id: i64,
+    watching: Watching,
+    session: &mut Session,
+    mut stream: MessageStream,
+) -> Option<CloseReason> {
+    let Watching {
+        spectator_id,
+        mut receiver,
+    } = watching;
+    let mut heartbeat = tokio::time::interval(settings.heartbeat_interval);
+    let mut last_heard_from = Instant::now();
+    let mut finished = false;
+    loop {
+        tokio::select! {
+            outgoing = receiver.recv() => match outgoing {
+                Some(event) => {
+                    finished = matches!(event, SpectatorEvent::MatchFinished { .. });
+                    send_spectator(session, &event).await.ok()?;
+                }
+                None if finished => return Some(CloseReason {
+                    code: CloseCode::Normal,
+                    description: Some(String::from("The match is over")),
+                }),
+                None => return Some(CloseReason {
+                    code: CloseCode::Policy,
+                    description: Some(String::from("Too far behind")),
+                }),
+            },
+            incoming = stream.recv() => {
+                last_heard_from = Instant::now();
+                match incoming? {
+                    Ok(Message::Text(text)) => {
+                        handle_spectator_text(server, match_id, spectator_id, &text)
+                    }
+                    Ok(Message::Ping(bytes)) => session.pong(&bytes).await.ok()?,
+                    Ok(Message::Close(reason)) => return reason,
+                    Ok(_) => {}
+                    Err(error) => {
+                        tracing::debug!(?error, "Web socket protocol error");
+                        return Some(CloseCode::Protocol.into());
+                    }
+                }
+            },
+            _ = heartbeat.tick() => {
+                if last_heard_from.elapsed() > settings.client_timeout {
+                    return Some(CloseReason {
+                        code: CloseCode::Away,
+                        description: Some(String::from("Heartbeat timed out")),
+                    });
+                }
+                session.ping(b"").await.ok()?;
+            },
+        }
+    }
+}
+
+fn handle_spectator_text(
+    server: &GameServer,
+    match_id: i64,
+    spectator_id: SpectatorId,
+    text: &str,
+) {
+    match serde_json::from_str::<SpectatorMessage>(text) {
+        Ok(SpectatorMessage::Focus { user_id }) => {
+            server.focus_spectator(match_id, spectator_id, user_id)
+        }
+        Err(error) => server.send_spectator_error(
+            match_id,
+            spectator_id,
+            &format!("Invalid message: {}", error),
+        ),
+    }
+}
+
+async fn send_spectator(
+    session: &mut Session,
+    event: &SpectatorEvent,
+) -> Result<(), actix_ws::Closed> {
+    let json = serde_json::to_string(event).expect("spectator events always serialize");
+    session.text(json).await
+}
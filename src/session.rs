@@ -0,0 +1,113 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use actix_web::{cookie::Cookie, dev::Payload, web, FromRequest, HttpRequest};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use deadpool_sqlite::rusqlite::types::Value;
+use snafu::ensure;
+
+use crate::{
+    db_handle::DbHandle,
+    domain::{errors::*, User, UserId},
+};
+
+/// The name of the cookie holding the session ID.
+pub(crate) const SESSION_COOKIE: &str = "session_id";
+
+/// Creates a new session for the user and returns the cookie that identifies it. `address` is
+/// where they logged in from, if it's known.
+#[tracing::instrument(name = "Creating a session", skip(db_handle))]
+pub(crate) async fn create_session(
+    db_handle: &DbHandle,
+    user_id: UserId,
+    address: Option<String>,
+) -> Result<Cookie<'static>, InnerError> {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let session_id: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    db_handle
+        .execute(
+            "INSERT INTO Session (Id, UserId, CreatedAt, Address) VALUES (?1, ?2, ?3, ?4)",
+            [
+                Value::from(session_id.clone()),
+                Value::from(user_id),
+                Value::from(unix_timestamp()),
+                Value::from(address),
+            ],
+        )
+        .await?;
+    Ok(Cookie::build(SESSION_COOKIE, session_id)
+        .path("/")
+        .http_only(true)
+        .finish())
+}
+
+/// Seconds since the Unix epoch, which is how timestamps are stored in the database.
+pub(crate) fn unix_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("the system clock is after 1970")
+        .as_secs() as i64
+}
+
+/// Milliseconds since the Unix epoch, for when seconds aren't precise enough.
+pub(crate) fn unix_timestamp_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("the system clock is after 1970")
+        .as_millis() as i64
+}
+
+/// The user making the request, according to their session cookie, and whether they're a
+/// moderator. Using this as a handler parameter makes the route respond with a 401 when
+/// there's no valid session.
+#[derive(Clone, Debug)]
+pub(crate) struct AuthenticatedUser(pub(crate) User, bool);
+
+impl AuthenticatedUser {
+    pub(crate) fn id(&self) -> UserId {
+        self.0.id()
+    }
+
+    /// Fails unless they're a moderator, for the routes only moderators can use.
+    pub(crate) fn ensure_moderator(&self) -> Result<(), InnerError> {
+        ensure!(self.1, NotModeratorSnafu);
+        Ok(())
+    }
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = ServerError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let session_id = req
+            .cookie(SESSION_COOKIE)
+            .map(|cookie| cookie.value().to_string());
+        let db_handle = req.app_data::<web::Data<DbHandle>>().cloned();
+        Box::pin(async move {
+            let (session_id, db_handle) = match (session_id, db_handle) {
+                (Some(session_id), Some(db_handle)) => (session_id, db_handle),
+                _ => return Err(ServerError(InnerError::Unauthenticated)),
+            };
+            let user = db_handle
+                .query_row(
+                    "SELECT User.Id, User.Username, User.IsModerator FROM Session
+                     INNER JOIN User ON User.Id = Session.UserId
+                     WHERE Session.Id = ?1",
+                    [session_id],
+                    |row| {
+                        Ok(AuthenticatedUser(
+                            User::new(row.get(0)?, row.get(1)?),
+                            row.get(2)?,
+                        ))
+                    },
+                )
+                .await?;
+            user.ok_or(ServerError(InnerError::Unauthenticated))
+        })
+    }
+}
@@ -0,0 +1,297 @@
+//! Keeping players' statistics and achievements up to date. What's counted, and what it takes
+//! to unlock each achievement, lives in [`crate::domain::stats`].
+
+use std::sync::Arc;
+
+use deadpool_sqlite::rusqlite::{params, Connection, Error, OptionalExtension};
+use serde::Serialize;
+use snafu::ResultExt;
+
+use crate::{
+    db_handle::DbHandle,
+    domain::{
+        errors::*,
+        notifications::NotificationKind,
+        stats::{newly_unlocked, Achievement, FastestClear, MatchStats, PlayerStats},
+        User, UserId,
+    },
+    matches::{load_finished_match, parse_match_log, read_match_log},
+    notifications::Notifications,
+};
+
+/// A player's stats, as they're shown on their profile.
+#[derive(Serialize)]
+pub(crate) struct ProfileStats {
+    #[serde(flatten)]
+    pub(crate) stats: PlayerStats,
+    pub(crate) average_placement: Option<f64>,
+}
+
+/// An achievement a player has unlocked.
+#[derive(Serialize)]
+pub(crate) struct UnlockedAchievement {
+    #[serde(flatten)]
+    pub(crate) achievement: &'static Achievement,
+    /// The match that unlocked it.
+    pub(crate) match_id: i64,
+    pub(crate) unlocked_at: i64,
+}
+
+/// Adds a finished match onto its players' stats in the background, and notifies them about
+/// any achievements they unlocked. Anything that goes wrong is only logged, since nobody's
+/// waiting on the result.
+pub(crate) fn spawn_stats_update(
+    db_handle: DbHandle,
+    notifications: Arc<Notifications>,
+    match_id: i64,
+) {
+    tokio::spawn(async move {
+        match update_stats(&db_handle, match_id).await {
+            Ok(unlocked) => {
+                for (user_id, achievement) in unlocked {
+                    tracing::info!(
+                        match_id,
+                        user_id,
+                        achievement = achievement.id,
+                        "Unlocked an achievement"
+                    );
+                    notifications.notify(
+                        vec![user_id],
+                        NotificationKind::AchievementUnlocked {
+                            achievement_id: achievement.id.to_string(),
+                            name: achievement.name.to_string(),
+                            match_id,
+                        },
+                    );
+                }
+            }
+            Err(error) => tracing::error!(?error, match_id, "Failed to update stats for a match"),
+        }
+    });
+}
+
+/// Adds a finished match onto its players' stats and unlocks whatever achievements that
+/// earns them. Matches that have already been counted are left alone. Returns the
+/// achievements that were unlocked.
+#[tracing::instrument(name = "Updating stats for a match", skip(db_handle))]
+pub(crate) async fn update_stats(
+    db_handle: &DbHandle,
+    match_id: i64,
+) -> Result<Vec<(UserId, &'static Achievement)>, InnerError> {
+    let (records, _) = load_finished_match(db_handle, match_id).await?;
+    let stats = MatchStats::from_log(&records).context(StatsSnafu)?;
+    db_handle
+        .transaction(move |transaction| record_match_stats(transaction, match_id, &stats))
+        .await
+}
+
+fn record_match_stats(
+    connection: &Connection,
+    match_id: i64,
+    stats: &[MatchStats],
+) -> Result<Vec<(UserId, &'static Achievement)>, Error> {
+    let claimed = connection.execute(
+        "UPDATE GameMatch SET StatsRecorded = 1
+         WHERE Id = ?1 AND StatsRecorded = 0 AND FinishedAt IS NOT NULL",
+        params![match_id],
+    )?;
+    if claimed == 0 {
+        return Ok(vec![]);
+    }
+    // Timestamps come from the match rather than the clock, so backfilled achievements are
+    // dated to when they were actually earned.
+    let finished_at: i64 = connection.query_row(
+        "SELECT FinishedAt FROM GameMatch WHERE Id = ?1",
+        params![match_id],
+        |row| row.get(0),
+    )?;
+    let mut unlocked = vec![];
+    for player in stats {
+        let is_bot: bool = connection.query_row(
+            "SELECT IsBot FROM User WHERE Id = ?1",
+            params![player.user_id],
+            |row| row.get(0),
+        )?;
+        if is_bot {
+            continue;
+        }
+        let mut totals = stats_of(connection, player.user_id)?.unwrap_or_default();
+        totals.record(match_id, player);
+        save_stats(connection, player.user_id, &totals, finished_at)?;
+        let already = connection
+            .prepare("SELECT AchievementId FROM PlayerAchievement WHERE UserId = ?1")?
+            .query_map(params![player.user_id], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        for achievement in newly_unlocked(&totals, player, &already) {
+            connection.execute(
+                "INSERT INTO PlayerAchievement (UserId, AchievementId, MatchId, UnlockedAt)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![player.user_id, achievement.id, match_id, finished_at],
+            )?;
+            unlocked.push((player.user_id, achievement));
+        }
+    }
+    Ok(unlocked)
+}
+
+fn stats_of(connection: &Connection, user_id: UserId) -> Result<Option<PlayerStats>, Error> {
+    connection
+        .query_row(
+            "SELECT MatchesPlayed, Wins, PlacementTotal, CellsRevealed, MinesFlagged, Clears,
+                 FastestClearMs, FastestClearMatchId
+             FROM PlayerStats WHERE UserId = ?1",
+            params![user_id],
+            |row| {
+                let fastest_ms: Option<i64> = row.get(6)?;
+                let fastest_match: Option<i64> = row.get(7)?;
+                Ok(PlayerStats {
+                    matches_played: row.get::<_, i64>(0)? as u64,
+                    wins: row.get::<_, i64>(1)? as u64,
+                    placement_total: row.get::<_, i64>(2)? as u64,
+                    cells_revealed: row.get::<_, i64>(3)? as u64,
+                    mines_flagged: row.get::<_, i64>(4)? as u64,
+                    clears: row.get::<_, i64>(5)? as u64,
+                    fastest_clear: fastest_ms.zip(fastest_match).map(|(time_ms, match_id)| {
+                        FastestClear {
+                            match_id,
+                            time_ms: time_ms as u64,
+                        }
+                    }),
+                })
+            },
+        )
+        .optional()
+}
+
+fn save_stats(
+    connection: &Connection,
+    user_id: UserId,
+    stats: &PlayerStats,
+    updated_at: i64,
+) -> Result<(), Error> {
+    connection.execute(
+        "INSERT INTO PlayerStats (UserId, MatchesPlayed, Wins, PlacementTotal, CellsRevealed,
+             MinesFlagged, Clears, FastestClearMs, FastestClearMatchId, UpdatedAt)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+         ON CONFLICT (UserId) DO UPDATE SET
+             MatchesPlayed = excluded.MatchesPlayed,
+             Wins = excluded.Wins,
+             PlacementTotal = excluded.PlacementTotal,
+             CellsRevealed = excluded.CellsRevealed,
+             MinesFlagged = excluded.MinesFlagged,
+             Clears = excluded.Clears,
+             FastestClearMs = excluded.FastestClearMs,
+             FastestClearMatchId = excluded.FastestClearMatchId,
+             UpdatedAt = excluded.UpdatedAt",
+        params![
+            user_id,
+            stats.matches_played as i64,
+            stats.wins as i64,
+            stats.placement_total as i64,
+            stats.cells_revealed as i64,
+            stats.mines_flagged as i64,
+            stats.clears as i64,
+            stats.fastest_clear.map(|fastest| fastest.time_ms as i64),
+            stats.fastest_clear.map(|fastest| fastest.match_id),
+            updated_at,
+        ],
+    )?;
+    Ok(())
+}
+
+/// A player, with their stats and the achievements they've unlocked in the order they were
+/// unlocked. Players who haven't finished a match yet have empty stats.
+pub(crate) async fn profile_of(
+    db_handle: &DbHandle,
+    user_id: UserId,
+) -> Result<(User, ProfileStats, Vec<UnlockedAchievement>), InnerError> {
+    let profile = db_handle
+        .transaction(move |transaction| {
+            let username: Option<String> = transaction
+                .query_row(
+                    "SELECT Username FROM User WHERE Id = ?1",
+                    params![user_id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let username = match username {
+                Some(username) => username,
+                None => return Ok(None),
+            };
+            let stats = stats_of(transaction, user_id)?.unwrap_or_default();
+            let achievements = transaction
+                .prepare(
+                    "SELECT AchievementId, MatchId, UnlockedAt FROM PlayerAchievement
+                     WHERE UserId = ?1
+                     ORDER BY UnlockedAt, MatchId",
+                )?
+                .query_map(params![user_id], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?))
+                })?
+                .collect::<Result<Vec<(String, i64, i64)>, _>>()?;
+            Ok(Some((User::new(user_id, username), stats, achievements)))
+        })
+        .await?;
+    let (user, stats, achievements) = match profile {
+        Some(profile) => profile,
+        None => return UserNotFoundSnafu { user_id }.fail(),
+    };
+    let achievements = achievements
+        .into_iter()
+        // Achievements that have since been taken out of the list aren't shown.
+        .filter_map(|(id, match_id, unlocked_at)| {
+            Achievement::find(&id).map(|achievement| UnlockedAchievement {
+                achievement,
+                match_id,
+                unlocked_at,
+            })
+        })
+        .collect();
+    Ok((
+        user,
+        ProfileStats {
+            average_placement: stats.average_placement(),
+            stats,
+        },
+        achievements,
+    ))
+}
+
+/// Works everyone's stats out again from scratch, from every finished match in the order they
+/// finished, and unlocks any achievements that were missed. Achievements players already have
+/// keep the time they were unlocked. Matches whose logs can't be read are skipped, and so are
+/// daily challenge attempts, which aren't counted when they finish either. Returns how many
+/// matches were counted.
+///
+/// It all happens in one transaction, so if anything else goes wrong the old stats are left
+/// as they were. It's still meant to be run while the server isn't, since it holds the write
+/// lock until it's done.
+#[tracing::instrument(name = "Backfilling stats", skip(db_handle))]
+pub(crate) async fn backfill_stats(db_handle: &DbHandle) -> Result<usize, InnerError> {
+    db_handle
+        .transaction(|transaction| {
+            transaction.execute("DELETE FROM PlayerStats", [])?;
+            transaction.execute("UPDATE GameMatch SET StatsRecorded = 0", [])?;
+            let match_ids = transaction
+                .prepare(
+                    "SELECT Id FROM GameMatch WHERE FinishedAt IS NOT NULL AND Mode != 'daily'
+                     ORDER BY FinishedAt, Id",
+                )?
+                .query_map([], |row| row.get(0))?
+                .collect::<Result<Vec<i64>, _>>()?;
+            let mut counted = 0;
+            for match_id in match_ids {
+                let stats = parse_match_log(read_match_log(transaction, match_id)?)
+                    .and_then(|records| MatchStats::from_log(&records).context(StatsSnafu));
+                match stats {
+                    Ok(stats) => {
+                        record_match_stats(transaction, match_id, &stats)?;
+                        counted += 1;
+                    }
+                    Err(error) => tracing::warn!(?error, match_id, "Skipping a match"),
+                }
+            }
+            Ok(counted)
+        })
+        .await
+}
@@ -0,0 +1,192 @@
+mod redaction;
+
+use opentelemetry::{
+    global,
+    sdk::{propagation::TraceContextPropagator, trace, Resource},
+    KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use snafu::{ResultExt, Whatever};
+use tracing::{subscriber::set_global_default, Subscriber};
+use tracing_appender::{
+    non_blocking::{NonBlocking, WorkerGuard},
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
+use tracing_log::LogTracer;
+use tracing_subscriber::{
+    fmt::{self, MakeWriter},
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    reload, EnvFilter, Layer, Registry,
+};
+
+use self::redaction::Redacting;
+use crate::{
+    config::{
+        LogFileSettings, LogFormat, LogRotation, OtlpProtocol, OtlpSettings, TelemetrySettings,
+    },
+    domain::errors::*,
+};
+
+/// Changes which spans and events are recorded while the server's running.
+#[derive(Clone, Debug)]
+pub struct LogFilter(reload::Handle<EnvFilter, Registry>);
+
+impl LogFilter {
+    /// The filter's directives, in the same form `RUST_LOG` takes.
+    pub(crate) fn current(&self) -> Result<String, InnerError> {
+        self.0
+            .with_current(|filter| filter.to_string())
+            .context(LogFilterReloadSnafu)
+    }
+
+    /// Replaces the filter with `directives`, which take the same form as `RUST_LOG`.
+    pub(crate) fn set(&self, directives: &str) -> Result<(), InnerError> {
+        let filter = EnvFilter::try_new(directives).context(InvalidLogFilterSnafu)?;
+        self.0.reload(filter).context(LogFilterReloadSnafu)
+    }
+}
+
+/// Keeps the thread that writes log files running. Whatever's still waiting to be written is
+/// flushed when it's dropped, so it needs holding on to until the server has shut down.
+#[must_use]
+pub struct LogGuard {
+    _worker: Option<WorkerGuard>,
+}
+
+/// Builds the subscriber that logs to `sink` (and anywhere else `telemetry` says to), along
+/// with the handle for changing its filter later and the guard for its log files.
+/// `env_filter` is only used if `RUST_LOG` isn't set. Secrets are redacted before they're
+/// logged or exported.
+///
+/// Exporting spans has to be set up from inside a Tokio runtime, since they're sent off in
+/// batches in the background.
+pub fn get_subscriber<Sink>(
+    name: impl Into<String>,
+    env_filter: impl Into<String>,
+    sink: Sink,
+    telemetry: &TelemetrySettings,
+) -> Result<(impl Subscriber + Send + Sync, LogFilter, LogGuard), Whatever>
+where
+    Sink: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let name = name.into();
+    let env_filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(env_filter.into()));
+    let (env_filter, log_filter) = reload::Layer::new(env_filter);
+    let otlp_layer = match &telemetry.otlp {
+        Some(otlp) => Some(tracing_opentelemetry::layer().with_tracer(otlp_tracer(&name, otlp)?)),
+        None => None,
+    };
+    let (file_layer, guard) = match &telemetry.file {
+        Some(file) => {
+            let (writer, guard) = log_files(file)?;
+            let layer = formatting_layer(name.clone(), telemetry.format, writer, false);
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+    let recorders = JsonStorageLayer
+        .and_then(otlp_layer)
+        .and_then(formatting_layer(name, telemetry.format, sink, true))
+        .and_then(file_layer);
+    let subscriber = Registry::default()
+        .with(env_filter)
+        .with(Redacting::new(recorders));
+    Ok((
+        subscriber,
+        LogFilter(log_filter),
+        LogGuard { _worker: guard },
+    ))
+}
+
+/// A layer that writes events to `writer` in `format`. Colours are only used for the pretty
+/// format, and only if `ansi` is set.
+fn formatting_layer<S, W>(
+    name: String,
+    format: LogFormat,
+    writer: W,
+    ansi: bool,
+) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    match format {
+        LogFormat::Bunyan => Box::new(BunyanFormattingLayer::new(name, writer)),
+        LogFormat::Pretty => Box::new(fmt::layer().pretty().with_ansi(ansi).with_writer(writer)),
+        LogFormat::Logfmt => Box::new(tracing_logfmt::builder().layer().with_writer(writer)),
+    }
+}
+
+/// Opens the log files, with the writing done on a thread of its own so requests don't wait
+/// on the disk.
+fn log_files(file: &LogFileSettings) -> Result<(NonBlocking, WorkerGuard), Whatever> {
+    let rotation = match file.rotation {
+        LogRotation::Minutely => Rotation::MINUTELY,
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Never => Rotation::NEVER,
+    };
+    let appender = RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(&file.prefix)
+        .build(&file.directory)
+        .with_whatever_context(|e| {
+            format!(
+                "Failed to open log files in {}: {:?}",
+                file.directory.display(),
+                e
+            )
+        })?;
+    Ok(tracing_appender::non_blocking(appender))
+}
+
+/// Sets up an OTLP exporter as the global tracer provider, and returns a tracer from it.
+fn otlp_tracer(service_name: &str, otlp: &OtlpSettings) -> Result<trace::Tracer, Whatever> {
+    let endpoint = otlp.endpoint.trim_end_matches('/');
+    let pipeline = opentelemetry_otlp::new_pipeline().tracing();
+    let pipeline = match otlp.protocol {
+        OtlpProtocol::Grpc => pipeline.with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        ),
+        OtlpProtocol::Http => pipeline.with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .http()
+                .with_endpoint(format!("{}/v1/traces", endpoint)),
+        ),
+    };
+    pipeline
+        .with_trace_config(trace::config().with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            service_name.to_string(),
+        )])))
+        .install_batch(opentelemetry::runtime::Tokio)
+        .with_whatever_context(|e| format!("Failed to set up the OTLP exporter: {:?}", e))
+}
+
+/// Sets `subscriber` as the global default. Trace context is read from incoming requests'
+/// `traceparent` headers from here on, so exported spans join the caller's trace.
+pub fn init_subscriber(subscriber: impl Subscriber + Send + Sync) -> Result<(), Whatever> {
+    LogTracer::init()
+        .with_whatever_context(|e| format!("Failed to init the LogTracer: {:?}", e))?;
+    set_global_default(subscriber).with_whatever_context(|e| {
+        format!("Could not set the global Tracing subscriber: {:?}", e)
+    })?;
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(())
+}
+
+/// Sends off any spans that haven't been exported yet and stops exporting, then finishes
+/// writing the log files.
+pub async fn shutdown_tracing(log_guard: LogGuard) {
+    // Both wait on background threads, so they mustn't block a runtime thread.
+    let _ = tokio::task::spawn_blocking(move || {
+        global::shutdown_tracer_provider();
+        drop(log_guard);
+    })
+    .await;
+}
@@ -0,0 +1,281 @@
+//! Masking secrets before anything that records spans and events gets to see them.
+//!
+//! Routes are expected to `skip` anything sensitive when they're instrumented, but this is
+//! the backstop for when one doesn't: passwords, hashes, tokens and session IDs are replaced
+//! with [`REDACTED`] whether they turn up as a field of their own or inside some other value.
+
+use std::any::TypeId;
+
+use regex::Regex;
+use tracing::{
+    field::{display, Field, Visit},
+    span::{Attributes, Id, Record},
+    Dispatch, Event, Metadata, Subscriber, Value,
+};
+use tracing_subscriber::{
+    layer::{Context, Layer},
+    registry::LookupSpan,
+};
+
+/// What secrets are replaced with.
+pub(crate) const REDACTED: &str = "[REDACTED]";
+
+/// Fields whose names contain any of these have their whole value masked. Names are compared
+/// in lowercase, so `PasswordHash` counts as well as `password_hash`.
+const SENSITIVE_NAMES: [&str; 10] = [
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "authorization",
+    "cookie",
+    "session_id",
+    "sessionid",
+    "email",
+    "api_key",
+];
+
+/// Patterns for secrets that can turn up inside any value, like a message or a debug-printed
+/// request.
+const SENSITIVE_PATTERNS: [&str; 3] = [
+    // PHC strings, which is how password hashes are stored: `$argon2id$v=19$m=...$salt$hash`.
+    r"\$[a-z0-9-]{1,32}(?:\$[^$\s]+){2,}",
+    // Bearer tokens, as they appear in `Authorization` headers.
+    r"(?i)\bbearer\s+[a-z0-9._~+/=-]+",
+    // Session IDs, which are 32 random bytes in hex.
+    r"\b[0-9a-fA-F]{64}\b",
+];
+
+/// Events and spans are rebuilt with their values redacted, and the rebuilt ones can only hold
+/// this many values. Any more than that are dropped.
+const MAX_VALUES: usize = 32;
+
+/// Wraps the layers that record spans and events, so they only ever see redacted values.
+pub(crate) struct Redacting<L> {
+    inner: L,
+    patterns: Vec<Regex>,
+}
+
+impl<L> Redacting<L> {
+    pub(crate) fn new(inner: L) -> Self {
+        let patterns = SENSITIVE_PATTERNS
+            .iter()
+            .map(|pattern| Regex::new(pattern).expect("redaction patterns are valid"))
+            .collect();
+        Self { inner, patterns }
+    }
+
+    /// Visits every value in `record`, and returns them all if any were redacted.
+    fn redact(&self, record: impl FnOnce(&mut dyn Visit)) -> Option<Vec<(Field, Box<dyn Value>)>> {
+        let mut redactor = Redactor {
+            patterns: &self.patterns,
+            values: vec![],
+            redacted: false,
+        };
+        record(&mut redactor);
+        redactor.redacted.then_some(redactor.values)
+    }
+}
+
+/// Collects a copy of every value it visits, with secrets masked.
+struct Redactor<'a> {
+    patterns: &'a [Regex],
+    values: Vec<(Field, Box<dyn Value>)>,
+    redacted: bool,
+}
+
+impl Redactor<'_> {
+    fn push(&mut self, field: &Field, value: Box<dyn Value>) {
+        self.values.push((field.clone(), value));
+    }
+
+    /// Whether the field's value should be masked no matter what it is.
+    fn is_sensitive(&mut self, field: &Field) -> bool {
+        let name = field.name().to_lowercase();
+        let sensitive = SENSITIVE_NAMES.iter().any(|secret| name.contains(secret));
+        self.redacted |= sensitive;
+        sensitive
+    }
+
+    /// `text` with anything that looks like a secret masked.
+    fn mask(&mut self, text: &str) -> String {
+        let mut masked = text.to_string();
+        for pattern in self.patterns {
+            if pattern.is_match(&masked) {
+                self.redacted = true;
+                masked = pattern.replace_all(&masked, REDACTED).into_owned();
+            }
+        }
+        masked
+    }
+
+    fn record_value(&mut self, field: &Field, value: Box<dyn Value>) {
+        if self.is_sensitive(field) {
+            self.push(field, Box::new(REDACTED));
+        } else {
+            self.push(field, value);
+        }
+    }
+}
+
+impl Visit for Redactor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if self.is_sensitive(field) {
+            self.push(field, Box::new(REDACTED));
+        } else {
+            let masked = self.mask(value);
+            self.push(field, Box::new(masked));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if self.is_sensitive(field) {
+            self.push(field, Box::new(REDACTED));
+        } else {
+            let masked = self.mask(&format!("{:?}", value));
+            self.push(field, Box::new(display(masked)));
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record_value(field, Box::new(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record_value(field, Box::new(value));
+    }
+
+    fn record_i128(&mut self, field: &Field, value: i128) {
+        self.record_value(field, Box::new(value));
+    }
+
+    fn record_u128(&mut self, field: &Field, value: u128) {
+        self.record_value(field, Box::new(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.record_value(field, Box::new(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.record_value(field, Box::new(value));
+    }
+}
+
+/// Lines `values` up the way `FieldSet::value_set` wants them. Slots past the end are filled
+/// with the first field and no value, which is skipped when the set is recorded.
+fn value_slots(values: &[(Field, Box<dyn Value>)]) -> [(&Field, Option<&dyn Value>); MAX_VALUES] {
+    let first = &values[0].0;
+    let mut slots = [(first, None); MAX_VALUES];
+    for (slot, (field, value)) in slots.iter_mut().zip(values) {
+        *slot = (field, Some(value.as_ref()));
+    }
+    slots
+}
+
+impl<S, L> Layer<S> for Redacting<L>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    L: Layer<S>,
+{
+    fn on_register_dispatch(&self, subscriber: &Dispatch) {
+        self.inner.on_register_dispatch(subscriber);
+    }
+
+    fn on_layer(&mut self, subscriber: &mut S) {
+        self.inner.on_layer(subscriber);
+    }
+
+    fn register_callsite(
+        &self,
+        metadata: &'static Metadata<'static>,
+    ) -> tracing::subscriber::Interest {
+        self.inner.register_callsite(metadata)
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, ctx: Context<'_, S>) -> bool {
+        self.inner.enabled(metadata, ctx)
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let values = match self.redact(|visitor| attrs.record(visitor)) {
+            Some(values) => values,
+            None => return self.inner.on_new_span(attrs, id, ctx),
+        };
+        let metadata = attrs.metadata();
+        let slots = value_slots(&values);
+        let value_set = metadata.fields().value_set(&slots);
+        let redacted = if attrs.is_root() {
+            Attributes::new_root(metadata, &value_set)
+        } else if let Some(parent) = attrs.parent() {
+            Attributes::child_of(parent.clone(), metadata, &value_set)
+        } else {
+            Attributes::new(metadata, &value_set)
+        };
+        self.inner.on_new_span(&redacted, id, ctx);
+    }
+
+    fn on_record(&self, span: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let redacted = match self.redact(|visitor| values.record(visitor)) {
+            Some(redacted) => redacted,
+            None => return self.inner.on_record(span, values, ctx),
+        };
+        let metadata = match ctx.metadata(span) {
+            Some(metadata) => metadata,
+            None => return,
+        };
+        let slots = value_slots(&redacted);
+        let value_set = metadata.fields().value_set(&slots);
+        self.inner.on_record(span, &Record::new(&value_set), ctx);
+    }
+
+    fn on_follows_from(&self, span: &Id, follows: &Id, ctx: Context<'_, S>) {
+        self.inner.on_follows_from(span, follows, ctx);
+    }
+
+    fn event_enabled(&self, event: &Event<'_>, ctx: Context<'_, S>) -> bool {
+        self.inner.event_enabled(event, ctx)
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let values = match self.redact(|visitor| event.record(visitor)) {
+            Some(values) => values,
+            None => return self.inner.on_event(event, ctx),
+        };
+        let metadata = event.metadata();
+        let slots = value_slots(&values);
+        let value_set = metadata.fields().value_set(&slots);
+        let redacted = if event.is_contextual() {
+            Event::new(metadata, &value_set)
+        } else {
+            Event::new_child_of(event.parent().cloned(), metadata, &value_set)
+        };
+        self.inner.on_event(&redacted, ctx);
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        self.inner.on_enter(id, ctx);
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        self.inner.on_exit(id, ctx);
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        self.inner.on_close(id, ctx);
+    }
+
+    fn on_id_change(&self, old: &Id, new: &Id, ctx: Context<'_, S>) {
+        self.inner.on_id_change(old, new, ctx);
+    }
+
+    // Layers find each other by downcasting (it's how spans get their OpenTelemetry parent),
+    // so the ones inside have to stay reachable.
+    unsafe fn downcast_raw(&self, id: TypeId) -> Option<*const ()> {
+        if id == TypeId::of::<Self>() {
+            Some(self as *const Self as *const ())
+        } else {
+            self.inner.downcast_raw(id)
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Storing tournaments. The rules for how they're run live in [`crate::domain::tournament`],
+//! and the game server is what plays their heats.
+
+use std::collections::HashMap;
+
+use deadpool_sqlite::rusqlite::{params, Connection, Error, OptionalExtension};
+use serde::Serialize;
+use snafu::ResultExt;
+
+use crate::{
+    db_handle::DbHandle,
+    domain::{
+        errors::*,
+        lobby::GameMode,
+        rating::RatingSettings,
+        tournament::{Tournament, TournamentError, TournamentFormat, TournamentStatus},
+        User, UserId,
+    },
+    ratings::current_rating,
+    session::{unix_timestamp, unix_timestamp_millis},
+};
+
+/// A tournament as it's listed, without its rounds.
+#[derive(Serialize)]
+pub(crate) struct TournamentSummary {
+    pub(crate) id: i64,
+    pub(crate) name: String,
+    pub(crate) status: TournamentStatus,
+    pub(crate) starts_at: i64,
+    pub(crate) entrants: usize,
+    pub(crate) winner: Option<User>,
+}
+
+/// How a heat's match went.
+pub(crate) enum HeatMatch {
+    /// The players in the order they finished.
+    Finished(Vec<UserId>),
+    /// It was never finished and never will be.
+    Lost,
+}
+
+pub(crate) async fn create_tournament(
+    db_handle: &DbHandle,
+    created_by: UserId,
+    name: String,
+    format: TournamentFormat,
+    starts_at: i64,
+) -> Result<Tournament, InnerError> {
+    let now = unix_timestamp_millis();
+    // Checked before anything's saved, since the real ID isn't known yet.
+    Tournament::new(0, name.clone(), format.clone(), starts_at, now).context(TournamentSnafu)?;
+    db_handle
+        .transaction(move |transaction| {
+            transaction.execute(
+                "INSERT INTO Tournament (Name, Status, StartsAt, CreatedBy, State)
+                 VALUES (?1, ?2, ?3, ?4, '')",
+                params![
+                    name,
+                    TournamentStatus::Registration.as_str(),
+                    starts_at,
+                    created_by
+                ],
+            )?;
+            let tournament = Tournament::new(
+                transaction.last_insert_rowid(),
+                name,
+                format,
+                starts_at,
+                now,
+            )
+            .expect("the tournament was already checked");
+            save(transaction, &tournament)?;
+            Ok(tournament)
+        })
+        .await
+}
+
+pub(crate) async fn tournament(
+    db_handle: &DbHandle,
+    tournament_id: i64,
+) -> Result<Tournament, InnerError> {
+    db_handle
+        .transaction(move |transaction| load(transaction, tournament_id))
+        .await?
+}
+
+/// Every tournament, the ones starting last first.
+pub(crate) async fn tournaments(
+    db_handle: &DbHandle,
+) -> Result<Vec<TournamentSummary>, InnerError> {
+    let states = db_handle
+        .transaction(|transaction| {
+            transaction
+                .prepare("SELECT State FROM Tournament ORDER BY StartsAt DESC, Id DESC")?
+                .query_map([], |row| row.get(0))?
+                .collect::<Result<Vec<String>, _>>()
+        })
+        .await?;
+    states
+        .iter()
+        .map(|state| {
+            let tournament: Tournament =
+                serde_json::from_str(state).context(TournamentStateSnafu)?;
+            Ok(TournamentSummary {
+                id: tournament.id(),
+                name: tournament.name().to_string(),
+                status: tournament.status(),
+                starts_at: tournament.starts_at(),
+                entrants: tournament.entrants().len(),
+                winner: tournament.winner().cloned(),
+            })
+        })
+        .collect()
+}
+
+/// The tournaments that aren't over yet.
+pub(crate) async fn active_tournaments(db_handle: &DbHandle) -> Result<Vec<i64>, InnerError> {
+    db_handle
+        .transaction(|transaction| {
+            transaction
+                .prepare(
+                    "SELECT Id FROM Tournament WHERE Status IN (?1, ?2) ORDER BY StartsAt, Id",
+                )?
+                .query_map(
+                    params![
+                        TournamentStatus::Registration.as_str(),
+                        TournamentStatus::Running.as_str()
+                    ],
+                    |row| row.get(0),
+                )?
+                .collect()
+        })
+        .await
+}
+
+/// Makes a change to a tournament and saves it, all in one transaction so that changes
+/// can't get lost by happening at the same time. Returns what `change` did along with the
+/// tournament as it was saved.
+pub(crate) async fn update_tournament<T, Change>(
+    db_handle: &DbHandle,
+    tournament_id: i64,
+    change: Change,
+) -> Result<(T, Tournament), InnerError>
+where
+    T: Send + 'static,
+    Change: FnOnce(&mut Tournament) -> Result<T, TournamentError> + Send + 'static,
+{
+    db_handle
+        .transaction(move |transaction| {
+            let mut tournament = match load(transaction, tournament_id)? {
+                Ok(tournament) => tournament,
+                Err(error) => return Ok(Err(error)),
+            };
+            let changed = match change(&mut tournament) {
+                Ok(changed) => changed,
+                Err(source) => return Ok(Err(InnerError::TournamentError { source })),
+            };
+            save(transaction, &tournament)?;
+            Ok(Ok((changed, tournament)))
+        })
+        .await?
+}
+
+fn load(
+    connection: &Connection,
+    tournament_id: i64,
+) -> Result<Result<Tournament, InnerError>, Error> {
+    let state: Option<String> = connection
+        .query_row(
+            "SELECT State FROM Tournament WHERE Id = ?1",
+            params![tournament_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(match state {
+        Some(state) => serde_json::from_str(&state).context(TournamentStateSnafu),
+        None => Err(TournamentError::TournamentNotFound { tournament_id }).context(TournamentSnafu),
+    })
+}
+
+fn save(connection: &Connection, tournament: &Tournament) -> Result<(), Error> {
+    connection.execute(
+        "UPDATE Tournament SET Status = ?1, State = ?2 WHERE Id = ?3",
+        params![
+            tournament.status().as_str(),
+            serde_json::to_string(tournament).expect("tournaments always serialize"),
+            tournament.id()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Everyone's current rating in `mode`, for seeding.
+pub(crate) async fn seeding_ratings(
+    db_handle: &DbHandle,
+    players: Vec<UserId>,
+    mode: GameMode,
+    settings: &RatingSettings,
+) -> Result<HashMap<UserId, f64>, InnerError> {
+    let settings = settings.clone();
+    db_handle
+        .transaction(move |transaction| {
+            let now = unix_timestamp();
+            players
+                .into_iter()
+                .map(|user_id| {
+                    let (rating, _) = current_rating(transaction, user_id, mode, &settings, now)?;
+                    Ok((user_id, rating.rating))
+                })
+                .collect()
+        })
+        .await
+}
+
+/// How the match a heat was played in went, for matches that aren't live on this server any
+/// more. Ones that never finished were lost, either because the server they were on stopped or
+/// because they couldn't be saved.
+pub(crate) async fn heat_match(
+    db_handle: &DbHandle,
+    match_id: i64,
+) -> Result<HeatMatch, InnerError> {
+    db_handle
+        .transaction(move |transaction| {
+            let finished_at: Option<Option<i64>> = transaction
+                .query_row(
+                    "SELECT FinishedAt FROM GameMatch WHERE Id = ?1",
+                    params![match_id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(match finished_at {
+                Some(Some(_)) => HeatMatch::Finished(
+                    transaction
+                        .prepare(
+                            "SELECT UserId FROM GameMatchPlayer
+                             WHERE MatchId = ?1 AND Placement IS NOT NULL
+                             ORDER BY Placement",
+                        )?
+                        .query_map(params![match_id], |row| row.get(0))?
+                        .collect::<Result<_, _>>()?,
+                ),
+                Some(None) | None => HeatMatch::Lost,
+            })
+        })
+        .await
+}
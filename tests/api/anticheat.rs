@@ -0,0 +1,115 @@
+use serde_json::{json, Value};
+use std::time::Duration;
+use testcontainers_test::config::ProxySettings;
+
+use crate::helpers::{spawn_test_app, spawn_test_app_with, TestApp, TestSettings, TestUser};
+
+/// Waits for the anti-cheat pipeline to flag someone, returning every open flag.
+async fn wait_for_flags(app: &TestApp, moderator: &TestUser) -> Vec<Value> {
+    for _ in 0..100 {
+        let flags: Vec<Value> = moderator
+            .client
+            .get(app.url("/moderation/flags"))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        if !flags.is_empty() {
+            return flags;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("nobody was flagged");
+}
+
+/// Sharing an address isn't enough to be flagged by default, so these tests turn it up.
+fn suspicious_of_shared_addresses(mut settings: TestSettings) -> TestSettings {
+    settings.anticheat.shared_address_score = settings.anticheat.flag_score;
+    settings
+}
+
+#[tokio::test]
+async fn players_sharing_an_address_are_flagged_for_review() {
+    let app = spawn_test_app_with(suspicious_of_shared_addresses(TestSettings::fast_storm())).await;
+    let moderator = app.sign_up_from("moderator", "10.0.0.1").await;
+    app.make_moderator(&moderator).await;
+    let honest = app.sign_up_from("honest", "10.0.0.2").await;
+    let rival = app.sign_up_from("rival", "10.0.0.3").await;
+    // Playing from different addresses is fine, so only the second match gets anyone flagged.
+    app.play_match(&honest, &rival).await;
+    let main = app.sign_up_from("main", "10.0.0.4").await;
+    let alt = app.sign_up_from("alt", "10.0.0.4").await;
+    let (match_id, _) = app.play_match(&main, &alt).await;
+
+    let flags = wait_for_flags(&app, &moderator).await;
+    assert_eq!(flags.len(), 2);
+    for flag in &flags {
+        assert_eq!(flag["match_id"], json!(match_id));
+        assert_eq!(flag["findings"][0]["detector"], json!("shared_address"));
+    }
+    let flagged: Vec<&Value> = flags.iter().map(|flag| &flag["user"]["username"]).collect();
+    assert!(flagged.contains(&&json!("main")));
+    assert!(flagged.contains(&&json!("alt")));
+
+    let review = |flag_id: &Value| {
+        moderator
+            .client
+            .post(app.url(&format!("/moderation/flags/{}/review", flag_id)))
+            .json(&json!({ "verdict": "cleared" }))
+            .send()
+    };
+    let response = review(&flags[0]["id"]).await.unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    let response = review(&flags[0]["id"]).await.unwrap();
+    assert_eq!(response.status().as_u16(), 409);
+    let response = review(&json!(9999)).await.unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+
+    let open: Vec<Value> = moderator
+        .client
+        .get(app.url("/moderation/flags"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0]["id"], flags[1]["id"]);
+}
+
+#[tokio::test]
+async fn forwarded_addresses_are_ignored_unless_a_trusted_proxy_sent_them() {
+    let app = spawn_test_app_with(suspicious_of_shared_addresses(TestSettings {
+        proxies: ProxySettings::default(),
+        ..TestSettings::fast_storm()
+    }))
+    .await;
+    let moderator = app.sign_up("moderator").await;
+    app.make_moderator(&moderator).await;
+    // Both of them are really connecting from 127.0.0.1, whatever they claim.
+    let main = app.sign_up_from("main", "10.0.0.4").await;
+    let alt = app.sign_up_from("alt", "10.0.0.5").await;
+    app.play_match(&main, &alt).await;
+
+    let flags = wait_for_flags(&app, &moderator).await;
+    assert_eq!(flags.len(), 2);
+    for flag in &flags {
+        assert_eq!(flag["findings"][0]["detector"], json!("shared_address"));
+    }
+}
+
+#[tokio::test]
+async fn only_moderators_can_see_flags() {
+    let app = spawn_test_app().await;
+    let player = app.sign_up("player").await;
+    let response = player
+        .client
+        .get(app.url("/moderation/flags"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+}
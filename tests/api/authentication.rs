@@ -5,7 +5,7 @@ use crate::helpers::{spawn_test_app, TestApp};
 
 #[tokio::test]
 async fn create_user_success() {
-    let TestApp { address } = spawn_test_app().await;
+    let TestApp { address, .. } = spawn_test_app().await;
     let client = Client::new();
     let username = String::from("josh");
     let password = String::from("freedman");
@@ -41,6 +41,51 @@ async fn create_user_success() {
     );
 }
 
+#[tokio::test]
+async fn create_user_username_already_taken() {
+    let app = spawn_test_app().await;
+    let moderator = app.sign_up("moderator").await;
+    app.make_moderator(&moderator).await;
+    let client = Client::new();
+    let password = String::from("freedman");
+    let response = client
+        .post(app.url("/signup"))
+        .form(&UserInput::new(
+            String::from("moderator"),
+            password.clone(),
+            password.clone(),
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 409);
+    // The original account is the only one that can log in under the name.
+    let response = client
+        .post(app.url("/"))
+        .form(&Login::new(String::from("moderator"), password))
+        .send()
+        .await
+        .unwrap();
+    assert_ne!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn create_user_username_looks_like_a_bot() {
+    let app = spawn_test_app().await;
+    let password = String::from("freedman");
+    let response = Client::new()
+        .post(app.url("/signup"))
+        .form(&UserInput::new(
+            String::from("bot 12"),
+            password.clone(),
+            password,
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+}
+
 // #[tokio::test]
 // async fn create_user_email_already_exists() {
 //     todo!("Implement this test!")
@@ -0,0 +1,169 @@
+use serde_json::{json, Value};
+use std::time::Duration;
+use testcontainers_test::domain::{
+    lobby::MatchmakingSettings, protocol::ServerEvent, replay::Replay, Login,
+};
+
+use crate::helpers::{spawn_test_app, spawn_test_app_with, TestApp, TestSettings, TestUser};
+
+/// Waits for every match in `match_ids` to finish, returning their replays.
+async fn wait_for_replays(app: &TestApp, user: &TestUser, match_ids: &[i64]) -> Vec<Replay> {
+    let mut replays = Vec::with_capacity(match_ids.len());
+    for match_id in match_ids {
+        let mut attempts = 0;
+        loop {
+            let response = user
+                .client
+                .get(app.url(&format!("/matches/{}/replay", match_id)))
+                .send()
+                .await
+                .unwrap();
+            if response.status().as_u16() == 200 {
+                replays.push(Replay::decode(&response.bytes().await.unwrap()).unwrap());
+                break;
+            }
+            assert_eq!(response.status().as_u16(), 409);
+            attempts += 1;
+            assert!(attempts < 600, "match {} never finished", match_id);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+    replays
+}
+
+#[tokio::test]
+async fn bots_fill_the_match_of_a_player_left_waiting() {
+    let mut settings = TestSettings::fast_storm();
+    settings.matchmaking = MatchmakingSettings {
+        fill_time: Duration::ZERO,
+        ..MatchmakingSettings::default()
+    };
+    settings.matchmaking.lobby.max_players = 3;
+    settings.bots.fill_after = Duration::from_millis(200);
+    let app = spawn_test_app_with(settings).await;
+    let player = app.sign_up("player").await;
+    let mut socket = app.connect(&player, None).await;
+
+    let status: Value = player
+        .client
+        .post(app.url("/matchmaking/queue"))
+        .json(&json!({ "mode": "battle_royale" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status, json!({ "status": "queued" }));
+
+    let started = socket
+        .next_matching(|event| matches!(event, ServerEvent::MatchStarted { .. }))
+        .await;
+    let (match_id, players) = match started.event {
+        ServerEvent::MatchStarted {
+            match_id, players, ..
+        } => (match_id, players),
+        _ => unreachable!(),
+    };
+    assert_eq!(players.len(), 3);
+    assert!(players.contains(&player.user.id()));
+
+    let replay = &wait_for_replays(&app, &player, &[match_id]).await[0];
+    assert!(replay
+        .actions
+        .iter()
+        .any(|action| action.user_id != player.user.id()));
+}
+
+#[tokio::test]
+async fn bot_accounts_cannot_be_logged_into() {
+    let app = spawn_test_app().await;
+    let moderator = app.sign_up("moderator").await;
+    app.make_moderator(&moderator).await;
+    let started: Value = moderator
+        .client
+        .post(app.url("/moderation/bot_matches"))
+        .json(&json!({ "matches": 1, "players": 2 }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(started["match_ids"].as_array().unwrap().len(), 1);
+
+    // The moderator is the first user, so the bot that was just made is the second.
+    let response = reqwest::Client::new()
+        .post(app.url("/"))
+        .form(&Login::new(String::from("Bot 2"), String::from("!")))
+        .send()
+        .await
+        .unwrap();
+    assert_ne!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn only_moderators_can_start_bot_matches() {
+    let app = spawn_test_app().await;
+    let player = app.sign_up("player").await;
+    let response = player
+        .client
+        .post(app.url("/moderation/bot_matches"))
+        .json(&json!({ "matches": 1, "players": 2 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn bot_match_requests_are_kept_within_the_limits() {
+    let app = spawn_test_app().await;
+    let moderator = app.sign_up("moderator").await;
+    app.make_moderator(&moderator).await;
+    for (matches, players) in [(0, 2), (251, 2), (1, 1), (1, 9)] {
+        let response = moderator
+            .client
+            .post(app.url("/moderation/bot_matches"))
+            .json(&json!({ "matches": matches, "players": players }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "{matches} matches of {players} players"
+        );
+    }
+}
+
+#[tokio::test]
+async fn hundreds_of_bot_matches_play_out_at_once() {
+    let app = spawn_test_app_with(TestSettings::fast_storm()).await;
+    let moderator = app.sign_up("moderator").await;
+    app.make_moderator(&moderator).await;
+    let started: Value = moderator
+        .client
+        .post(app.url("/moderation/bot_matches"))
+        .json(&json!({ "matches": 200, "players": 2 }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let match_ids: Vec<i64> = started["match_ids"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|id| id.as_i64().unwrap())
+        .collect();
+    assert_eq!(match_ids.len(), 200);
+
+    let replays = wait_for_replays(&app, &moderator, &match_ids).await;
+    for replay in &replays {
+        assert_eq!(replay.players.len(), 2);
+        assert!(!replay.players.contains(&moderator.user.id()));
+    }
+    assert!(replays.iter().any(|replay| !replay.actions.is_empty()));
+}
@@ -0,0 +1,288 @@
+use serde_json::{json, Value};
+use std::time::Duration;
+use testcontainers_test::domain::protocol::{ClientMessage, ServerEvent};
+
+use crate::helpers::{
+    restart_test_app, spawn_test_app, GameSocket, TestApp, TestSettings, TestUser,
+};
+
+/// Puts everyone into a private lobby that `players[0]` hosts, without starting a match.
+async fn share_lobby(app: &TestApp, players: &[&TestUser]) {
+    let lobby: Value = players[0]
+        .client
+        .post(app.url("/lobbies"))
+        .json(&json!({
+            "visibility": "private",
+            "mode": "battle_royale",
+            "width": 9,
+            "height": 9,
+            "mine_density": 12,
+            "max_players": 4
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    for player in &players[1..] {
+        let response = player
+            .client
+            .post(app.url(&format!(
+                "/lobbies/{}/join",
+                lobby["code"].as_str().unwrap()
+            )))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+    }
+}
+
+async fn say(socket: &mut GameSocket, text: &str) {
+    socket
+        .send(&ClientMessage::Chat {
+            text: text.to_string(),
+        })
+        .await;
+}
+
+/// Waits for the next chat message, returning its ID and text.
+async fn next_chat(socket: &mut GameSocket) -> (i64, String) {
+    let message = socket
+        .next_matching(|event| matches!(event, ServerEvent::Chat { .. }))
+        .await;
+    match message.event {
+        ServerEvent::Chat {
+            message_id, text, ..
+        } => (message_id, text),
+        _ => unreachable!(),
+    }
+}
+
+async fn next_error(socket: &mut GameSocket) -> String {
+    let message = socket
+        .next_matching(|event| matches!(event, ServerEvent::Error { .. }))
+        .await;
+    match message.event {
+        ServerEvent::Error { message } => message,
+        _ => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn lobby_chat_is_filtered_and_checked() {
+    let app = spawn_test_app().await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    share_lobby(&app, &[&host, &guest]).await;
+    let mut host_socket = app.connect(&host, None).await;
+    let mut guest_socket = app.connect(&guest, None).await;
+
+    say(&mut host_socket, "oh HECK").await;
+    let (message_id, text) = next_chat(&mut guest_socket).await;
+    assert_eq!(text, "oh ****");
+    assert_eq!(next_chat(&mut host_socket).await, (message_id, text));
+
+    say(&mut host_socket, &"a".repeat(201)).await;
+    assert_eq!(
+        next_error(&mut host_socket).await,
+        "Chat messages can be at most 200 characters long"
+    );
+    for _ in 0..5 {
+        say(&mut host_socket, "spam").await;
+    }
+    let error = next_error(&mut host_socket).await;
+    assert!(error.starts_with("You're sending messages too quickly"));
+}
+
+#[tokio::test]
+async fn players_need_somewhere_to_chat() {
+    let app = spawn_test_app().await;
+    let loner = app.sign_up("loner").await;
+    let mut socket = app.connect(&loner, None).await;
+
+    say(&mut socket, "anyone there?").await;
+    assert_eq!(
+        next_error(&mut socket).await,
+        "You need to be in a lobby or a match to chat"
+    );
+}
+
+#[tokio::test]
+async fn muted_and_blocked_players_are_not_heard() {
+    let app = spawn_test_app().await;
+    let host = app.sign_up("host").await;
+    let muter = app.sign_up("muter").await;
+    let blocker = app.sign_up("blocker").await;
+    share_lobby(&app, &[&host, &muter, &blocker]).await;
+    let mut host_socket = app.connect(&host, None).await;
+    let mut muter_socket = app.connect(&muter, None).await;
+    let mut blocker_socket = app.connect(&blocker, None).await;
+
+    let response = muter
+        .client
+        .post(app.url(&format!("/users/{}/mute", host.user.id())))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    let response = blocker
+        .client
+        .post(app.url(&format!("/users/{}/block", host.user.id())))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    let ignores: Value = blocker
+        .client
+        .get(app.url("/chat/ignores"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(ignores["blocked"][0]["username"], "host");
+
+    say(&mut host_socket, "hello?").await;
+    assert_eq!(next_chat(&mut host_socket).await.1, "hello?");
+    // Blocking goes both ways, muting doesn't.
+    say(&mut blocker_socket, "not to you").await;
+    assert_eq!(next_chat(&mut muter_socket).await.1, "not to you");
+    assert_eq!(next_chat(&mut blocker_socket).await.1, "not to you");
+    say(&mut muter_socket, "hi host").await;
+    assert_eq!(next_chat(&mut host_socket).await.1, "hi host");
+
+    let response = muter
+        .client
+        .post(app.url(&format!("/users/{}/mute", muter.user.id())))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn moderators_can_delete_reported_messages_and_mute_players() {
+    let app = spawn_test_app().await;
+    let troll = app.sign_up("troll").await;
+    let reporter = app.sign_up("reporter").await;
+    let moderator = app.sign_up("moderator").await;
+    app.make_moderator(&moderator).await;
+    share_lobby(&app, &[&troll, &reporter]).await;
+    let mut troll_socket = app.connect(&troll, None).await;
+    let mut reporter_socket = app.connect(&reporter, None).await;
+
+    say(&mut troll_socket, "you're terrible").await;
+    let (message_id, _) = next_chat(&mut reporter_socket).await;
+    let response = reporter
+        .client
+        .post(app.url(&format!("/chat/messages/{}/report", message_id)))
+        .json(&json!({ "reason": "rude" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+
+    // Only moderators get to see reports.
+    let response = reporter
+        .client
+        .get(app.url("/moderation/reports"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+    let reports: Value = moderator
+        .client
+        .get(app.url("/moderation/reports"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(reports[0]["message_id"], message_id);
+    assert_eq!(reports[0]["text"], "you're terrible");
+    assert_eq!(reports[0]["author"]["username"], "troll");
+    assert_eq!(reports[0]["reason"], "rude");
+
+    let response = moderator
+        .client
+        .delete(app.url(&format!("/chat/messages/{}", message_id)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    for socket in [&mut troll_socket, &mut reporter_socket] {
+        let deleted = socket
+            .next_matching(|event| matches!(event, ServerEvent::ChatDeleted { .. }))
+            .await;
+        assert_eq!(deleted.event, ServerEvent::ChatDeleted { message_id });
+    }
+
+    let response = moderator
+        .client
+        .post(app.url("/moderation/mutes"))
+        .json(&json!({ "user_id": troll.user.id(), "minutes": 10, "reason": "rude" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    say(&mut troll_socket, "still here").await;
+    assert!(next_error(&mut troll_socket)
+        .await
+        .starts_with("You've been muted until"));
+
+    let response = moderator
+        .client
+        .delete(app.url(&format!("/moderation/mutes/{}", troll.user.id())))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    say(&mut troll_socket, "sorry").await;
+    assert_eq!(next_chat(&mut reporter_socket).await.1, "sorry");
+}
+
+#[tokio::test]
+async fn old_messages_are_cleaned_up_when_the_server_starts() {
+    let app = spawn_test_app().await;
+    let troll = app.sign_up("troll").await;
+    let reporter = app.sign_up("reporter").await;
+    let moderator = app.sign_up("moderator").await;
+    app.make_moderator(&moderator).await;
+    share_lobby(&app, &[&troll, &reporter]).await;
+    let mut troll_socket = app.connect(&troll, None).await;
+    let mut reporter_socket = app.connect(&reporter, None).await;
+    say(&mut troll_socket, "you're terrible").await;
+    let (message_id, _) = next_chat(&mut reporter_socket).await;
+    reporter
+        .client
+        .post(app.url(&format!("/chat/messages/{}/report", message_id)))
+        .json(&json!({ "reason": "rude" }))
+        .send()
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let mut settings = TestSettings::default();
+    settings.chat.retention = Duration::from_millis(10);
+    let app = restart_test_app(&app, settings).await;
+    for _ in 0..100 {
+        let reports: Vec<Value> = moderator
+            .client
+            .get(app.url("/moderation/reports"))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap_or_default();
+        if reports.is_empty() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("the old message was never cleaned up");
+}
@@ -0,0 +1,107 @@
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+
+use crate::helpers::spawn_test_app;
+
+async fn get_json(client: &Client, url: String) -> Value {
+    client.get(url).send().await.unwrap().json().await.unwrap()
+}
+
+#[tokio::test]
+async fn everyone_gets_the_same_challenge_once_a_day() {
+    let app = spawn_test_app().await;
+    let alice = app.sign_up("alice").await;
+    let bob = app.sign_up("bob").await;
+
+    let challenge = get_json(&alice.client, app.url("/daily")).await;
+    assert_eq!(challenge["attempt"], Value::Null);
+    assert_eq!(challenge["streak"]["current"], 0);
+    let theirs = get_json(&bob.client, app.url("/daily")).await;
+    assert_eq!(challenge["date"], theirs["date"]);
+    assert_eq!(challenge["start"], theirs["start"]);
+
+    let no_attempt = alice
+        .client
+        .get(app.url("/daily/attempt"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(no_attempt.status(), StatusCode::NOT_FOUND);
+
+    let started = alice
+        .client
+        .post(app.url("/daily/attempt"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(started.status(), StatusCode::CREATED);
+    let attempt: Value = started.json().await.unwrap();
+    assert_eq!(attempt["cleared"], Value::Null);
+
+    let again = alice
+        .client
+        .post(app.url("/daily/attempt"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(again.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn finished_attempts_can_be_replayed() {
+    let app = spawn_test_app().await;
+    let alice = app.sign_up("alice").await;
+    let last = app.finish_daily_attempt(&alice).await;
+    assert_ne!(last["cleared"], Value::Null);
+
+    let over = alice
+        .client
+        .post(app.url("/daily/attempt/moves"))
+        .json(&json!({ "action": "reveal", "position": { "x": 0, "y": 0 } }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(over.status(), StatusCode::CONFLICT);
+
+    let replay = alice
+        .client
+        .get(app.url(&format!("/matches/{}/replay/view", last["match_id"])))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(replay.status(), StatusCode::OK);
+
+    let leaderboard = get_json(
+        &alice.client,
+        app.url(&format!(
+            "/daily/{}/leaderboard",
+            last["date"].as_str().unwrap()
+        )),
+    )
+    .await;
+    let cleared = last["cleared"] == true;
+    assert_eq!(leaderboard.as_array().unwrap().len(), usize::from(cleared));
+    let streak = get_json(&alice.client, app.url("/daily")).await["streak"].clone();
+    assert_eq!(streak["current"], u32::from(cleared));
+}
+
+#[tokio::test]
+async fn invalid_dates_are_rejected() {
+    let app = spawn_test_app().await;
+    let alice = app.sign_up("alice").await;
+    let response = alice
+        .client
+        .get(app.url("/daily/2022-02-30/leaderboard"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let history = alice
+        .client
+        .get(app.url("/daily/history"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(history.status(), StatusCode::OK);
+}
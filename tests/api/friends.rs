@@ -0,0 +1,273 @@
+use serde_json::{json, Value};
+use testcontainers_test::domain::{friends::Presence, protocol::ServerEvent};
+
+use crate::helpers::{spawn_test_app, GameSocket, TestApp, TestUser};
+
+async fn request_friend(app: &TestApp, user: &TestUser, other: &TestUser) -> (u16, Value) {
+    let response = user
+        .client
+        .post(app.url(&format!("/friends/{}", other.user.id())))
+        .send()
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    (status, response.json().await.unwrap_or(Value::Null))
+}
+
+async fn befriend(app: &TestApp, user: &TestUser, other: &TestUser) {
+    assert_eq!(request_friend(app, user, other).await.0, 200);
+    let response = other
+        .client
+        .post(app.url(&format!("/friends/{}/accept", user.user.id())))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+}
+
+async fn friend_list(app: &TestApp, user: &TestUser) -> Value {
+    user.client
+        .get(app.url("/friends"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+/// Waits for the next presence update about `user`.
+async fn next_presence(socket: &mut GameSocket, user: &TestUser) -> Presence {
+    let user_id = user.user.id();
+    let message = socket
+        .next_matching(|event| {
+            matches!(event, ServerEvent::FriendPresence { user_id: id, .. } if *id == user_id)
+        })
+        .await;
+    match message.event {
+        ServerEvent::FriendPresence { presence, .. } => presence,
+        _ => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn friend_requests_can_be_sent_and_accepted() {
+    let app = spawn_test_app().await;
+    let alice = app.sign_up("alice").await;
+    let bob = app.sign_up("bob").await;
+    let mut bob_socket = app.connect(&bob, None).await;
+
+    let (status, body) = request_friend(&app, &alice, &bob).await;
+    assert_eq!(status, 200);
+    assert_eq!(body, json!({ "status": "sent" }));
+    let request = bob_socket
+        .next_matching(|event| matches!(event, ServerEvent::FriendRequest { .. }))
+        .await;
+    assert_eq!(
+        request.event,
+        ServerEvent::FriendRequest {
+            from: alice.user.clone()
+        }
+    );
+    assert_eq!(
+        friend_list(&app, &bob).await["incoming"][0]["username"],
+        "alice"
+    );
+    assert_eq!(
+        friend_list(&app, &alice).await["outgoing"][0]["username"],
+        "bob"
+    );
+    assert_eq!(request_friend(&app, &alice, &bob).await.0, 409);
+
+    let response = bob
+        .client
+        .post(app.url(&format!("/friends/{}/accept", alice.user.id())))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    let added = bob_socket
+        .next_matching(|event| matches!(event, ServerEvent::FriendAdded { .. }))
+        .await;
+    assert_eq!(
+        added.event,
+        ServerEvent::FriendAdded {
+            user: alice.user.clone(),
+            presence: Presence::Offline,
+        }
+    );
+
+    let list = friend_list(&app, &alice).await;
+    assert_eq!(list["friends"][0]["user"]["username"], "bob");
+    assert_eq!(
+        list["friends"][0]["presence"],
+        json!({ "status": "online" })
+    );
+    assert_eq!(list["outgoing"], json!([]));
+    assert_eq!(request_friend(&app, &alice, &bob).await.0, 409);
+}
+
+#[tokio::test]
+async fn requests_can_be_declined_or_crossed() {
+    let app = spawn_test_app().await;
+    let alice = app.sign_up("alice").await;
+    let bob = app.sign_up("bob").await;
+
+    assert_eq!(request_friend(&app, &alice, &alice).await.0, 400);
+    let response = alice
+        .client
+        .post(app.url("/friends/12345"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+
+    request_friend(&app, &alice, &bob).await;
+    let response = bob
+        .client
+        .post(app.url(&format!("/friends/{}/decline", alice.user.id())))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(friend_list(&app, &alice).await["outgoing"], json!([]));
+
+    // Asking someone who already asked you is as good as accepting.
+    request_friend(&app, &alice, &bob).await;
+    let (status, body) = request_friend(&app, &bob, &alice).await;
+    assert_eq!(status, 200);
+    assert_eq!(body, json!({ "status": "friends" }));
+    assert_eq!(
+        friend_list(&app, &bob).await["friends"][0]["user"]["username"],
+        "alice"
+    );
+
+    let response = bob
+        .client
+        .delete(app.url(&format!("/friends/{}", alice.user.id())))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(friend_list(&app, &alice).await["friends"], json!([]));
+}
+
+#[tokio::test]
+async fn friends_hear_about_presence_changes() {
+    let app = spawn_test_app().await;
+    let alice = app.sign_up("alice").await;
+    let bob = app.sign_up("bob").await;
+    befriend(&app, &alice, &bob).await;
+    let mut alice_socket = app.connect(&alice, None).await;
+
+    let bob_socket = app.connect(&bob, None).await;
+    assert_eq!(
+        next_presence(&mut alice_socket, &bob).await,
+        Presence::Online
+    );
+    bob.client
+        .post(app.url("/lobbies"))
+        .json(&json!({
+            "visibility": "private",
+            "mode": "battle_royale",
+            "width": 9,
+            "height": 9,
+            "mine_density": 12,
+            "max_players": 2
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        next_presence(&mut alice_socket, &bob).await,
+        Presence::InLobby
+    );
+    bob_socket.close().await;
+    assert_eq!(
+        next_presence(&mut alice_socket, &bob).await,
+        Presence::Offline
+    );
+}
+
+#[tokio::test]
+async fn friends_can_be_invited_to_a_lobby() {
+    let app = spawn_test_app().await;
+    let host = app.sign_up("host").await;
+    let friend = app.sign_up("friend").await;
+    let stranger = app.sign_up("stranger").await;
+    befriend(&app, &host, &friend).await;
+    let mut friend_socket = app.connect(&friend, None).await;
+
+    let invite = |target: &TestUser| {
+        host.client
+            .post(app.url(&format!("/friends/{}/invite", target.user.id())))
+            .send()
+    };
+    assert_eq!(invite(&friend).await.unwrap().status().as_u16(), 404);
+    let lobby: Value = host
+        .client
+        .post(app.url("/lobbies"))
+        .json(&json!({
+            "visibility": "private",
+            "mode": "battle_royale",
+            "width": 9,
+            "height": 9,
+            "mine_density": 12,
+            "max_players": 2
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(invite(&stranger).await.unwrap().status().as_u16(), 404);
+    assert_eq!(invite(&friend).await.unwrap().status().as_u16(), 204);
+
+    let invited = friend_socket
+        .next_matching(|event| matches!(event, ServerEvent::LobbyInvite { .. }))
+        .await;
+    let code = match invited.event {
+        ServerEvent::LobbyInvite { from, code } => {
+            assert_eq!(from, host.user);
+            code
+        }
+        _ => unreachable!(),
+    };
+    assert_eq!(code.as_str(), lobby["code"].as_str().unwrap());
+    let response = friend
+        .client
+        .post(app.url(&format!("/lobbies/{}/join", code.as_str())))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn blocking_ends_a_friendship() {
+    let app = spawn_test_app().await;
+    let alice = app.sign_up("alice").await;
+    let bob = app.sign_up("bob").await;
+    befriend(&app, &alice, &bob).await;
+    let mut bob_socket = app.connect(&bob, None).await;
+
+    let response = alice
+        .client
+        .post(app.url(&format!("/users/{}/block", bob.user.id())))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    let removed = bob_socket
+        .next_matching(|event| matches!(event, ServerEvent::FriendRemoved { .. }))
+        .await;
+    assert_eq!(
+        removed.event,
+        ServerEvent::FriendRemoved {
+            user_id: alice.user.id()
+        }
+    );
+    assert_eq!(friend_list(&app, &bob).await["friends"], json!([]));
+    assert_eq!(request_friend(&app, &bob, &alice).await.0, 403);
+}
@@ -1,44 +1,566 @@
+use futures_util::{SinkExt, StreamExt};
 use once_cell::sync::Lazy;
+use reqwest::{header::SET_COOKIE, Client, Response};
+use serde_json::{json, Value};
 use std::{
     env, io,
-    net::{SocketAddr, TcpListener},
+    net::{IpAddr, SocketAddr, TcpListener},
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
 };
 use testcontainers_test::{
-    config::ApplicationConfiguration,
-    run,
-    telemetry::{get_subscriber, init_subscriber},
+    config::{
+        AdminSettings, ApplicationConfiguration, EventStreamSettings, ProxySettings,
+        TelemetrySettings, WebSocketSettings,
+    },
+    domain::{
+        anticheat::AntiCheatSettings,
+        battle_royale::{BattleRoyaleSettings, ReconnectSettings},
+        bot::{BotSettings, BotSkill},
+        chat::ChatSettings,
+        daily::DailySettings,
+        leaderboard::LeaderboardSettings,
+        lobby::MatchmakingSettings,
+        minesweeper::{CellView, Position},
+        notifications::Notification,
+        protocol::{ClientMessage, ServerEvent, ServerMessage, SpectatorEvent},
+        rating::RatingSettings,
+        spectate::SpectatorSettings,
+        tournament::TournamentSettings,
+        Login, User, UserInput,
+    },
+    grant_moderator, run,
+    telemetry::{get_subscriber, init_subscriber, LogFilter},
+};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{client::IntoClientRequest, http::header::COOKIE, Error, Message},
+    MaybeTlsStream, WebSocketStream,
 };
 
-static TRACING: Lazy<()> = Lazy::new(|| {
+static TRACING: Lazy<LogFilter> = Lazy::new(|| {
     let name = "app";
     let env_filter = "info";
+    let telemetry = TelemetrySettings::default();
     match env::var("SHOW_LOGS") {
         Ok(val) if val == "1" => {
-            let subscriber = get_subscriber(name, env_filter, io::stdout);
+            let (subscriber, log_filter, _) =
+                get_subscriber(name, env_filter, io::stdout, &telemetry).unwrap();
             init_subscriber(subscriber).unwrap();
+            log_filter
         }
         _ => {
-            let subscriber = get_subscriber(name, env_filter, io::sink);
+            let (subscriber, log_filter, _) =
+                get_subscriber(name, env_filter, io::sink, &telemetry).unwrap();
             init_subscriber(subscriber).unwrap();
+            log_filter
         }
-    };
+    }
 });
 
+static DATABASE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
 pub struct TestApp {
     pub address: SocketAddr,
+    pub db_path: PathBuf,
+}
+
+/// A user that's signed up and logged in.
+pub struct TestUser {
+    pub user: User,
+    /// Holds the user's session.
+    pub client: Client,
+    /// The session cookie, as it would be sent in a `Cookie` header.
+    pub cookie: String,
+}
+
+impl TestApp {
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.address, path)
+    }
+
+    /// Signs up a new user and logs them in, returning a client that holds their session.
+    pub async fn signed_in_client(&self, username: &str) -> (Client, User) {
+        let TestUser { client, user, .. } = self.sign_up(username).await;
+        (client, user)
+    }
+
+    /// Puts both users into a private lobby and readies them up, which starts the match.
+    pub async fn start_match(&self, host: &TestUser, guest: &TestUser) {
+        self.start_match_on(host, guest, "square").await
+    }
+
+    /// Same as `start_match`, but on a board with the given topology.
+    pub async fn start_match_on(&self, host: &TestUser, guest: &TestUser, topology: &str) {
+        self.start_match_with(host, guest, json!({ "topology": topology }))
+            .await
+    }
+
+    /// Same as `start_match`, but with some of the lobby's settings swapped out.
+    pub async fn start_match_with(
+        &self,
+        host: &TestUser,
+        guest: &TestUser,
+        overrides: serde_json::Value,
+    ) {
+        let mut settings = json!({
+            "visibility": "private",
+            "mode": "battle_royale",
+            "width": 9,
+            "height": 9,
+            "mine_density": 12,
+            "max_players": 2
+        });
+        for (key, value) in overrides.as_object().unwrap() {
+            settings[key] = value.clone();
+        }
+        let lobby: serde_json::Value = host
+            .client
+            .post(self.url("/lobbies"))
+            .json(&settings)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        guest
+            .client
+            .post(self.url(&format!(
+                "/lobbies/{}/join",
+                lobby["code"].as_str().unwrap()
+            )))
+            .send()
+            .await
+            .unwrap();
+        for user in [host, guest] {
+            user.client
+                .post(self.url("/lobbies/current/ready"))
+                .json(&json!({ "ready": true }))
+                .send()
+                .await
+                .unwrap();
+        }
+    }
+
+    /// Plays a match where the host flags a cell, and waits for the storm to finish it, which
+    /// the host wins. Returns the match's ID and the cell that was flagged.
+    pub async fn play_match(&self, host: &TestUser, guest: &TestUser) -> (i64, Position) {
+        let mut socket = self.connect(host, None).await;
+        self.start_match(host, guest).await;
+        let (match_id, board) = match socket
+            .next_matching(|event| matches!(event, ServerEvent::MatchStarted { .. }))
+            .await
+            .event
+        {
+            ServerEvent::MatchStarted {
+                match_id, board, ..
+            } => (match_id, board),
+            _ => unreachable!(),
+        };
+        let hidden = board
+            .cells
+            .iter()
+            .position(|cell| *cell == CellView::Hidden)
+            .unwrap();
+        let position = Position::new(hidden % board.width, hidden / board.width);
+        socket.send(&ClientMessage::Flag { position }).await;
+        socket
+            .next_matching(|event| matches!(event, ServerEvent::MatchFinished { .. }))
+            .await;
+        (match_id, position)
+    }
+
+    /// Starts today's daily challenge for `user` and reveals every cell in turn until the
+    /// attempt is over, one way or another. Returns the attempt as it was left.
+    pub async fn finish_daily_attempt(&self, user: &TestUser) -> Value {
+        let mut last: Value = user
+            .client
+            .post(self.url("/daily/attempt"))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let width = last["board"]["width"].as_u64().unwrap();
+        let height = last["board"]["height"].as_u64().unwrap();
+        for y in 0..height {
+            for x in 0..width {
+                let index = (y * width + x) as usize;
+                if last["board"]["cells"][index]["kind"] != "hidden" {
+                    continue;
+                }
+                last = user
+                    .client
+                    .post(self.url("/daily/attempt/moves"))
+                    .json(&json!({ "action": "reveal", "position": { "x": x, "y": y } }))
+                    .send()
+                    .await
+                    .unwrap()
+                    .json()
+                    .await
+                    .unwrap();
+                if last["cleared"] != Value::Null {
+                    return last;
+                }
+            }
+        }
+        panic!("The attempt never finished");
+    }
+
+    /// Signs up a new user and logs them in.
+    pub async fn sign_up(&self, username: &str) -> TestUser {
+        self.sign_up_from(username, "127.0.0.1").await
+    }
+
+    /// Lets `user` moderate.
+    pub async fn make_moderator(&self, user: &TestUser) {
+        grant_moderator(&self.db_path, user.user.username())
+            .await
+            .unwrap();
+    }
+
+    /// Signs up a new user and logs them in, as if they were connecting from `address`.
+    pub async fn sign_up_from(&self, username: &str, address: &str) -> TestUser {
+        let client = Client::builder().cookie_store(true).build().unwrap();
+        let password = String::from("hunter2");
+        let response = client
+            .post(self.url("/signup"))
+            .form(&UserInput::new(
+                username.into(),
+                password.clone(),
+                password.clone(),
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        let response = client
+            .post(self.url("/"))
+            .header("X-Forwarded-For", address)
+            .form(&Login::new(username.into(), password))
+            .send()
+            .await
+            .unwrap();
+        let cookie = response
+            .headers()
+            .get(SET_COOKIE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+        let user = response.json::<User>().await.unwrap();
+        TestUser {
+            user,
+            client,
+            cookie,
+        }
+    }
+
+    /// Opens the real-time game channel as `user`, optionally resuming after `last_seq`.
+    pub async fn connect(&self, user: &TestUser, last_seq: Option<u64>) -> GameSocket {
+        self.try_connect(&user.cookie, last_seq).await.unwrap()
+    }
+
+    pub async fn try_connect(
+        &self,
+        cookie: &str,
+        last_seq: Option<u64>,
+    ) -> Result<GameSocket, Error> {
+        let query = last_seq
+            .map(|seq| format!("?last_seq={}", seq))
+            .unwrap_or_default();
+        self.open_socket(&format!("/ws{}", query), cookie).await
+    }
+
+    /// Starts spectating a match as `user`.
+    pub async fn spectate(&self, user: &TestUser, match_id: i64) -> Result<GameSocket, Error> {
+        self.open_socket(&format!("/matches/{}/spectate", match_id), &user.cookie)
+            .await
+    }
+
+    /// Opens `user`'s notification stream.
+    pub async fn events(&self, user: &TestUser) -> EventStream {
+        let response = user.client.get(self.url("/events")).send().await.unwrap();
+        assert!(response.status().is_success());
+        EventStream {
+            response,
+            buffer: String::new(),
+        }
+    }
+
+    async fn open_socket(&self, path: &str, cookie: &str) -> Result<GameSocket, Error> {
+        let mut request = format!("ws://{}{}", self.address, path)
+            .into_client_request()
+            .unwrap();
+        request
+            .headers_mut()
+            .insert(COOKIE, cookie.parse().unwrap());
+        let (stream, _) = connect_async(request).await?;
+        Ok(GameSocket { stream })
+    }
+}
+
+/// A client reading server-sent events from `/events`.
+pub struct EventStream {
+    pub response: Response,
+    buffer: String,
+}
+
+impl EventStream {
+    /// Waits for the next notification, skipping over keep-alives.
+    pub async fn next_notification(&mut self) -> Notification {
+        loop {
+            if let Some(end) = self.buffer.find("\n\n") {
+                let frame: String = self.buffer.drain(..end + 2).collect();
+                if let Some(data) = frame.lines().find_map(|line| line.strip_prefix("data: ")) {
+                    return serde_json::from_str(data).unwrap();
+                }
+                continue;
+            }
+            let chunk = tokio::time::timeout(Duration::from_secs(5), self.response.chunk())
+                .await
+                .expect("no notification arrived")
+                .unwrap()
+                .expect("the event stream ended");
+            self.buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }
+}
+
+/// A client connection to the real-time game channel.
+pub struct GameSocket {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl GameSocket {
+    pub async fn send(&mut self, message: &ClientMessage) {
+        self.stream
+            .send(Message::Text(serde_json::to_string(message).unwrap()))
+            .await
+            .unwrap();
+    }
+
+    pub async fn send_raw(&mut self, text: &str) {
+        self.stream
+            .send(Message::Text(text.to_string()))
+            .await
+            .unwrap();
+    }
+
+    /// The next message from the server, skipping over pings. Panics if nothing shows up
+    /// within a few seconds.
+    pub async fn next_message(&mut self) -> ServerMessage {
+        loop {
+            let message = tokio::time::timeout(Duration::from_secs(5), self.stream.next())
+                .await
+                .expect("timed out waiting for a message")
+                .expect("the connection was closed")
+                .unwrap();
+            if let Message::Text(text) = message {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    /// The next event sent to a spectator.
+    pub async fn next_spectator_event(&mut self) -> SpectatorEvent {
+        loop {
+            let message = tokio::time::timeout(Duration::from_secs(5), self.stream.next())
+                .await
+                .expect("timed out waiting for a message")
+                .expect("the connection was closed")
+                .unwrap();
+            if let Message::Text(text) = message {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    /// Skips spectator events until one matches `predicate`.
+    pub async fn next_spectating(
+        &mut self,
+        predicate: impl Fn(&SpectatorEvent) -> bool,
+    ) -> SpectatorEvent {
+        loop {
+            let event = self.next_spectator_event().await;
+            if predicate(&event) {
+                return event;
+            }
+        }
+    }
+
+    /// Skips messages until one matches `predicate`.
+    pub async fn next_matching(
+        &mut self,
+        predicate: impl Fn(&ServerEvent) -> bool,
+    ) -> ServerMessage {
+        loop {
+            let message = self.next_message().await;
+            if predicate(&message.event) {
+                return message;
+            }
+        }
+    }
+
+    /// Waits for the server to close the connection, returning the frames seen before then.
+    /// The connection counts as closed once a close frame arrives or the stream ends.
+    pub async fn closed(&mut self) -> Vec<Message> {
+        let mut seen = vec![];
+        loop {
+            match tokio::time::timeout(Duration::from_secs(5), self.stream.next()).await {
+                Ok(Some(Ok(Message::Close(_)))) | Ok(Some(Err(_))) | Ok(None) => return seen,
+                Ok(Some(Ok(message))) => seen.push(message),
+                Err(_) => panic!("the connection was not closed, saw {:?}", seen),
+            }
+        }
+    }
+
+    pub async fn close(mut self) {
+        self.stream.close(None).await.unwrap();
+    }
+}
+
+/// Every test app gets its own database file. In-memory databases can't be used since every
+/// connection in the pool would get its own, separate database.
+fn test_database_path() -> PathBuf {
+    let path = env::temp_dir().join(format!(
+        "testcontainers_test-{}-{}.sqlite3",
+        std::process::id(),
+        DATABASE_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+    }
+    path
+}
+
+/// The parts of the configuration that tests are allowed to tweak.
+pub struct TestSettings {
+    pub matchmaking: MatchmakingSettings,
+    pub battle_royale: BattleRoyaleSettings,
+    pub reconnects: ReconnectSettings,
+    pub ratings: RatingSettings,
+    pub leaderboards: LeaderboardSettings,
+    pub daily: DailySettings,
+    pub spectators: SpectatorSettings,
+    pub chat: ChatSettings,
+    pub bots: BotSettings,
+    pub anticheat: AntiCheatSettings,
+    pub tournaments: TournamentSettings,
+    pub websocket: WebSocketSettings,
+    pub events: EventStreamSettings,
+    pub proxies: ProxySettings,
+    pub admin: AdminSettings,
+}
+
+impl Default for TestSettings {
+    fn default() -> Self {
+        Self {
+            matchmaking: MatchmakingSettings {
+                fill_time: Duration::ZERO,
+                ..MatchmakingSettings::default()
+            },
+            battle_royale: BattleRoyaleSettings::default(),
+            reconnects: ReconnectSettings::default(),
+            ratings: RatingSettings::default(),
+            leaderboards: LeaderboardSettings {
+                refresh_interval: Duration::from_millis(100),
+                ..LeaderboardSettings::default()
+            },
+            daily: DailySettings::default(),
+            spectators: SpectatorSettings {
+                delay: Duration::from_millis(200),
+            },
+            chat: ChatSettings {
+                banned_words: vec![String::from("heck")],
+                ..ChatSettings::default()
+            },
+            bots: BotSettings {
+                skill: BotSkill {
+                    reaction_delay: Duration::from_millis(20),
+                    ..BotSkill::default()
+                },
+                ..BotSettings::default()
+            },
+            anticheat: AntiCheatSettings::default(),
+            tournaments: TournamentSettings {
+                round_break: Duration::from_millis(300),
+                check_interval: Duration::from_millis(100),
+            },
+            websocket: WebSocketSettings::default(),
+            events: EventStreamSettings::default(),
+            // `sign_up_from` says where players are with `X-Forwarded-For`.
+            proxies: ProxySettings {
+                trusted: vec![IpAddr::from([127, 0, 0, 1])],
+            },
+            admin: AdminSettings::default(),
+        }
+    }
+}
+
+impl TestSettings {
+    /// Settings where the storm finishes a match within a few hundred milliseconds.
+    pub fn fast_storm() -> Self {
+        Self {
+            battle_royale: BattleRoyaleSettings {
+                tick_length: Duration::from_millis(10),
+                first_storm: 20,
+                storm_interval: 20,
+                ..BattleRoyaleSettings::default()
+            },
+            ..Self::default()
+        }
+    }
 }
 
 pub async fn spawn_test_app() -> TestApp {
-    Lazy::force(&TRACING);
+    spawn_test_app_with(TestSettings::default()).await
+}
+
+pub async fn spawn_test_app_with(settings: TestSettings) -> TestApp {
+    spawn_test_app_on(test_database_path(), settings).await
+}
+
+/// Starts another server on the same database, like it would be after a restart. The old one
+/// keeps running, so it's best left alone afterwards.
+pub async fn restart_test_app(app: &TestApp, settings: TestSettings) -> TestApp {
+    spawn_test_app_on(app.db_path.clone(), settings).await
+}
+
+async fn spawn_test_app_on(db_path: PathBuf, settings: TestSettings) -> TestApp {
+    let log_filter = Lazy::force(&TRACING).clone();
     let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
     let address = listener.local_addr().unwrap();
     let app_config = ApplicationConfiguration {
         listener,
-        db_path: ":memory:",
+        db_path: db_path.clone(),
+        matchmaking: settings.matchmaking,
+        battle_royale: settings.battle_royale,
+        reconnects: settings.reconnects,
+        ratings: settings.ratings,
+        leaderboards: settings.leaderboards,
+        daily: settings.daily,
+        spectators: settings.spectators,
+        chat: settings.chat,
+        bots: settings.bots,
+        anticheat: settings.anticheat,
+        tournaments: settings.tournaments,
+        websocket: settings.websocket,
+        events: settings.events,
+        proxies: settings.proxies,
+        admin: settings.admin,
+        log_filter,
     };
-    let _ = tokio::spawn(async move {
+    tokio::spawn(async move {
         let server = run(app_config).await.unwrap();
         server.await.unwrap();
     });
-    TestApp { address }
+    TestApp { address, db_path }
 }
@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use reqwest::Client;
+use serde_json::Value;
+use testcontainers_test::domain::leaderboard::LeaderboardSettings;
+
+use crate::helpers::{spawn_test_app, spawn_test_app_with, TestApp, TestSettings};
+
+async fn get_json(client: &Client, url: String) -> Value {
+    client.get(url).send().await.unwrap().json().await.unwrap()
+}
+
+/// Waits for the next leaderboard snapshot that has `len` entries in it.
+async fn leaderboard_with(app: &TestApp, client: &Client, len: usize) -> Value {
+    for _ in 0..50 {
+        let page = get_json(client, app.url("/leaderboards/battle_royale")).await;
+        if page["entries"].as_array().unwrap().len() == len {
+            return page;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("the leaderboard never had {} entries", len);
+}
+
+#[tokio::test]
+async fn leaderboards_rank_players_who_have_played() {
+    let app = spawn_test_app_with(TestSettings::fast_storm()).await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    let bystander = app.sign_up("bystander").await;
+    app.play_match(&host, &guest).await;
+
+    let page = leaderboard_with(&app, &host.client, 2).await;
+    assert_eq!(page["board"], "battle_royale");
+    assert_eq!(page["entries"][0]["rank"], 1);
+    assert_eq!(page["entries"][0]["username"], "host");
+    assert_eq!(page["entries"][1]["rank"], 2);
+    assert_eq!(page["entries"][1]["username"], "guest");
+    assert_eq!(page["next"], Value::Null);
+
+    let global = get_json(&host.client, app.url("/leaderboards/global")).await;
+    assert_eq!(global["entries"][0]["username"], "host");
+    assert_eq!(global["entries"][0]["matches_played"], 1);
+
+    let first = get_json(&host.client, app.url("/leaderboards/battle_royale?limit=1")).await;
+    assert_eq!(first["entries"].as_array().unwrap().len(), 1);
+    let second = get_json(
+        &host.client,
+        app.url(&format!(
+            "/leaderboards/battle_royale?limit=1&after={}",
+            first["next"].as_str().unwrap()
+        )),
+    )
+    .await;
+    assert_eq!(second["entries"][0]["username"], "guest");
+
+    let around = get_json(&guest.client, app.url("/leaderboards/battle_royale/me")).await;
+    assert_eq!(around["entries"].as_array().unwrap().len(), 2);
+    let unranked = bystander
+        .client
+        .get(app.url("/leaderboards/battle_royale/me"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(unranked.status().as_u16(), 404);
+
+    let html = guest
+        .client
+        .get(app.url("/leaderboards/battle_royale/view"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("host"));
+    assert!(html.contains("bg-light-yellow"));
+}
+
+#[tokio::test]
+async fn bad_leaderboard_requests_are_rejected() {
+    let app = spawn_test_app().await;
+    let player = app.sign_up("player").await;
+    for (path, status) in [
+        ("/leaderboards/chess", 404),
+        ("/leaderboards/global?after=nonsense", 400),
+        ("/seasons/1234/leaderboards/global", 404),
+        // The first season has only just started.
+        ("/seasons/1/leaderboards/global", 409),
+    ] {
+        let response = player.client.get(app.url(path)).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), status, "{}", path);
+    }
+    let response = reqwest::get(app.url("/leaderboards/global")).await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn finished_seasons_are_archived_and_ratings_reset() {
+    let app = spawn_test_app_with(TestSettings {
+        leaderboards: LeaderboardSettings {
+            refresh_interval: Duration::from_millis(100),
+            season_length: Duration::from_secs(3),
+            min_matches: 1,
+        },
+        ..TestSettings::fast_storm()
+    })
+    .await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    app.play_match(&host, &guest).await;
+
+    // The match may run into the second season, so look for whichever one it was played in.
+    let mut archived = Value::Null;
+    let mut season_id = Value::Null;
+    'wait: for _ in 0..100 {
+        let seasons = get_json(&host.client, app.url("/seasons")).await;
+        for season in seasons.as_array().unwrap() {
+            if season["ended_at"].is_null() {
+                continue;
+            }
+            let page = get_json(
+                &host.client,
+                app.url(&format!(
+                    "/seasons/{}/leaderboards/battle_royale?limit=1",
+                    season["id"]
+                )),
+            )
+            .await;
+            if !page["entries"].as_array().unwrap().is_empty() {
+                season_id = season["id"].clone();
+                archived = page;
+                break 'wait;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(!season_id.is_null(), "the season never ended");
+    assert_eq!(archived["season_id"], season_id);
+    assert_eq!(archived["entries"][0]["username"], "host");
+    assert_eq!(archived["entries"][0]["matches_played"], 1);
+    let rest = get_json(
+        &host.client,
+        app.url(&format!(
+            "/seasons/{}/leaderboards/battle_royale?after={}",
+            season_id,
+            archived["next"].as_str().unwrap()
+        )),
+    )
+    .await;
+    assert_eq!(rest["entries"][0]["username"], "guest");
+    assert_eq!(rest["entries"][0]["rank"], 2);
+    assert_eq!(rest["next"], Value::Null);
+
+    let ratings = get_json(
+        &host.client,
+        app.url(&format!("/users/{}/ratings", host.user.id())),
+    )
+    .await;
+    // The match no longer counts for the leaderboards, but it's still been played.
+    assert_eq!(ratings[0]["matches_played"], 1);
+    assert_eq!(ratings[0]["provisional"], true);
+    assert!(ratings[0]["rating"].as_f64().unwrap() > 1500.0);
+    let current = get_json(&host.client, app.url("/leaderboards/battle_royale")).await;
+    assert_eq!(current["entries"].as_array().unwrap().len(), 0);
+}
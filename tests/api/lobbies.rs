@@ -0,0 +1,257 @@
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+
+use crate::helpers::{spawn_test_app, TestApp};
+
+async fn create_lobby(app: &TestApp, client: &Client, settings: Value) -> Value {
+    let response = client
+        .post(app.url("/lobbies"))
+        .json(&settings)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json().await.unwrap()
+}
+
+fn settings(visibility: &str) -> Value {
+    json!({
+        "visibility": visibility,
+        "mode": "battle_royale",
+        "width": 16,
+        "height": 16,
+        "mine_density": 15,
+        "max_players": 2
+    })
+}
+
+#[tokio::test]
+async fn lobbies_require_a_session() {
+    let app = spawn_test_app().await;
+    let response = Client::new().get(app.url("/lobbies")).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn players_can_join_by_code_and_auto_start_by_readying_up() {
+    let app = spawn_test_app().await;
+    let (host, host_user) = app.signed_in_client("host").await;
+    let (guest, guest_user) = app.signed_in_client("guest").await;
+
+    let lobby = create_lobby(&app, &host, settings("private")).await;
+    assert_eq!(lobby["host"], json!(host_user.id()));
+    let code = lobby["code"].as_str().unwrap().to_lowercase();
+
+    // Codes aren't case sensitive.
+    let joined: Value = guest
+        .post(app.url(&format!("/lobbies/{}/join", code)))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(joined["members"].as_array().unwrap().len(), 2);
+    assert_eq!(joined["members"][1]["user"]["id"], json!(guest_user.id()));
+
+    for client in [&host, &guest] {
+        let response = client
+            .post(app.url("/lobbies/current/ready"))
+            .json(&json!({ "ready": true }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let lobby: Value = host
+        .get(app.url("/lobbies/current"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(lobby["status"], json!("in_match"));
+}
+
+#[tokio::test]
+async fn private_lobbies_are_not_listed() {
+    let app = spawn_test_app().await;
+    let (first, _) = app.signed_in_client("first").await;
+    let (second, _) = app.signed_in_client("second").await;
+    let (browser, _) = app.signed_in_client("browser").await;
+    let public = create_lobby(&app, &first, settings("public")).await;
+    create_lobby(&app, &second, settings("private")).await;
+
+    let listed: Vec<Value> = browser
+        .get(app.url("/lobbies"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(listed, vec![public]);
+}
+
+#[tokio::test]
+async fn only_the_host_can_start_or_kick() {
+    let app = spawn_test_app().await;
+    let (host, _) = app.signed_in_client("host").await;
+    let (guest, guest_user) = app.signed_in_client("guest").await;
+    let lobby = create_lobby(&app, &host, settings("public")).await;
+    guest
+        .post(app.url(&format!(
+            "/lobbies/{}/join",
+            lobby["code"].as_str().unwrap()
+        )))
+        .send()
+        .await
+        .unwrap();
+
+    let response = guest
+        .post(app.url("/lobbies/current/start"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = host
+        .post(app.url("/lobbies/current/kick"))
+        .json(&json!({ "user_id": guest_user.id() }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = guest.get(app.url("/lobbies/current")).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn full_lobbies_reject_new_players() {
+    let app = spawn_test_app().await;
+    let (host, _) = app.signed_in_client("host").await;
+    let (guest, _) = app.signed_in_client("guest").await;
+    let (latecomer, _) = app.signed_in_client("latecomer").await;
+    let lobby = create_lobby(&app, &host, settings("public")).await;
+    let join_url = app.url(&format!(
+        "/lobbies/{}/join",
+        lobby["code"].as_str().unwrap()
+    ));
+
+    assert_eq!(
+        guest.post(&join_url).send().await.unwrap().status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        latecomer.post(&join_url).send().await.unwrap().status(),
+        StatusCode::CONFLICT
+    );
+}
+
+#[tokio::test]
+async fn invalid_settings_are_rejected() {
+    let app = spawn_test_app().await;
+    let (host, _) = app.signed_in_client("host").await;
+    let mut invalid = settings("public");
+    invalid["mine_density"] = json!(90);
+    let response = host
+        .post(app.url("/lobbies"))
+        .json(&invalid)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn boards_have_to_be_big_enough_for_their_topology() {
+    let app = spawn_test_app().await;
+    let (host, _) = app.signed_in_client("host").await;
+    let mut torus = settings("public");
+    torus["topology"] = json!("torus");
+    torus["width"] = json!(2);
+    let response = host
+        .post(app.url("/lobbies"))
+        .json(&torus)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    torus["width"] = json!(3);
+    let lobby = create_lobby(&app, &host, torus).await;
+    assert_eq!(lobby["settings"]["topology"], json!("torus"));
+}
+
+#[tokio::test]
+async fn matchmaking_puts_queued_players_into_a_match() {
+    let app = spawn_test_app().await;
+    let (first, _) = app.signed_in_client("first").await;
+    let (second, _) = app.signed_in_client("second").await;
+    let queue = json!({ "mode": "battle_royale" });
+
+    let status: Value = first
+        .post(app.url("/matchmaking/queue"))
+        .json(&queue)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status, json!({ "status": "queued" }));
+
+    let status: Value = second
+        .post(app.url("/matchmaking/queue"))
+        .json(&queue)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status["status"], json!("matched"));
+
+    let first_status: Value = first
+        .get(app.url("/matchmaking/queue"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(first_status, status);
+}
+
+#[tokio::test]
+async fn players_cannot_walk_out_of_a_match_into_another() {
+    let app = spawn_test_app().await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    app.start_match(&host, &guest).await;
+
+    let response = guest
+        .client
+        .post(app.url("/lobbies/current/leave"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let response = guest
+        .client
+        .post(app.url("/matchmaking/queue"))
+        .json(&json!({ "mode": "battle_royale" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let response = guest
+        .client
+        .post(app.url("/lobbies"))
+        .json(&settings("private"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
@@ -0,0 +1,71 @@
+use testcontainers_test::config::AdminSettings;
+
+use crate::helpers::{spawn_test_app, spawn_test_app_with, TestSettings};
+
+#[tokio::test]
+async fn the_log_filter_cant_be_touched_without_a_token() {
+    let app = spawn_test_app().await;
+    let client = reqwest::Client::new();
+    let response = client
+        .get(app.url("/admin/log-filter"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+    let response = client
+        .put(app.url("/admin/log-filter"))
+        .body("trace")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn the_log_filter_can_be_changed_while_running() {
+    let app = spawn_test_app_with(TestSettings {
+        admin: AdminSettings {
+            token: Some(String::from("operator")),
+            listener: None,
+        },
+        ..TestSettings::default()
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let set_filter = |token: &str, directives: &str| {
+        client
+            .put(app.url("/admin/log-filter"))
+            .bearer_auth(token)
+            .body(directives.to_string())
+            .send()
+    };
+    assert_eq!(
+        set_filter("guess", "debug")
+            .await
+            .unwrap()
+            .status()
+            .as_u16(),
+        401
+    );
+    let response = set_filter("operator", "app=loud").await.unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = set_filter("operator", "info,testcontainers_test=debug")
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    let current = client
+        .get(app.url("/admin/log-filter"))
+        .bearer_auth("operator")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(current.contains("testcontainers_test=debug"), "{}", current);
+
+    // The filter's shared with every other test, so put it back how it was.
+    let response = set_filter("operator", "info").await.unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+}
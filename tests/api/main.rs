@@ -1,3 +1,22 @@
+mod anticheat;
 mod authentication;
+mod bots;
+mod chat;
+mod daily;
+mod friends;
 mod health_check;
 mod helpers;
+mod leaderboards;
+mod lobbies;
+mod log_filter;
+mod metrics;
+mod notifications;
+mod power_ups;
+mod practice;
+mod ratings;
+mod reconnects;
+mod replays;
+mod spectate;
+mod stats;
+mod tournaments;
+mod web_socket;
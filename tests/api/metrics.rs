@@ -0,0 +1,87 @@
+use std::net::TcpListener;
+
+use testcontainers_test::config::AdminSettings;
+
+use crate::helpers::{spawn_test_app, spawn_test_app_with, TestSettings};
+
+#[tokio::test]
+async fn metrics_count_requests_by_route() {
+    let app = spawn_test_app().await;
+    let user = app.sign_up("player").await;
+    let mut socket = app.connect(&user, None).await;
+    socket.next_message().await;
+    let response = user
+        .client
+        .get(app.url(&format!("/users/{}/stats", user.user.id())))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = reqwest::get(app.url("/metrics")).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let metrics = response.text().await.unwrap();
+    for line in [
+        "# TYPE http_requests_total counter",
+        r#"http_requests_total{method="GET",route="/users/{user_id}/stats",status="200"} 1"#,
+        r#"http_request_duration_seconds_count{method="POST",route="/signup",status="200"} 1"#,
+        r#"password_hash_duration_seconds_count{operation="hash"} 1"#,
+        r#"password_hash_duration_seconds_count{operation="verify"} 1"#,
+        r#"websocket_connections{kind="player"} 1"#,
+        "live_matches 0",
+        "db_pool_max_size ",
+    ] {
+        assert!(
+            metrics.lines().any(|metric| metric.starts_with(line)),
+            "{:?} is missing from\n{}",
+            line,
+            metrics
+        );
+    }
+    assert!(
+        metrics.contains(r#"db_query_duration_seconds_bucket{operation="transaction",le="+Inf"}"#)
+    );
+}
+
+#[tokio::test]
+async fn metrics_can_need_a_token() {
+    let app = spawn_test_app_with(TestSettings {
+        admin: AdminSettings {
+            token: Some(String::from("scraper")),
+            listener: None,
+        },
+        ..TestSettings::default()
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let scrape = |token: &str| client.get(app.url("/metrics")).bearer_auth(token).send();
+    assert_eq!(scrape("guess").await.unwrap().status().as_u16(), 401);
+    assert_eq!(scrape("scraper").await.unwrap().status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn metrics_can_be_served_on_a_listener_of_their_own() {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let address = listener.local_addr().unwrap();
+    let app = spawn_test_app_with(TestSettings {
+        admin: AdminSettings {
+            token: None,
+            listener: Some(listener),
+        },
+        ..TestSettings::default()
+    })
+    .await;
+    app.sign_up("player").await;
+
+    let response = reqwest::get(app.url("/metrics")).await.unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+    let response = reqwest::get(format!("http://{}/metrics", address))
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains(r#"route="/signup",status="200""#));
+}
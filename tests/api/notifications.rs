@@ -0,0 +1,119 @@
+use serde_json::{json, Value};
+use testcontainers_test::domain::notifications::NotificationKind;
+
+use crate::helpers::{spawn_test_app, spawn_test_app_with, TestApp, TestSettings, TestUser};
+
+async fn request_friend(app: &TestApp, user: &TestUser, other: &TestUser) {
+    let response = user
+        .client
+        .post(app.url(&format!("/friends/{}", other.user.id())))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+async fn unread(app: &TestApp, user: &TestUser) -> Value {
+    user.client
+        .get(app.url("/notifications"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn the_event_stream_needs_a_session() {
+    let app = spawn_test_app().await;
+    let response = reqwest::get(app.url("/events")).await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn friend_requests_are_pushed_as_they_happen() {
+    let app = spawn_test_app().await;
+    let alice = app.sign_up("alice").await;
+    let bob = app.sign_up("bob").await;
+    let mut events = app.events(&bob).await;
+    assert_eq!(
+        events.response.headers()["content-type"],
+        "text/event-stream"
+    );
+
+    request_friend(&app, &alice, &bob).await;
+    let notification = events.next_notification().await;
+    assert_eq!(
+        notification.kind,
+        NotificationKind::FriendRequest {
+            from: alice.user.clone()
+        }
+    );
+    assert!(!notification.read);
+}
+
+#[tokio::test]
+async fn notifications_are_sent_again_until_they_are_read() {
+    let app = spawn_test_app().await;
+    let alice = app.sign_up("alice").await;
+    let bob = app.sign_up("bob").await;
+    let carol = app.sign_up("carol").await;
+    request_friend(&app, &alice, &bob).await;
+    let first = app.events(&bob).await.next_notification().await;
+    // Opening the stream again sends it again, since it still hasn't been read.
+    let mut events = app.events(&bob).await;
+    assert_eq!(events.next_notification().await, first);
+    assert_eq!(
+        unread(&app, &bob).await["notifications"][0]["id"],
+        json!(first.id)
+    );
+
+    let mark_read = |notification_id: i64| {
+        bob.client
+            .post(app.url(&format!("/notifications/{}/read", notification_id)))
+            .send()
+    };
+    assert_eq!(mark_read(first.id).await.unwrap().status().as_u16(), 204);
+    assert_eq!(
+        mark_read(first.id + 100).await.unwrap().status().as_u16(),
+        404
+    );
+    assert_eq!(unread(&app, &bob).await["notifications"], json!([]));
+
+    request_friend(&app, &carol, &bob).await;
+    let second = events.next_notification().await;
+    assert_eq!(
+        second.kind,
+        NotificationKind::FriendRequest {
+            from: carol.user.clone()
+        }
+    );
+    let mut events = app.events(&bob).await;
+    assert_eq!(events.next_notification().await, second);
+    let response = bob
+        .client
+        .post(app.url("/notifications/read"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(unread(&app, &bob).await["notifications"], json!([]));
+}
+
+#[tokio::test]
+async fn players_are_told_about_the_achievements_they_unlock() {
+    let app = spawn_test_app_with(TestSettings::fast_storm()).await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    let mut events = app.events(&guest).await;
+    let (match_id, _) = app.play_match(&host, &guest).await;
+    assert_eq!(
+        events.next_notification().await.kind,
+        NotificationKind::AchievementUnlocked {
+            achievement_id: String::from("first_match"),
+            name: String::from("Into the Fray"),
+            match_id
+        }
+    );
+}
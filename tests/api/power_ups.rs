@@ -0,0 +1,72 @@
+use serde_json::json;
+use testcontainers_test::domain::{
+    battle_royale::PowerUp,
+    practice::Level,
+    protocol::{ClientMessage, ServerEvent},
+};
+
+use crate::helpers::{spawn_test_app, GameSocket};
+
+/// Aims fog at `target` and returns the error that comes back.
+async fn use_fog(socket: &mut GameSocket, target: i64) -> String {
+    socket
+        .send(&ClientMessage::UsePowerUp {
+            power_up: PowerUp::Fog,
+            target: Some(target),
+        })
+        .await;
+    match socket
+        .next_matching(|event| matches!(event, ServerEvent::Error { .. }))
+        .await
+        .event
+    {
+        ServerEvent::Error { message } => message,
+        _ => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn power_ups_are_turned_on_per_lobby() {
+    let app = spawn_test_app().await;
+    let is_match_start = |event: &ServerEvent| matches!(event, ServerEvent::MatchStarted { .. });
+
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    let mut socket = app.connect(&host, None).await;
+    app.start_match(&host, &guest).await;
+    socket.next_matching(is_match_start).await;
+    assert_eq!(
+        use_fog(&mut socket, guest.user.id()).await,
+        "Power-ups are turned off for this match"
+    );
+
+    let host = app.sign_up("other_host").await;
+    let guest = app.sign_up("other_guest").await;
+    let mut socket = app.connect(&host, None).await;
+    app.start_match_with(&host, &guest, json!({ "power_ups": true }))
+        .await;
+    socket.next_matching(is_match_start).await;
+    assert_eq!(
+        use_fog(&mut socket, guest.user.id()).await,
+        "You don't have a fog power-up"
+    );
+}
+
+#[tokio::test]
+async fn power_ups_cant_be_used_while_practicing() {
+    let app = spawn_test_app().await;
+    let user = app.sign_up("player").await;
+    let mut socket = app.connect(&user, None).await;
+    socket
+        .send(&ClientMessage::StartPractice {
+            level: Level::Beginner,
+        })
+        .await;
+    socket
+        .next_matching(|event| matches!(event, ServerEvent::PracticeStarted { .. }))
+        .await;
+    assert_eq!(
+        use_fog(&mut socket, user.user.id()).await,
+        "Power-ups can only be used in matches"
+    );
+}
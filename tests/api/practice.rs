@@ -0,0 +1,93 @@
+use serde_json::Value;
+use testcontainers_test::domain::{
+    minesweeper::{BoardConfig, GameStatus, Position},
+    practice::Level,
+    protocol::{ClientMessage, ServerEvent},
+};
+
+use crate::helpers::spawn_test_app;
+
+#[tokio::test]
+async fn practice_games_are_played_over_the_game_channel() {
+    let app = spawn_test_app().await;
+    let user = app.sign_up("player").await;
+    let mut socket = app.connect(&user, None).await;
+
+    let level = Level::Custom(BoardConfig::new(2, 1, 1).unwrap());
+    socket.send(&ClientMessage::StartPractice { level }).await;
+    let started = socket
+        .next_matching(|event| matches!(event, ServerEvent::PracticeStarted { .. }))
+        .await;
+    match started.event {
+        ServerEvent::PracticeStarted {
+            level: started_level,
+            board,
+        } => {
+            assert_eq!(started_level, level);
+            assert_eq!(board.status, GameStatus::Ready);
+        }
+        event => panic!("expected the practice game to start, got {:?}", event),
+    }
+
+    socket
+        .send(&ClientMessage::Reveal {
+            position: Position::new(0, 0),
+        })
+        .await;
+    let delta = socket.next_message().await;
+    assert!(matches!(
+        delta.event,
+        ServerEvent::BoardDelta {
+            status: GameStatus::Won,
+            ..
+        }
+    ));
+    let finished = socket.next_message().await;
+    match finished.event {
+        ServerEvent::PracticeFinished {
+            won,
+            stats,
+            personal_best,
+            ..
+        } => {
+            assert!(won);
+            assert_eq!(stats.three_bv, 1);
+            assert_eq!(stats.clicks, 1);
+            assert!(personal_best);
+        }
+        event => panic!("expected the practice game to finish, got {:?}", event),
+    }
+
+    let bests: Value = user
+        .client
+        .get(app.url(&format!("/users/{}/personal_bests", user.user.id())))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(bests.as_array().unwrap().len(), 1);
+    assert_eq!(bests[0]["level"], "custom");
+    assert_eq!(bests[0]["width"], 2);
+    assert_eq!(bests[0]["three_bv"], 1);
+}
+
+#[tokio::test]
+async fn oversized_practice_boards_are_refused() {
+    let app = spawn_test_app().await;
+    let user = app.sign_up("player").await;
+    let mut socket = app.connect(&user, None).await;
+    socket
+        .send_raw(r#"{"type": "start_practice", "level": "custom", "width": 500, "height": 500, "mines": 10}"#)
+        .await;
+    let error = socket
+        .next_matching(|event| matches!(event, ServerEvent::Error { .. }))
+        .await;
+    assert_eq!(
+        error.event,
+        ServerEvent::Error {
+            message: String::from("Custom boards can be at most 100x100")
+        }
+    );
+}
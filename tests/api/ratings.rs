@@ -0,0 +1,83 @@
+use serde_json::Value;
+
+use crate::helpers::{spawn_test_app, spawn_test_app_with, TestSettings};
+
+#[tokio::test]
+async fn new_players_have_a_provisional_starting_rating() {
+    let app = spawn_test_app().await;
+    let player = app.sign_up("player").await;
+    let ratings: Value = player
+        .client
+        .get(app.url(&format!("/users/{}/ratings", player.user.id())))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(ratings[0]["mode"], "battle_royale");
+    assert_eq!(ratings[0]["rating"], 1500.0);
+    assert_eq!(ratings[0]["deviation"], 350.0);
+    assert_eq!(ratings[0]["matches_played"], 0);
+    assert_eq!(ratings[0]["provisional"], true);
+}
+
+#[tokio::test]
+async fn finished_matches_update_ratings() {
+    let app = spawn_test_app_with(TestSettings::fast_storm()).await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    let (match_id, _) = app.play_match(&host, &guest).await;
+
+    let rating_of = |user_id| {
+        let client = host.client.clone();
+        let url = app.url(&format!("/users/{}/ratings", user_id));
+        async move {
+            let ratings: Value = client.get(url).send().await.unwrap().json().await.unwrap();
+            ratings[0].clone()
+        }
+    };
+    let winner = rating_of(host.user.id()).await;
+    let loser = rating_of(guest.user.id()).await;
+    assert!(winner["rating"].as_f64().unwrap() > 1500.0);
+    assert!(loser["rating"].as_f64().unwrap() < 1500.0);
+    assert!(winner["deviation"].as_f64().unwrap() < 350.0);
+    assert_eq!(winner["matches_played"], 1);
+    assert_eq!(loser["matches_played"], 1);
+    assert_eq!(winner["provisional"], true);
+
+    let history: Value = guest
+        .client
+        .get(app.url(&format!(
+            "/users/{}/ratings/battle_royale/history",
+            host.user.id()
+        )))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let history = history.as_array().unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0]["match_id"], match_id);
+    assert_eq!(history[0]["placement"], 1);
+    assert_eq!(history[0]["rating_before"], 1500.0);
+    assert_eq!(history[0]["rating_after"], winner["rating"]);
+}
+
+#[tokio::test]
+async fn rating_history_needs_a_known_mode() {
+    let app = spawn_test_app().await;
+    let player = app.sign_up("player").await;
+    let response = player
+        .client
+        .get(app.url(&format!(
+            "/users/{}/ratings/tic_tac_toe/history",
+            player.user.id()
+        )))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use testcontainers_test::domain::{
+    battle_royale::{EliminationReason, ForfeitRule, ReconnectSettings},
+    protocol::{ClientMessage, ServerEvent},
+};
+
+use crate::helpers::{
+    spawn_test_app, spawn_test_app_with, GameSocket, TestApp, TestSettings, TestUser,
+};
+
+fn short_grace(forfeit: ForfeitRule) -> TestSettings {
+    TestSettings {
+        reconnects: ReconnectSettings {
+            grace_period: Duration::from_millis(50),
+            forfeit,
+        },
+        ..TestSettings::default()
+    }
+}
+
+/// Starts a match between two new players, and returns their sockets along with the guest and
+/// the last seq they've seen.
+async fn in_a_match(app: &TestApp) -> (GameSocket, GameSocket, TestUser, u64) {
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    let mut host_socket = app.connect(&host, None).await;
+    let mut guest_socket = app.connect(&guest, None).await;
+    app.start_match(&host, &guest).await;
+    let is_match_start = |event: &ServerEvent| matches!(event, ServerEvent::MatchStarted { .. });
+    host_socket.next_matching(is_match_start).await;
+    let last_seq = guest_socket.next_matching(is_match_start).await.seq;
+    (host_socket, guest_socket, guest, last_seq)
+}
+
+#[tokio::test]
+async fn opponents_hear_when_a_player_drops_out_and_comes_back() {
+    let app = spawn_test_app().await;
+    let (mut host_socket, guest_socket, guest, last_seq) = in_a_match(&app).await;
+    let guest_id = guest.user.id();
+    // Dropping the socket cuts the connection without a close frame, like a network dropping.
+    drop(guest_socket);
+    let disconnected = host_socket
+        .next_matching(|event| matches!(event, ServerEvent::PlayerDisconnected { .. }))
+        .await;
+    match disconnected.event {
+        ServerEvent::PlayerDisconnected {
+            user_id,
+            forfeit_in_ms,
+        } => {
+            assert_eq!(user_id, guest_id);
+            assert!(forfeit_in_ms.is_some_and(|ms| ms > 0 && ms <= 30_000));
+        }
+        _ => unreachable!(),
+    }
+
+    let mut guest_socket = app.connect(&guest, Some(last_seq)).await;
+    let connected = guest_socket
+        .next_matching(|event| matches!(event, ServerEvent::Connected { .. }))
+        .await;
+    assert_eq!(
+        connected.event,
+        ServerEvent::Connected {
+            user_id: guest_id,
+            resumed: true
+        }
+    );
+    let reconnected = host_socket
+        .next_matching(|event| matches!(event, ServerEvent::PlayerReconnected { .. }))
+        .await;
+    assert_eq!(
+        reconnected.event,
+        ServerEvent::PlayerReconnected { user_id: guest_id }
+    );
+}
+
+#[tokio::test]
+async fn players_who_stay_away_too_long_forfeit() {
+    let app = spawn_test_app_with(short_grace(ForfeitRule::Eliminate)).await;
+    let (mut host_socket, guest_socket, guest, _) = in_a_match(&app).await;
+    let guest_id = guest.user.id();
+    drop(guest_socket);
+    let eliminated = host_socket
+        .next_matching(|event| matches!(event, ServerEvent::Eliminated { .. }))
+        .await;
+    assert_eq!(
+        eliminated.event,
+        ServerEvent::Eliminated {
+            user_id: guest_id,
+            placement: 2,
+            reason: EliminationReason::Forfeit
+        }
+    );
+    host_socket
+        .next_matching(|event| matches!(event, ServerEvent::MatchFinished { .. }))
+        .await;
+}
+
+#[tokio::test]
+async fn players_can_forfeit_on_purpose() {
+    let app = spawn_test_app().await;
+    let (mut host_socket, mut guest_socket, guest, _) = in_a_match(&app).await;
+    let guest_id = guest.user.id();
+    guest_socket.send(&ClientMessage::Forfeit).await;
+    let eliminated = host_socket
+        .next_matching(|event| matches!(event, ServerEvent::Eliminated { .. }))
+        .await;
+    assert_eq!(
+        eliminated.event,
+        ServerEvent::Eliminated {
+            user_id: guest_id,
+            placement: 2,
+            reason: EliminationReason::Forfeit
+        }
+    );
+}
+
+#[tokio::test]
+async fn players_can_be_left_to_the_storm_instead() {
+    let app = spawn_test_app_with(short_grace(ForfeitRule::LeaveToStorm)).await;
+    let (mut host_socket, guest_socket, guest, _) = in_a_match(&app).await;
+    let guest_id = guest.user.id();
+    guest_socket.close().await;
+    let disconnected = host_socket
+        .next_matching(|event| matches!(event, ServerEvent::PlayerDisconnected { .. }))
+        .await;
+    assert_eq!(
+        disconnected.event,
+        ServerEvent::PlayerDisconnected {
+            user_id: guest_id,
+            forfeit_in_ms: None
+        }
+    );
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Without replaying what they missed, they're sent the whole match again.
+    let mut guest_socket = app.connect(&guest, None).await;
+    let in_progress = guest_socket
+        .next_matching(|event| matches!(event, ServerEvent::MatchInProgress { .. }))
+        .await;
+    match in_progress.event {
+        ServerEvent::MatchInProgress {
+            players, power_ups, ..
+        } => {
+            assert!(players.contains(&guest_id));
+            assert!(power_ups.is_empty());
+        }
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,165 @@
+use reqwest::header::CONTENT_DISPOSITION;
+use testcontainers_test::domain::{
+    battle_royale::Action, minesweeper::BoardTopology, protocol::ServerEvent, replay::Replay,
+};
+
+use crate::helpers::{spawn_test_app, spawn_test_app_with, TestSettings};
+
+#[tokio::test]
+async fn finished_matches_can_be_downloaded_as_replays() {
+    let app = spawn_test_app_with(TestSettings::fast_storm()).await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    let (match_id, flagged) = app.play_match(&host, &guest).await;
+
+    let response = guest
+        .client
+        .get(app.url(&format!("/matches/{}/replay", match_id)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()[CONTENT_DISPOSITION],
+        format!("attachment; filename=\"match-{}.replay\"", match_id)
+    );
+    let replay = Replay::decode(&response.bytes().await.unwrap()).unwrap();
+    assert_eq!(replay.match_id, match_id);
+    assert_eq!(replay.players, vec![host.user.id(), guest.user.id()]);
+    assert_eq!((replay.width, replay.height), (9, 9));
+    assert_eq!(replay.actions.len(), 1);
+    assert_eq!(replay.actions[0].user_id, host.user.id());
+    assert_eq!(replay.actions[0].action, Action::Flag(flagged));
+    assert_eq!(replay.last_tick, 20);
+}
+
+#[tokio::test]
+async fn replays_can_be_viewed_step_by_step() {
+    let app = spawn_test_app_with(TestSettings::fast_storm()).await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    let (match_id, _) = app.play_match(&host, &guest).await;
+
+    let page = host
+        .client
+        .get(app.url(&format!("/matches/{}/replay/view?index=1", match_id)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(page.status().as_u16(), 200);
+    let html = page.text().await.unwrap();
+    assert!(html.contains("The match started"));
+    assert!(html.contains("?index=2"));
+    assert!(html.contains("host (Playing)"));
+
+    let end = host
+        .client
+        .get(app.url(&format!("/matches/{}/replay/view", match_id)))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(end.contains("The match is over, host won"));
+
+    let out_of_range = host
+        .client
+        .get(app.url(&format!("/matches/{}/replay/view?index=1000", match_id)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(out_of_range.status().as_u16(), 400);
+    assert!(out_of_range
+        .text()
+        .await
+        .unwrap()
+        .contains("There is no event 1000"));
+}
+
+#[tokio::test]
+async fn hex_matches_are_replayed_on_hex_boards() {
+    let app = spawn_test_app_with(TestSettings::fast_storm()).await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    let mut socket = app.connect(&host, None).await;
+    app.start_match_on(&host, &guest, "hex").await;
+    let match_id = match socket
+        .next_matching(|event| matches!(event, ServerEvent::MatchStarted { .. }))
+        .await
+        .event
+    {
+        ServerEvent::MatchStarted {
+            match_id, board, ..
+        } => {
+            assert_eq!(board.topology, BoardTopology::Hex);
+            match_id
+        }
+        _ => unreachable!(),
+    };
+    socket
+        .next_matching(|event| matches!(event, ServerEvent::MatchFinished { .. }))
+        .await;
+
+    let bytes = host
+        .client
+        .get(app.url(&format!("/matches/{}/replay", match_id)))
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+    assert_eq!(Replay::decode(&bytes).unwrap().topology, BoardTopology::Hex);
+    let html = host
+        .client
+        .get(app.url(&format!("/matches/{}/replay/view", match_id)))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("flex pl2"));
+}
+
+#[tokio::test]
+async fn matches_being_played_have_no_replay_yet() {
+    let app = spawn_test_app().await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    let mut socket = app.connect(&host, None).await;
+    app.start_match(&host, &guest).await;
+    let match_id = match socket
+        .next_matching(|event| matches!(event, ServerEvent::MatchStarted { .. }))
+        .await
+        .event
+    {
+        ServerEvent::MatchStarted { match_id, .. } => match_id,
+        _ => unreachable!(),
+    };
+    let response = host
+        .client
+        .get(app.url(&format!("/matches/{}/replay", match_id)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[tokio::test]
+async fn unknown_matches_have_no_replay() {
+    let app = spawn_test_app().await;
+    let (client, _) = app.signed_in_client("player").await;
+    let response = client
+        .get(app.url("/matches/12345/replay"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = reqwest::get(app.url("/matches/12345/replay"))
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+}
@@ -0,0 +1,156 @@
+use std::time::{Duration, Instant};
+
+use testcontainers_test::domain::{
+    minesweeper::{BoardView, CellView, Position},
+    protocol::{ClientMessage, ServerEvent, SpectatorEvent},
+};
+use tokio_tungstenite::tungstenite::{Error, Message};
+
+use crate::helpers::{spawn_test_app, GameSocket};
+
+fn status_of(error: Error) -> u16 {
+    match error {
+        Error::Http(response) => response.status().as_u16(),
+        error => panic!("expected an HTTP error, got {:?}", error),
+    }
+}
+
+async fn match_started(socket: &mut GameSocket) -> (i64, BoardView) {
+    match socket
+        .next_matching(|event| matches!(event, ServerEvent::MatchStarted { .. }))
+        .await
+        .event
+    {
+        ServerEvent::MatchStarted {
+            match_id, board, ..
+        } => (match_id, board),
+        _ => unreachable!(),
+    }
+}
+
+fn assert_no_mines(board: &BoardView) {
+    assert!(!board.cells.contains(&CellView::Mine));
+}
+
+#[tokio::test]
+async fn only_live_matches_can_be_spectated_by_outsiders() {
+    let app = spawn_test_app().await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    let mut socket = app.connect(&host, None).await;
+
+    assert_eq!(status_of(app.spectate(&host, 1).await.err().unwrap()), 404);
+
+    app.start_match(&host, &guest).await;
+    let (match_id, _) = match_started(&mut socket).await;
+    assert_eq!(
+        status_of(app.spectate(&guest, match_id).await.err().unwrap()),
+        403
+    );
+}
+
+#[tokio::test]
+async fn spectators_follow_every_board_a_little_behind() {
+    let app = spawn_test_app().await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    let watcher = app.sign_up("watcher").await;
+    let mut host_socket = app.connect(&host, None).await;
+    app.start_match(&host, &guest).await;
+    let (match_id, mut board) = match_started(&mut host_socket).await;
+
+    let mut spectator = app.spectate(&watcher, match_id).await.unwrap();
+    match spectator.next_spectator_event().await {
+        SpectatorEvent::Spectating {
+            match_id: watched,
+            boards,
+            focus,
+            spectators,
+            delay_ms,
+        } => {
+            assert_eq!(watched, match_id);
+            assert_eq!(boards.len(), 2);
+            boards
+                .iter()
+                .for_each(|player| assert_no_mines(&player.board));
+            assert_eq!(focus, boards[0].user_id);
+            assert_eq!(spectators, 1);
+            assert_eq!(delay_ms, 200);
+        }
+        event => panic!("expected the spectating event first, got {:?}", event),
+    }
+    let count = host_socket
+        .next_matching(|event| matches!(event, ServerEvent::SpectatorCount { .. }))
+        .await;
+    assert!(matches!(
+        count.event,
+        ServerEvent::SpectatorCount { count: 1, .. }
+    ));
+
+    spectator
+        .send_raw(&format!(
+            r#"{{"type": "focus", "user_id": {}}}"#,
+            guest.user.id()
+        ))
+        .await;
+    let focused = spectator
+        .next_spectating(|event| matches!(event, SpectatorEvent::Focused { .. }))
+        .await;
+    assert!(
+        matches!(focused, SpectatorEvent::Focused { user_id, .. } if user_id == guest.user.id())
+    );
+    spectator
+        .send_raw(r#"{"type": "focus", "user_id": 12345}"#)
+        .await;
+    spectator
+        .next_spectating(|event| matches!(event, SpectatorEvent::Error { .. }))
+        .await;
+
+    // The host clicks around until their board is over one way or another, which ends the match.
+    let played_at = Instant::now();
+    let mut first_delay = None;
+    'playing: for index in 0..board.cells.len() {
+        if board.cells[index] != CellView::Hidden {
+            continue;
+        }
+        let position = Position::new(index % board.width, index / board.width);
+        host_socket.send(&ClientMessage::Reveal { position }).await;
+        let delta = host_socket
+            .next_matching(|event| matches!(event, ServerEvent::BoardDelta { .. }))
+            .await;
+        if let ServerEvent::BoardDelta {
+            revealed, status, ..
+        } = delta.event
+        {
+            for cell in revealed {
+                board.cells[cell.position.y * board.width + cell.position.x] =
+                    CellView::Revealed(cell.adjacent_mines);
+            }
+            if status.is_over() {
+                break 'playing;
+            }
+        }
+    }
+
+    loop {
+        match spectator.next_spectator_event().await {
+            SpectatorEvent::BoardDelta { user_id, .. } => {
+                assert_eq!(user_id, host.user.id());
+                first_delay.get_or_insert_with(|| played_at.elapsed());
+            }
+            SpectatorEvent::Focused { board, .. } => assert_no_mines(&board),
+            SpectatorEvent::MatchFinished {
+                match_id: finished, ..
+            } => {
+                assert_eq!(finished, match_id);
+                break;
+            }
+            _ => {}
+        }
+    }
+    assert!(first_delay.unwrap() >= Duration::from_millis(200));
+    let after = spectator.closed().await;
+    assert!(after
+        .iter()
+        .all(|message| !matches!(message, Message::Text(_))));
+}
@@ -0,0 +1,109 @@
+use serde_json::{json, Value};
+use std::time::Duration;
+use testcontainers_test::backfill_stats;
+
+use crate::helpers::{spawn_test_app, spawn_test_app_with, TestApp, TestSettings, TestUser};
+
+async fn stats_of(app: &TestApp, viewer: &TestUser, user: &TestUser) -> Value {
+    let response = viewer
+        .client
+        .get(app.url(&format!("/users/{}/stats", user.user.id())))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+/// Stats are updated in the background once a match is saved, so this waits for them to
+/// catch up.
+async fn wait_for_matches(app: &TestApp, user: &TestUser, matches: u64) -> Value {
+    for _ in 0..50 {
+        let stats = stats_of(app, user, user).await;
+        if stats["stats"]["matches_played"] == json!(matches) {
+            return stats;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("The stats were never updated");
+}
+
+fn achievement_ids(stats: &Value) -> Vec<&str> {
+    stats["achievements"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|achievement| achievement["id"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn finishing_a_match_counts_towards_stats_and_achievements() {
+    let app = spawn_test_app_with(TestSettings::fast_storm()).await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    let (match_id, _) = app.play_match(&host, &guest).await;
+
+    let stats = wait_for_matches(&app, &host, 1).await;
+    assert_eq!(stats["stats"]["wins"], json!(1));
+    assert_eq!(stats["stats"]["average_placement"], json!(1.0));
+    assert_eq!(achievement_ids(&stats), vec!["first_match", "first_win"]);
+    assert_eq!(stats["achievements"][0]["match_id"], json!(match_id));
+
+    let stats = wait_for_matches(&app, &guest, 1).await;
+    assert_eq!(stats["stats"]["wins"], json!(0));
+    assert_eq!(stats["stats"]["average_placement"], json!(2.0));
+    assert_eq!(achievement_ids(&stats), vec!["first_match"]);
+
+    let response = guest
+        .client
+        .get(app.url(&format!("/users/{}/profile", host.user.id())))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let page = response.text().await.unwrap();
+    assert!(page.contains("host"));
+    assert!(page.contains("Last One Standing"));
+}
+
+#[tokio::test]
+async fn backfilling_gives_the_same_stats() {
+    let app = spawn_test_app_with(TestSettings::fast_storm()).await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    app.play_match(&host, &guest).await;
+    let before = wait_for_matches(&app, &host, 1).await;
+
+    assert_eq!(backfill_stats(&app.db_path).await.unwrap(), 1);
+    let after = stats_of(&app, &host, &host).await;
+    assert_eq!(after, before);
+}
+
+#[tokio::test]
+async fn backfilling_leaves_daily_attempts_out_like_the_live_count_does() {
+    let app = spawn_test_app_with(TestSettings::fast_storm()).await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    app.play_match(&host, &guest).await;
+    let before = wait_for_matches(&app, &host, 1).await;
+    app.finish_daily_attempt(&host).await;
+
+    assert_eq!(backfill_stats(&app.db_path).await.unwrap(), 1);
+    let after = stats_of(&app, &host, &host).await;
+    assert_eq!(after, before);
+}
+
+#[tokio::test]
+async fn unknown_players_have_no_stats() {
+    let app = spawn_test_app().await;
+    let user = app.sign_up("user").await;
+    let stats = stats_of(&app, &user, &user).await;
+    assert_eq!(stats["stats"]["matches_played"], json!(0));
+    assert_eq!(stats["achievements"], json!([]));
+
+    for path in ["/users/9999/stats", "/users/9999/profile"] {
+        let response = user.client.get(app.url(path)).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 404);
+    }
+}
@@ -0,0 +1,216 @@
+use serde_json::{json, Value};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use testcontainers_test::domain::protocol::ServerEvent;
+
+use crate::helpers::{spawn_test_app, spawn_test_app_with, TestApp, TestSettings, TestUser};
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
+/// Creates a tournament with heats of two where the winner of each goes through, starting
+/// `starts_in` from now.
+async fn create_tournament(app: &TestApp, moderator: &TestUser, starts_in: Duration) -> Value {
+    let response = moderator
+        .client
+        .post(app.url("/tournaments"))
+        .json(&json!({
+            "name": "Friday Cup",
+            "starts_at": now_millis() + starts_in.as_millis() as i64,
+            "width": 9,
+            "height": 9,
+            "mine_density": 12,
+            "heat_size": 2,
+            "advance": 1,
+            "max_entrants": 8
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+async fn register(app: &TestApp, user: &TestUser, tournament_id: &Value) -> u16 {
+    user.client
+        .post(app.url(&format!("/tournaments/{}/registration", tournament_id)))
+        .send()
+        .await
+        .unwrap()
+        .status()
+        .as_u16()
+}
+
+#[tokio::test]
+async fn a_tournament_is_played_out_with_no_shows_knocked_out() {
+    let app = spawn_test_app_with(TestSettings::fast_storm()).await;
+    let moderator = app.sign_up("moderator").await;
+    app.make_moderator(&moderator).await;
+    let mut players = vec![];
+    for name in ["ada", "bob", "cy", "dee"] {
+        players.push(app.sign_up(name).await);
+    }
+    let tournament = create_tournament(&app, &moderator, Duration::from_secs(1)).await;
+    let tournament_id = &tournament["id"];
+    for player in &players {
+        assert_eq!(register(&app, player, tournament_id).await, 204);
+    }
+    // Nobody has a rating yet, so they're seeded in the order they registered: "ada" and
+    // "dee" are drawn together, and "dee" never turns up.
+    let mut sockets = vec![];
+    for player in &players[..3] {
+        sockets.push(app.connect(player, None).await);
+    }
+
+    let heat = sockets[0]
+        .next_matching(|event| matches!(event, ServerEvent::TournamentHeat { .. }))
+        .await;
+    match heat.event {
+        ServerEvent::TournamentHeat {
+            round,
+            heat,
+            players: drawn,
+            ..
+        } => {
+            assert_eq!((round, heat), (1, 1));
+            assert_eq!(
+                drawn,
+                vec![players[0].user.clone(), players[3].user.clone()]
+            );
+        }
+        _ => unreachable!(),
+    }
+    let over = sockets[0]
+        .next_matching(|event| matches!(event, ServerEvent::TournamentOver { .. }))
+        .await;
+    let winner = match over.event {
+        ServerEvent::TournamentOver { winner, .. } => winner.unwrap(),
+        _ => unreachable!(),
+    };
+    assert_ne!(winner, players[3].user);
+
+    let details: Value = players[0]
+        .client
+        .get(app.url(&format!("/tournaments/{}", tournament_id)))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(details["status"], json!("finished"));
+    assert_eq!(details["rounds"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        details["rounds"][0]["heats"][0]["players"][1]["no_show"],
+        json!(true)
+    );
+    let standings = details["standings"].as_array().unwrap();
+    assert_eq!(standings[0]["user"]["username"], json!(winner.username()));
+    assert_eq!(standings[3]["user"]["username"], json!("dee"));
+
+    let response = players[0]
+        .client
+        .get(app.url(&format!("/tournaments/{}/bracket", tournament_id)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let page = response.text().await.unwrap();
+    assert!(page.contains("Friday Cup"));
+    assert!(page.contains("no-show"));
+}
+
+#[tokio::test]
+async fn tournaments_without_enough_players_are_cancelled() {
+    let app = spawn_test_app().await;
+    let moderator = app.sign_up("moderator").await;
+    app.make_moderator(&moderator).await;
+    let player = app.sign_up("player").await;
+    let tournament = create_tournament(&app, &moderator, Duration::from_millis(200)).await;
+    assert_eq!(register(&app, &player, &tournament["id"]).await, 204);
+    let mut socket = app.connect(&player, None).await;
+    let over = socket
+        .next_matching(|event| matches!(event, ServerEvent::TournamentOver { .. }))
+        .await;
+    assert!(matches!(
+        over.event,
+        ServerEvent::TournamentOver { winner: None, .. }
+    ));
+    let listed: Vec<Value> = player
+        .client
+        .get(app.url("/tournaments"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(listed[0]["status"], json!("cancelled"));
+    assert_eq!(register(&app, &player, &tournament["id"]).await, 409);
+}
+
+#[tokio::test]
+async fn only_moderators_can_create_tournaments() {
+    let app = spawn_test_app().await;
+    let player = app.sign_up("player").await;
+    let response = player
+        .client
+        .post(app.url("/tournaments"))
+        .json(&json!({
+            "name": "My Cup",
+            "starts_at": now_millis() + 60_000,
+            "width": 9,
+            "height": 9,
+            "mine_density": 12,
+            "heat_size": 2,
+            "advance": 1,
+            "max_entrants": 8
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn registration_is_checked() {
+    let app = spawn_test_app().await;
+    let moderator = app.sign_up("moderator").await;
+    app.make_moderator(&moderator).await;
+    let tournament = create_tournament(&app, &moderator, Duration::from_secs(60)).await;
+    let tournament_id = &tournament["id"];
+    let player = app.sign_up("player").await;
+    assert_eq!(register(&app, &player, tournament_id).await, 204);
+    assert_eq!(register(&app, &player, tournament_id).await, 409);
+    assert_eq!(register(&app, &player, &json!(9999)).await, 404);
+
+    let withdraw = || {
+        player
+            .client
+            .delete(app.url(&format!("/tournaments/{}/registration", tournament_id)))
+            .send()
+    };
+    assert_eq!(withdraw().await.unwrap().status().as_u16(), 204);
+    assert_eq!(withdraw().await.unwrap().status().as_u16(), 404);
+
+    let response = moderator
+        .client
+        .post(app.url("/tournaments"))
+        .json(&json!({
+            "name": "Too late",
+            "starts_at": now_millis() - 1_000,
+            "width": 9,
+            "height": 9,
+            "mine_density": 12,
+            "heat_size": 2,
+            "advance": 1,
+            "max_entrants": 8
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+}
@@ -0,0 +1,385 @@
+use std::time::Duration;
+
+use testcontainers_test::{
+    config::WebSocketSettings,
+    domain::{
+        battle_royale::{BattleRoyaleSettings, EliminationReason},
+        minesweeper::{CellView, GameStatus, Position},
+        protocol::{ClientMessage, ServerEvent},
+    },
+};
+
+use crate::helpers::{spawn_test_app, spawn_test_app_with, TestSettings};
+
+#[tokio::test]
+async fn web_socket_requires_a_session() {
+    let app = spawn_test_app().await;
+    assert!(app.try_connect("session_id=nope", None).await.is_err());
+}
+
+#[tokio::test]
+async fn connecting_sends_a_connected_event() {
+    let app = spawn_test_app().await;
+    let user = app.sign_up("player").await;
+    let mut socket = app.connect(&user, None).await;
+    let message = socket.next_message().await;
+    assert_eq!(message.seq, 1);
+    assert_eq!(
+        message.event,
+        ServerEvent::Connected {
+            user_id: user.user.id(),
+            resumed: false
+        }
+    );
+}
+
+#[tokio::test]
+async fn invalid_messages_are_answered_with_an_error() {
+    let app = spawn_test_app().await;
+    let user = app.sign_up("player").await;
+    let mut socket = app.connect(&user, None).await;
+    socket.send_raw(r#"{"type": "dance"}"#).await;
+    let message = socket
+        .next_matching(|event| matches!(event, ServerEvent::Error { .. }))
+        .await;
+    assert_eq!(message.seq, 2);
+
+    socket
+        .send(&ClientMessage::Reveal {
+            position: Position::new(0, 0),
+        })
+        .await;
+    let message = socket
+        .next_matching(|event| matches!(event, ServerEvent::Error { .. }))
+        .await;
+    assert_eq!(
+        message.event,
+        ServerEvent::Error {
+            message: String::from("You are not in a match")
+        }
+    );
+}
+
+#[tokio::test]
+async fn players_in_a_match_play_their_boards_to_the_end() {
+    let app = spawn_test_app().await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    let mut host_socket = app.connect(&host, None).await;
+    let mut guest_socket = app.connect(&guest, None).await;
+    app.start_match(&host, &guest).await;
+
+    let is_match_start = |event: &ServerEvent| matches!(event, ServerEvent::MatchStarted { .. });
+    let started = host_socket.next_matching(is_match_start).await.event;
+    let (match_id, board, start) = match started {
+        ServerEvent::MatchStarted {
+            match_id,
+            board,
+            start,
+            ..
+        } => (match_id, board, start),
+        _ => unreachable!(),
+    };
+    guest_socket.next_matching(is_match_start).await;
+    assert!(matches!(
+        board.cells[start.y * board.width + start.x],
+        CellView::Revealed(_)
+    ));
+    assert!(board.cells.iter().all(|cell| *cell != CellView::Mine));
+
+    // The host clicks through every hidden cell until they either clear the board or hit
+    // a mine. Either way the match ends, since there are only two players.
+    for (index, _) in board
+        .cells
+        .iter()
+        .enumerate()
+        .filter(|(_, cell)| **cell == CellView::Hidden)
+    {
+        host_socket
+            .send(&ClientMessage::Reveal {
+                position: Position::new(index % board.width, index / board.width),
+            })
+            .await;
+        let delta = host_socket
+            .next_matching(|event| matches!(event, ServerEvent::BoardDelta { .. }))
+            .await;
+        match delta.event {
+            ServerEvent::BoardDelta { status, .. } if status.is_over() => break,
+            ServerEvent::BoardDelta {
+                status: GameStatus::Playing,
+                ..
+            } => {}
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+    let opponent_update = guest_socket
+        .next_matching(|event| {
+            matches!(
+                event,
+                ServerEvent::OpponentProgress { .. } | ServerEvent::Eliminated { .. }
+            )
+        })
+        .await;
+    match opponent_update.event {
+        ServerEvent::OpponentProgress { user_id, .. } | ServerEvent::Eliminated { user_id, .. } => {
+            assert_eq!(user_id, host.user.id())
+        }
+        _ => unreachable!(),
+    }
+    for socket in [&mut host_socket, &mut guest_socket] {
+        let finished = socket
+            .next_matching(|event| matches!(event, ServerEvent::MatchFinished { .. }))
+            .await;
+        match finished.event {
+            ServerEvent::MatchFinished {
+                match_id: finished_id,
+                standings,
+            } => {
+                assert_eq!(finished_id, match_id);
+                assert_eq!(standings.len(), 2);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[tokio::test]
+async fn the_storm_finishes_matches_nobody_plays() {
+    let app = spawn_test_app_with(TestSettings {
+        battle_royale: BattleRoyaleSettings {
+            tick_length: Duration::from_millis(10),
+            first_storm: 5,
+            storm_interval: 5,
+            ..BattleRoyaleSettings::default()
+        },
+        ..TestSettings::default()
+    })
+    .await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    let mut host_socket = app.connect(&host, None).await;
+    let mut guest_socket = app.connect(&guest, None).await;
+    app.start_match(&host, &guest).await;
+
+    let timer = host_socket
+        .next_matching(|event| matches!(event, ServerEvent::Timer { .. }))
+        .await;
+    assert_eq!(timer.event, ServerEvent::Timer { remaining_ms: 50 });
+    for socket in [&mut host_socket, &mut guest_socket] {
+        let eliminated = socket
+            .next_matching(|event| matches!(event, ServerEvent::Eliminated { .. }))
+            .await;
+        // Neither player has moved, so the one who joined last is out first.
+        assert_eq!(
+            eliminated.event,
+            ServerEvent::Eliminated {
+                user_id: guest.user.id(),
+                placement: 2,
+                reason: EliminationReason::Storm,
+            }
+        );
+        let finished = socket
+            .next_matching(|event| matches!(event, ServerEvent::MatchFinished { .. }))
+            .await;
+        match finished.event {
+            ServerEvent::MatchFinished { standings, .. } => {
+                assert_eq!(standings[0].user_id, host.user.id());
+                assert_eq!(standings[1].user_id, guest.user.id());
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[tokio::test]
+async fn chat_is_sent_to_everyone_in_the_lobby() {
+    let app = spawn_test_app().await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    app.start_match(&host, &guest).await;
+    let mut host_socket = app.connect(&host, None).await;
+    let mut guest_socket = app.connect(&guest, None).await;
+
+    host_socket
+        .send(&ClientMessage::Chat {
+            text: String::from("  good luck  "),
+        })
+        .await;
+    let mut message_ids = vec![];
+    for socket in [&mut host_socket, &mut guest_socket] {
+        let message = socket
+            .next_matching(|event| matches!(event, ServerEvent::Chat { .. }))
+            .await;
+        match message.event {
+            ServerEvent::Chat {
+                message_id,
+                from,
+                text,
+            } => {
+                assert_eq!(from, host.user.id());
+                assert_eq!(text, "good luck");
+                message_ids.push(message_id);
+            }
+            _ => unreachable!(),
+        }
+    }
+    assert_eq!(message_ids[0], message_ids[1]);
+}
+
+#[tokio::test]
+async fn reconnecting_resumes_from_the_last_event_seen() {
+    let app = spawn_test_app().await;
+    let host = app.sign_up("host").await;
+    let guest = app.sign_up("guest").await;
+    app.start_match(&host, &guest).await;
+    let mut guest_socket = app.connect(&guest, None).await;
+    // Connecting in the middle of the match catches the guest up with it, timer last.
+    let last_seen = guest_socket
+        .next_matching(|event| matches!(event, ServerEvent::Timer { .. }))
+        .await
+        .seq;
+    guest_socket.close().await;
+
+    let mut host_socket = app.connect(&host, None).await;
+    host_socket
+        .send(&ClientMessage::Chat {
+            text: String::from("where did you go?"),
+        })
+        .await;
+    host_socket
+        .next_matching(|event| matches!(event, ServerEvent::Chat { .. }))
+        .await;
+
+    let mut guest_socket = app.connect(&guest, Some(last_seen)).await;
+    let missed = guest_socket.next_message().await;
+    assert_eq!(missed.seq, last_seen + 1);
+    assert!(matches!(missed.event, ServerEvent::Chat { .. }));
+    let connected = guest_socket.next_message().await;
+    assert_eq!(
+        connected.event,
+        ServerEvent::Connected {
+            user_id: guest.user.id(),
+            resumed: true
+        }
+    );
+}
+
+#[tokio::test]
+async fn resuming_from_a_forgotten_event_asks_for_a_full_refresh() {
+    let app = spawn_test_app_with(TestSettings {
+        websocket: WebSocketSettings {
+            replay_limit: 2,
+            ..WebSocketSettings::default()
+        },
+        ..TestSettings::default()
+    })
+    .await;
+    let user = app.sign_up("player").await;
+    for _ in 0..3 {
+        app.connect(&user, None).await.next_message().await;
+    }
+    let mut socket = app.connect(&user, Some(0)).await;
+    let connected = socket
+        .next_matching(|event| matches!(event, ServerEvent::Connected { .. }))
+        .await;
+    assert_eq!(
+        connected.event,
+        ServerEvent::Connected {
+            user_id: user.user.id(),
+            resumed: false
+        }
+    );
+}
+
+#[tokio::test]
+async fn resuming_after_the_replay_window_asks_for_a_full_refresh() {
+    let app = spawn_test_app_with(TestSettings {
+        websocket: WebSocketSettings {
+            replay_window: Duration::from_millis(100),
+            ..WebSocketSettings::default()
+        },
+        ..TestSettings::default()
+    })
+    .await;
+    let user = app.sign_up("player").await;
+    let mut socket = app.connect(&user, None).await;
+    let last_seen = socket.next_message().await.seq;
+    socket.close().await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let mut socket = app.connect(&user, Some(last_seen)).await;
+    let connected = socket
+        .next_matching(|event| matches!(event, ServerEvent::Connected { .. }))
+        .await;
+    assert_eq!(
+        connected.event,
+        ServerEvent::Connected {
+            user_id: user.user.id(),
+            resumed: false
+        }
+    );
+}
+
+#[tokio::test]
+async fn resuming_from_past_the_last_event_asks_for_a_full_refresh() {
+    let app = spawn_test_app().await;
+    let user = app.sign_up("player").await;
+    for _ in 0..2 {
+        let mut socket = app.connect(&user, Some(u64::MAX)).await;
+        let connected = socket
+            .next_matching(|event| matches!(event, ServerEvent::Connected { .. }))
+            .await;
+        assert_eq!(
+            connected.event,
+            ServerEvent::Connected {
+                user_id: user.user.id(),
+                resumed: false
+            }
+        );
+    }
+}
+
+#[tokio::test]
+async fn acknowledged_events_are_not_replayed() {
+    let app = spawn_test_app().await;
+    let user = app.sign_up("player").await;
+    let mut socket = app.connect(&user, None).await;
+    let first = socket.next_message().await;
+    socket.send(&ClientMessage::Ack { seq: first.seq }).await;
+    socket.send_raw("not json").await;
+    socket.next_message().await;
+    socket.close().await;
+
+    // Event 1 was acknowledged, so it can't be replayed, but event 2 still can be.
+    let mut socket = app.connect(&user, Some(1)).await;
+    assert_eq!(socket.next_message().await.seq, 2);
+}
+
+#[tokio::test]
+async fn a_new_connection_replaces_the_old_one() {
+    let app = spawn_test_app().await;
+    let user = app.sign_up("player").await;
+    let mut first = app.connect(&user, None).await;
+    first.next_message().await;
+    let _second = app.connect(&user, None).await;
+    first.closed().await;
+}
+
+#[tokio::test]
+async fn silent_clients_are_disconnected() {
+    let app = spawn_test_app_with(TestSettings {
+        websocket: WebSocketSettings {
+            heartbeat_interval: Duration::from_millis(50),
+            client_timeout: Duration::from_millis(200),
+            ..WebSocketSettings::default()
+        },
+        ..TestSettings::default()
+    })
+    .await;
+    let user = app.sign_up("player").await;
+    let mut socket = app.connect(&user, None).await;
+    // Not reading means the client never answers the server's pings.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let seen = socket.closed().await;
+    assert!(!seen.is_empty());
+}
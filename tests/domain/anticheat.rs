@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use testcontainers_test::domain::{
+    anticheat::*,
+    battle_royale::{Action, BattleRoyaleSettings},
+    bot::{Bot, BotSkill, GuessPolicy},
+    minesweeper::{
+        generate_no_guess, BoardConfig, BoardTopology, GeneratorOptions, Position, Technique,
+    },
+    replay::{Replay, ReplayAction},
+};
+
+fn pos(x: usize, y: usize) -> Position {
+    Position::new(x, y)
+}
+
+fn evidence(replay: Replay) -> MatchEvidence {
+    MatchEvidence {
+        match_id: replay.match_id,
+        replay,
+        addresses: HashMap::new(),
+    }
+}
+
+/// A 9x9 board where a third of the cells are mines, so guessing blind goes wrong a lot.
+fn dense_replay(actions: Vec<ReplayAction>) -> Replay {
+    let mines = (0..9)
+        .flat_map(|y| (0..9).map(move |x| pos(x, y)))
+        .filter(|position| (position.x + 2 * position.y) % 3 == 0)
+        .collect();
+    Replay {
+        match_id: 1,
+        width: 9,
+        height: 9,
+        topology: BoardTopology::Square,
+        mines,
+        start: pos(1, 0),
+        players: vec![1, 2],
+        settings: BattleRoyaleSettings::default(),
+        actions,
+        last_tick: 1000,
+    }
+}
+
+fn reveal(tick: u64, user_id: i64, position: Position) -> ReplayAction {
+    ReplayAction {
+        tick,
+        user_id,
+        action: Action::Reveal(position),
+    }
+}
+
+#[test]
+fn bursts_of_actions_are_too_quick_to_be_human() {
+    // Ticks are 100ms, so 30 actions in 5 ticks is 60 a second, while a reveal every 2 ticks
+    // is only 5 a second.
+    let mut actions = vec![];
+    for tick in 0..40 {
+        actions.push(reveal(tick * 2, 2, pos(0, 0)));
+    }
+    for index in 0..30 {
+        actions.push(reveal(100 + index / 6, 1, pos(0, 0)));
+    }
+    actions.sort_by_key(|action| action.tick);
+    let suspicions =
+        ClickRate::new(&AntiCheatSettings::default()).inspect(&evidence(dense_replay(actions)));
+    assert_eq!(suspicions.len(), 1);
+    assert_eq!(suspicions[0].user_id, 1);
+    assert!(suspicions[0].score > 1.0);
+}
+
+#[test]
+fn never_hitting_a_mine_while_guessing_is_suspicious() {
+    // Player 1 knows where every mine is and reveals everything else in order, regardless
+    // of whether the numbers gave anything away.
+    let replay = dense_replay(vec![]);
+    let actions = (0..9)
+        .flat_map(|y| (0..9).map(move |x| pos(x, y)))
+        .filter(|position| !replay.mines.contains(position))
+        .enumerate()
+        .map(|(index, position)| reveal(index as u64 * 10, 1, position))
+        .collect();
+    let suspicions =
+        GuessLuck::new(&AntiCheatSettings::default()).inspect(&evidence(dense_replay(actions)));
+    assert_eq!(suspicions.len(), 1);
+    assert_eq!(suspicions[0].user_id, 1);
+    assert!(suspicions[0].score >= 1.0);
+}
+
+#[test]
+fn reveals_that_could_be_worked_out_are_not_guesses() {
+    let config = BoardConfig::new(9, 9, 10).unwrap();
+    let generated = generate_no_guess(config, 3, pos(4, 4), &GeneratorOptions::default()).unwrap();
+    let mut replay = Replay {
+        match_id: 1,
+        width: 9,
+        height: 9,
+        topology: BoardTopology::Square,
+        mines: generated.layout.mine_positions(),
+        start: generated.start,
+        players: vec![1],
+        settings: BattleRoyaleSettings::default(),
+        actions: vec![],
+        last_tick: 0,
+    };
+    let mut engine = replay.engine().unwrap();
+    let skill = BotSkill {
+        error_rate: 0.0,
+        guess_policy: GuessPolicy::Never,
+        max_technique: Technique::Enumeration,
+        ..BotSkill::default()
+    };
+    let mut bot = Bot::new(skill, 3);
+    while let Some(action) = engine.view(1).and_then(|view| bot.next_action(&view)) {
+        replay.actions.push(ReplayAction {
+            tick: replay.actions.len() as u64 * 10,
+            user_id: 1,
+            action,
+        });
+        engine.apply(1, action).unwrap();
+    }
+    let settings = AntiCheatSettings {
+        min_guesses: 0,
+        ..AntiCheatSettings::default()
+    };
+    assert!(GuessLuck::new(&settings)
+        .inspect(&evidence(replay))
+        .is_empty());
+}
+
+#[test]
+fn players_sharing_an_address_are_suspicious() {
+    let mut replay = dense_replay(vec![]);
+    replay.players = vec![1, 2, 3];
+    let mut evidence = evidence(replay);
+    evidence.addresses = HashMap::from([
+        (1, vec![String::from("10.0.0.1"), String::from("10.0.0.2")]),
+        (2, vec![String::from("10.0.0.2")]),
+        (3, vec![String::from("10.0.0.3")]),
+    ]);
+    let suspicions = SharedAddress::new(&AntiCheatSettings::default()).inspect(&evidence);
+    let users: Vec<i64> = suspicions
+        .iter()
+        .map(|suspicion| suspicion.user_id)
+        .collect();
+    assert_eq!(users, vec![1, 2]);
+    assert!(suspicions[0].reason.contains("player 2"));
+}
+
+#[test]
+fn sharing_an_address_is_not_enough_to_be_flagged_on_its_own() {
+    let mut evidence = evidence(dense_replay(vec![]));
+    evidence.addresses = HashMap::from([
+        (1, vec![String::from("10.0.0.1")]),
+        (2, vec![String::from("10.0.0.1")]),
+    ]);
+    let review = Pipeline::standard(&AntiCheatSettings::default()).review(&evidence);
+    assert_eq!(review.findings.len(), 2);
+    assert!(review.flagged.is_empty());
+}
+
+/// A detector that finds every player a little suspicious.
+struct Wary(f64);
+
+impl Detector for Wary {
+    fn name(&self) -> &'static str {
+        "wary"
+    }
+
+    fn inspect(&self, evidence: &MatchEvidence) -> Vec<Suspicion> {
+        evidence
+            .replay
+            .players
+            .iter()
+            .map(|&user_id| Suspicion {
+                user_id,
+                score: self.0,
+                reason: String::from("Seemed shifty"),
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn players_are_flagged_once_their_findings_add_up() {
+    let evidence = evidence(dense_replay(vec![]));
+    let once = Pipeline::new(1.0).with(Wary(0.6)).review(&evidence);
+    assert_eq!(once.findings.len(), 2);
+    assert_eq!(once.findings[0].detector, "wary");
+    assert!(once.flagged.is_empty());
+
+    let twice = Pipeline::new(1.0)
+        .with(Wary(0.6))
+        .with(Wary(0.6))
+        .review(&evidence);
+    assert_eq!(twice.findings.len(), 4);
+    let flagged: Vec<i64> = twice.flagged.iter().map(|(user_id, _)| *user_id).collect();
+    assert_eq!(flagged, vec![1, 2]);
+}
+
+#[test]
+fn an_ordinary_match_is_not_flagged() {
+    let actions = vec![reveal(10, 1, pos(2, 0)), reveal(20, 2, pos(7, 8))];
+    let review =
+        Pipeline::standard(&AntiCheatSettings::default()).review(&evidence(dense_replay(actions)));
+    assert_eq!(review, Review::default());
+}
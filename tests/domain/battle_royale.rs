@@ -0,0 +1,450 @@
+use std::time::{Duration, Instant};
+
+use testcontainers_test::domain::{
+    battle_royale::*,
+    minesweeper::{BoardError, CellView, MineLayout, Position},
+};
+
+fn pos(x: usize, y: usize) -> Position {
+    Position::new(x, y)
+}
+
+/// ```text
+/// 1 1 1 . .
+/// 1 * 1 . .
+/// 1 1 2 1 1
+/// . . 1 * 1
+/// . . 1 1 1
+/// ```
+///
+/// Everyone starts on the top left corner, which only reveals itself.
+fn battle_royale(players: &[i64], settings: BattleRoyaleSettings) -> BattleRoyale {
+    let layout = MineLayout::from_positions(5, 5, &[pos(1, 1), pos(3, 3)]).unwrap();
+    BattleRoyale::new(layout, pos(0, 0), players, settings).unwrap()
+}
+
+fn storm_every(first_storm: u64, storm_interval: u64) -> BattleRoyaleSettings {
+    BattleRoyaleSettings {
+        first_storm,
+        storm_interval,
+        ..BattleRoyaleSettings::default()
+    }
+}
+
+/// Ticks the clock up to `tick`, or until the match is over.
+fn tick_until(engine: &mut BattleRoyale, tick: u64) -> Vec<MatchEvent> {
+    let mut events = vec![];
+    while engine.current_tick() < tick && !engine.is_over() {
+        events.extend(engine.tick());
+    }
+    events
+}
+
+fn eliminations(events: &[MatchEvent]) -> Vec<(i64, usize, EliminationReason)> {
+    events
+        .iter()
+        .filter_map(|event| match *event {
+            MatchEvent::Eliminated {
+                user_id,
+                placement,
+                reason,
+            } => Some((user_id, placement, reason)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn every_player_gets_the_same_board() {
+    let engine = battle_royale(&[1, 2, 3], BattleRoyaleSettings::default());
+    let view = engine.view(1).unwrap();
+    assert_eq!(view.cells[0], CellView::Revealed(1));
+    assert_eq!(engine.view(2).unwrap(), view);
+    assert_eq!(engine.view(3).unwrap(), view);
+    assert_eq!(engine.view(4), None);
+}
+
+#[test]
+fn players_only_see_their_own_moves() {
+    let mut engine = battle_royale(&[1, 2], BattleRoyaleSettings::default());
+    let events = engine.apply(1, Action::Reveal(pos(4, 0))).unwrap();
+    assert!(matches!(
+        events[..],
+        [
+            MatchEvent::BoardChanged { user_id: 1, .. },
+            MatchEvent::Progress {
+                user_id: 1,
+                safe_cells: 23,
+                ..
+            }
+        ]
+    ));
+    assert_ne!(engine.view(1), engine.view(2));
+}
+
+#[test]
+fn hitting_a_mine_knocks_a_player_out() {
+    let mut engine = battle_royale(&[1, 2, 3], BattleRoyaleSettings::default());
+    let events = engine.apply(1, Action::Reveal(pos(1, 1))).unwrap();
+    assert_eq!(eliminations(&events), vec![(1, 3, EliminationReason::Mine)]);
+    assert!(!engine.is_active(1));
+    assert!(!engine.is_over());
+    assert_eq!(
+        engine.apply(1, Action::Reveal(pos(4, 0))),
+        Err(ActionError::InvalidMove {
+            source: BoardError::GameOver
+        })
+    );
+}
+
+#[test]
+fn the_last_player_standing_wins() {
+    let mut engine = battle_royale(&[1, 2], BattleRoyaleSettings::default());
+    engine.apply(1, Action::Reveal(pos(0, 1))).unwrap();
+    let events = engine.apply(2, Action::Reveal(pos(3, 3))).unwrap();
+    let result = match events.last() {
+        Some(MatchEvent::Finished(result)) => result.clone(),
+        other => panic!("expected the match to finish, got {:?}", other),
+    };
+    assert_eq!(result.winner(), Some(1));
+    assert_eq!(
+        result.standings,
+        vec![
+            Standing {
+                user_id: 1,
+                placement: 1,
+                revealed: 2,
+                outcome: Outcome::Survived,
+            },
+            Standing {
+                user_id: 2,
+                placement: 2,
+                revealed: 1,
+                outcome: Outcome::Eliminated {
+                    reason: EliminationReason::Mine,
+                    tick: 0,
+                },
+            },
+        ]
+    );
+    assert_eq!(engine.result(), Some(&result));
+    assert_eq!(engine.next_storm(), None);
+}
+
+#[test]
+fn clearing_the_board_wins_and_ranks_everyone_else_by_progress() {
+    let mut engine = battle_royale(&[1, 2, 3], BattleRoyaleSettings::default());
+    engine.apply(3, Action::Reveal(pos(0, 1))).unwrap();
+    engine.tick();
+    for y in 0..5 {
+        for x in 0..5 {
+            if ![pos(1, 1), pos(3, 3)].contains(&pos(x, y)) && !engine.is_over() {
+                let _ = engine.apply(1, Action::Reveal(pos(x, y)));
+            }
+        }
+    }
+    let result = engine.result().unwrap();
+    let placements: Vec<(i64, usize, Outcome)> = result
+        .standings
+        .iter()
+        .map(|standing| (standing.user_id, standing.placement, standing.outcome))
+        .collect();
+    assert_eq!(
+        placements,
+        vec![
+            (1, 1, Outcome::Cleared { tick: 1 }),
+            (3, 2, Outcome::Survived),
+            (2, 3, Outcome::Survived),
+        ]
+    );
+}
+
+#[test]
+fn the_storm_knocks_out_whoever_is_furthest_behind() {
+    let mut engine = battle_royale(&[1, 2, 3], storm_every(10, 5));
+    engine.apply(1, Action::Reveal(pos(0, 1))).unwrap();
+    engine.apply(2, Action::Reveal(pos(4, 0))).unwrap();
+
+    let events = tick_until(&mut engine, 9);
+    assert!(events.is_empty());
+    let events = engine.tick();
+    assert_eq!(
+        events,
+        vec![
+            MatchEvent::Eliminated {
+                user_id: 3,
+                placement: 3,
+                reason: EliminationReason::Storm,
+            },
+            MatchEvent::StormScheduled { tick: 15 },
+        ]
+    );
+    assert_eq!(
+        engine.apply(3, Action::Reveal(pos(0, 1))),
+        Err(ActionError::InvalidMove {
+            source: BoardError::GameOver
+        })
+    );
+
+    let events = tick_until(&mut engine, 15);
+    assert_eq!(
+        eliminations(&events),
+        vec![(1, 2, EliminationReason::Storm)]
+    );
+    let result = engine.result().unwrap();
+    assert_eq!(result.winner(), Some(2));
+    assert_eq!(result.ticks, 15);
+    assert!(engine.tick().is_empty());
+    assert_eq!(engine.current_tick(), 15);
+}
+
+#[test]
+fn the_storm_breaks_ties_against_whoever_got_there_last() {
+    let mut engine = battle_royale(&[1, 2, 3], storm_every(10, 5));
+    engine.apply(2, Action::Reveal(pos(0, 1))).unwrap();
+    tick_until(&mut engine, 3);
+    engine.apply(1, Action::Reveal(pos(2, 0))).unwrap();
+    engine.apply(3, Action::Reveal(pos(4, 0))).unwrap();
+    let events = tick_until(&mut engine, 10);
+    assert_eq!(
+        eliminations(&events),
+        vec![(1, 3, EliminationReason::Storm)]
+    );
+}
+
+#[test]
+fn the_storm_keeps_coming_until_one_player_is_left() {
+    let players: Vec<i64> = (1..=6).collect();
+    let mut engine = battle_royale(&players, storm_every(100, 50));
+    let events = tick_until(&mut engine, 1_000);
+    let eliminated: Vec<i64> = eliminations(&events)
+        .into_iter()
+        .map(|(user_id, _, _)| user_id)
+        .collect();
+    // Nobody has moved, so the players that joined last go first.
+    assert_eq!(eliminated, vec![6, 5, 4, 3, 2]);
+    let result = engine.result().unwrap();
+    assert_eq!(result.winner(), Some(1));
+    assert_eq!(result.ticks, 300);
+}
+
+#[test]
+fn a_tick_lasts_as_long_as_the_settings_say() {
+    let settings = BattleRoyaleSettings::default();
+    assert_eq!(settings.duration_of(600), Duration::from_secs(60));
+}
+
+#[test]
+fn forfeiting_knocks_a_player_out_on_the_spot() {
+    let mut engine = battle_royale(&[1, 2, 3], BattleRoyaleSettings::default());
+    let events = engine.apply(2, Action::Forfeit).unwrap();
+    assert_eq!(
+        eliminations(&events),
+        vec![(2, 3, EliminationReason::Forfeit)]
+    );
+    assert!(!engine.is_active(2));
+    assert_eq!(
+        engine.apply(2, Action::Forfeit),
+        Err(ActionError::InvalidMove {
+            source: BoardError::GameOver
+        })
+    );
+
+    engine.apply(3, Action::Forfeit).unwrap();
+    assert_eq!(engine.result().unwrap().winner(), Some(1));
+}
+
+#[test]
+fn absent_players_keep_their_first_grace_period() {
+    let start = Instant::now();
+    let grace = Duration::from_secs(10);
+    let mut absences = Absences::default();
+    assert_eq!(absences.left(1, start, grace), start + grace);
+    assert_eq!(
+        absences.left(2, start + Duration::from_secs(5), grace),
+        start + Duration::from_secs(15)
+    );
+    // Dropping out again before coming back doesn't buy any more time.
+    assert_eq!(
+        absences.left(1, start + Duration::from_secs(8), grace),
+        start + grace
+    );
+    assert!(absences.overdue(start + Duration::from_secs(9)).is_empty());
+    assert_eq!(absences.overdue(start + grace), vec![1]);
+    assert!(!absences.is_away(1));
+
+    assert!(absences.returned(2));
+    assert!(!absences.returned(2));
+    assert!(absences.overdue(start + Duration::from_secs(60)).is_empty());
+}
+
+/// Power-ups on, with the board split into 2x2 regions and the storm out of the way.
+fn with_power_ups() -> BattleRoyaleSettings {
+    BattleRoyaleSettings {
+        power_ups: PowerUpSettings {
+            enabled: true,
+            region_size: 2,
+            region_ticks: 10,
+            max_held: 3,
+            fog_ticks: 5,
+        },
+        ..storm_every(1000, 1000)
+    }
+}
+
+fn earned(events: &[MatchEvent]) -> Vec<PowerUp> {
+    events
+        .iter()
+        .filter_map(|event| match *event {
+            MatchEvent::PowerUpEarned { power_up, .. } => Some(power_up),
+            _ => None,
+        })
+        .collect()
+}
+
+fn use_power_up(power_up: PowerUp, target: Option<i64>) -> Action {
+    Action::UsePowerUp(PowerUpUse { power_up, target })
+}
+
+#[test]
+fn clearing_a_region_quickly_earns_a_power_up() {
+    let mut engine = battle_royale(&[1, 2], with_power_ups());
+    // The top left region is the start, (1, 0), (0, 1) and the mine at (1, 1).
+    assert_eq!(
+        earned(&engine.apply(1, Action::Reveal(pos(1, 0))).unwrap()),
+        vec![]
+    );
+    assert_eq!(
+        earned(&engine.apply(1, Action::Reveal(pos(0, 1))).unwrap()),
+        vec![PowerUp::Reveal]
+    );
+    assert_eq!(engine.power_ups(1), [PowerUp::Reveal]);
+
+    // Too slow.
+    engine.apply(2, Action::Reveal(pos(1, 0))).unwrap();
+    tick_until(&mut engine, 11);
+    assert_eq!(
+        earned(&engine.apply(2, Action::Reveal(pos(0, 1))).unwrap()),
+        vec![]
+    );
+    assert_eq!(engine.power_ups(2), []);
+
+    // This clears the two regions to the right of the start at once, and they're handed
+    // out in order.
+    assert_eq!(
+        earned(&engine.apply(2, Action::Reveal(pos(3, 0))).unwrap()),
+        vec![PowerUp::Reveal, PowerUp::Shield]
+    );
+}
+
+#[test]
+fn power_ups_are_off_unless_the_settings_turn_them_on() {
+    let mut engine = battle_royale(&[1, 2], storm_every(1000, 1000));
+    engine.apply(1, Action::Reveal(pos(1, 0))).unwrap();
+    assert_eq!(
+        earned(&engine.apply(1, Action::Reveal(pos(0, 1))).unwrap()),
+        vec![]
+    );
+    assert_eq!(
+        engine.apply(1, use_power_up(PowerUp::Reveal, None)),
+        Err(ActionError::PowerUpsDisabled)
+    );
+}
+
+#[test]
+fn players_can_only_use_power_ups_they_hold() {
+    let mut engine = battle_royale(&[1, 2], with_power_ups());
+    assert_eq!(
+        engine.apply(1, use_power_up(PowerUp::Shield, None)),
+        Err(ActionError::NoPowerUp {
+            power_up: PowerUp::Shield
+        })
+    );
+}
+
+#[test]
+fn a_reveal_power_up_uncovers_a_safe_cell_next_to_what_is_already_revealed() {
+    let mut engine = battle_royale(&[1, 2], with_power_ups());
+    engine.apply(1, Action::Reveal(pos(1, 0))).unwrap();
+    engine.apply(1, Action::Reveal(pos(0, 1))).unwrap();
+    let events = engine
+        .apply(1, use_power_up(PowerUp::Reveal, None))
+        .unwrap();
+    assert_eq!(
+        events[0],
+        MatchEvent::PowerUpUsed {
+            user_id: 1,
+            power_up: PowerUp::Reveal,
+            target: None,
+        }
+    );
+    let revealed = match &events[1] {
+        MatchEvent::BoardChanged { revealed, .. } => revealed.clone(),
+        event => panic!("Expected the board to change, got {:?}", event),
+    };
+    // The only hidden safe cells touching the top left are (2, 0), (2, 1), (0, 2) and (1, 2).
+    assert!([pos(2, 0), pos(2, 1), pos(0, 2), pos(1, 2)].contains(&revealed[0].position));
+    assert_eq!(engine.power_ups(1), []);
+    assert!(engine.is_active(1));
+}
+
+#[test]
+fn a_shield_takes_the_hit_from_one_mine() {
+    let mut engine = battle_royale(&[1, 2], with_power_ups());
+    engine.apply(1, Action::Reveal(pos(3, 0))).unwrap();
+    engine
+        .apply(1, use_power_up(PowerUp::Shield, None))
+        .unwrap();
+    assert!(engine.is_shielded(1));
+    assert_eq!(
+        engine.apply(1, use_power_up(PowerUp::Shield, None)),
+        Err(ActionError::NoPowerUp {
+            power_up: PowerUp::Shield
+        })
+    );
+
+    let events = engine.apply(1, Action::Reveal(pos(1, 1))).unwrap();
+    assert_eq!(eliminations(&events), vec![]);
+    assert!(events.contains(&MatchEvent::ShieldBroken {
+        user_id: 1,
+        position: pos(1, 1),
+    }));
+    assert!(engine.is_active(1));
+    assert!(!engine.is_shielded(1));
+    assert_eq!(engine.view(1).unwrap().cells[6], CellView::Flagged);
+
+    let events = engine.apply(1, Action::Reveal(pos(3, 3))).unwrap();
+    assert_eq!(eliminations(&events), vec![(1, 2, EliminationReason::Mine)]);
+}
+
+#[test]
+fn fog_hides_an_opponents_numbers_for_a_while() {
+    let mut engine = battle_royale(&[1, 2, 3], with_power_ups());
+    engine.apply(1, Action::Reveal(pos(3, 0))).unwrap();
+    engine.apply(1, Action::Reveal(pos(1, 0))).unwrap();
+    let events = engine.apply(1, Action::Reveal(pos(0, 1))).unwrap();
+    assert_eq!(earned(&events), vec![PowerUp::Fog]);
+
+    for target in [None, Some(1), Some(99)] {
+        assert_eq!(
+            engine.apply(1, use_power_up(PowerUp::Fog, target)),
+            Err(ActionError::InvalidTarget)
+        );
+    }
+    engine.apply(3, Action::Reveal(pos(1, 1))).unwrap();
+    assert_eq!(
+        engine.apply(1, use_power_up(PowerUp::Fog, Some(3))),
+        Err(ActionError::InvalidTarget)
+    );
+
+    engine
+        .apply(1, use_power_up(PowerUp::Fog, Some(2)))
+        .unwrap();
+    assert!(engine.is_fogged(2));
+    assert_eq!(engine.view(2).unwrap().cells[0], CellView::Fogged);
+    assert_eq!(engine.view(1).unwrap().cells[0], CellView::Revealed(1));
+
+    let events = tick_until(&mut engine, 5);
+    assert_eq!(events, vec![MatchEvent::FogLifted { user_id: 2 }]);
+    assert_eq!(engine.view(2).unwrap().cells[0], CellView::Revealed(1));
+}
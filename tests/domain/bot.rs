@@ -0,0 +1,134 @@
+use testcontainers_test::domain::{
+    battle_royale::Action,
+    bot::*,
+    minesweeper::{
+        generate_no_guess, Board, BoardConfig, CellView, GameStatus, GeneratorOptions, MineLayout,
+        Position, Technique,
+    },
+};
+
+fn pos(x: usize, y: usize) -> Position {
+    Position::new(x, y)
+}
+
+fn skill(guess_policy: GuessPolicy) -> BotSkill {
+    BotSkill {
+        error_rate: 0.0,
+        guess_policy,
+        max_technique: Technique::Enumeration,
+        ..BotSkill::default()
+    }
+}
+
+/// ```text
+/// 1 . . . .
+/// . * . . .
+/// . . . . .
+/// . . . * .
+/// . . . . .
+/// ```
+///
+/// Only the top left corner is revealed, which doesn't give anything away.
+fn stuck_board() -> Board {
+    let layout = MineLayout::from_positions(5, 5, &[pos(1, 1), pos(3, 3)]).unwrap();
+    let mut board = Board::from_layout(layout);
+    board.reveal(pos(0, 0)).unwrap();
+    board
+}
+
+fn play(board: &mut Board, action: Action) {
+    match action {
+        Action::Reveal(position) => {
+            board.reveal(position).unwrap();
+        }
+        Action::Flag(position) => {
+            board.toggle_flag(position).unwrap();
+        }
+        Action::Chord(position) => {
+            board.chord(position).unwrap();
+        }
+        Action::UsePowerUp(_) | Action::Forfeit => panic!("Bots don't use power-ups or give up"),
+    }
+}
+
+#[test]
+fn careful_bots_clear_boards_that_need_no_guessing() {
+    for seed in 0..5 {
+        let config = BoardConfig::new(9, 9, 10).unwrap();
+        let generated =
+            generate_no_guess(config, seed, pos(4, 4), &GeneratorOptions::default()).unwrap();
+        let mut board = generated.board();
+        board.reveal(generated.start).unwrap();
+        let mut bot = Bot::new(skill(GuessPolicy::Never), seed);
+        while let Some(action) = bot.next_action(&board.view()) {
+            play(&mut board, action);
+        }
+        assert_eq!(board.status(), GameStatus::Won, "seed {}", seed);
+    }
+}
+
+#[test]
+fn bots_that_never_guess_wait_when_stuck() {
+    let mut bot = Bot::new(skill(GuessPolicy::Never), 1);
+    assert_eq!(bot.next_action(&stuck_board().view()), None);
+}
+
+#[test]
+fn cautious_bots_guess_away_from_the_numbers() {
+    let frontier = [pos(1, 0), pos(0, 1), pos(1, 1)];
+    for seed in 0..20 {
+        let mut bot = Bot::new(skill(GuessPolicy::Cautious), seed);
+        match bot.next_action(&stuck_board().view()) {
+            Some(Action::Reveal(position)) => {
+                assert!(!frontier.contains(&position));
+                assert_ne!(position, pos(0, 0));
+            }
+            action => panic!("expected a guess, got {:?}", action),
+        }
+    }
+}
+
+#[test]
+fn bots_flag_the_mines_they_find() {
+    // The corner only touches one hidden cell, so the mine is certain straight away.
+    let layout = MineLayout::from_positions(3, 3, &[pos(1, 1)]).unwrap();
+    let mut board = Board::from_layout(layout);
+    for position in [pos(0, 0), pos(1, 0), pos(0, 1)] {
+        board.reveal(position).unwrap();
+    }
+    let mut bot = Bot::new(skill(GuessPolicy::Never), 1);
+    assert_eq!(
+        bot.next_action(&board.view()),
+        Some(Action::Flag(pos(1, 1)))
+    );
+    let mut actions = Vec::new();
+    while let Some(action) = bot.next_action(&board.view()) {
+        actions.push(action);
+        play(&mut board, action);
+    }
+    assert_eq!(board.status(), GameStatus::Won);
+    assert!(!actions.contains(&Action::Reveal(pos(1, 1))));
+}
+
+#[test]
+fn clumsy_bots_reveal_cells_they_had_not_worked_out() {
+    let layout = MineLayout::from_positions(3, 3, &[pos(1, 1)]).unwrap();
+    let mut board = Board::from_layout(layout);
+    board.reveal(pos(0, 0)).unwrap();
+    board.toggle_flag(pos(1, 1)).unwrap();
+    let clumsy = BotSkill {
+        error_rate: 1.0,
+        ..skill(GuessPolicy::Never)
+    };
+    let view = board.view();
+    for seed in 0..20 {
+        let mut bot = Bot::new(clumsy.clone(), seed);
+        match bot.next_action(&view) {
+            Some(Action::Reveal(position)) => {
+                let index = position.y * view.width + position.x;
+                assert_eq!(view.cells[index], CellView::Hidden);
+            }
+            action => panic!("expected a reveal, got {:?}", action),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::time::{Duration, Instant};
+
+use testcontainers_test::domain::{chat::*, lobby::LobbyCode};
+
+#[test]
+fn banned_words_are_starred_out_whole() {
+    let filter = WordFilter::new(&["heck", " darn "]);
+    assert_eq!(filter.censor("Heck, DARN it"), "****, **** it");
+    assert_eq!(
+        filter.censor("checking the darnedest"),
+        "checking the darnedest"
+    );
+    assert_eq!(WordFilter::default().censor("heck"), "heck");
+}
+
+#[test]
+fn messages_are_trimmed_and_length_checked() {
+    let settings = ChatSettings {
+        max_length: 5,
+        ..ChatSettings::default()
+    };
+    let filter = WordFilter::new(&["heck"]);
+    assert_eq!(
+        settings.prepare("  heck  ", &filter),
+        Ok(String::from("****"))
+    );
+    assert_eq!(
+        settings.prepare("   ", &filter),
+        Err(ChatError::EmptyMessage)
+    );
+    assert_eq!(
+        settings.prepare("too long", &filter),
+        Err(ChatError::MessageTooLong { max: 5 })
+    );
+    // Length is counted in characters, not bytes.
+    assert!(settings.prepare("ééééé", &filter).is_ok());
+}
+
+#[test]
+fn players_can_only_send_so_many_messages_at_once() {
+    let settings = ChatSettings {
+        rate_limit: 2,
+        rate_window: Duration::from_secs(10),
+        ..ChatSettings::default()
+    };
+    let mut limiter = RateLimiter::new(&settings);
+    let start = Instant::now();
+    assert_eq!(limiter.check(1, start), Ok(()));
+    assert_eq!(limiter.check(1, start + Duration::from_secs(4)), Ok(()));
+    assert_eq!(
+        limiter.check(1, start + Duration::from_secs(6)),
+        Err(ChatError::RateLimited {
+            retry_after_ms: 4000
+        })
+    );
+    // Someone else isn't held up by it.
+    assert_eq!(limiter.check(2, start + Duration::from_secs(6)), Ok(()));
+    // Once the first message is out of the window there's room for another.
+    assert_eq!(limiter.check(1, start + Duration::from_secs(10)), Ok(()));
+}
+
+#[test]
+fn rooms_survive_being_stored() {
+    for room in [
+        ChatRoom::Lobby(LobbyCode::from(String::from("ABCD"))),
+        ChatRoom::Match(42),
+    ] {
+        let (kind, id) = room.to_parts();
+        assert_eq!(ChatRoom::from_parts(kind, &id), Some(room));
+    }
+    assert_eq!(ChatRoom::from_parts("match", "not a number"), None);
+    assert_eq!(ChatRoom::from_parts("party", "1"), None);
+}
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use testcontainers_test::domain::{daily::*, minesweeper::BoardConfig};
+use time::{Date, Month};
+
+fn date(year: i32, month: Month, day: u8) -> Date {
+    Date::from_calendar_date(year, month, day).unwrap()
+}
+
+#[test]
+fn the_challenge_rolls_over_at_the_configured_time() {
+    let settings = DailySettings {
+        rollover: Duration::from_secs(6 * 60 * 60),
+        ..DailySettings::default()
+    };
+    let day = date(2022, Month::July, 31);
+    let opens_at = settings.opens_at(day);
+    assert_eq!(opens_at, 1_659_225_600 + 6 * 60 * 60);
+    assert_eq!(settings.closes_at(day), opens_at + 24 * 60 * 60);
+    assert_eq!(settings.date_at(opens_at), day);
+    assert_eq!(settings.date_at(opens_at - 1), date(2022, Month::July, 30));
+    assert_eq!(
+        settings.date_at(settings.closes_at(day)),
+        date(2022, Month::August, 1)
+    );
+}
+
+#[test]
+fn every_date_has_its_own_fixed_seed() {
+    let day = date(2022, Month::July, 31);
+    assert_eq!(seed_for(day), seed_for(day));
+    assert_ne!(seed_for(day), seed_for(day.next_day().unwrap()));
+
+    let challenge = DailyChallenge::new(day, BoardConfig::beginner());
+    assert_eq!(challenge, DailyChallenge::new(day, BoardConfig::beginner()));
+    assert_eq!(challenge.seed, seed_for(day));
+}
+
+#[test]
+fn dates_round_trip_through_text() {
+    let day = date(2022, Month::March, 5);
+    assert_eq!(format_date(day), "2022-03-05");
+    assert_eq!(parse_date("2022-03-05"), Some(day));
+    assert_eq!(parse_date("2022-02-30"), None);
+    assert_eq!(parse_date("2022-13-01"), None);
+    assert_eq!(parse_date("yesterday"), None);
+}
+
+#[test]
+fn clearing_days_in_a_row_builds_a_streak() {
+    let first = date(2022, Month::July, 30);
+    let streak = Streak::default()
+        .cleared(first)
+        .cleared(first.next_day().unwrap());
+    assert_eq!(streak.current, 2);
+    assert_eq!(streak.best, 2);
+    assert_eq!(streak.cleared(first.next_day().unwrap()), streak);
+
+    let restarted = streak.cleared(date(2022, Month::August, 5));
+    assert_eq!(restarted.current, 1);
+    assert_eq!(restarted.best, 2);
+}
+
+#[test]
+fn a_streak_breaks_once_a_day_is_missed() {
+    let cleared = date(2022, Month::July, 31);
+    let streak = Streak::default().cleared(cleared);
+    assert_eq!(streak.as_of(cleared).current, 1);
+    assert_eq!(streak.as_of(date(2022, Month::August, 1)).current, 1);
+
+    let broken = streak.as_of(date(2022, Month::August, 2));
+    assert_eq!(broken.current, 0);
+    assert_eq!(broken.best, 1);
+}
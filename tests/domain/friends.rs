@@ -0,0 +1,61 @@
+use testcontainers_test::domain::friends::*;
+
+#[test]
+fn requests_go_to_strangers_or_accept_theirs() {
+    assert_eq!(
+        Relationship::Strangers.send_request(),
+        Ok(RequestOutcome::Sent)
+    );
+    assert_eq!(
+        Relationship::RequestReceived.send_request(),
+        Ok(RequestOutcome::Accepted)
+    );
+    assert_eq!(
+        Relationship::Friends.send_request(),
+        Err(FriendError::AlreadyFriends)
+    );
+    assert_eq!(
+        Relationship::RequestSent.send_request(),
+        Err(FriendError::AlreadyRequested)
+    );
+    assert_eq!(
+        Relationship::Blocked.send_request(),
+        Err(FriendError::Blocked)
+    );
+}
+
+#[test]
+fn only_received_requests_can_be_answered() {
+    assert_eq!(Relationship::RequestReceived.answer_request(2), Ok(()));
+    for relationship in [
+        Relationship::Strangers,
+        Relationship::Friends,
+        Relationship::RequestSent,
+        Relationship::Blocked,
+    ] {
+        assert_eq!(
+            relationship.answer_request(2),
+            Err(FriendError::NoRequest { user_id: 2 })
+        );
+    }
+    assert_eq!(Relationship::Friends.ensure_friends(2), Ok(()));
+    assert_eq!(
+        Relationship::RequestSent.ensure_friends(2),
+        Err(FriendError::NotFriends { user_id: 2 })
+    );
+}
+
+#[test]
+fn presence_is_the_most_specific_place_a_connected_player_is() {
+    assert_eq!(Presence::new(false, true, Some(3)), Presence::Offline);
+    assert_eq!(Presence::new(true, false, None), Presence::Online);
+    assert_eq!(Presence::new(true, true, None), Presence::InLobby);
+    assert_eq!(
+        Presence::new(true, true, Some(3)),
+        Presence::InMatch { match_id: 3 }
+    );
+    assert_eq!(
+        serde_json::to_value(Presence::InMatch { match_id: 3 }).unwrap(),
+        serde_json::json!({ "status": "in_match", "match_id": 3 })
+    );
+}
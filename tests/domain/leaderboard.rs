@@ -0,0 +1,100 @@
+use testcontainers_test::domain::{leaderboard::*, lobby::GameMode};
+
+fn player(user_id: i64, rating: f64) -> PlayerRating {
+    PlayerRating {
+        user_id,
+        username: format!("player{}", user_id),
+        rating,
+        deviation: 80.0,
+        matches_played: 3,
+    }
+}
+
+fn board(players: Vec<PlayerRating>) -> Leaderboard {
+    Leaderboard::new(Board::Global, 1, 1000, players)
+}
+
+fn user_ids(page: &LeaderboardPage) -> Vec<i64> {
+    page.entries
+        .iter()
+        .map(|entry| entry.player.user_id)
+        .collect()
+}
+
+#[test]
+fn players_are_ranked_by_rating_and_ties_share_a_rank() {
+    let leaderboard = board(vec![
+        player(1, 1500.0),
+        player(2, 1700.0),
+        player(3, 1500.0),
+        player(4, 1400.0),
+    ]);
+    let ranks: Vec<(usize, i64)> = leaderboard
+        .entries()
+        .iter()
+        .map(|entry| (entry.rank, entry.player.user_id))
+        .collect();
+    assert_eq!(ranks, vec![(1, 2), (2, 1), (2, 3), (4, 4)]);
+}
+
+#[test]
+fn pages_follow_on_from_their_cursor() {
+    let leaderboard = board((1..=5).map(|id| player(id, 2000.0 - id as f64)).collect());
+    let first = leaderboard.page(None, 2);
+    assert_eq!(user_ids(&first), vec![1, 2]);
+    let cursor: Cursor = first.next.unwrap().parse().unwrap();
+    let second = leaderboard.page(Some(cursor), 2);
+    assert_eq!(user_ids(&second), vec![3, 4]);
+    let cursor: Cursor = second.next.unwrap().parse().unwrap();
+    let last = leaderboard.page(Some(cursor), 2);
+    assert_eq!(user_ids(&last), vec![5]);
+    assert_eq!(last.next, None);
+}
+
+#[test]
+fn cursors_stay_put_when_the_leaderboard_changes() {
+    let before = board((1..=4).map(|id| player(id, 2000.0 - id as f64)).collect());
+    let cursor: Cursor = before.page(None, 2).next.unwrap().parse().unwrap();
+    // Someone new climbs to the top between pages, which would shift an offset by one.
+    let mut players: Vec<PlayerRating> = (1..=4).map(|id| player(id, 2000.0 - id as f64)).collect();
+    players.push(player(9, 2500.0));
+    let after = board(players);
+    assert_eq!(user_ids(&after.page(Some(cursor), 2)), vec![3, 4]);
+}
+
+#[test]
+fn around_shows_the_players_either_side() {
+    let leaderboard = board((1..=10).map(|id| player(id, 2000.0 - id as f64)).collect());
+    let page = leaderboard.around(5, 2).unwrap();
+    assert_eq!(user_ids(&page), vec![3, 4, 5, 6, 7]);
+    assert_eq!(user_ids(&leaderboard.around(1, 2).unwrap()), vec![1, 2, 3]);
+    assert_eq!(leaderboard.around(10, 1).unwrap().next, None);
+    assert_eq!(leaderboard.around(42, 2), None);
+}
+
+#[test]
+fn cursors_round_trip_through_text() {
+    let cursor = Cursor {
+        rating: 1623.456,
+        user_id: 12,
+    };
+    assert_eq!(cursor.to_string().parse::<Cursor>(), Ok(cursor));
+    for invalid in ["", "1500", "abc_1", "1500_abc", "NaN_1"] {
+        assert_eq!(
+            invalid.parse::<Cursor>(),
+            Err(LeaderboardError::InvalidCursor {
+                cursor: invalid.to_string()
+            })
+        );
+    }
+}
+
+#[test]
+fn boards_are_found_by_name() {
+    assert_eq!(Board::from_name("global"), Ok(Board::Global));
+    assert_eq!(
+        Board::from_name("battle_royale"),
+        Ok(Board::Mode(GameMode::BattleRoyale))
+    );
+    assert!(Board::from_name("chess").is_err());
+}
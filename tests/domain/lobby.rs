@@ -0,0 +1,254 @@
+use std::time::{Duration, Instant};
+
+use testcontainers_test::domain::{lobby::*, User};
+
+fn user(id: i64) -> User {
+    User::new(id, format!("player{}", id))
+}
+
+fn settings(max_players: usize) -> LobbySettings {
+    LobbySettings {
+        max_players,
+        ..LobbySettings::default()
+    }
+}
+
+#[test]
+fn host_leaving_hands_the_lobby_over() {
+    let mut lobbies = Lobbies::new(1);
+    let code = lobbies.create(user(1), settings(4)).unwrap().code.clone();
+    lobbies.join(&code, user(2)).unwrap();
+    lobbies.leave(1).unwrap();
+    assert_eq!(lobbies.get(&code).unwrap().host, 2);
+    lobbies.leave(2).unwrap();
+    assert_eq!(
+        lobbies.get(&code),
+        Err(LobbyError::LobbyNotFound { code: code.clone() })
+    );
+}
+
+#[test]
+fn players_can_only_be_in_one_lobby() {
+    let mut lobbies = Lobbies::new(1);
+    let code = lobbies.create(user(1), settings(4)).unwrap().code.clone();
+    assert_eq!(
+        lobbies.create(user(1), settings(4)).unwrap_err(),
+        LobbyError::AlreadyInLobby { code }
+    );
+}
+
+#[test]
+fn everyone_readying_up_starts_the_match() {
+    let mut lobbies = Lobbies::new(1);
+    let code = lobbies.create(user(1), settings(4)).unwrap().code.clone();
+    assert_eq!(lobbies.set_ready(1, true), Ok(None));
+    lobbies.join(&code, user(2)).unwrap();
+    let start = lobbies.set_ready(2, true).unwrap().unwrap();
+    assert_eq!(start.players, vec![user(1), user(2)]);
+    assert_eq!(start.board, settings(4).board_config().unwrap());
+    assert_eq!(lobbies.get(&code).unwrap().status, LobbyStatus::InMatch);
+    assert_eq!(
+        lobbies.join(&code, user(3)).unwrap_err(),
+        LobbyError::MatchInProgress
+    );
+
+    lobbies.finish_match(&code).unwrap();
+    let lobby = lobbies.get(&code).unwrap();
+    assert_eq!(lobby.status, LobbyStatus::Waiting);
+    assert!(lobby.members.iter().all(|member| !member.ready));
+}
+
+#[test]
+fn changing_settings_resets_ready_states() {
+    let mut lobbies = Lobbies::new(1);
+    let code = lobbies.create(user(1), settings(4)).unwrap().code.clone();
+    lobbies.join(&code, user(2)).unwrap();
+    lobbies.set_ready(2, true).unwrap();
+    assert_eq!(
+        lobbies.update_settings(2, settings(8)).unwrap_err(),
+        LobbyError::NotHost
+    );
+    let lobby = lobbies.update_settings(1, settings(8)).unwrap();
+    assert!(lobby.members.iter().all(|member| !member.ready));
+    assert_eq!(lobby.settings.max_players, 8);
+}
+
+#[test]
+fn host_cannot_start_alone() {
+    let mut lobbies = Lobbies::new(1);
+    lobbies.create(user(1), settings(4)).unwrap();
+    assert_eq!(
+        lobbies.start(1).unwrap_err(),
+        LobbyError::NotEnoughPlayers { min: MIN_PLAYERS }
+    );
+}
+
+#[test]
+fn settings_must_make_a_valid_board() {
+    let too_dense = LobbySettings {
+        mine_density: 80,
+        ..LobbySettings::default()
+    };
+    assert!(matches!(
+        too_dense.board_config(),
+        Err(LobbyError::InvalidSettings { .. })
+    ));
+    let empty = LobbySettings {
+        width: 0,
+        ..LobbySettings::default()
+    };
+    assert!(matches!(
+        empty.board_config(),
+        Err(LobbyError::InvalidBoard { .. })
+    ));
+}
+
+fn matchmaker(group_size: usize) -> Matchmaker {
+    Matchmaker::new(MatchmakingSettings {
+        initial_window: 100.0,
+        widen_per_second: 10.0,
+        max_window: 500.0,
+        fill_time: Duration::from_secs(10),
+        lobby: settings(group_size),
+    })
+}
+
+#[test]
+fn full_groups_are_matched_immediately() {
+    let now = Instant::now();
+    let mut matchmaker = matchmaker(2);
+    matchmaker
+        .enqueue(user(1), GameMode::BattleRoyale, 1500.0, now)
+        .unwrap();
+    matchmaker
+        .enqueue(user(2), GameMode::BattleRoyale, 1550.0, now)
+        .unwrap();
+    let groups = matchmaker.find_matches(now);
+    assert_eq!(groups.len(), 1);
+    assert!(matchmaker.is_empty());
+}
+
+#[test]
+fn distant_ratings_wait_for_the_window_to_widen() {
+    let now = Instant::now();
+    let mut matchmaker = matchmaker(2);
+    matchmaker
+        .enqueue(user(1), GameMode::BattleRoyale, 1500.0, now)
+        .unwrap();
+    matchmaker
+        .enqueue(user(2), GameMode::BattleRoyale, 1800.0, now)
+        .unwrap();
+    assert!(matchmaker.find_matches(now).is_empty());
+    assert!(matchmaker
+        .find_matches(now + Duration::from_secs(10))
+        .is_empty());
+    assert_eq!(
+        matchmaker.find_matches(now + Duration::from_secs(20)).len(),
+        1
+    );
+}
+
+#[test]
+fn windows_stop_growing_at_the_max() {
+    let now = Instant::now();
+    let matchmaker = matchmaker(2);
+    assert_eq!(matchmaker.window(now, now), 100.0);
+    assert_eq!(matchmaker.window(now, now + Duration::from_secs(5)), 150.0);
+    assert_eq!(
+        matchmaker.window(now, now + Duration::from_secs(3600)),
+        500.0
+    );
+}
+
+#[test]
+fn partial_groups_wait_for_the_fill_time() {
+    let now = Instant::now();
+    let mut matchmaker = matchmaker(4);
+    for id in 1..=3 {
+        matchmaker
+            .enqueue(user(id), GameMode::BattleRoyale, 1500.0, now)
+            .unwrap();
+    }
+    assert!(matchmaker.find_matches(now).is_empty());
+    let groups = matchmaker.find_matches(now + Duration::from_secs(10));
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].players.len(), 3);
+}
+
+#[test]
+fn closest_ratings_are_grouped_together() {
+    let now = Instant::now();
+    let mut matchmaker = matchmaker(2);
+    matchmaker
+        .enqueue(user(1), GameMode::BattleRoyale, 1500.0, now)
+        .unwrap();
+    matchmaker
+        .enqueue(user(2), GameMode::BattleRoyale, 1590.0, now)
+        .unwrap();
+    matchmaker
+        .enqueue(user(3), GameMode::BattleRoyale, 1510.0, now)
+        .unwrap();
+    let groups = matchmaker.find_matches(now);
+    let ids: Vec<i64> = groups[0]
+        .players
+        .iter()
+        .map(|entry| entry.player.id())
+        .collect();
+    assert_eq!(ids, vec![1, 3]);
+    assert!(matchmaker.is_queued(2));
+}
+
+#[test]
+fn players_cannot_queue_twice() {
+    let now = Instant::now();
+    let mut matchmaker = matchmaker(2);
+    matchmaker
+        .enqueue(user(1), GameMode::BattleRoyale, 1500.0, now)
+        .unwrap();
+    assert_eq!(
+        matchmaker.enqueue(user(1), GameMode::BattleRoyale, 1500.0, now),
+        Err(LobbyError::AlreadyQueued)
+    );
+    assert!(matchmaker.dequeue(1));
+    assert!(!matchmaker.dequeue(1));
+}
+
+#[test]
+fn players_left_waiting_can_be_taken_out_of_the_queue() {
+    let now = Instant::now();
+    let mut matchmaker = matchmaker(2);
+    matchmaker
+        .enqueue(user(1), GameMode::BattleRoyale, 1500.0, now)
+        .unwrap();
+    matchmaker
+        .enqueue(
+            user(2),
+            GameMode::BattleRoyale,
+            1500.0,
+            now + Duration::from_secs(5),
+        )
+        .unwrap();
+    let later = now + Duration::from_secs(30);
+    let waiting = matchmaker.take_waiting(Duration::from_secs(30), later);
+    assert_eq!(waiting.len(), 1);
+    assert_eq!(waiting[0].player.id(), 1);
+    assert!(matchmaker.is_queued(2));
+}
+
+#[test]
+fn requeued_players_keep_how_long_they_had_waited() {
+    let mut matchmaker = Matchmaker::new(MatchmakingSettings::default());
+    let now = Instant::now();
+    matchmaker
+        .enqueue(user(1), GameMode::BattleRoyale, 1500.0, now)
+        .unwrap();
+    let later = now + Duration::from_secs(30);
+    let waiting = matchmaker.take_waiting(Duration::from_secs(30), later);
+    for entry in waiting {
+        matchmaker.requeue(entry.clone());
+        matchmaker.requeue(entry);
+    }
+    assert_eq!(matchmaker.len(), 1);
+    let waiting = matchmaker.take_waiting(Duration::from_secs(30), later);
+    assert_eq!(waiting[0].enqueued_at, now);
+}
@@ -0,0 +1,17 @@
+mod anticheat;
+mod battle_royale;
+mod bot;
+mod chat;
+mod daily;
+mod friends;
+mod leaderboard;
+mod lobby;
+mod minesweeper;
+mod notifications;
+mod practice;
+mod rating;
+mod replay;
+mod solver;
+mod spectate;
+mod stats;
+mod tournament;
@@ -0,0 +1,403 @@
+use proptest::prelude::*;
+use testcontainers_test::domain::minesweeper::*;
+
+fn pos(x: usize, y: usize) -> Position {
+    Position::new(x, y)
+}
+
+/// ```text
+/// * 1 .
+/// 1 1 .
+/// . . .
+/// ```
+fn corner_mine_board() -> Board {
+    Board::from_layout(MineLayout::from_positions(3, 3, &[pos(0, 0)]).unwrap())
+}
+
+#[test]
+fn config_rejects_empty_boards() {
+    assert_eq!(
+        BoardConfig::new(0, 5, 0),
+        Err(BoardError::InvalidDimensions {
+            width: 0,
+            height: 5
+        })
+    );
+}
+
+#[test]
+fn config_always_leaves_a_safe_cell() {
+    assert!(BoardConfig::new(3, 3, 8).is_ok());
+    assert_eq!(
+        BoardConfig::new(3, 3, 9),
+        Err(BoardError::TooManyMines {
+            mines: 9,
+            cells: 9,
+            max: 8
+        })
+    );
+}
+
+#[test]
+fn config_validates_when_deserialized() {
+    let valid: BoardConfig =
+        serde_json::from_str(r#"{"width": 9, "height": 9, "mines": 10}"#).unwrap();
+    assert_eq!(valid, BoardConfig::beginner());
+    assert!(
+        serde_json::from_str::<BoardConfig>(r#"{"width": 2, "height": 2, "mines": 4}"#).is_err()
+    );
+}
+
+#[test]
+fn neighbours_are_clipped_at_the_edges() {
+    let config = BoardConfig::new(4, 3, 0).unwrap();
+    assert_eq!(config.neighbours(0).count(), 3);
+    assert_eq!(config.neighbours(1).count(), 5);
+    assert_eq!(config.neighbours(5).count(), 8);
+}
+
+#[test]
+fn hex_rows_lean_alternate_ways() {
+    let config = BoardConfig::with_topology(4, 4, 0, BoardTopology::Hex).unwrap();
+    let around = |x, y| {
+        let mut neighbours: Vec<Position> = config
+            .neighbours(config.index_of(pos(x, y)).unwrap())
+            .map(|index| config.position_of(index))
+            .collect();
+        neighbours.sort();
+        neighbours
+    };
+    assert_eq!(
+        around(1, 2),
+        vec![
+            pos(0, 1),
+            pos(0, 2),
+            pos(0, 3),
+            pos(1, 1),
+            pos(1, 3),
+            pos(2, 2)
+        ]
+    );
+    assert_eq!(
+        around(1, 1),
+        vec![
+            pos(0, 1),
+            pos(1, 0),
+            pos(1, 2),
+            pos(2, 0),
+            pos(2, 1),
+            pos(2, 2)
+        ]
+    );
+    assert_eq!(around(0, 0), vec![pos(0, 1), pos(1, 0)]);
+}
+
+#[test]
+fn torus_boards_wrap_around() {
+    let config = BoardConfig::with_topology(4, 3, 0, BoardTopology::Torus).unwrap();
+    let mut corner: Vec<Position> = config
+        .neighbours(0)
+        .map(|index| config.position_of(index))
+        .collect();
+    corner.sort();
+    assert_eq!(
+        corner,
+        vec![
+            pos(0, 1),
+            pos(0, 2),
+            pos(1, 0),
+            pos(1, 1),
+            pos(1, 2),
+            pos(3, 0),
+            pos(3, 1),
+            pos(3, 2)
+        ]
+    );
+    assert_eq!(
+        BoardConfig::with_topology(2, 5, 0, BoardTopology::Torus),
+        Err(BoardError::TooSmallForTopology {
+            topology: BoardTopology::Torus,
+            min: 3
+        })
+    );
+}
+
+#[test]
+fn configs_are_square_unless_they_say_otherwise() {
+    let hex: BoardConfig =
+        serde_json::from_str(r#"{"width": 9, "height": 9, "mines": 10, "topology": "hex"}"#)
+            .unwrap();
+    assert_eq!(hex.topology(), BoardTopology::Hex);
+    assert_eq!(BoardConfig::beginner().topology(), BoardTopology::Square);
+    assert!(serde_json::from_str::<BoardConfig>(
+        r#"{"width": 2, "height": 2, "mines": 1, "topology": "torus"}"#
+    )
+    .is_err());
+    assert_eq!(
+        BoardTopology::from_name("torus"),
+        Some(BoardTopology::Torus)
+    );
+}
+
+#[test]
+fn hex_numbers_only_count_six_cells() {
+    // A mine in every cell but the middle one.
+    let mines: Vec<Position> = (0..3)
+        .flat_map(|y| (0..3).map(move |x| pos(x, y)))
+        .filter(|&position| position != pos(1, 1))
+        .collect();
+    let layout =
+        MineLayout::from_positions_with_topology(3, 3, BoardTopology::Hex, &mines).unwrap();
+    let mut board = Board::from_layout(layout);
+    board.reveal(pos(1, 1)).unwrap();
+    assert_eq!(board.adjacent_mines(pos(1, 1)), Ok(Some(6)));
+    assert_eq!(board.view().topology, BoardTopology::Hex);
+}
+
+#[test]
+fn rng_is_deterministic() {
+    let first: Vec<u64> = {
+        let mut rng = SeededRng::new(42);
+        (0..10).map(|_| rng.next_u64()).collect()
+    };
+    let second: Vec<u64> = {
+        let mut rng = SeededRng::new(42);
+        (0..10).map(|_| rng.next_u64()).collect()
+    };
+    assert_eq!(first, second);
+}
+
+#[test]
+fn same_seed_and_first_click_produce_the_same_board() {
+    let mut first = Board::new(BoardConfig::expert(), 1234);
+    let mut second = Board::new(BoardConfig::expert(), 1234);
+    first.reveal(pos(10, 10)).unwrap();
+    second.reveal(pos(10, 10)).unwrap();
+    assert_eq!(first.layout(), second.layout());
+
+    let mut other_seed = Board::new(BoardConfig::expert(), 4321);
+    other_seed.reveal(pos(10, 10)).unwrap();
+    assert_ne!(first.layout(), other_seed.layout());
+}
+
+#[test]
+fn first_click_opens_an_area_when_there_is_room() {
+    let mut board = Board::new(BoardConfig::expert(), 7);
+    let outcome = board.reveal(pos(0, 0)).unwrap();
+    assert_eq!(outcome.exploded, None);
+    assert_eq!(outcome.revealed[0].adjacent_mines, 0);
+    assert!(outcome.revealed.len() > 1);
+}
+
+#[test]
+fn revealing_a_zero_flood_fills_to_the_numbers() {
+    let mut board = corner_mine_board();
+    let outcome = board.reveal(pos(2, 2)).unwrap();
+    assert_eq!(outcome.revealed.len(), 8);
+    assert_eq!(board.status(), GameStatus::Won);
+    assert_eq!(board.adjacent_mines(pos(1, 1)).unwrap(), Some(1));
+    assert_eq!(board.adjacent_mines(pos(0, 0)).unwrap(), None);
+}
+
+#[test]
+fn revealing_a_number_only_reveals_that_cell() {
+    let mut board = corner_mine_board();
+    let outcome = board.reveal(pos(1, 1)).unwrap();
+    assert_eq!(
+        outcome.revealed,
+        vec![RevealedCell {
+            position: pos(1, 1),
+            adjacent_mines: 1
+        }]
+    );
+    assert_eq!(board.status(), GameStatus::Playing);
+}
+
+#[test]
+fn revealing_twice_is_a_no_op() {
+    let mut board = corner_mine_board();
+    board.reveal(pos(1, 1)).unwrap();
+    assert!(board.reveal(pos(1, 1)).unwrap().is_empty());
+}
+
+#[test]
+fn revealing_a_mine_loses_the_game() {
+    let mut board = corner_mine_board();
+    board.reveal(pos(1, 1)).unwrap();
+    let outcome = board.reveal(pos(0, 0)).unwrap();
+    assert_eq!(outcome.exploded, Some(pos(0, 0)));
+    assert_eq!(
+        board.status(),
+        GameStatus::Lost {
+            exploded: pos(0, 0)
+        }
+    );
+    assert_eq!(board.reveal(pos(2, 2)), Err(BoardError::GameOver));
+    assert_eq!(board.toggle_flag(pos(2, 2)), Err(BoardError::GameOver));
+}
+
+#[test]
+fn out_of_bounds_actions_are_rejected() {
+    let mut board = corner_mine_board();
+    assert_eq!(
+        board.reveal(pos(3, 0)),
+        Err(BoardError::OutOfBounds {
+            position: pos(3, 0)
+        })
+    );
+}
+
+#[test]
+fn flags_toggle_and_protect_cells() {
+    let mut board = corner_mine_board();
+    assert_eq!(board.toggle_flag(pos(0, 0)), Ok(CellState::Flagged));
+    assert_eq!(board.mines_remaining(), 0);
+    assert_eq!(
+        board.reveal(pos(0, 0)),
+        Err(BoardError::CellFlagged {
+            position: pos(0, 0)
+        })
+    );
+    assert_eq!(board.toggle_flag(pos(0, 0)), Ok(CellState::Hidden));
+    assert_eq!(board.mines_remaining(), 1);
+}
+
+#[test]
+fn revealed_cells_cannot_be_flagged() {
+    let mut board = corner_mine_board();
+    board.reveal(pos(1, 1)).unwrap();
+    assert_eq!(
+        board.toggle_flag(pos(1, 1)),
+        Err(BoardError::CellRevealed {
+            position: pos(1, 1)
+        })
+    );
+}
+
+#[test]
+fn chording_with_correct_flags_reveals_the_rest() {
+    let mut board = corner_mine_board();
+    board.reveal(pos(1, 1)).unwrap();
+    board.toggle_flag(pos(0, 0)).unwrap();
+    let outcome = board.chord(pos(1, 1)).unwrap();
+    assert_eq!(outcome.exploded, None);
+    assert_eq!(board.status(), GameStatus::Won);
+}
+
+#[test]
+fn chording_with_wrong_flags_can_explode() {
+    let mut board = corner_mine_board();
+    board.reveal(pos(1, 1)).unwrap();
+    board.toggle_flag(pos(1, 0)).unwrap();
+    let outcome = board.chord(pos(1, 1)).unwrap();
+    assert_eq!(outcome.exploded, Some(pos(0, 0)));
+}
+
+#[test]
+fn chording_without_enough_flags_does_nothing() {
+    let mut board = corner_mine_board();
+    board.reveal(pos(1, 1)).unwrap();
+    assert!(board.chord(pos(1, 1)).unwrap().is_empty());
+    assert!(board.chord(pos(2, 2)).unwrap().is_empty());
+}
+
+#[test]
+fn view_hides_mines_until_the_game_is_over() {
+    let mut board = corner_mine_board();
+    board.reveal(pos(1, 1)).unwrap();
+    let view = board.view();
+    assert_eq!(view.cells[0], CellView::Hidden);
+    assert_eq!(view.cells[4], CellView::Revealed(1));
+
+    board.reveal(pos(0, 0)).unwrap();
+    assert_eq!(board.view().cells[0], CellView::Exploded);
+}
+
+#[test]
+fn view_serializes_cells_with_a_kind_tag() {
+    let mut board = corner_mine_board();
+    board.reveal(pos(1, 1)).unwrap();
+    let json = serde_json::to_value(board.view()).unwrap();
+    assert_eq!(json["cells"][0], serde_json::json!({ "kind": "hidden" }));
+    assert_eq!(
+        json["cells"][4],
+        serde_json::json!({ "kind": "revealed", "adjacent_mines": 1 })
+    );
+    assert_eq!(json["status"], serde_json::json!({ "state": "playing" }));
+}
+
+fn config_strategy() -> impl Strategy<Value = BoardConfig> {
+    prop::sample::select(vec![
+        BoardTopology::Square,
+        BoardTopology::Hex,
+        BoardTopology::Torus,
+    ])
+    .prop_flat_map(|topology| {
+        let min = topology.min_size();
+        (Just(topology), min..20, min..20)
+    })
+    .prop_flat_map(|(topology, width, height)| {
+        (Just(topology), Just(width), Just(height), 0..width * height)
+    })
+    .prop_map(|(topology, width, height, mines)| {
+        BoardConfig::with_topology(width, height, mines, topology).unwrap()
+    })
+}
+
+fn config_and_click() -> impl Strategy<Value = (BoardConfig, Position)> {
+    config_strategy().prop_flat_map(|config| {
+        (
+            Just(config),
+            (0..config.width(), 0..config.height()).prop_map(|(x, y)| Position::new(x, y)),
+        )
+    })
+}
+
+proptest! {
+    #[test]
+    fn first_click_is_never_a_mine((config, click) in config_and_click(), seed: u64) {
+        let mut board = Board::new(config, seed);
+        let outcome = board.reveal(click).unwrap();
+        prop_assert_eq!(outcome.exploded, None);
+        prop_assert!(!outcome.revealed.is_empty());
+    }
+
+    #[test]
+    fn generated_layouts_have_the_configured_mines((config, click) in config_and_click(), seed: u64) {
+        let layout = MineLayout::generate(config, seed, click).unwrap();
+        prop_assert_eq!(layout.mine_positions().len(), config.mines());
+        prop_assert_eq!(layout, MineLayout::generate(config, seed, click).unwrap());
+    }
+
+    #[test]
+    fn adjacent_counts_match_the_mines((config, click) in config_and_click(), seed: u64) {
+        let layout = MineLayout::generate(config, seed, click).unwrap();
+        for index in 0..config.cell_count() {
+            let expected = config.neighbours(index).filter(|&n| layout.is_mine(n)).count();
+            prop_assert_eq!(layout.adjacent_mines(index) as usize, expected);
+        }
+    }
+
+    #[test]
+    fn revealing_every_safe_cell_wins(
+        (config, click) in config_and_click(),
+        seed: u64,
+        order_seed: u64,
+    ) {
+        let mut board = Board::new(config, seed);
+        board.reveal(click).unwrap();
+        let layout = board.layout().unwrap().clone();
+        let mut safe: Vec<usize> = (0..config.cell_count()).filter(|&i| !layout.is_mine(i)).collect();
+        SeededRng::new(order_seed).shuffle(&mut safe);
+        for index in safe {
+            if board.status() == GameStatus::Won {
+                break;
+            }
+            prop_assert!(board.view().cells.iter().all(|cell| *cell != CellView::Mine));
+            let outcome = board.reveal(config.position_of(index)).unwrap();
+            prop_assert_eq!(outcome.exploded, None);
+        }
+        prop_assert_eq!(board.status(), GameStatus::Won);
+        prop_assert_eq!(board.revealed_count(), config.safe_cell_count());
+    }
+}
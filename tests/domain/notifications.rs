@@ -0,0 +1,32 @@
+use serde_json::json;
+use testcontainers_test::domain::{lobby::LobbyCode, notifications::*, User};
+
+#[test]
+fn notifications_say_what_kind_they_are_alongside_everything_else() {
+    let notification = Notification {
+        id: 3,
+        created_at: 1_700_000_000,
+        read: false,
+        kind: NotificationKind::LobbyInvite {
+            from: User::new(1, String::from("host")),
+            code: LobbyCode::from(String::from("ABCD")),
+        },
+    };
+    let serialized = serde_json::to_value(&notification).unwrap();
+    assert_eq!(
+        serialized,
+        json!({
+            "id": 3,
+            "created_at": 1_700_000_000,
+            "read": false,
+            "kind": "lobby_invite",
+            "from": { "id": 1, "username": "host" },
+            "code": "ABCD"
+        })
+    );
+    assert_eq!(notification.kind.as_str(), "lobby_invite");
+    assert_eq!(
+        serde_json::from_value::<Notification>(serialized).unwrap(),
+        notification
+    );
+}
@@ -0,0 +1,118 @@
+use std::time::{Duration, Instant};
+
+use testcontainers_test::domain::{
+    battle_royale::Action,
+    minesweeper::{BoardConfig, CellView, GameStatus, MineLayout, Position},
+    practice::*,
+};
+
+#[test]
+fn an_opening_and_its_edges_take_one_click() {
+    let layout = MineLayout::from_positions(3, 3, &[Position::new(0, 0)]).unwrap();
+    assert_eq!(three_bv(&layout, |_| true), 1);
+}
+
+#[test]
+fn cells_outside_openings_take_a_click_each() {
+    let layout = MineLayout::from_positions(3, 1, &[Position::new(1, 0)]).unwrap();
+    assert_eq!(three_bv(&layout, |_| true), 2);
+
+    // Two openings either side of a mine, each with a number on its edge.
+    let layout = MineLayout::from_positions(5, 1, &[Position::new(2, 0)]).unwrap();
+    assert_eq!(three_bv(&layout, |_| true), 2);
+    // Revealing the number on an opening's edge doesn't open it up.
+    assert_eq!(three_bv(&layout, |index| index == 1), 0);
+    assert_eq!(three_bv(&layout, |index| index == 0), 1);
+}
+
+#[test]
+fn stats_work_out_efficiency_and_speed() {
+    let stats = GameStats::new(10, 8, 16, 4000);
+    assert_eq!(stats.efficiency, 50.0);
+    assert_eq!(stats.three_bv_per_second, 2.0);
+
+    let untouched = GameStats::new(10, 0, 0, 0);
+    assert_eq!(untouched.efficiency, 0.0);
+    assert_eq!(untouched.three_bv_per_second, 0.0);
+}
+
+#[test]
+fn levels_come_off_the_wire_with_their_boards() {
+    let level: Level = serde_json::from_str(r#"{"level": "expert"}"#).unwrap();
+    assert_eq!(level.config().unwrap(), BoardConfig::expert());
+
+    let level: Level =
+        serde_json::from_str(r#"{"level": "custom", "width": 20, "height": 10, "mines": 30}"#)
+            .unwrap();
+    assert_eq!(level.name(), "custom");
+    assert_eq!(
+        level.config().unwrap(),
+        BoardConfig::new(20, 10, 30).unwrap()
+    );
+
+    assert!(serde_json::from_str::<Level>(
+        r#"{"level": "custom", "width": 2, "height": 2, "mines": 4}"#
+    )
+    .is_err());
+    let huge = Level::Custom(BoardConfig::new(101, 10, 10).unwrap());
+    assert_eq!(
+        huge.config(),
+        Err(PracticeError::BoardTooLarge {
+            max: MAX_CUSTOM_SIZE
+        })
+    );
+}
+
+#[test]
+fn games_are_timed_from_the_first_click_to_the_last() {
+    let level = Level::Custom(BoardConfig::new(2, 1, 1).unwrap());
+    let mut game = PracticeGame::new(level, 7).unwrap();
+    assert!(game.result().is_none());
+
+    let start = Instant::now();
+    game.apply(Action::Flag(Position::new(1, 0)), start)
+        .unwrap();
+    game.apply(Action::Flag(Position::new(1, 0)), start)
+        .unwrap();
+    // The first reveal is never a mine, and here it's the only safe cell.
+    let change = game
+        .apply(
+            Action::Reveal(Position::new(0, 0)),
+            start + Duration::from_millis(1234),
+        )
+        .unwrap();
+    assert_eq!(change.status, GameStatus::Won);
+
+    let result = game.result().unwrap();
+    assert!(result.won);
+    assert_eq!(result.time_ms, 1234);
+    assert_eq!(result.stats.three_bv, 1);
+    assert_eq!(result.stats.solved_three_bv, 1);
+    assert_eq!(result.stats.clicks, 3);
+    assert!(game
+        .apply(Action::Reveal(Position::new(1, 0)), start)
+        .is_err());
+}
+
+#[test]
+fn lost_games_only_count_what_was_solved() {
+    let mut game = PracticeGame::new(Level::Beginner, 3).unwrap();
+    let now = Instant::now();
+    game.apply(Action::Reveal(Position::new(4, 4)), now)
+        .unwrap();
+    // Clicking around blindly hits a mine long before the board is cleared.
+    let config = BoardConfig::beginner();
+    for index in 0..config.cell_count() {
+        if game.is_over() {
+            break;
+        }
+        if game.view().cells[index] == CellView::Hidden {
+            game.apply(Action::Reveal(config.position_of(index)), now)
+                .unwrap();
+        }
+    }
+
+    let result = game.result().unwrap();
+    assert!(!result.won);
+    assert!(result.stats.solved_three_bv < result.stats.three_bv);
+}
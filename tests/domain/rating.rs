@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use testcontainers_test::domain::rating::*;
+
+fn rating(rating: f64, deviation: f64) -> Rating {
+    Rating {
+        rating,
+        deviation,
+        volatility: 0.06,
+    }
+}
+
+fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+    assert!(
+        (actual - expected).abs() < tolerance,
+        "expected {} to be within {} of {}",
+        actual,
+        tolerance,
+        expected
+    );
+}
+
+#[test]
+fn matches_the_example_in_glickmans_paper() {
+    let updated = rating(1500.0, 200.0).updated(
+        &[
+            (rating(1400.0, 30.0), 1.0),
+            (rating(1550.0, 100.0), 0.0),
+            (rating(1700.0, 300.0), 0.0),
+        ],
+        &RatingSettings::default(),
+    );
+    assert_close(updated.rating, 1464.06, 0.01);
+    assert_close(updated.deviation, 151.52, 0.01);
+    assert_close(updated.volatility, 0.05999, 0.00001);
+}
+
+#[test]
+fn time_away_makes_ratings_less_certain() {
+    let settings = RatingSettings::default();
+    let player = rating(1700.0, 50.0);
+    assert_eq!(player.decayed(0, &settings), player);
+    let decayed = player.decayed(10, &settings);
+    assert_eq!(decayed.rating, 1700.0);
+    assert!(decayed.deviation > 50.0);
+    assert_eq!(
+        player.decayed(1_000_000, &settings).deviation,
+        settings.max_deviation
+    );
+}
+
+#[test]
+fn periods_are_counted_in_whole_periods() {
+    let settings = RatingSettings {
+        rating_period: Duration::from_secs(60),
+        ..RatingSettings::default()
+    };
+    assert_eq!(settings.periods_in(Duration::from_secs(59)), 0);
+    assert_eq!(settings.periods_in(Duration::from_secs(150)), 2);
+}
+
+#[test]
+fn players_stop_being_provisional_once_their_rating_is_certain() {
+    let settings = RatingSettings::default();
+    assert!(settings.initial.is_provisional(&settings));
+    assert!(!rating(1500.0, 80.0).is_provisional(&settings));
+}
+
+#[test]
+fn placements_are_scored_against_every_opponent() {
+    let settings = RatingSettings::default();
+    let players: Vec<RatedPlacement> = (1..=4)
+        .map(|user_id| RatedPlacement {
+            user_id,
+            rating: settings.initial,
+            placement: user_id as usize,
+        })
+        .collect();
+    let ratings = rate_match(&players, &settings);
+    assert!(ratings
+        .windows(2)
+        .all(|pair| pair[0].rating > pair[1].rating));
+    assert!(ratings[0].rating > 1500.0);
+    assert!(ratings[3].rating < 1500.0);
+    // The field was evenly matched, so the points won and lost balance out.
+    assert_close(ratings[0].rating - 1500.0, 1500.0 - ratings[3].rating, 0.01);
+}
+
+#[test]
+fn shared_placements_are_draws() {
+    let settings = RatingSettings::default();
+    let players = [
+        RatedPlacement {
+            user_id: 1,
+            rating: settings.initial,
+            placement: 1,
+        },
+        RatedPlacement {
+            user_id: 2,
+            rating: settings.initial,
+            placement: 1,
+        },
+    ];
+    let ratings = rate_match(&players, &settings);
+    assert_close(ratings[0].rating, 1500.0, 0.001);
+    assert_close(ratings[1].rating, 1500.0, 0.001);
+    assert!(ratings[0].deviation < settings.initial.deviation);
+}
+
+#[test]
+fn new_seasons_pull_ratings_back_towards_the_start() {
+    let settings = RatingSettings::default();
+    let reset = rating(1900.0, 60.0).for_new_season(&settings);
+    assert_eq!(reset.rating, 1700.0);
+    assert_eq!(reset.deviation, settings.provisional_deviation);
+    assert!(reset.is_provisional(&settings));
+    assert_eq!(
+        rating(1100.0, 200.0).for_new_season(&settings).deviation,
+        200.0
+    );
+}
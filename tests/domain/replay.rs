@@ -0,0 +1,306 @@
+use std::time::Duration;
+
+use testcontainers_test::domain::{
+    battle_royale::*,
+    minesweeper::{BoardError, BoardTopology, CellView, GameStatus, Position},
+    replay::*,
+};
+
+fn pos(x: usize, y: usize) -> Position {
+    Position::new(x, y)
+}
+
+fn action(tick: u64, user_id: i64, action: Action) -> ReplayAction {
+    ReplayAction {
+        tick,
+        user_id,
+        action,
+    }
+}
+
+/// The same board as the battle royale tests:
+///
+/// ```text
+/// 1 1 1 . .
+/// 1 * 1 . .
+/// 1 1 2 1 1
+/// . . 1 * 1
+/// . . 1 1 1
+/// ```
+fn replay(actions: Vec<ReplayAction>, last_tick: u64) -> Replay {
+    Replay {
+        match_id: 7,
+        width: 5,
+        height: 5,
+        topology: BoardTopology::Square,
+        mines: vec![pos(1, 1), pos(3, 3)],
+        start: pos(0, 0),
+        players: vec![-1, 20, 300_000],
+        settings: BattleRoyaleSettings {
+            tick_length: Duration::from_millis(100),
+            first_storm: 50,
+            storm_interval: 25,
+            power_ups: PowerUpSettings::default(),
+        },
+        actions,
+        last_tick,
+    }
+}
+
+fn sample_replay() -> Replay {
+    replay(
+        vec![
+            action(3, 20, Action::Reveal(pos(4, 0))),
+            action(3, -1, Action::Flag(pos(1, 1))),
+            action(10, 300_000, Action::Reveal(pos(0, 1))),
+            action(12, -1, Action::Reveal(pos(3, 3))),
+        ],
+        75,
+    )
+}
+
+#[test]
+fn replays_survive_being_encoded() {
+    let replay = sample_replay();
+    let bytes = replay.encode();
+    assert!(bytes.starts_with(REPLAY_MAGIC));
+    assert_eq!(bytes[4], REPLAY_VERSION);
+    assert_eq!(Replay::decode(&bytes), Ok(replay));
+}
+
+#[test]
+fn replays_keep_their_topology() {
+    let replay = Replay {
+        topology: BoardTopology::Hex,
+        ..sample_replay()
+    };
+    assert_eq!(Replay::decode(&replay.encode()), Ok(replay));
+
+    // Version 1 files don't have a topology or power-ups, and they're all square.
+    let mut old = sample_replay().encode();
+    old[4] = 1;
+    // After the storm settings.
+    old.drain(13..18);
+    // After the match ID, width and height.
+    assert_eq!(old.remove(8), 0);
+    assert_eq!(Replay::decode(&old), Ok(sample_replay()));
+}
+
+#[test]
+fn replays_keep_their_power_ups() {
+    let mut replay = replay(
+        vec![
+            action(3, 20, Action::Reveal(pos(1, 0))),
+            action(4, 20, Action::Reveal(pos(0, 1))),
+            action(5, 20, Action::Reveal(pos(3, 0))),
+            action(
+                6,
+                20,
+                Action::UsePowerUp(PowerUpUse {
+                    power_up: PowerUp::Fog,
+                    target: Some(-1),
+                }),
+            ),
+            action(
+                7,
+                20,
+                Action::UsePowerUp(PowerUpUse {
+                    power_up: PowerUp::Shield,
+                    target: None,
+                }),
+            ),
+        ],
+        20,
+    );
+    replay.settings.power_ups = PowerUpSettings {
+        enabled: true,
+        region_size: 2,
+        fog_ticks: 5,
+        ..PowerUpSettings::default()
+    };
+    assert_eq!(Replay::decode(&replay.encode()), Ok(replay.clone()));
+
+    let replayer = Replayer::new(replay).unwrap();
+    assert!(replayer.timeline().iter().any(|record| matches!(
+        record.entry,
+        LogEntry::Event {
+            event: MatchEvent::FogLifted { user_id: -1 }
+        }
+    )));
+    let state = replayer.state_at(replayer.timeline().len()).unwrap();
+    assert_eq!(state.boards[0].0, -1);
+    assert_eq!(state.boards[0].1.cells[0], CellView::Revealed(1));
+
+    // Version 2 files are from before power-ups.
+    let mut old = sample_replay().encode();
+    old[4] = 2;
+    old.drain(13..18);
+    assert_eq!(Replay::decode(&old), Ok(sample_replay()));
+}
+
+#[test]
+fn replays_keep_forfeits() {
+    let replay = replay(
+        vec![
+            action(3, 20, Action::Reveal(pos(4, 0))),
+            action(9, 300_000, Action::Forfeit),
+        ],
+        75,
+    );
+    assert_eq!(Replay::decode(&replay.encode()), Ok(replay.clone()));
+
+    let replayer = Replayer::new(replay).unwrap();
+    assert!(replayer.timeline().iter().any(|record| matches!(
+        record.entry,
+        LogEntry::Event {
+            event: MatchEvent::Eliminated {
+                user_id: 300_000,
+                reason: EliminationReason::Forfeit,
+                ..
+            }
+        }
+    )));
+}
+
+#[test]
+fn replay_files_are_small() {
+    // Two bytes for most actions' player, kind and tick delta, and one or two for the cell.
+    let actions = (0..100)
+        .map(|index| action(index, 20, Action::Flag(pos(4, 4))))
+        .collect();
+    assert!(replay(actions, 100).encode().len() < 450);
+}
+
+#[test]
+fn decoding_rejects_other_files() {
+    assert_eq!(Replay::decode(b"PK\x03\x04"), Err(ReplayError::NotAReplay));
+    let mut bytes = sample_replay().encode();
+    bytes[4] = 99;
+    assert_eq!(
+        Replay::decode(&bytes),
+        Err(ReplayError::UnsupportedVersion { version: 99 })
+    );
+}
+
+#[test]
+fn decoding_rejects_damaged_files() {
+    let bytes = sample_replay().encode();
+    for len in 0..bytes.len() {
+        assert!(Replay::decode(&bytes[..len]).is_err(), "{} bytes", len);
+    }
+    let mut extra = bytes.clone();
+    extra.push(0);
+    assert!(matches!(
+        Replay::decode(&extra),
+        Err(ReplayError::Corrupt { .. })
+    ));
+    let mut huge_count = bytes[..5].to_vec();
+    huge_count.extend([0xff; 9]);
+    assert!(Replay::decode(&huge_count).is_err());
+}
+
+#[test]
+fn the_timeline_rebuilds_every_event() {
+    let replayer = Replayer::new(sample_replay()).unwrap();
+    let timeline = replayer.timeline();
+    assert!(matches!(timeline[0].entry, LogEntry::Started { .. }));
+    assert!(timeline
+        .iter()
+        .enumerate()
+        .all(|(index, record)| record.seq == index as u64));
+    assert!(timeline.windows(2).all(|pair| pair[0].tick <= pair[1].tick));
+    let eliminated: Vec<(i64, EliminationReason, u64)> = timeline
+        .iter()
+        .filter_map(|record| match record.entry {
+            LogEntry::Event {
+                event:
+                    MatchEvent::Eliminated {
+                        user_id, reason, ..
+                    },
+            } => Some((user_id, reason, record.tick)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        eliminated,
+        vec![
+            (-1, EliminationReason::Mine, 12),
+            (300_000, EliminationReason::Storm, 50),
+        ]
+    );
+    match &timeline.last().unwrap().entry {
+        LogEntry::Event {
+            event: MatchEvent::Finished(result),
+        } => assert_eq!(result.winner(), Some(20)),
+        other => panic!("expected the match to finish, got {:?}", other),
+    }
+}
+
+#[test]
+fn a_timeline_turns_back_into_the_same_replay() {
+    let replay = sample_replay();
+    let replayer = Replayer::new(replay.clone()).unwrap();
+    let rebuilt = Replay::from_log(replay.match_id, replayer.timeline()).unwrap();
+    assert_eq!(rebuilt.actions, replay.actions);
+    assert_eq!(rebuilt.last_tick, 50);
+    assert_eq!(Replay::from_log(1, &[]), Err(ReplayError::MissingStart));
+}
+
+#[test]
+fn the_state_can_be_looked_at_anywhere_in_the_timeline() {
+    let replayer = Replayer::new(sample_replay()).unwrap();
+    let board_of = |state: &ReplayState, user_id: i64| {
+        state
+            .boards
+            .iter()
+            .find(|(player, _)| *player == user_id)
+            .unwrap()
+            .1
+            .clone()
+    };
+
+    let start = replayer.state_at(0).unwrap();
+    assert_eq!(start.tick, 0);
+    assert_eq!(board_of(&start, 20).cells[4], CellView::Hidden);
+
+    let first_action = replayer
+        .timeline()
+        .iter()
+        .position(|record| matches!(record.entry, LogEntry::Action { .. }))
+        .unwrap();
+    let after = replayer.state_at(first_action + 1).unwrap();
+    assert_eq!(after.tick, 3);
+    assert_eq!(board_of(&after, 20).cells[4], CellView::Revealed(0));
+    assert_eq!(board_of(&after, -1).cells[4], CellView::Hidden);
+
+    let end = replayer.state_at(replayer.timeline().len()).unwrap();
+    assert!(matches!(board_of(&end, -1).status, GameStatus::Lost { .. }));
+    assert_eq!(end.result.unwrap().winner(), Some(20));
+    assert_eq!(
+        replayer.state_at(1_000),
+        Err(ReplayError::IndexOutOfRange {
+            index: 1_000,
+            len: replayer.timeline().len()
+        })
+    );
+}
+
+#[test]
+fn impossible_actions_are_rejected() {
+    let replay = replay(
+        vec![
+            action(1, 20, Action::Reveal(pos(1, 1))),
+            action(2, 20, Action::Reveal(pos(4, 4))),
+        ],
+        2,
+    );
+    assert_eq!(
+        Replayer::new(replay).err(),
+        Some(ReplayError::InvalidAction {
+            index: 1,
+            source: ActionError::InvalidMove {
+                source: BoardError::GameOver
+            }
+        })
+    );
+}
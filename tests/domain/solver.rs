@@ -0,0 +1,237 @@
+use std::time::Duration;
+
+use proptest::prelude::*;
+use testcontainers_test::domain::minesweeper::*;
+
+fn pos(x: usize, y: usize) -> Position {
+    Position::new(x, y)
+}
+
+#[test]
+fn single_rule_finds_forced_mines() {
+    let mut solver = Solver::new(BoardConfig::new(2, 1, 1).unwrap());
+    solver.reveal(pos(0, 0), 1);
+    let deduction = solver.deduce().unwrap();
+    assert_eq!(deduction.technique, Technique::Single);
+    assert_eq!(deduction.mines, vec![pos(1, 0)]);
+    assert!(deduction.safe.is_empty());
+}
+
+/// ```text
+/// * ? *
+/// 1 2 1
+/// ```
+fn one_two_one() -> Solver {
+    let mut solver = Solver::new(BoardConfig::new(3, 2, 2).unwrap());
+    solver.reveal(pos(0, 1), 1);
+    solver.reveal(pos(1, 1), 2);
+    solver.reveal(pos(2, 1), 1);
+    solver
+}
+
+#[test]
+fn subset_rule_is_used_when_single_cells_are_not_enough() {
+    let deduction = one_two_one().deduce().unwrap();
+    assert_eq!(deduction.technique, Technique::Subset);
+    assert!(deduction.mines.contains(&pos(0, 0)));
+    assert!(deduction.mines.contains(&pos(2, 0)));
+}
+
+#[test]
+fn solver_respects_the_max_technique() {
+    let mut solver = Solver::with_options(
+        BoardConfig::new(3, 2, 2).unwrap(),
+        SolverOptions {
+            max_technique: Technique::Single,
+            ..SolverOptions::default()
+        },
+    );
+    solver.reveal(pos(0, 1), 1);
+    solver.reveal(pos(1, 1), 2);
+    solver.reveal(pos(2, 1), 1);
+    assert_eq!(solver.deduce(), None);
+}
+
+#[test]
+fn enumeration_uses_the_total_mine_count() {
+    // ? 1 ? ?  with one mine in total: the mine is next to the 1, so the last cell is safe.
+    let mut solver = Solver::new(BoardConfig::new(4, 1, 1).unwrap());
+    solver.reveal(pos(1, 0), 1);
+    let deduction = solver.deduce().unwrap();
+    assert_eq!(deduction.technique, Technique::Enumeration);
+    assert_eq!(deduction.safe, vec![pos(3, 0)]);
+}
+
+#[test]
+fn solver_does_not_guess_fifty_fifties() {
+    // ? 1 ?  with one mine: it could be either side.
+    let mut solver = Solver::new(BoardConfig::new(3, 1, 1).unwrap());
+    solver.reveal(pos(1, 0), 1);
+    assert_eq!(solver.deduce(), None);
+}
+
+#[test]
+fn solver_can_read_a_board_in_progress() {
+    let mut board = Board::from_layout(MineLayout::from_positions(3, 3, &[pos(0, 0)]).unwrap());
+    board.reveal(pos(1, 1)).unwrap();
+    board.reveal(pos(1, 0)).unwrap();
+    board.reveal(pos(0, 1)).unwrap();
+    let solver = Solver::from_board(&board, SolverOptions::default());
+    let deduction = solver.deduce().unwrap();
+    assert_eq!(deduction.technique, Technique::Subset);
+    // Both 1s along the edge cover the mine, so everything else around the middle 1 is safe.
+    assert_eq!(
+        deduction.safe,
+        vec![pos(2, 0), pos(2, 1), pos(0, 2), pos(1, 2), pos(2, 2)]
+    );
+}
+
+#[test]
+fn solve_clears_an_open_board() {
+    let mut board = Board::from_layout(MineLayout::from_positions(3, 3, &[pos(0, 0)]).unwrap());
+    let report = Solver::solve(&mut board, pos(2, 2), SolverOptions::default());
+    assert!(report.solved);
+    assert_eq!(board.status(), GameStatus::Won);
+}
+
+#[test]
+fn generated_boards_are_deterministic() {
+    let options = GeneratorOptions::default();
+    let first = generate_no_guess(BoardConfig::intermediate(), 99, pos(8, 8), &options).unwrap();
+    let second = generate_no_guess(BoardConfig::intermediate(), 99, pos(8, 8), &options).unwrap();
+    assert_eq!(first, second);
+}
+
+#[test]
+fn generated_boards_can_be_cleared_without_guessing() {
+    let generated = generate_no_guess(
+        BoardConfig::expert(),
+        2022,
+        pos(15, 8),
+        &GeneratorOptions::default(),
+    )
+    .unwrap();
+    let mut board = generated.board();
+    assert!(Solver::solve(&mut board, generated.start, SolverOptions::default()).solved);
+    assert_eq!(
+        generated.layout.mine_positions().len(),
+        BoardConfig::expert().mines()
+    );
+}
+
+#[test]
+fn every_topology_gets_no_guess_boards() {
+    for topology in [BoardTopology::Hex, BoardTopology::Torus] {
+        let config = BoardConfig::with_topology(16, 16, 40, topology).unwrap();
+        let generated =
+            generate_no_guess(config, 7, pos(8, 8), &GeneratorOptions::default()).unwrap();
+        assert_eq!(generated.layout.config(), config);
+        let mut board = generated.board();
+        assert!(Solver::solve(&mut board, generated.start, SolverOptions::default()).solved);
+
+        // A bot working from what the player sees reads the board the same way.
+        let mut board = generated.board();
+        board.reveal(generated.start).unwrap();
+        let solver = Solver::from_view(&board.view(), SolverOptions::default()).unwrap();
+        assert_eq!(solver.config(), config);
+        assert!(solver.deduce().is_some());
+    }
+}
+
+#[test]
+fn limiting_the_solver_limits_the_difficulty() {
+    let options = GeneratorOptions {
+        solver: SolverOptions {
+            max_technique: Technique::Single,
+            ..SolverOptions::default()
+        },
+        ..GeneratorOptions::default()
+    };
+    let generated = generate_no_guess(BoardConfig::beginner(), 5, pos(4, 4), &options).unwrap();
+    assert_eq!(generated.difficulty.difficulty, Difficulty::Easy);
+    assert!(!generated
+        .difficulty
+        .techniques
+        .contains_key(&Technique::Subset));
+}
+
+#[test]
+fn generation_gives_up_when_out_of_time() {
+    let options = GeneratorOptions {
+        time_budget: Duration::from_nanos(1),
+        ..GeneratorOptions::default()
+    };
+    assert!(matches!(
+        generate_no_guess(BoardConfig::expert(), 1, pos(0, 0), &options),
+        Err(GenerationError::BudgetExceeded { .. })
+    ));
+}
+
+#[test]
+fn generation_gives_up_after_the_attempt_limit() {
+    let options = GeneratorOptions {
+        max_attempts: 0,
+        ..GeneratorOptions::default()
+    };
+    assert!(matches!(
+        generate_no_guess(BoardConfig::beginner(), 1, pos(0, 0), &options),
+        Err(GenerationError::AttemptsExhausted { attempts: 0 })
+    ));
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn every_generated_board_is_solvable(seed: u64, x in 0usize..16, y in 0usize..16) {
+        let start = pos(x, y);
+        let generated = generate_no_guess(
+            BoardConfig::intermediate(),
+            seed,
+            start,
+            &GeneratorOptions::default(),
+        )
+        .unwrap();
+        prop_assert_eq!(generated.start, start);
+        let mut board = generated.board();
+        let report = Solver::solve(&mut board, start, SolverOptions::default());
+        prop_assert!(report.solved);
+        prop_assert_eq!(report.techniques, generated.difficulty.techniques);
+    }
+}
+
+#[test]
+fn safe_chances_are_certain_for_proven_cells() {
+    let solver = one_two_one();
+    assert_eq!(solver.safe_chance(pos(1, 0)), Some(1.0));
+    assert_eq!(solver.safe_chance(pos(0, 0)), Some(0.0));
+    assert_eq!(solver.safe_chance(pos(1, 1)), None);
+
+    // ? 1 ?  with one mine: it could be either side.
+    let mut solver = Solver::new(BoardConfig::new(3, 1, 1).unwrap());
+    solver.reveal(pos(1, 0), 1);
+    assert_eq!(solver.safe_chance(pos(0, 0)), Some(0.5));
+}
+
+#[test]
+fn safe_chances_count_every_arrangement_of_mines() {
+    // ? 1 ? 1 ? ? ? ?  with two mines: either the cell between the 1s is a mine and the other
+    // is one of the three on the right, or the 1s have a mine each on their outsides. That's
+    // three arrangements against one.
+    let mut solver = Solver::new(BoardConfig::new(8, 1, 2).unwrap());
+    solver.reveal(pos(1, 0), 1);
+    solver.reveal(pos(3, 0), 1);
+    let close = |position: Position, expected: f64| {
+        let chance = solver.safe_chance(position).unwrap();
+        assert!(
+            (chance - expected).abs() < 1e-9,
+            "{:?}: {}",
+            position,
+            chance
+        );
+    };
+    close(pos(2, 0), 0.25);
+    close(pos(0, 0), 0.75);
+    close(pos(4, 0), 0.75);
+    close(pos(6, 0), 0.75);
+}
@@ -0,0 +1,152 @@
+use std::time::{Duration, Instant};
+
+use testcontainers_test::domain::{
+    battle_royale::{MatchEvent, MatchResult},
+    minesweeper::{BoardTopology, BoardView, CellView, GameStatus, Position, RevealedCell},
+    protocol::{FlagChange, SpectatorEvent},
+    spectate::*,
+};
+
+const DELAY: Duration = Duration::from_secs(3);
+
+fn board(cells: Vec<CellView>) -> BoardView {
+    BoardView {
+        width: 2,
+        height: 2,
+        topology: BoardTopology::Square,
+        mines: 1,
+        mines_remaining: 1,
+        status: GameStatus::Playing,
+        cells,
+    }
+}
+
+fn feed() -> SpectatorFeed {
+    SpectatorFeed::new(
+        7,
+        vec![
+            PlayerBoard {
+                user_id: 1,
+                board: board(vec![
+                    CellView::Revealed(1),
+                    CellView::Hidden,
+                    CellView::Hidden,
+                    CellView::Hidden,
+                ]),
+            },
+            PlayerBoard {
+                user_id: 2,
+                board: board(vec![
+                    CellView::Revealed(1),
+                    CellView::Hidden,
+                    CellView::Hidden,
+                    CellView::Hidden,
+                ]),
+            },
+        ],
+        &SpectatorSettings { delay: DELAY },
+    )
+}
+
+#[test]
+fn events_are_held_back_for_the_delay() {
+    let mut feed = feed();
+    let now = Instant::now();
+    feed.record(
+        vec![MatchEvent::BoardChanged {
+            user_id: 2,
+            revealed: vec![RevealedCell {
+                position: Position::new(1, 0),
+                adjacent_mines: 1,
+            }],
+            exploded: None,
+            flag: None,
+            status: GameStatus::Playing,
+        }],
+        now,
+    );
+    assert!(feed.release(now + DELAY / 2).is_empty());
+    assert_eq!(feed.board(2).unwrap().cells[1], CellView::Hidden);
+
+    let released = feed.release(now + DELAY);
+    assert!(matches!(
+        released.as_slice(),
+        [SpectatorEvent::BoardDelta { user_id: 2, .. }]
+    ));
+    assert_eq!(feed.board(2).unwrap().cells[1], CellView::Revealed(1));
+    assert_eq!(feed.board(1).unwrap().cells[1], CellView::Hidden);
+}
+
+#[test]
+fn flags_and_explosions_show_up_on_the_boards() {
+    let mut feed = feed();
+    let now = Instant::now();
+    feed.record(
+        vec![
+            MatchEvent::BoardChanged {
+                user_id: 1,
+                revealed: vec![],
+                exploded: None,
+                flag: Some(FlagChange {
+                    position: Position::new(0, 1),
+                    flagged: true,
+                }),
+                status: GameStatus::Playing,
+            },
+            MatchEvent::BoardChanged {
+                user_id: 1,
+                revealed: vec![],
+                exploded: Some(Position::new(1, 1)),
+                flag: None,
+                status: GameStatus::Lost {
+                    exploded: Position::new(1, 1),
+                },
+            },
+        ],
+        now,
+    );
+    feed.release(now + DELAY);
+    let board = feed.board(1).unwrap();
+    assert_eq!(board.cells[2], CellView::Flagged);
+    assert_eq!(board.cells[3], CellView::Exploded);
+    assert_eq!(board.mines_remaining, 0);
+    assert!(board.status.is_over());
+}
+
+#[test]
+fn mines_are_never_shown_to_spectators() {
+    let feed = SpectatorFeed::new(
+        7,
+        vec![PlayerBoard {
+            user_id: 1,
+            board: board(vec![
+                CellView::Revealed(1),
+                CellView::Mine,
+                CellView::Revealed(1),
+                CellView::Revealed(1),
+            ]),
+        }],
+        &SpectatorSettings { delay: DELAY },
+    );
+    assert_eq!(feed.board(1).unwrap().cells[1], CellView::Hidden);
+}
+
+#[test]
+fn the_feed_finishes_once_the_end_is_shown() {
+    let mut feed = feed();
+    let now = Instant::now();
+    feed.record(
+        vec![MatchEvent::Finished(MatchResult {
+            standings: vec![],
+            ticks: 10,
+        })],
+        now,
+    );
+    assert!(!feed.is_finished());
+    let released = feed.release(now + DELAY);
+    assert!(matches!(
+        released.as_slice(),
+        [SpectatorEvent::MatchFinished { match_id: 7, .. }]
+    ));
+    assert!(feed.is_finished());
+}
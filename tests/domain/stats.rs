@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use testcontainers_test::domain::{
+    battle_royale::*,
+    minesweeper::{BoardTopology, GameStatus, Position},
+    protocol::FlagChange,
+    replay::*,
+    stats::*,
+    UserId,
+};
+
+fn pos(x: usize, y: usize) -> Position {
+    Position::new(x, y)
+}
+
+fn record(seq: u64, tick: u64, entry: LogEntry) -> MatchRecord {
+    MatchRecord { seq, tick, entry }
+}
+
+fn started() -> LogEntry {
+    LogEntry::Started {
+        width: 5,
+        height: 5,
+        topology: BoardTopology::Square,
+        mines: vec![pos(1, 1), pos(3, 3)],
+        start: pos(0, 0),
+        players: vec![1, 2],
+        settings: BattleRoyaleSettings {
+            tick_length: Duration::from_millis(100),
+            first_storm: 50,
+            storm_interval: 25,
+            power_ups: PowerUpSettings::default(),
+        },
+    }
+}
+
+fn flagged(user_id: UserId, position: Position, flagged: bool) -> LogEntry {
+    LogEntry::Event {
+        event: MatchEvent::BoardChanged {
+            user_id,
+            revealed: vec![],
+            exploded: None,
+            flag: Some(FlagChange { position, flagged }),
+            status: GameStatus::Playing,
+        },
+    }
+}
+
+fn finished() -> LogEntry {
+    LogEntry::Event {
+        event: MatchEvent::Finished(MatchResult {
+            standings: vec![
+                Standing {
+                    user_id: 1,
+                    placement: 1,
+                    revealed: 23,
+                    outcome: Outcome::Cleared { tick: 40 },
+                },
+                Standing {
+                    user_id: 2,
+                    placement: 2,
+                    revealed: 5,
+                    outcome: Outcome::Eliminated {
+                        reason: EliminationReason::Mine,
+                        tick: 30,
+                    },
+                },
+            ],
+            ticks: 40,
+        }),
+    }
+}
+
+fn match_stats(placement: usize, revealed: usize, clear_ms: Option<u64>) -> MatchStats {
+    MatchStats {
+        user_id: 1,
+        placement,
+        revealed,
+        mines_flagged: 0,
+        clear_ms,
+    }
+}
+
+fn ids(achievements: Vec<&'static Achievement>) -> Vec<&'static str> {
+    achievements
+        .into_iter()
+        .map(|achievement| achievement.id)
+        .collect()
+}
+
+#[test]
+fn only_mines_still_flagged_at_the_end_count() {
+    let records = vec![
+        record(0, 0, started()),
+        record(1, 2, flagged(1, pos(1, 1), true)),
+        record(2, 3, flagged(1, pos(3, 3), true)),
+        record(3, 4, flagged(1, pos(3, 3), false)),
+        // Not a mine.
+        record(4, 5, flagged(1, pos(4, 4), true)),
+        record(5, 6, flagged(2, pos(3, 3), true)),
+        record(6, 40, finished()),
+    ];
+    let stats = MatchStats::from_log(&records).unwrap();
+    assert_eq!(
+        stats,
+        vec![
+            MatchStats {
+                user_id: 1,
+                placement: 1,
+                revealed: 23,
+                mines_flagged: 1,
+                clear_ms: Some(4_000),
+            },
+            MatchStats {
+                user_id: 2,
+                placement: 2,
+                revealed: 5,
+                mines_flagged: 1,
+                clear_ms: None,
+            },
+        ]
+    );
+    assert!(stats[0].won());
+    assert!(!stats[1].won());
+}
+
+#[test]
+fn logs_need_a_start_and_a_result() {
+    assert_eq!(
+        MatchStats::from_log(&[record(0, 40, finished())]),
+        Err(StatsError::MissingStart)
+    );
+    assert_eq!(
+        MatchStats::from_log(&[record(0, 0, started())]),
+        Err(StatsError::MissingResult)
+    );
+}
+
+#[test]
+fn totals_keep_the_fastest_clear() {
+    let mut totals = PlayerStats::default();
+    assert_eq!(totals.average_placement(), None);
+    totals.record(1, &match_stats(1, 20, Some(30_000)));
+    totals.record(2, &match_stats(4, 3, None));
+    totals.record(3, &match_stats(2, 20, Some(10_000)));
+    totals.record(4, &match_stats(1, 20, Some(15_000)));
+    assert_eq!(totals.matches_played, 4);
+    assert_eq!(totals.wins, 2);
+    assert_eq!(totals.cells_revealed, 63);
+    assert_eq!(totals.clears, 3);
+    assert_eq!(
+        totals.fastest_clear,
+        Some(FastestClear {
+            match_id: 3,
+            time_ms: 10_000
+        })
+    );
+    assert_eq!(totals.average_placement(), Some(2.0));
+}
+
+#[test]
+fn achievements_are_only_unlocked_once() {
+    let latest = match_stats(1, 20, Some(45_000));
+    let mut totals = PlayerStats::default();
+    totals.record(1, &latest);
+    assert_eq!(
+        ids(newly_unlocked(&totals, &latest, &[])),
+        vec![
+            "first_match",
+            "first_win",
+            "clean_sweep",
+            "finish_line",
+            "speed_demon"
+        ]
+    );
+
+    let already: Vec<String> = ["first_match", "first_win", "clean_sweep"]
+        .iter()
+        .map(|id| id.to_string())
+        .collect();
+    let latest = MatchStats {
+        mines_flagged: 10,
+        ..match_stats(2, 20, Some(15_000))
+    };
+    totals.record(2, &latest);
+    assert_eq!(
+        ids(newly_unlocked(&totals, &latest, &already)),
+        vec!["speed_demon", "lightning", "bomb_squad"]
+    );
+}
+
+#[test]
+fn lifetime_achievements_unlock_on_the_match_that_reaches_them() {
+    let latest = match_stats(3, 0, None);
+    let totals = PlayerStats {
+        matches_played: 49,
+        ..PlayerStats::default()
+    };
+    assert!(!Achievement::find("regular")
+        .unwrap()
+        .rule
+        .is_met(&totals, &latest));
+    let totals = PlayerStats {
+        matches_played: 50,
+        ..totals
+    };
+    assert!(Achievement::find("regular")
+        .unwrap()
+        .rule
+        .is_met(&totals, &latest));
+    assert!(Achievement::find("no_such_achievement").is_none());
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use testcontainers_test::domain::{lobby::LobbySettings, tournament::*, User, UserId};
+
+const STARTS_AT: i64 = 1_000;
+const BREAK: i64 = 500;
+
+fn user(id: i64) -> User {
+    User::new(id, format!("player{}", id))
+}
+
+fn format(heat_size: usize, advance: usize) -> TournamentFormat {
+    TournamentFormat {
+        lobby: LobbySettings {
+            max_players: heat_size,
+            ..LobbySettings::default()
+        },
+        advance,
+        max_entrants: 16,
+    }
+}
+
+/// A tournament with `players` registered, where higher IDs have higher ratings.
+fn drawn(heat_size: usize, advance: usize, players: i64) -> Tournament {
+    let mut tournament = Tournament::new(
+        1,
+        String::from("Cup"),
+        format(heat_size, advance),
+        STARTS_AT,
+        0,
+    )
+    .unwrap();
+    for id in 1..=players {
+        tournament.register(user(id), 0).unwrap();
+    }
+    let ratings: HashMap<UserId, f64> = (1..=players).map(|id| (id, id as f64 * 100.0)).collect();
+    tournament.close_registration(&ratings, STARTS_AT, BREAK);
+    tournament
+}
+
+fn heat_ids(tournament: &Tournament, round: usize, heat: usize) -> Vec<UserId> {
+    tournament.rounds()[round].heats[heat]
+        .players
+        .iter()
+        .map(|player| player.user.id())
+        .collect()
+}
+
+#[test]
+fn registration_has_rules() {
+    let mut tournament =
+        Tournament::new(1, String::from("Cup"), format(4, 2), STARTS_AT, 0).unwrap();
+    tournament.register(user(1), 0).unwrap();
+    assert_eq!(
+        tournament.register(user(1), 0),
+        Err(TournamentError::AlreadyRegistered)
+    );
+    assert_eq!(
+        tournament.withdraw(2, 0),
+        Err(TournamentError::NotRegistered)
+    );
+    assert_eq!(
+        tournament.register(user(2), STARTS_AT),
+        Err(TournamentError::RegistrationClosed)
+    );
+    tournament.withdraw(1, 0).unwrap();
+    assert!(!tournament.is_registered(1));
+
+    let mut small = Tournament::new(
+        2,
+        String::from("Small"),
+        TournamentFormat {
+            max_entrants: 2,
+            ..format(2, 1)
+        },
+        STARTS_AT,
+        0,
+    )
+    .unwrap();
+    small.register(user(1), 0).unwrap();
+    small.register(user(2), 0).unwrap();
+    assert_eq!(
+        small.register(user(3), 0),
+        Err(TournamentError::TournamentFull { max: 2 })
+    );
+}
+
+#[test]
+fn formats_have_to_knock_someone_out() {
+    assert!(matches!(
+        Tournament::new(1, String::from("Cup"), format(4, 4), STARTS_AT, 0),
+        Err(TournamentError::InvalidFormat { .. })
+    ));
+    assert_eq!(
+        Tournament::new(1, String::from("Cup"), format(4, 2), STARTS_AT, STARTS_AT),
+        Err(TournamentError::StartsInPast)
+    );
+}
+
+#[test]
+fn players_are_seeded_across_the_heats() {
+    let tournament = drawn(4, 2, 8);
+    assert_eq!(tournament.status(), TournamentStatus::Running);
+    assert_eq!(tournament.entrants()[0].user, user(8));
+    assert_eq!(tournament.entrants()[0].seed, Some(1));
+    // Seeds 1, 4, 5 and 8 in one heat, 2, 3, 6 and 7 in the other.
+    assert_eq!(heat_ids(&tournament, 0, 0), vec![8, 5, 4, 1]);
+    assert_eq!(heat_ids(&tournament, 0, 1), vec![7, 6, 3, 2]);
+    assert!(tournament.due_heats(STARTS_AT + BREAK - 1).is_empty());
+    assert_eq!(tournament.due_heats(STARTS_AT + BREAK).len(), 2);
+}
+
+#[test]
+fn the_top_of_each_heat_goes_through_to_the_final() {
+    let mut tournament = drawn(4, 2, 8);
+    let now = STARTS_AT + BREAK;
+    tournament.heat_started(0, 0, &[8, 5, 4, 1], Some(10), now, BREAK);
+    tournament.heat_started(0, 1, &[7, 6, 3, 2], Some(11), now, BREAK);
+    assert_eq!(tournament.playing_matches(), vec![10, 11]);
+    assert!(tournament.heat_finished(10, &[5, 8, 1, 4], now, BREAK));
+    assert_eq!(tournament.rounds().len(), 1);
+    assert!(tournament.heat_finished(11, &[7, 6, 3, 2], now, BREAK));
+    assert!(!tournament.heat_finished(11, &[7, 6, 3, 2], now, BREAK));
+
+    // Heat winners first, then the runners up, each by seed.
+    assert_eq!(tournament.rounds().len(), 2);
+    assert!(tournament.rounds()[1].is_final());
+    assert_eq!(heat_ids(&tournament, 1, 0), vec![7, 5, 8, 6]);
+
+    let now = now + BREAK;
+    tournament.heat_started(1, 0, &[7, 5, 8, 6], Some(12), now, BREAK);
+    tournament.heat_finished(12, &[8, 7, 6, 5], now, BREAK);
+    assert_eq!(tournament.status(), TournamentStatus::Finished);
+    assert_eq!(tournament.winner(), Some(&user(8)));
+    let standings: Vec<UserId> = tournament
+        .standings()
+        .iter()
+        .map(|standing| standing.user.id())
+        .collect();
+    assert_eq!(standings, vec![8, 7, 6, 5, 3, 1, 4, 2]);
+}
+
+#[test]
+fn no_shows_are_knocked_out() {
+    let mut tournament = drawn(2, 1, 4);
+    assert_eq!(heat_ids(&tournament, 0, 0), vec![4, 1]);
+    assert_eq!(heat_ids(&tournament, 0, 1), vec![3, 2]);
+    let now = STARTS_AT + BREAK;
+    // Only one player turned up, so there's nobody to play.
+    tournament.heat_started(0, 0, &[4], None, now, BREAK);
+    let heat = &tournament.rounds()[0].heats[0];
+    assert_eq!(heat.state, HeatState::Finished);
+    assert!(heat.players[0].advanced);
+    assert!(heat.players[1].no_show);
+    tournament.heat_started(0, 1, &[3, 2], Some(10), now, BREAK);
+    tournament.heat_finished(10, &[2, 3], now, BREAK);
+    assert_eq!(heat_ids(&tournament, 1, 0), vec![4, 2]);
+
+    // Nobody turning up for the final means nobody wins.
+    tournament.heat_started(1, 0, &[], None, now + BREAK, BREAK);
+    assert_eq!(tournament.status(), TournamentStatus::Finished);
+    assert_eq!(tournament.winner(), None);
+    assert_eq!(tournament.standings().last().unwrap().user, user(1));
+}
+
+#[test]
+fn tournaments_without_enough_players_are_cancelled() {
+    let mut tournament = drawn(4, 2, 1);
+    assert_eq!(tournament.status(), TournamentStatus::Cancelled);
+    assert!(tournament.rounds().is_empty());
+    assert!(tournament.due_heats(i64::MAX).is_empty());
+    assert_eq!(
+        tournament.register(user(2), 0),
+        Err(TournamentError::RegistrationClosed)
+    );
+}
+
+#[test]
+fn lost_heats_are_played_again() {
+    let mut tournament = drawn(2, 1, 2);
+    let now = STARTS_AT + BREAK;
+    tournament.heat_started(0, 0, &[2], Some(10), now, BREAK);
+    tournament.heat_abandoned(10, now, BREAK);
+    let heat = &tournament.rounds()[0].heats[0];
+    assert_eq!(
+        heat.state,
+        HeatState::Waiting {
+            starts_at: now + BREAK
+        }
+    );
+    assert!(heat.players.iter().all(|player| !player.no_show));
+    assert!(tournament.playing_matches().is_empty());
+    assert!(tournament.due_heats(now).is_empty());
+    assert_eq!(tournament.due_heats(now + BREAK).len(), 1);
+}
+
+#[test]
+fn tournaments_carry_on_after_being_saved() {
+    let mut tournament = drawn(2, 1, 4);
+    let now = STARTS_AT + BREAK;
+    tournament.heat_started(0, 0, &[4, 1], Some(10), now, BREAK);
+    let saved = serde_json::to_string(&tournament).unwrap();
+    let mut loaded: Tournament = serde_json::from_str(&saved).unwrap();
+    assert_eq!(loaded, tournament);
+    assert_eq!(loaded.playing_matches(), vec![10]);
+    assert!(loaded.heat_finished(10, &[1, 4], now, BREAK));
+    assert_eq!(loaded.due_heats(now).len(), 1);
+}
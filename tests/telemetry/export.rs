@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use reqwest::Client;
+use testcontainers_test::domain::{Login, UserInput};
+
+use crate::helpers::{contains, spawn_test_app};
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+fn hex_bytes(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn database_spans_are_exported_in_the_callers_trace() {
+    let app = spawn_test_app();
+    let traceparent = format!("00-{}-00f067aa0ba902b7-01", TRACE_ID);
+    let client = Client::new();
+    let password = String::from("hunter2");
+    let response = client
+        .post(app.url("/signup"))
+        .header("traceparent", &traceparent)
+        .form(&UserInput::new(
+            "traced".into(),
+            password.clone(),
+            password.clone(),
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let response = client
+        .post(app.url("/"))
+        .header("traceparent", &traceparent)
+        .form(&Login::new("traced".into(), password))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    // OTLP is protobuf, where trace IDs are raw bytes and strings are left as they are.
+    let expected: Vec<Vec<u8>> = [
+        "Executing a modification statement against the database",
+        "Querying a single record (if it exists) from the database",
+        "db.system",
+        "sqlite",
+        "db.operation",
+        "INSERT",
+        "SELECT",
+        "db.statement",
+        "service.name",
+    ]
+    .iter()
+    .map(|text| text.as_bytes().to_vec())
+    .chain([hex_bytes(TRACE_ID)])
+    .collect();
+    // Spans are sent off in batches every few seconds.
+    for _ in 0..100 {
+        let exported = app.exported();
+        if expected.iter().all(|needle| contains(&exported, needle)) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let exported = app.exported();
+    let missing: Vec<String> = expected
+        .iter()
+        .filter(|needle| !contains(&exported, needle))
+        .map(|needle| String::from_utf8_lossy(needle).into_owned())
+        .collect();
+    panic!("nothing exported had {:?} in it", missing);
+}
@@ -0,0 +1,74 @@
+use testcontainers_test::config::{LogFileSettings, LogFormat, LogRotation, TelemetrySettings};
+
+use crate::helpers::logs_from;
+
+/// Logs an event inside a span with `telemetry`'s settings, and returns what was written to
+/// the sink.
+fn log_with(telemetry: &TelemetrySettings) -> String {
+    logs_from(telemetry, || {
+        let span = tracing::info_span!("Logging In", username = "player");
+        let _entered = span.enter();
+        tracing::info!(attempts = 3, "Checked the password");
+    })
+}
+
+fn format(format: LogFormat) -> TelemetrySettings {
+    TelemetrySettings {
+        format,
+        ..TelemetrySettings::default()
+    }
+}
+
+#[test]
+fn bunyan_logs_are_json() {
+    let logs = log_with(&format(LogFormat::Bunyan));
+    let events: Vec<serde_json::Value> = logs
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert!(events.iter().any(|event| {
+        let msg = event["msg"].as_str().unwrap_or_default();
+        msg.ends_with("Checked the password") && event["attempts"] == 3
+    }));
+}
+
+#[test]
+fn logfmt_logs_are_one_line_of_pairs_per_event() {
+    let logs = log_with(&format(LogFormat::Logfmt));
+    let line = logs
+        .lines()
+        .find(|line| line.contains("Checked the password"))
+        .unwrap();
+    assert!(line.contains("level=info"), "{}", line);
+    assert!(line.contains("attempts=3"), "{}", line);
+    assert!(!line.starts_with('{'), "{}", line);
+}
+
+#[test]
+fn pretty_logs_are_spread_over_several_lines() {
+    let logs = log_with(&format(LogFormat::Pretty));
+    assert!(logs.contains("Checked the password"), "{}", logs);
+    assert!(logs.lines().count() > 1, "{}", logs);
+    assert!(!logs.starts_with('{'), "{}", logs);
+}
+
+#[test]
+fn logs_can_also_go_to_files() {
+    let directory =
+        std::env::temp_dir().join(format!("testcontainers_test-logs-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    let logs = log_with(&TelemetrySettings {
+        format: LogFormat::Logfmt,
+        file: Some(LogFileSettings {
+            directory: directory.clone(),
+            prefix: String::from("app.log"),
+            rotation: LogRotation::Never,
+        }),
+        otlp: None,
+    });
+    assert!(logs.contains("Checked the password"));
+    let file = std::fs::read_to_string(directory.join("app.log")).unwrap();
+    assert!(file.contains("Checked the password"), "{}", file);
+    // Colours are only for terminals.
+    assert!(!file.contains('\u{1b}'), "{}", file);
+}
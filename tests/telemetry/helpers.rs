@@ -0,0 +1,187 @@
+use actix_web::{web, App, HttpServer};
+use once_cell::sync::Lazy;
+use std::{
+    io,
+    net::{SocketAddr, TcpListener},
+    sync::{mpsc, Arc, Mutex},
+};
+use testcontainers_test::{
+    config::{
+        AdminSettings, ApplicationConfiguration, EventStreamSettings, OtlpProtocol, OtlpSettings,
+        ProxySettings, TelemetrySettings, WebSocketSettings,
+    },
+    domain::{
+        anticheat::AntiCheatSettings,
+        battle_royale::{BattleRoyaleSettings, ReconnectSettings},
+        bot::BotSettings,
+        chat::ChatSettings,
+        daily::DailySettings,
+        leaderboard::LeaderboardSettings,
+        lobby::MatchmakingSettings,
+        rating::RatingSettings,
+        spectate::SpectatorSettings,
+        tournament::TournamentSettings,
+    },
+    run,
+    telemetry::{get_subscriber, init_subscriber},
+};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Keeps everything that's written to it.
+#[derive(Clone, Default)]
+pub struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Capture {
+    pub fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl io::Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Capture {
+    type Writer = Capture;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// Runs `log` with a subscriber built from `telemetry` as the default, and returns what was
+/// written to the sink.
+pub fn logs_from(telemetry: &TelemetrySettings, log: impl FnOnce()) -> String {
+    let capture = Capture::default();
+    let (subscriber, _, log_guard) =
+        get_subscriber("app", "info", capture.clone(), telemetry).unwrap();
+    tracing::subscriber::with_default(subscriber, log);
+    // Log files are written in the background, so wait for them to catch up.
+    drop(log_guard);
+    capture.contents()
+}
+
+/// The server every test in this binary shares, since it's tied to the global subscriber.
+pub struct TestApp {
+    pub address: SocketAddr,
+    /// Everything the server has logged.
+    pub logs: Capture,
+    /// The bodies of every export request the fake OTLP collector has received.
+    pub exports: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl TestApp {
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.address, path)
+    }
+
+    /// Everything that's been exported so far, run together.
+    pub fn exported(&self) -> Vec<u8> {
+        self.exports.lock().unwrap().concat()
+    }
+}
+
+static APP: Lazy<TestApp> = Lazy::new(|| {
+    let logs = Capture::default();
+    let exports = Arc::default();
+    let (sender, receiver) = mpsc::channel();
+    let (thread_logs, thread_exports) = (logs.clone(), Arc::clone(&exports));
+    // Each test has a runtime of its own that's gone when it finishes, so the server, the
+    // collector and the exporter get one that lasts as long as the process.
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async move {
+            let telemetry = TelemetrySettings {
+                otlp: Some(OtlpSettings {
+                    endpoint: spawn_fake_collector(thread_exports),
+                    protocol: OtlpProtocol::Http,
+                }),
+                ..TelemetrySettings::default()
+            };
+            let (subscriber, log_filter, _) =
+                get_subscriber("app", "info", thread_logs, &telemetry).unwrap();
+            init_subscriber(subscriber).unwrap();
+            let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+            sender.send(listener.local_addr().unwrap()).unwrap();
+            let db_path = std::env::temp_dir().join(format!(
+                "testcontainers_test-telemetry-{}.sqlite3",
+                std::process::id()
+            ));
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{}", db_path.display(), suffix));
+            }
+            let server = run(ApplicationConfiguration {
+                listener,
+                db_path,
+                matchmaking: MatchmakingSettings::default(),
+                battle_royale: BattleRoyaleSettings::default(),
+                reconnects: ReconnectSettings::default(),
+                ratings: RatingSettings::default(),
+                leaderboards: LeaderboardSettings::default(),
+                daily: DailySettings::default(),
+                spectators: SpectatorSettings::default(),
+                chat: ChatSettings::default(),
+                bots: BotSettings::default(),
+                anticheat: AntiCheatSettings::default(),
+                tournaments: TournamentSettings::default(),
+                websocket: WebSocketSettings::default(),
+                events: EventStreamSettings::default(),
+                proxies: ProxySettings::default(),
+                admin: AdminSettings::default(),
+                log_filter,
+            })
+            .await
+            .unwrap();
+            server.await.unwrap();
+        });
+    });
+    TestApp {
+        address: receiver.recv().unwrap(),
+        logs,
+        exports,
+    }
+});
+
+pub fn spawn_test_app() -> &'static TestApp {
+    &APP
+}
+
+/// Starts a collector that accepts OTLP over HTTP and keeps whatever it's sent. Returns its
+/// base URL.
+fn spawn_fake_collector(exports: Arc<Mutex<Vec<Vec<u8>>>>) -> String {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = HttpServer::new(move || {
+        let exports = exports.clone();
+        App::new().route(
+            "/v1/traces",
+            web::post().to(move |body: web::Bytes| {
+                exports.lock().unwrap().push(body.to_vec());
+                async { "" }
+            }),
+        )
+    })
+    .workers(1)
+    .listen(listener)
+    .unwrap()
+    .run();
+    tokio::spawn(server);
+    format!("http://{}", address)
+}
+
+/// Whether `needle` appears anywhere in `haystack`.
+pub fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
@@ -0,0 +1,7 @@
+//! Logging and span export get a test binary of their own, since the tracing subscriber is
+//! global and the other tests' one doesn't export anything.
+
+mod export;
+mod formats;
+mod helpers;
+mod redaction;
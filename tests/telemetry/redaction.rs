@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use reqwest::{header::SET_COOKIE, Client};
+use testcontainers_test::{
+    config::TelemetrySettings,
+    domain::{Login, UserInput},
+};
+
+use crate::helpers::{logs_from, spawn_test_app};
+
+const SESSION_ID: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+const PASSWORD_HASH: &str =
+    "$argon2id$v=19$m=4096,t=3,p=1$c2FsdHNhbHRzYWx0$Ym9ndXNoYXNoYm9ndXNoYXNoYm9ndXM";
+
+/// Every event logged, parsed from bunyan's JSON.
+fn events(logs: &str) -> Vec<serde_json::Value> {
+    logs.lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn sensitive_fields_are_masked() {
+    let logs = logs_from(&TelemetrySettings::default(), || {
+        tracing::info!(
+            password = "hunter2",
+            api_token = %"letmein",
+            user_id = 3,
+            "Checked the password"
+        );
+    });
+    let events = events(&logs);
+    let event = events
+        .iter()
+        .find(|event| event["msg"] == "Checked the password")
+        .unwrap();
+    assert_eq!(event["password"], "[REDACTED]");
+    assert_eq!(event["api_token"], "[REDACTED]");
+    assert_eq!(event["user_id"], 3);
+}
+
+#[test]
+fn secrets_inside_other_values_are_masked() {
+    let logs = logs_from(&TelemetrySettings::default(), || {
+        tracing::info!(
+            header = "Authorization: Bearer abc.def-ghi",
+            stored = PASSWORD_HASH,
+            "Session {} started",
+            SESSION_ID
+        );
+    });
+    for secret in ["abc.def-ghi", PASSWORD_HASH, "$argon2id$", SESSION_ID] {
+        assert!(
+            !logs.contains(secret),
+            "{:?} was logged in\n{}",
+            secret,
+            logs
+        );
+    }
+    let events = events(&logs);
+    let event = events
+        .iter()
+        .find(|event| event["msg"] == "Session [REDACTED] started")
+        .unwrap();
+    assert_eq!(event["header"], "Authorization: [REDACTED]");
+    assert_eq!(event["stored"], "[REDACTED]");
+}
+
+#[test]
+fn span_fields_are_masked_when_theyre_recorded_later() {
+    let logs = logs_from(&TelemetrySettings::default(), || {
+        let span = tracing::info_span!("Inviting a friend", email = tracing::field::Empty);
+        let _entered = span.enter();
+        span.record("email", "player@example.com");
+        tracing::info!("Sent the invite");
+    });
+    assert!(!logs.contains("player@example.com"), "{}", logs);
+    assert!(logs.contains("Sent the invite"));
+}
+
+#[tokio::test]
+async fn signing_up_and_logging_in_never_log_secrets() {
+    let app = spawn_test_app();
+    let client = Client::new();
+    let password = String::from("correct-horse-battery-staple");
+    let response = client
+        .post(app.url("/signup"))
+        .form(&UserInput::new(
+            "careful".into(),
+            password.clone(),
+            password.clone(),
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let response = client
+        .post(app.url("/"))
+        .form(&Login::new("careful".into(), password.clone()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let session_id = response
+        .headers()
+        .get(SET_COOKIE)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .trim_start_matches("session_id=")
+        .to_string();
+    // Request targets are logged, so a session ID in a query string would be too.
+    let response = client
+        .get(app.url(&format!("/health_check?session_id={}", session_id)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let logs = app.logs.contents();
+    assert!(logs.contains("careful"), "the sign up wasn't logged");
+    assert!(logs.contains("/health_check"), "the request wasn't logged");
+    for secret in [password.as_str(), session_id.as_str(), "$argon2"] {
+        assert!(!logs.contains(secret), "{:?} was logged", secret);
+    }
+}
@@ -0,0 +1,96 @@
+//! Exporting over gRPC gets a test binary of its own, since the tracer provider is global and
+//! the telemetry binary's is already exporting over HTTP.
+
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use hyper::{
+    body::{self, Bytes},
+    header::HeaderValue,
+    service::{make_service_fn, service_fn},
+    Body, HeaderMap, Request, Response, Server,
+};
+use testcontainers_test::{
+    config::{OtlpProtocol, TelemetrySettings},
+    telemetry::{get_subscriber, shutdown_tracing},
+};
+
+#[tokio::test]
+async fn spans_are_exported_over_grpc_when_the_environment_asks_for_it() {
+    let exports = Arc::default();
+    let endpoint = spawn_fake_collector(Arc::clone(&exports));
+    // This is the only test in the binary, so nothing else reads the environment meanwhile.
+    std::env::set_var("OTEL_EXPORTER_OTLP_ENDPOINT", endpoint);
+    std::env::set_var("OTEL_EXPORTER_OTLP_PROTOCOL", "grpc");
+    let telemetry = TelemetrySettings::from_env().unwrap();
+    assert_eq!(
+        telemetry.otlp.as_ref().unwrap().protocol,
+        OtlpProtocol::Grpc
+    );
+
+    let (subscriber, _, log_guard) =
+        get_subscriber("app", "info", std::io::sink, &telemetry).unwrap();
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info_span!("Sent to the collector over gRPC").in_scope(|| {});
+    });
+    // Shutting down sends off whatever hasn't been exported yet.
+    tokio::time::timeout(Duration::from_secs(30), shutdown_tracing(log_guard))
+        .await
+        .unwrap();
+
+    let exported = exports.lock().unwrap().concat();
+    for needle in ["Sent to the collector over gRPC", "service.name"] {
+        assert!(
+            exported
+                .windows(needle.len())
+                .any(|window| window == needle.as_bytes()),
+            "nothing exported had {:?} in it",
+            needle
+        );
+    }
+}
+
+/// Starts a collector that accepts OTLP over gRPC and keeps whatever it's sent. Returns its
+/// base URL.
+fn spawn_fake_collector(exports: Arc<Mutex<Vec<Vec<u8>>>>) -> String {
+    let make_service = make_service_fn(move |_| {
+        let exports = Arc::clone(&exports);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let exports = Arc::clone(&exports);
+                async move {
+                    let export = body::to_bytes(request.into_body()).await?;
+                    exports.lock().unwrap().push(export.to_vec());
+                    Ok::<_, hyper::Error>(grpc_ok())
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+        .http2_only(true)
+        .serve(make_service);
+    let address = server.local_addr();
+    tokio::spawn(server);
+    format!("http://{}", address)
+}
+
+/// A successful gRPC response with an empty message, which is all an export's response has in
+/// it. gRPC puts the status in trailers, after the message.
+fn grpc_ok() -> Response<Body> {
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        // Uncompressed, and zero bytes long.
+        let _ = sender.send_data(Bytes::from_static(&[0, 0, 0, 0, 0])).await;
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", HeaderValue::from_static("0"));
+        let _ = sender.send_trailers(trailers).await;
+    });
+    Response::builder()
+        .header("content-type", "application/grpc")
+        .body(body)
+        .unwrap()
+}